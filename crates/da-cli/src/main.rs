@@ -2,14 +2,23 @@
 //!
 //! Command-line tool for scanning, viewing, and exporting Dragon Age 2DA tables.
 
-use clap::{Parser, Subcommand};
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand};
 use da_core::{
-    apply_patch, create_history_entry, export_with_edits, merge_family, parse_csv, scan_directory,
-    BatchFile, Edit, HistoryFile, PatchFile,
+    apply_in_place, apply_patch, create_history_entry, create_marker_entry, diff_tables,
+    export_multi_with_edits, export_sqlite, export_with_edits_with_options, invert_patch,
+    merge_all_with_options, merge_family_with_options, merge_tables, parse_csv_with_options,
+    parse_row_selection, render_aligned, scan_directory, search_values, validate_patch,
+    verify_roundtrip, write_2da, write_resolved_with_line_ending, BatchFile, CellValue, ColumnOp,
+    Edit, ExportOptions, IdColumn, MergeOptions, MergeReport, ExportResult, Family, FilterExpr,
+    HistoryFile, LineEnding, MergeWarning, MultiPatchFile, OutputEncoding, ParseOptions, PatchFile,
+    PatchResult, ResolvedTable, RowErrorPolicy, RowOp, RowSelection, ScanResult, TextRenderOptions,
+    ValidationReport, ValueSearchOptions,
 };
-use std::fs::File;
-use std::io::{BufWriter, Write};
-use std::path::PathBuf;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
 
 #[derive(Parser)]
 #[command(name = "da-cli")]
@@ -18,6 +27,101 @@ use std::path::PathBuf;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    #[command(flatten)]
+    parse: ParseArgs,
+}
+
+/// How source files are read, for every command that reads them
+#[derive(Args)]
+struct ParseArgs {
+    /// Column the row IDs are read from, by index (1) or name (RowID)
+    #[arg(long, global = true)]
+    id_column: Option<String>,
+
+    /// Read 0x-prefixed cells, row IDs included, as hex numbers
+    #[arg(long, global = true)]
+    hex_values: bool,
+
+    /// Encoding the source files are written in, e.g. windows-1252
+    #[arg(long, global = true)]
+    encoding: Option<String>,
+
+    /// Skip lines starting with this character, e.g. '#'
+    #[arg(long, global = true, value_parser = parse_comment)]
+    comment: Option<u8>,
+
+    /// Headers carry their column position as <n>:<name>
+    #[arg(long, global = true)]
+    indexed_headers: bool,
+
+    /// Fail on a row ID repeated within one file instead of warning
+    #[arg(long, global = true)]
+    strict_ids: bool,
+
+    /// Leave out malformed rows with a warning instead of failing
+    #[arg(long, global = true)]
+    skip_bad_rows: bool,
+}
+
+impl ParseArgs {
+    fn options(&self) -> ParseOptions {
+        let id_column = match &self.id_column {
+            Some(column) => match column.parse() {
+                Ok(index) => IdColumn::Index(index),
+                Err(_) => IdColumn::Name(column.clone()),
+            },
+            None => IdColumn::default(),
+        };
+        ParseOptions {
+            comment: self.comment,
+            error_on_duplicate_ids: self.strict_ids,
+            indexed_headers: self.indexed_headers,
+            encoding: self.encoding.clone(),
+            hex_values: self.hex_values,
+            on_error: if self.skip_bad_rows {
+                RowErrorPolicy::Skip
+            } else {
+                RowErrorPolicy::Fail
+            },
+            id_column,
+            ..Default::default()
+        }
+    }
+}
+
+/// Read a `--comment` marker, which must be a single ASCII character
+fn parse_comment(value: &str) -> Result<u8, String> {
+    match value.as_bytes() {
+        [byte] if byte.is_ascii() => Ok(*byte),
+        _ => Err("expected a single ASCII character".to_string()),
+    }
+}
+
+/// Options from the global parse flags, set once at startup
+static PARSE_OPTIONS: OnceLock<ParseOptions> = OnceLock::new();
+
+/// How source files are read, as set by the global flags
+fn parse_options() -> &'static ParseOptions {
+    PARSE_OPTIONS.get_or_init(ParseOptions::default)
+}
+
+/// Default merge options, reading files as the global flags say
+fn merge_options() -> MergeOptions {
+    MergeOptions {
+        parse: parse_options().clone(),
+        ..Default::default()
+    }
+}
+
+/// Merge a family, reading its files as the global flags say
+fn merge(family: &Family) -> da_core::Result<ResolvedTable> {
+    merge_with_report(family).map(|(table, _)| table)
+}
+
+/// Merge a family with a report, reading its files as the global flags say
+fn merge_with_report(family: &Family) -> da_core::Result<(ResolvedTable, MergeReport)> {
+    merge_family_with_options(family, &merge_options())
 }
 
 #[derive(Subcommand)]
@@ -35,11 +139,38 @@ enum Commands {
         #[arg(short, long, required = true)]
         root: Vec<PathBuf>,
 
-        /// Show member files for each family
+        /// Show member files, merged row count and column mismatches for each
+        /// family (merges every family)
         #[arg(short, long)]
         verbose: bool,
     },
 
+    /// Summarize how a family merges: sources, counts and conflicts
+    Merge {
+        /// Root directories to scan
+        #[arg(short, long, required = true)]
+        root: Vec<PathBuf>,
+
+        /// Family name to merge
+        #[arg(short, long)]
+        family: String,
+    },
+
+    /// Merge files named directly, without scanning for a family
+    MergeFiles {
+        /// Files to merge in order; each overrides the ones before it
+        #[arg(short, long = "file", required = true)]
+        files: Vec<PathBuf>,
+
+        /// Output file path (printed to stdout if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Output format (csv, json, jsonl, 2da, or markdown)
+        #[arg(long, default_value = "csv")]
+        format: String,
+    },
+
     /// Show a merged table
     Show {
         /// Root directories to scan
@@ -57,6 +188,27 @@ enum Commands {
         /// Columns to display (comma-separated)
         #[arg(short, long)]
         columns: Option<String>,
+
+        /// Truncate cells wider than this many characters
+        #[arg(long)]
+        max_width: Option<usize>,
+
+        /// Show the last rows instead of the first (with --limit)
+        #[arg(long)]
+        tail: bool,
+
+        /// Show only rows with merge conflicts, marking conflicting cells with `*`
+        #[arg(long)]
+        only_conflicts: bool,
+
+        /// Show only rows where a variant overrode the base file, marking
+        /// the overridden cells with `*`
+        #[arg(long, conflicts_with = "only_conflicts")]
+        changed_only: bool,
+
+        /// Order rows by a column, as <column>[:asc|:desc]; repeat for more keys
+        #[arg(long)]
+        sort: Vec<SortKey>,
     },
 
     /// Export a merged table to a file
@@ -69,13 +221,75 @@ enum Commands {
         #[arg(short, long)]
         family: String,
 
-        /// Output format (csv or json)
+        /// Output format (csv, json, jsonl, 2da, or markdown)
         #[arg(long, default_value = "csv")]
         format: String,
 
+        /// Write json output on a single line
+        #[arg(long)]
+        compact: bool,
+
+        /// Encoding for csv and markdown output, e.g. windows-1252 (default UTF-8)
+        #[arg(long)]
+        output_encoding: Option<String>,
+
+        /// Write `?` for characters the output encoding lacks instead of failing
+        #[arg(long, requires = "output_encoding")]
+        replace_unmappable: bool,
+
+        /// End csv lines with CRLF instead of LF
+        #[arg(long)]
+        crlf: bool,
+
         /// Output file path
         #[arg(short, long)]
         output: PathBuf,
+
+        /// Columns to include in markdown output (comma-separated)
+        #[arg(short, long)]
+        columns: Option<String>,
+
+        /// Order rows by a column, as <column>[:asc|:desc]; repeat for more keys
+        #[arg(long)]
+        sort: Vec<SortKey>,
+
+        /// Export only these row IDs, e.g. 100-150,200,305
+        #[arg(long, value_parser = parse_row_selection)]
+        rows: Option<RowSelection>,
+    },
+
+    /// Export every family to its own file, merging them in parallel
+    ExportAll {
+        /// Root directories to scan
+        #[arg(short, long, required = true)]
+        root: Vec<PathBuf>,
+
+        /// Output directory; each family is written to <family>.<format>
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Output format (csv, json, jsonl, 2da, or markdown)
+        #[arg(long, default_value = "json")]
+        format: String,
+
+        /// Write json output on a single line
+        #[arg(long)]
+        compact: bool,
+    },
+
+    /// Export merged families to a SQLite database
+    ExportDb {
+        /// Root directories to scan
+        #[arg(short, long, required = true)]
+        root: Vec<PathBuf>,
+
+        /// Families to export (all if not specified)
+        #[arg(short, long)]
+        family: Vec<String>,
+
+        /// Output database path
+        #[arg(short, long)]
+        output: PathBuf,
     },
 
     /// Explain the provenance of a specific cell
@@ -97,11 +311,30 @@ enum Commands {
         col: String,
     },
 
+    /// Count the cells each source file of a family won
+    Provenance {
+        /// Root directories to scan
+        #[arg(short, long, required = true)]
+        root: Vec<PathBuf>,
+
+        /// Family name
+        #[arg(short, long)]
+        family: String,
+    },
+
     /// Parse and display a single CSV file
     Parse {
         /// Path to CSV file
         #[arg(short, long)]
         file: PathBuf,
+
+        /// Align columns for reading instead of separating them with tabs
+        #[arg(long)]
+        pretty: bool,
+
+        /// With --pretty, truncate cells wider than this many characters
+        #[arg(long, requires = "pretty")]
+        max_width: Option<usize>,
     },
 
     /// Apply a patch file and export modified source files
@@ -115,8 +348,50 @@ enum Commands {
         patch: PathBuf,
 
         /// Output directory for modified files
-        #[arg(short, long)]
-        output: PathBuf,
+        #[arg(short, long, required_unless_present = "in_place", conflicts_with = "in_place")]
+        output: Option<PathBuf>,
+
+        /// Overwrite files that already exist in the output directory
+        #[arg(long)]
+        force: bool,
+
+        /// Edit the source files themselves
+        #[arg(long)]
+        in_place: bool,
+
+        /// Don't copy each source file to <name>.bak before editing it in place
+        #[arg(long, requires = "in_place")]
+        no_backup: bool,
+
+        /// Don't ask for confirmation before editing in place
+        #[arg(short, long, requires = "in_place")]
+        yes: bool,
+
+        /// Encoding for the written files, e.g. windows-1252 (default UTF-8)
+        #[arg(long, conflicts_with = "in_place")]
+        output_encoding: Option<String>,
+
+        /// Write `?` for characters the output encoding lacks instead of failing
+        #[arg(long, requires = "output_encoding")]
+        replace_unmappable: bool,
+
+        /// Copy unchanged lines byte for byte, keeping number formatting,
+        /// quoting, comments and line endings
+        #[arg(long)]
+        fidelity: bool,
+
+        /// End the written lines with CRLF instead of LF
+        #[arg(long, conflicts_with = "fidelity")]
+        crlf: bool,
+
+        /// Print the files that would be written instead of writing them
+        #[arg(long, conflicts_with = "in_place")]
+        dry_run: bool,
+
+        /// History file to record the patch in (in-place edits always record,
+        /// by default to .da-history.json)
+        #[arg(short = 'H', long)]
+        history_file: Option<PathBuf>,
     },
 
     /// Run a batch of patch operations
@@ -124,6 +399,10 @@ enum Commands {
         /// Path to batch file (JSON)
         #[arg(short, long)]
         batch: PathBuf,
+
+        /// Overwrite files that already exist in the output directory
+        #[arg(long)]
+        force: bool,
     },
 
     /// Create an empty patch file template
@@ -139,6 +418,34 @@ enum Commands {
         /// Example edits to include (row_id:column:value)
         #[arg(short, long)]
         example: Vec<String>,
+
+        /// Root directories to scan; each edit records the cell's current
+        /// value as `expected_old`, so the patch refuses stale cells
+        #[arg(short, long)]
+        root: Vec<PathBuf>,
+
+        /// What the patch is for, shown by `history`
+        #[arg(short, long)]
+        description: Option<String>,
+
+        /// Who wrote the patch
+        #[arg(long)]
+        author: Option<String>,
+    },
+
+    /// Write the patch that undoes a patch on the current source files
+    Invert {
+        /// Root directories to scan (before the patch is applied)
+        #[arg(short, long, required = true)]
+        root: Vec<PathBuf>,
+
+        /// Path to the patch file to invert
+        #[arg(short, long)]
+        patch: PathBuf,
+
+        /// Output path for the reverse patch
+        #[arg(short, long)]
+        output: PathBuf,
     },
 
     /// Create an empty batch file template
@@ -167,6 +474,33 @@ enum Commands {
         pattern: String,
     },
 
+    /// Search merged cell values in every family
+    Grep {
+        /// Root directories to scan
+        #[arg(short, long, required = true)]
+        root: Vec<PathBuf>,
+
+        /// Text to look for (substring match)
+        #[arg(short, long, allow_hyphen_values = true)]
+        pattern: String,
+
+        /// Only search this family; repeat for more
+        #[arg(short, long)]
+        family: Vec<String>,
+
+        /// Only search this column; repeat for more
+        #[arg(short, long)]
+        column: Vec<String>,
+
+        /// Match case exactly
+        #[arg(long)]
+        case_sensitive: bool,
+
+        /// Stop after this many matches
+        #[arg(short, long)]
+        limit: Option<usize>,
+    },
+
     /// Filter rows in a family by column value
     Filter {
         /// Root directories to scan
@@ -178,16 +512,63 @@ enum Commands {
         family: String,
 
         /// Column to filter on
-        #[arg(short, long)]
-        column: String,
+        #[arg(short, long, required_unless_present = "query")]
+        column: Option<String>,
 
         /// Value to search for (substring match, case-insensitive)
-        #[arg(short, long)]
-        value: String,
+        #[arg(short, long, allow_hyphen_values = true, required_unless_present = "query")]
+        value: Option<String>,
+
+        /// Compare numerically instead: <, <=, >, >=, == or != (text and
+        /// empty cells never match)
+        #[arg(long)]
+        op: Option<String>,
+
+        /// Query over several columns, e.g. "Category=weapon & Tier>=3"
+        #[arg(short, long, conflicts_with_all = ["column", "value", "op"])]
+        query: Option<String>,
 
         /// Maximum rows to display
         #[arg(short, long)]
         limit: Option<usize>,
+
+        /// Align columns for reading instead of separating them with tabs
+        #[arg(long)]
+        pretty: bool,
+
+        /// With --pretty, truncate cells wider than this many characters
+        #[arg(long, requires = "pretty")]
+        max_width: Option<usize>,
+    },
+
+    /// Summarize the values of one column in a merged family
+    Stats {
+        /// Root directories to scan
+        #[arg(short, long, required = true)]
+        root: Vec<PathBuf>,
+
+        /// Family name
+        #[arg(short, long)]
+        family: String,
+
+        /// Column to summarize
+        #[arg(short, long)]
+        column: String,
+    },
+
+    /// List a family's columns with their 0-based indices
+    Columns {
+        /// Root directories to scan
+        #[arg(short, long, required = true)]
+        root: Vec<PathBuf>,
+
+        /// Family name
+        #[arg(short, long)]
+        family: String,
+
+        /// Show the decimal label ID next to hex column names such as 0xC4FDA9ED
+        #[arg(long)]
+        decimal: bool,
     },
 
     /// Validate a patch file without applying it
@@ -199,6 +580,51 @@ enum Commands {
         /// Path to patch file (JSON)
         #[arg(short, long)]
         patch: PathBuf,
+
+        /// Warn about values that don't fit the column's inferred type
+        #[arg(long)]
+        check_types: bool,
+
+        /// Print the validation report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show what parsing a file and writing it back out would change
+    Verify {
+        /// CSV file to check
+        #[arg(short, long)]
+        file: PathBuf,
+    },
+
+    /// Check families for problems that make merges misbehave
+    Check {
+        /// Root directories to scan
+        #[arg(short, long, required = true)]
+        root: Vec<PathBuf>,
+
+        /// Family name (optional, check all if not specified)
+        #[arg(short, long)]
+        family: Option<String>,
+    },
+
+    /// Compare a family's merged table between two sets of roots
+    Diff {
+        /// Root directories of the old version
+        #[arg(long, required = true)]
+        left: Vec<PathBuf>,
+
+        /// Root directories of the new version
+        #[arg(long, required = true)]
+        right: Vec<PathBuf>,
+
+        /// Family name
+        #[arg(short, long)]
+        family: String,
+
+        /// Print the diff as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Show patch history for a family
@@ -210,9 +636,13 @@ enum Commands {
         /// Family name (optional, show all if not specified)
         #[arg(short, long)]
         family: Option<String>,
+
+        /// Record a labelled marker for the family instead of showing history
+        #[arg(long, value_name = "LABEL", requires = "family")]
+        mark: Option<String>,
     },
 
-    /// Undo the last patch applied to a family
+    /// Undo the last patches applied to a family
     Undo {
         /// Root directories to scan
         #[arg(short, long, required = true)]
@@ -229,6 +659,37 @@ enum Commands {
         /// Output directory for restored files
         #[arg(short, long)]
         output: PathBuf,
+
+        /// Number of entries to undo
+        #[arg(long, conflicts_with = "until")]
+        steps: Option<usize>,
+
+        /// Undo every entry recorded after this RFC 3339 timestamp
+        #[arg(long)]
+        until: Option<DateTime<Utc>>,
+
+        /// Undo even if files changed after the patches wrote them
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Re-apply the last patch undone for a family
+    Redo {
+        /// Root directories to scan
+        #[arg(short, long, required = true)]
+        root: Vec<PathBuf>,
+
+        /// Path to history file
+        #[arg(short = 'H', long, default_value = ".da-history.json")]
+        history_file: PathBuf,
+
+        /// Family name
+        #[arg(short, long)]
+        family: String,
+
+        /// Output directory (defaults to the one the patch was first written to)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
 }
 
@@ -241,48 +702,195 @@ fn main() {
 
 fn run() -> da_core::Result<()> {
     let cli = Cli::parse();
+    PARSE_OPTIONS.get_or_init(|| cli.parse.options());
 
     match cli.command {
         Commands::Scan { root } => cmd_scan(&root),
         Commands::ListFamilies { root, verbose } => cmd_list_families(&root, verbose),
+        Commands::Merge { root, family } => cmd_merge(&root, &family),
+        Commands::MergeFiles { files, output, format } => {
+            let format = ExportFormat {
+                format: format.to_lowercase(),
+                compact: false,
+                columns: None,
+                line_ending: LineEnding::Lf,
+            };
+            cmd_merge_files(&files, output.as_deref(), &format)
+        }
         Commands::Show {
             root,
             family,
             limit,
             columns,
-        } => cmd_show(&root, &family, limit, columns),
+            max_width,
+            tail,
+            only_conflicts,
+            changed_only,
+            sort,
+        } => {
+            let rows = if only_conflicts {
+                ShowRows::Conflicts
+            } else if changed_only {
+                ShowRows::Changed
+            } else {
+                ShowRows::All
+            };
+            let opts = TextRenderOptions {
+                max_column_width: max_width,
+                columns: columns.map(|c| c.split(',').map(str::to_string).collect()),
+                limit,
+                tail,
+                ..Default::default()
+            };
+            cmd_show(&root, &family, opts, rows, &sort)
+        }
         Commands::Export {
             root,
             family,
             format,
             output,
-        } => cmd_export(&root, &family, &format, &output),
+            compact,
+            columns,
+            output_encoding,
+            replace_unmappable,
+            crlf,
+            sort,
+            rows,
+        } => {
+            let encoding = parse_output_encoding(output_encoding, replace_unmappable)?;
+            let format = ExportFormat {
+                format: format.to_lowercase(),
+                compact,
+                columns,
+                line_ending: line_ending(crlf),
+            };
+            cmd_export(&root, &family, &format, &output, encoding, rows.as_ref(), &sort)
+        }
+        Commands::ExportAll { root, output, format, compact } => {
+            cmd_export_all(&root, &output, &format, compact)
+        }
+        Commands::ExportDb { root, family, output } => cmd_export_db(&root, &family, &output),
         Commands::Explain {
             root,
             family,
             row,
             col,
         } => cmd_explain(&root, &family, row, &col),
-        Commands::Parse { file } => cmd_parse(&file),
-        Commands::Patch { root, patch, output } => cmd_patch(&root, &patch, &output, None),
-        Commands::Batch { batch } => cmd_batch(&batch),
-        Commands::CreatePatch { family, output, example } => cmd_create_patch(&family, &output, &example),
+        Commands::Provenance { root, family } => cmd_provenance(&root, &family),
+        Commands::Parse { file, pretty, max_width } => cmd_parse(&file, pretty, max_width),
+        Commands::Patch {
+            root,
+            patch,
+            output,
+            force,
+            in_place,
+            no_backup,
+            yes,
+            history_file,
+            output_encoding,
+            replace_unmappable,
+            fidelity,
+            crlf,
+            dry_run,
+        } => match output {
+            Some(output) if !in_place => {
+                let options = ExportOptions {
+                    overwrite: force,
+                    dry_run,
+                    encoding: parse_output_encoding(output_encoding, replace_unmappable)?,
+                    line_ending: line_ending(crlf),
+                    ..export_options(fidelity)
+                };
+                cmd_patch(&root, &patch, &output, history_file.as_ref(), &options)
+            }
+            _ => {
+                let history_path = history_file.unwrap_or_else(|| PathBuf::from(".da-history.json"));
+                let options = ExportOptions {
+                    backup: !no_backup,
+                    line_ending: line_ending(crlf),
+                    ..export_options(fidelity)
+                };
+                cmd_patch_in_place(&root, &patch, &history_path, yes, &options)
+            }
+        },
+        Commands::Batch { batch, force } => cmd_batch(&batch, force),
+        Commands::Invert { root, patch, output } => cmd_invert(&root, &patch, &output),
+        Commands::CreatePatch {
+            family,
+            output,
+            example,
+            root,
+            description,
+            author,
+        } => {
+            let mut patch = PatchFile::new(family);
+            patch.description = description;
+            patch.author = author;
+            cmd_create_patch(patch, &output, &example, &root)
+        }
         Commands::CreateBatch { output, root, export_dir } => cmd_create_batch(&output, &root, &export_dir),
         Commands::Search { root, pattern } => cmd_search(&root, &pattern),
-        Commands::Filter { root, family, column, value, limit } => cmd_filter(&root, &family, &column, &value, limit),
-        Commands::Validate { root, patch } => cmd_validate(&root, &patch),
-        Commands::History { history_file, family } => cmd_history(&history_file, family.as_deref()),
-        Commands::Undo { root, history_file, family, output } => cmd_undo(&root, &history_file, &family, &output),
-    }
-}
-
-fn cmd_scan(roots: &[PathBuf]) -> da_core::Result<()> {
-    let result = scan_directory(roots)?;
-
-    println!("Scanned {} root(s):", result.roots.len());
-    for root in &result.roots {
-        println!("  {}", root.display());
-    }
+        Commands::Grep {
+            root,
+            pattern,
+            family,
+            column,
+            case_sensitive,
+            limit,
+        } => {
+            let options = ValueSearchOptions {
+                families: family,
+                columns: column,
+                case_sensitive,
+                limit,
+                parse: parse_options().clone(),
+            };
+            cmd_grep(&root, &pattern, &options)
+        }
+        Commands::Filter { root, family, column, value, op, query, limit, pretty, max_width } => {
+            let query = match (query, column, value) {
+                (Some(query), _, _) => FilterQuery::Expr(query),
+                (None, Some(column), Some(value)) => FilterQuery::Column { column, value, op },
+                _ => unreachable!("clap requires --query or --column and --value"),
+            };
+            cmd_filter(&root, &family, &query, limit, pretty, max_width)
+        }
+        Commands::Stats { root, family, column } => cmd_stats(&root, &family, &column),
+        Commands::Columns { root, family, decimal } => cmd_columns(&root, &family, decimal),
+        Commands::Validate { root, patch, check_types, json } => {
+            if !cmd_validate(&root, &patch, check_types, json)? {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Commands::Verify { file } => {
+            if !cmd_verify(&file)? {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Commands::Check { root, family } => cmd_check(&root, family.as_deref()),
+        Commands::Diff { left, right, family, json } => cmd_diff(&left, &right, &family, json),
+        Commands::History { history_file, family, mark } => match (family, mark) {
+            (Some(family), Some(label)) => cmd_history_mark(&history_file, &family, &label),
+            (family, _) => cmd_history(&history_file, family.as_deref()),
+        },
+        Commands::Undo { root, history_file, family, output, steps, until, force } => {
+            cmd_undo(&root, &history_file, &family, &output, steps, until, force)
+        }
+        Commands::Redo { root, history_file, family, output } => {
+            cmd_redo(&root, &history_file, &family, output.as_ref())
+        }
+    }
+}
+
+fn cmd_scan(roots: &[PathBuf]) -> da_core::Result<()> {
+    let result = scan_directory(roots)?;
+
+    println!("Scanned {} root(s):", result.roots.len());
+    for root in &result.roots {
+        println!("  {}", root.display());
+    }
     println!();
     println!("Found {} files in {} families", result.total_files, result.families.len());
 
@@ -297,7 +905,22 @@ fn cmd_list_families(roots: &[PathBuf], verbose: bool) -> da_core::Result<()> {
 
     for family in &result.families {
         if verbose {
-            println!("{} ({} files)", family.name, family.members.len());
+            // Merging every family is slow on large trees, so only do it here
+            let shape = family.validate_shape(parse_options());
+            let badge = match &shape {
+                Ok(report) if !report.is_consistent() => " [columns differ]",
+                _ => "",
+            };
+            match family.merged_row_count_with_options(&merge_options()) {
+                Ok(rows) => println!(
+                    "{} ({} files, {} rows){}",
+                    family.name,
+                    family.members.len(),
+                    rows,
+                    badge
+                ),
+                Err(e) => println!("{} ({} files, merge failed: {})", family.name, family.members.len(), e),
+            }
             for member in &family.members {
                 let suffix_str = match &member.suffix {
                     Some(s) => format!(" [{}]", s),
@@ -305,113 +928,451 @@ fn cmd_list_families(roots: &[PathBuf], verbose: bool) -> da_core::Result<()> {
                 };
                 println!("  {}{}", member.path.display(), suffix_str);
             }
+            if let Ok(report) = shape {
+                for missing in &report.missing {
+                    for member in &missing.members {
+                        println!("  ! {} lacks column '{}'", member.display(), missing.column);
+                    }
+                }
+                for extra in &report.extra {
+                    println!(
+                        "  ! {} has extra columns: {}",
+                        extra.member.display(),
+                        extra.columns.join(", ")
+                    );
+                }
+            }
             println!();
         } else {
             println!("  {} ({} files)", family.name, family.members.len());
         }
     }
 
+    for warning in &result.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    Ok(())
+}
+
+fn cmd_merge(roots: &[PathBuf], family_name: &str) -> da_core::Result<()> {
+    let scan_result = scan_directory(roots)?;
+
+    let family = scan_result
+        .find_family_ci(family_name)?
+        .ok_or_else(|| da_core::Error::FamilyNotFound(family_name.to_string()))?;
+
+    for line in merge_summary(family)? {
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+fn cmd_merge_files(
+    files: &[PathBuf],
+    output: Option<&Path>,
+    format: &ExportFormat,
+) -> da_core::Result<()> {
+    let merged = merge_files(files)?;
+    let bytes = render_table(&merged, format)?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, bytes)?;
+            println!(
+                "Merged {} files into {} rows -> {}",
+                files.len(),
+                merged.row_count(),
+                path.display()
+            );
+        }
+        None => io::stdout().write_all(&bytes)?,
+    }
+
     Ok(())
 }
 
+/// Parse the files and merge them in order, later files winning
+///
+/// The merged table is named after the first file.
+fn merge_files(files: &[PathBuf]) -> da_core::Result<ResolvedTable> {
+    let tables = files
+        .iter()
+        .map(|file| parse_csv_with_options(file, parse_options()))
+        .collect::<da_core::Result<Vec<_>>>()?;
+    let name = files
+        .first()
+        .and_then(|f| f.file_stem())
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    merge_tables(&name, tables)
+}
+
+/// Describe a family's merge: sources with what each contributed, then totals
+fn merge_summary(family: &Family) -> da_core::Result<Vec<String>> {
+    let (merged, report) = merge_with_report(family)?;
+
+    let mut lines = vec![
+        format!("Family: {}", merged.family_name),
+        "Sources (in merge order):".to_string(),
+    ];
+    for (i, source) in merged.sources.iter().enumerate() {
+        let rows = merged
+            .rows
+            .iter()
+            .filter(|r| r.cells.iter().any(|c| &c.source == source))
+            .count();
+        let cells: usize = merged
+            .rows
+            .iter()
+            .map(|r| {
+                r.cells
+                    .iter()
+                    .filter(|c| &c.source == source && !c.value.is_empty())
+                    .count()
+            })
+            .sum();
+        lines.push(format!(
+            "  {}. {}: {} rows, {} cells",
+            i + 1,
+            source.display(),
+            rows,
+            cells
+        ));
+    }
+
+    lines.push(format!("Columns: {}", merged.column_count()));
+    lines.push(format!("Rows: {}", merged.row_count()));
+    lines.push(format!("Conflicts: {}", report.conflicts.len()));
+    lines.push(format!("Type divergences: {}", report.type_divergences.len()));
+
+    Ok(lines)
+}
+
 fn cmd_show(
     roots: &[PathBuf],
     family_name: &str,
-    limit: Option<usize>,
-    columns: Option<String>,
+    opts: TextRenderOptions,
+    rows: ShowRows,
+    sort: &[SortKey],
 ) -> da_core::Result<()> {
     let scan_result = scan_directory(roots)?;
 
     let family = scan_result
-        .find_family(family_name)
+        .find_family_ci(family_name)?
         .ok_or_else(|| da_core::Error::FamilyNotFound(family_name.to_string()))?;
 
-    let merged = merge_family(family)?;
+    print!("{}", show_family(family, opts, rows, sort)?);
 
-    // Filter columns if specified
-    let col_filter: Option<Vec<&str>> = columns.as_ref().map(|c| c.split(',').collect());
+    Ok(())
+}
 
-    let display_cols: Vec<&da_core::Column> = if let Some(ref filter) = col_filter {
-        merged
-            .columns
-            .iter()
-            .filter(|c| filter.contains(&c.name.as_str()))
-            .collect()
-    } else {
-        merged.columns.iter().collect()
-    };
+/// A `--sort` key: a column name with an optional `:asc` or `:desc`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SortKey {
+    column: String,
+    ascending: bool,
+}
 
-    // Print header
-    let header: Vec<&str> = display_cols.iter().map(|c| c.name.as_str()).collect();
-    println!("{}", header.join("\t"));
-    println!("{}", "-".repeat(header.len() * 12));
+impl FromStr for SortKey {
+    type Err = String;
 
-    // Print rows
-    let row_limit = limit.unwrap_or(merged.rows.len());
-    for row in merged.rows.iter().take(row_limit) {
-        let values: Vec<String> = display_cols
-            .iter()
-            .map(|col| {
-                row.cells
-                    .get(col.index)
-                    .map(|c| c.value.to_string_value())
-                    .unwrap_or_default()
-            })
-            .collect();
-        println!("{}", values.join("\t"));
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (column, ascending) = match s.rsplit_once(':') {
+            Some((column, "asc")) => (column, true),
+            Some((column, "desc")) => (column, false),
+            _ => (s, true),
+        };
+        if column.is_empty() {
+            return Err(format!("'{}' names no column", s));
+        }
+        Ok(Self {
+            column: column.to_string(),
+            ascending,
+        })
     }
+}
 
-    if merged.rows.len() > row_limit {
-        println!("... ({} more rows)", merged.rows.len() - row_limit);
+/// Sort rows by each key in turn, the first key deciding first
+///
+/// Columns whose merged values are numeric sort by value, others as text.
+fn sort_rows(merged: &mut ResolvedTable, keys: &[SortKey]) -> da_core::Result<()> {
+    // Stable sorts applied from the last key to the first leave the first
+    // key primary and break its ties by the later ones
+    for key in keys.iter().rev() {
+        let index = merged
+            .find_column_ci(&key.column)?
+            .ok_or_else(|| da_core::Error::ColumnNotFound(key.column.clone()))?
+            .index;
+        let numeric = merged.infer_column_type(index).is_numeric();
+        merged.sort_by_column(index, key.ascending, numeric);
     }
-
     Ok(())
 }
 
+/// Which rows `show` displays
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShowRows {
+    /// Every row
+    All,
+    /// Rows with merge conflicts
+    Conflicts,
+    /// Rows with a cell a variant overrode
+    Changed,
+}
+
+/// Render a family's merged table, optionally limited to rows with
+/// conflicts or overridden cells, which are marked
+fn show_family(
+    family: &Family,
+    mut opts: TextRenderOptions,
+    rows: ShowRows,
+    sort: &[SortKey],
+) -> da_core::Result<String> {
+    if rows != ShowRows::Conflicts {
+        let mut merged = merge(family)?;
+        sort_rows(&mut merged, sort)?;
+        opts.columns = opts.columns.map(|names| column_names(&merged, &names)).transpose()?;
+        if rows == ShowRows::Changed {
+            let changed: Vec<(i64, String)> = merged
+                .overridden_cells()
+                .into_iter()
+                .map(|(id, column, _)| (id, column.to_string()))
+                .collect();
+            merged
+                .rows
+                .retain(|row| row.id.is_some_and(|id| changed.iter().any(|(c, _)| *c == id)));
+            merged.invalidate_row_index();
+            opts.marked_cells = changed;
+        }
+        return Ok(merged.to_aligned_string(&opts));
+    }
+
+    let (mut merged, report) = merge_with_report(family)?;
+    sort_rows(&mut merged, sort)?;
+    opts.columns = opts.columns.map(|names| column_names(&merged, &names)).transpose()?;
+    merged.rows.retain(|row| {
+        row.id
+            .is_some_and(|id| report.conflicts.iter().any(|c| c.row_id == id))
+    });
+    merged.invalidate_row_index();
+    opts.marked_cells = report
+        .conflicts
+        .iter()
+        .map(|c| (c.row_id, c.column.clone()))
+        .collect();
+
+    Ok(merged.to_aligned_string(&opts))
+}
+
+/// Match `--columns` names to the table's own, ignoring case when a name
+/// has no exact match; names matching no column are passed through
+fn column_names(merged: &ResolvedTable, names: &[String]) -> da_core::Result<Vec<String>> {
+    names
+        .iter()
+        .map(|name| {
+            Ok(merged
+                .find_column_ci(name)?
+                .map_or_else(|| name.clone(), |col| col.name.clone()))
+        })
+        .collect()
+}
+
+/// How `export` and `export-all` render a table
+struct ExportFormat {
+    /// Format name, lowercased
+    format: String,
+    /// Write json output on a single line
+    compact: bool,
+    /// Columns to include in markdown output (comma-separated)
+    columns: Option<String>,
+    /// How csv output lines end
+    line_ending: LineEnding,
+}
+
 fn cmd_export(
     roots: &[PathBuf],
     family_name: &str,
-    format: &str,
+    format: &ExportFormat,
     output: &PathBuf,
+    encoding: Option<OutputEncoding>,
+    rows: Option<&RowSelection>,
+    sort: &[SortKey],
 ) -> da_core::Result<()> {
+    if encoding.is_some() && !matches!(format.format.as_str(), "csv" | "markdown" | "md") {
+        return Err(da_core::Error::Export(
+            "--output-encoding only applies to csv and markdown output".to_string(),
+        ));
+    }
+
     let scan_result = scan_directory(roots)?;
 
     let family = scan_result
-        .find_family(family_name)
+        .find_family_ci(family_name)?
         .ok_or_else(|| da_core::Error::FamilyNotFound(family_name.to_string()))?;
 
-    let merged = merge_family(family)?;
+    let mut merged = merge(family)?;
+    if let Some(rows) = rows {
+        merged.select_rows(rows);
+    }
+    sort_rows(&mut merged, sort)?;
+    let writer = render_table(&merged, format)?;
+
+    let bytes = match encoding {
+        Some(encoding) => encoding.encode(&String::from_utf8_lossy(&writer))?,
+        None => writer,
+    };
+    std::fs::write(output, bytes)?;
+
+    println!("Exported {} rows to {}", merged.rows.len(), output.display());
+
+    Ok(())
+}
+
+/// Get the file extension for an export format
+fn format_extension(format: &str) -> da_core::Result<&'static str> {
+    match format {
+        "csv" => Ok("csv"),
+        "json" => Ok("json"),
+        "jsonl" => Ok("jsonl"),
+        "2da" | "gda" => Ok("gda"),
+        "markdown" | "md" => Ok("md"),
+        _ => Err(unknown_format(format)),
+    }
+}
+
+fn unknown_format(format: &str) -> da_core::Error {
+    da_core::Error::Export(format!(
+        "Unknown format: {}. Supported formats: csv, json, jsonl, 2da, markdown",
+        format
+    ))
+}
 
-    let file = File::create(output)?;
-    let mut writer = BufWriter::new(file);
+/// Render a merged table in an export format
+fn render_table(merged: &ResolvedTable, format: &ExportFormat) -> da_core::Result<Vec<u8>> {
+    let mut writer: Vec<u8> = Vec::new();
 
-    match format.to_lowercase().as_str() {
+    match format.format.as_str() {
         "csv" => {
-            // Write header
-            let header: Vec<&str> = merged.columns.iter().map(|c| c.name.as_str()).collect();
-            writeln!(writer, "{}", header.join(","))?;
-
-            // Write rows
-            for row in &merged.rows {
-                let values: Vec<String> = row
-                    .cells
-                    .iter()
-                    .map(|c| escape_csv(&c.value.to_string_value()))
-                    .collect();
-                writeln!(writer, "{}", values.join(","))?;
-            }
+            write_resolved_with_line_ending(&mut writer, merged, format.line_ending)?;
         }
         "json" => {
-            let json = serde_json::to_string_pretty(&merged)?;
+            let json = if format.compact {
+                serde_json::to_string(merged)?
+            } else {
+                serde_json::to_string_pretty(merged)?
+            };
             writeln!(writer, "{}", json)?;
         }
-        _ => {
-            eprintln!("Unknown format: {}. Supported formats: csv, json", format);
-            std::process::exit(1);
+        "jsonl" => {
+            for object in merged.rows_as_json_objects() {
+                writeln!(writer, "{}", object)?;
+            }
+        }
+        "2da" | "gda" => {
+            write_2da(merged, &mut writer)?;
+        }
+        "markdown" | "md" => {
+            let columns: Option<Vec<String>> = format
+                .columns
+                .as_ref()
+                .map(|c| {
+                    let names: Vec<String> = c.split(',').map(str::to_string).collect();
+                    column_names(merged, &names)
+                })
+                .transpose()?;
+            write!(writer, "{}", merged.to_markdown(columns.as_deref()))?;
         }
+        _ => return Err(unknown_format(&format.format)),
     }
 
-    println!("Exported {} rows to {}", merged.rows.len(), output.display());
+    Ok(writer)
+}
+
+fn cmd_export_all(
+    roots: &[PathBuf],
+    output_dir: &Path,
+    format: &str,
+    compact: bool,
+) -> da_core::Result<()> {
+    let format = ExportFormat {
+        format: format.to_lowercase(),
+        compact,
+        columns: None,
+        line_ending: LineEnding::Lf,
+    };
+    let extension = format_extension(&format.format)?;
+
+    let scan_result = scan_directory(roots)?;
+    let results = merge_all_with_options(&scan_result.families, &merge_options());
+    std::fs::create_dir_all(output_dir)?;
+
+    let mut failed = 0;
+    for (family, result) in scan_result.families.iter().zip(results) {
+        let exported = result.and_then(|merged| {
+            let path = output_dir.join(format!("{}.{}", family.name, extension));
+            std::fs::write(&path, render_table(&merged, &format)?)?;
+            Ok((merged.rows.len(), path))
+        });
+        match exported {
+            Ok((rows, path)) => println!("  {}: {} rows -> {}", family.name, rows, path.display()),
+            Err(e) => {
+                failed += 1;
+                println!("  {}: {}", family.name, e);
+            }
+        }
+    }
+
+    println!(
+        "Exported {} of {} families to {}",
+        scan_result.families.len() - failed,
+        scan_result.families.len(),
+        output_dir.display()
+    );
+    if failed > 0 {
+        return Err(da_core::Error::Export(format!("{} families could not be exported", failed)));
+    }
+
+    Ok(())
+}
+
+/// Resolve the `--output-encoding` flag
+fn parse_output_encoding(
+    label: Option<String>,
+    replace_unmappable: bool,
+) -> da_core::Result<Option<OutputEncoding>> {
+    label
+        .map(|label| OutputEncoding::for_label(&label, replace_unmappable))
+        .transpose()
+}
+
+fn cmd_export_db(roots: &[PathBuf], family_names: &[String], output: &Path) -> da_core::Result<()> {
+    let scan_result = scan_directory(roots)?;
+
+    let families: Vec<&Family> = if family_names.is_empty() {
+        scan_result.families.iter().collect()
+    } else {
+        family_names
+            .iter()
+            .map(|name| {
+                scan_result
+                    .find_family_ci(name)?
+                    .ok_or_else(|| da_core::Error::FamilyNotFound(name.clone()))
+            })
+            .collect::<da_core::Result<_>>()?
+    };
+
+    let tables = families
+        .iter()
+        .map(|family| merge(family))
+        .collect::<da_core::Result<Vec<_>>>()?;
+    export_sqlite(&tables, output)?;
+
+    for table in &tables {
+        println!("  {}: {} rows", table.family_name, table.rows.len());
+    }
+    println!("Exported {} families to {}", tables.len(), output.display());
 
     Ok(())
 }
@@ -420,13 +1381,13 @@ fn cmd_explain(roots: &[PathBuf], family_name: &str, row_id: i64, col_name: &str
     let scan_result = scan_directory(roots)?;
 
     let family = scan_result
-        .find_family(family_name)
+        .find_family_ci(family_name)?
         .ok_or_else(|| da_core::Error::FamilyNotFound(family_name.to_string()))?;
 
-    let merged = merge_family(family)?;
+    let merged = merge(family)?;
 
     let col = merged
-        .find_column(col_name)
+        .find_column_ci(col_name)?
         .ok_or_else(|| da_core::Error::InvalidFamilyName(format!("column '{}' not found", col_name)))?;
 
     let row = merged
@@ -451,54 +1412,125 @@ fn cmd_explain(roots: &[PathBuf], family_name: &str, row_id: i64, col_name: &str
     Ok(())
 }
 
-fn cmd_parse(file: &PathBuf) -> da_core::Result<()> {
-    let table = parse_csv(file)?;
+fn cmd_provenance(roots: &[PathBuf], family_name: &str) -> da_core::Result<()> {
+    let scan_result = scan_directory(roots)?;
 
-    println!("File: {}", file.display());
-    println!("Columns: {}", table.column_count());
-    println!("Rows: {}", table.row_count());
-    println!();
+    let family = scan_result
+        .find_family_ci(family_name)?
+        .ok_or_else(|| da_core::Error::FamilyNotFound(family_name.to_string()))?;
+
+    let merged = merge(family)?;
+    let counts = merged.source_contribution();
+
+    println!("Family: {}", merged.family_name);
+    println!("Cells won per file (merge order):");
+    for (i, source) in merged.sources.iter().enumerate() {
+        let marker = if counts[source] == 0 { " <-- contributes nothing" } else { "" };
+        println!("  {}. {}: {}{}", i + 1, source.display(), counts[source], marker);
+    }
+
+    let unused = merged.unused_sources();
+    if !unused.is_empty() {
+        println!("\n{} files could be removed without changing the merge", unused.len());
+    }
 
-    // Print header
-    let header: Vec<&str> = table.columns.iter().map(|c| c.name.as_str()).collect();
+    Ok(())
+}
+
+/// Print a header and rows, tab-separated for piping or aligned for reading
+fn print_rows(header: &[String], rows: &[Vec<String>], pretty: bool, max_width: Option<usize>) {
+    if pretty {
+        print!("{}", render_aligned(header, rows, max_width));
+        return;
+    }
     println!("{}", header.join("\t"));
     println!("{}", "-".repeat(header.len() * 12));
-
-    // Print first 10 rows
-    for row in table.rows.iter().take(10) {
-        let values: Vec<String> = row.cells.iter().map(|c| c.to_string_value()).collect();
+    for values in rows {
         println!("{}", values.join("\t"));
     }
+}
+
+fn cmd_parse(file: &PathBuf, pretty: bool, max_width: Option<usize>) -> da_core::Result<()> {
+    let table = parse_csv_with_options(file, parse_options())?;
+
+    println!("File: {}", file.display());
+    println!("Columns: {}", table.column_count());
+    println!("Rows: {}", table.row_count());
+    println!();
+
+    // Print header and first 10 rows
+    let header: Vec<String> = table.columns.iter().map(|c| c.name.clone()).collect();
+    let rows: Vec<Vec<String>> = table
+        .rows
+        .iter()
+        .take(10)
+        .map(|row| row.cells.iter().map(|c| c.to_string_value()).collect())
+        .collect();
+    print_rows(&header, &rows, pretty, max_width);
 
     if table.row_count() > 10 {
         println!("... ({} more rows)", table.row_count() - 10);
     }
 
+    for warning in &table.warnings {
+        let line = warning.line.map(|l| format!(":{}", l)).unwrap_or_default();
+        eprintln!("Warning: {}{}: {}", table.source_path.display(), line, warning.message);
+    }
+    for warning in &table.parse_warnings {
+        let line = warning.line.map(|l| format!(":{}", l)).unwrap_or_default();
+        eprintln!(
+            "Warning: {}{}: skipped row '{}': {}",
+            table.source_path.display(),
+            line,
+            warning.raw,
+            warning.message
+        );
+    }
+
     Ok(())
 }
 
-fn cmd_patch(
+/// Load a patch, merge its family, and print what applying it would change
+fn preview_patch(
     roots: &[PathBuf],
     patch_path: &PathBuf,
-    output_dir: &PathBuf,
-    history_file: Option<&PathBuf>,
-) -> da_core::Result<()> {
+) -> da_core::Result<(PatchFile, ResolvedTable, PatchResult)> {
     // Load the patch file
     let patch = PatchFile::load(patch_path)?;
-    println!("Loaded patch for family '{}' with {} edits", patch.family, patch.edits.len());
-
-    // Scan and find the family
     let scan_result = scan_directory(roots)?;
+    let (merged, preview) = preview_family_patch(&scan_result, &patch)?;
+    Ok((patch, merged, preview))
+}
+
+/// Merge a patch's family and print which edits fail and which files change
+fn preview_family_patch(
+    scan_result: &ScanResult,
+    patch: &PatchFile,
+) -> da_core::Result<(ResolvedTable, PatchResult)> {
+    println!(
+        "Loaded patch for family '{}' with {} edits and {} row operations",
+        patch.family,
+        patch.edits.len(),
+        patch.row_ops.len()
+    );
+
     let family = scan_result
         .find_family(&patch.family)
         .ok_or_else(|| da_core::Error::FamilyNotFound(patch.family.clone()))?;
 
     // Merge the family
-    let merged = merge_family(family)?;
+    let merged = merge(family)?;
     println!("Merged {} rows from {} source files", merged.rows.len(), merged.sources.len());
 
     // Preview which files will be affected
-    let preview = apply_patch(&merged, &patch)?;
+    let preview = apply_patch(&merged, patch)?;
+
+    for (bulk, count) in &preview.bulk_matches {
+        println!("Bulk edit on '{}' (/{}/) matched {} rows", bulk.column, bulk.match_regex, count);
+    }
+    if preview.unmatched_bulk_edits().next().is_some() {
+        println!("\nWarning: some bulk edits matched no rows");
+    }
 
     if !preview.failed_edits.is_empty() {
         println!("\nWarning: {} edits could not be applied:", preview.failed_edits.len());
@@ -507,39 +1539,114 @@ fn cmd_patch(
         }
     }
 
-    if preview.modified_sources.is_empty() {
-        println!("\nNo files to modify.");
-        return Ok(());
+    if !preview.failed_row_ops.is_empty() {
+        println!("\nWarning: {} row operations could not be applied:", preview.failed_row_ops.len());
+        for (op, reason) in &preview.failed_row_ops {
+            println!("  - Row {}: {}", op.row_id(), reason);
+        }
     }
 
-    println!("\nFiles to be modified:");
-    for (source, row_ids) in &preview.modified_sources {
-        println!("  {} ({} edits)", source.display(), row_ids.len());
+    if !preview.failed_column_ops.is_empty() {
+        println!(
+            "\nWarning: {} column operations could not be applied:",
+            preview.failed_column_ops.len()
+        );
+        for (op, reason) in &preview.failed_column_ops {
+            println!("  - {}: {}", column_op_name(op), reason);
+        }
     }
 
-    // Export with edits
-    let result = export_with_edits(&merged, &patch, output_dir)?;
+    if !preview.modified_sources.is_empty() {
+        println!("\nFiles to be modified:");
+        for (source, row_ids) in &preview.modified_sources {
+            println!("  {} ({} edits)", source.display(), row_ids.len());
+        }
+    }
 
-    println!("\nExport complete:");
-    println!("  {} files written to {}", result.files_written.len(), output_dir.display());
+    Ok((merged, preview))
+}
+
+fn print_export_result(result: &ExportResult) {
     println!("  {} edits applied", result.edits_applied);
+    println!("  {} rows deleted", result.rows_deleted);
+    println!("  {} rows inserted", result.rows_inserted);
 
     for path in &result.files_written {
         println!("  - {}", path.display());
     }
 
+    if !result.skipped_edits.is_empty() {
+        println!("\nSkipped edits:");
+        for (edit, reason) in &result.skipped_edits {
+            println!("  Row {}, {}: {}", edit.row_id, edit.column, reason);
+        }
+    }
+
     if !result.errors.is_empty() {
         println!("\nErrors:");
         for (path, err) in &result.errors {
             println!("  {}: {}", path.display(), err);
         }
     }
+}
+
+fn cmd_patch(
+    roots: &[PathBuf],
+    patch_path: &PathBuf,
+    output_dir: &PathBuf,
+    history_file: Option<&PathBuf>,
+    options: &ExportOptions,
+) -> da_core::Result<()> {
+    let patches = MultiPatchFile::load(patch_path)?;
+    let scan_result = scan_directory(roots)?;
+
+    let mut any_modified = false;
+    let mut merged_tables = Vec::new();
+    for patch in &patches.patches {
+        let (merged, preview) = preview_family_patch(&scan_result, patch)?;
+        any_modified |= !preview.modified_sources.is_empty();
+        merged_tables.push(merged);
+    }
+    if !any_modified {
+        println!("\nNo files to modify.");
+        return Ok(());
+    }
+
+    // Export with edits
+    let result = export_multi_with_edits(&scan_result, &patches, output_dir, options)?;
+
+    if options.dry_run {
+        let mut files: Vec<_> = result.dry_run_files.iter().collect();
+        files.sort();
+        for (path, contents) in files {
+            println!("\n--- {} ---", path.display());
+            print!("{}", contents);
+        }
+        println!("\nDry run: {} files would be written", result.dry_run_files.len());
+        print_export_result(&result);
+        if !result.errors.is_empty() {
+            return Err(da_core::Error::Export(format!(
+                "{} files could not be built",
+                result.errors.len()
+            )));
+        }
+        return Ok(());
+    }
+
+    println!("\nExport complete:");
+    println!("  {} files written to {}", result.files_written.len(), output_dir.display());
+    print_export_result(&result);
 
-    // Record in history if history file specified
+    // Record each family in history if history file specified
     if let Some(hist_path) = history_file {
         let mut history = HistoryFile::load(hist_path)?;
-        let entry = create_history_entry(&patch, result.files_written, output_dir.clone());
-        history.add_entry(entry);
+        for (patch, merged) in patches.patches.iter().zip(&merged_tables) {
+            let files = family_files(&scan_result, &patch.family, &result.files_written);
+            if files.is_empty() {
+                continue;
+            }
+            history.add_entry(create_history_entry(merged, patch, files, output_dir.clone()));
+        }
         history.save(hist_path)?;
         println!("\nRecorded in history: {}", hist_path.display());
     }
@@ -547,25 +1654,146 @@ fn cmd_patch(
     Ok(())
 }
 
-fn cmd_batch(batch_path: &PathBuf) -> da_core::Result<()> {
-    let batch = BatchFile::load(batch_path)?;
+/// The written files that came from a family's source files
+fn family_files(scan_result: &ScanResult, family_name: &str, written: &[PathBuf]) -> Vec<PathBuf> {
+    let Some(family) = scan_result.find_family(family_name) else {
+        return Vec::new();
+    };
+    written
+        .iter()
+        .filter(|path| {
+            family
+                .members
+                .iter()
+                .any(|m| m.path.file_name() == path.file_name())
+        })
+        .cloned()
+        .collect()
+}
 
-    println!("Running batch with {} patch files", batch.patches.len());
-    println!("Roots: {:?}", batch.roots);
-    println!("Output: {}", batch.output_dir.display());
-    println!();
+/// Apply a patch directly to the source files
+///
+/// Refuses to write anything if part of the patch fails validation, and
+/// asks for confirmation unless `yes` is set. The history entry carries the
+/// reverse patch so the edit can be undone.
+fn cmd_patch_in_place(
+    roots: &[PathBuf],
+    patch_path: &PathBuf,
+    history_path: &Path,
+    yes: bool,
+    options: &ExportOptions,
+) -> da_core::Result<()> {
+    let (patch, merged, preview) = preview_patch(roots, patch_path)?;
+
+    let failed_ops = preview.failed_row_ops.len() + preview.failed_column_ops.len();
+    if !preview.failed_edits.is_empty() || failed_ops > 0 {
+        return Err(da_core::Error::Export(format!(
+            "{} edits and {} operations failed validation; no source files were changed",
+            preview.failed_edits.len(),
+            failed_ops
+        )));
+    }
 
-    // Scan once for all patches
-    let scan_result = scan_directory(&batch.roots)?;
+    if preview.modified_sources.is_empty() && patch.column_ops.is_empty() {
+        println!("\nNo files to modify.");
+        return Ok(());
+    }
+
+    if !yes {
+        let question = format!(
+            "\nOverwrite {} source files in place?",
+            preview.modified_sources.len()
+        );
+        if !confirm(&question)? {
+            println!("Aborted; no files were changed.");
+            return Ok(());
+        }
+    }
+
+    let reverse = invert_patch(&merged, &patch);
+    let result = apply_in_place(&merged, &patch, options)?;
+
+    println!("\nIn-place patch complete:");
+    println!("  {} files overwritten", result.files_written.len());
+    if options.backup {
+        println!("  {} backups written", result.backups.len());
+    }
+    print_export_result(&result);
+
+    let mut history = HistoryFile::load(history_path)?;
+    let output_dir = roots.first().cloned().unwrap_or_default();
+    let mut entry = create_history_entry(&merged, &patch, result.files_written, output_dir);
+    entry.reverse = Some(reverse);
+    history.add_entry(entry);
+    history.save(history_path)?;
+    println!("\nRecorded in history: {}", history_path.display());
+
+    Ok(())
+}
+
+/// Base export options: the fidelity preset, or the defaults
+fn export_options(fidelity: bool) -> ExportOptions {
+    let options = if fidelity {
+        ExportOptions::fidelity()
+    } else {
+        ExportOptions::default()
+    };
+    ExportOptions {
+        parse: parse_options().clone(),
+        ..options
+    }
+}
+
+/// Resolve the `--crlf` flag
+fn line_ending(crlf: bool) -> LineEnding {
+    if crlf {
+        LineEnding::Crlf
+    } else {
+        LineEnding::Lf
+    }
+}
+
+/// Ask a yes/no question on the terminal; fails when stdin isn't interactive
+fn confirm(question: &str) -> da_core::Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Err(da_core::Error::Export(
+            "confirmation required; pass --yes to run non-interactively".to_string(),
+        ));
+    }
+
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn cmd_batch(batch_path: &PathBuf, force: bool) -> da_core::Result<()> {
+    let batch = BatchFile::load(batch_path)?;
+    let options = ExportOptions {
+        overwrite: force,
+        ..export_options(false)
+    };
+
+    println!("Running batch with {} patch files", batch.patches.len());
+    println!("Roots: {:?}", batch.roots);
+    println!("Output: {}", batch.output_dir.display());
+    println!();
+
+    // Scan once for all patches
+    let scan_result = scan_directory(&batch.roots)?;
 
     let mut total_edits = 0;
     let mut total_files = 0;
     let mut errors = Vec::new();
 
-    for patch_path in &batch.patches {
+    for entry in &batch.patches {
+        let patch_path = entry.patch().to_path_buf();
+        let output_dir = entry.output_dir(&batch.output_dir);
         println!("Processing patch: {}", patch_path.display());
 
-        let patch = match PatchFile::load(patch_path) {
+        let patch = match PatchFile::load(&patch_path) {
             Ok(p) => p,
             Err(e) => {
                 errors.push((patch_path.clone(), e.to_string()));
@@ -581,7 +1809,7 @@ fn cmd_batch(batch_path: &PathBuf) -> da_core::Result<()> {
             }
         };
 
-        let merged = match merge_family(family) {
+        let merged = match merge(family) {
             Ok(m) => m,
             Err(e) => {
                 errors.push((patch_path.clone(), e.to_string()));
@@ -589,14 +1817,30 @@ fn cmd_batch(batch_path: &PathBuf) -> da_core::Result<()> {
             }
         };
 
-        match export_with_edits(&merged, &patch, &batch.output_dir) {
-            Ok(result) => {
-                total_edits += result.edits_applied;
-                total_files += result.files_written.len();
-                println!("  Applied {} edits, wrote {} files", result.edits_applied, result.files_written.len());
-            }
+        let result = match export_with_edits_with_options(&merged, &patch, output_dir, &options) {
+            Ok(result) => result,
             Err(e) => {
                 errors.push((patch_path.clone(), e.to_string()));
+                continue;
+            }
+        };
+        total_edits += result.edits_applied;
+        total_files += result.files_written.len();
+        println!(
+            "  Applied {} edits, wrote {} files",
+            result.edits_applied,
+            result.files_written.len()
+        );
+
+        if let Some(history_path) = entry.history_file() {
+            let recorded = HistoryFile::load(history_path).and_then(|mut history| {
+                let files = result.files_written;
+                history.add_entry(create_history_entry(&merged, &patch, files, output_dir.into()));
+                history.save(history_path)
+            });
+            match recorded {
+                Ok(()) => println!("  Recorded in history: {}", history_path.display()),
+                Err(e) => errors.push((patch_path.clone(), e.to_string())),
             }
         }
     }
@@ -616,8 +1860,54 @@ fn cmd_batch(batch_path: &PathBuf) -> da_core::Result<()> {
     Ok(())
 }
 
-fn cmd_create_patch(family: &str, output: &PathBuf, examples: &[String]) -> da_core::Result<()> {
-    let mut patch = PatchFile::new(family);
+/// Save the reverse of a patch, computed against the unpatched sources
+///
+/// Multi-family patches produce a multi-family reverse patch.
+fn cmd_invert(roots: &[PathBuf], patch_path: &Path, output: &Path) -> da_core::Result<()> {
+    let patches = MultiPatchFile::load(patch_path)?;
+    let scan_result = scan_directory(roots)?;
+
+    let mut reverse = MultiPatchFile::default();
+    for patch in &patches.patches {
+        let family = scan_result
+            .find_family(&patch.family)
+            .ok_or_else(|| da_core::Error::FamilyNotFound(patch.family.clone()))?;
+        let inverse = invert_patch(&merge(family)?, patch);
+        println!(
+            "{}: {} edits and {} row operations",
+            inverse.family,
+            inverse.edits.len(),
+            inverse.row_ops.len()
+        );
+        reverse.patches.push(inverse);
+    }
+
+    match reverse.patches.as_slice() {
+        [single] => single.save(output)?,
+        _ => reverse.save(output)?,
+    }
+    println!("Created reverse patch: {}", output.display());
+
+    Ok(())
+}
+
+fn cmd_create_patch(
+    mut patch: PatchFile,
+    output: &PathBuf,
+    examples: &[String],
+    roots: &[PathBuf],
+) -> da_core::Result<()> {
+    let family = patch.family.clone();
+
+    let merged = if roots.is_empty() {
+        None
+    } else {
+        let scan_result = scan_directory(roots)?;
+        let family = scan_result
+            .find_family_ci(&family)?
+            .ok_or_else(|| da_core::Error::FamilyNotFound(family.clone()))?;
+        Some(merge(family)?)
+    };
 
     // Parse example edits: "row_id:column:value"
     for example in examples {
@@ -635,7 +1925,19 @@ fn cmd_create_patch(family: &str, output: &PathBuf, examples: &[String]) -> da_c
             }
         };
 
-        patch.add_edit(Edit::new(row_id, parts[1], parts[2]));
+        let mut edit = Edit::new(row_id, parts[1], parts[2]);
+        let current = merged.as_ref().and_then(|merged| {
+            let col = merged.find_column(parts[1])?;
+            Some(merged.find_row(row_id)?.cells[col.index].value.to_string_value())
+        });
+        match current {
+            Some(current) => edit = edit.with_expected_old(current),
+            None if merged.is_some() => {
+                eprintln!("Warning: no cell at row {}, column '{}'", row_id, parts[1]);
+            }
+            None => {}
+        }
+        patch.add_edit(edit);
     }
 
     // If no examples provided, add a placeholder
@@ -654,11 +1956,11 @@ fn cmd_create_patch(family: &str, output: &PathBuf, examples: &[String]) -> da_c
     Ok(())
 }
 
-fn cmd_create_batch(output: &PathBuf, roots: &[PathBuf], export_dir: &PathBuf) -> da_core::Result<()> {
+fn cmd_create_batch(output: &PathBuf, roots: &[PathBuf], export_dir: &Path) -> da_core::Result<()> {
     let batch = BatchFile {
         roots: roots.to_vec(),
-        output_dir: export_dir.clone(),
-        patches: vec![PathBuf::from("patch1.json"), PathBuf::from("patch2.json")],
+        output_dir: export_dir.to_path_buf(),
+        patches: vec![PathBuf::from("patch1.json").into(), PathBuf::from("patch2.json").into()],
     };
 
     batch.save(output)?;
@@ -670,13 +1972,29 @@ fn cmd_create_batch(output: &PathBuf, roots: &[PathBuf], export_dir: &PathBuf) -
     Ok(())
 }
 
-/// Escape a value for CSV output
-fn escape_csv(s: &str) -> String {
-    if s.contains(',') || s.contains('"') || s.contains('\n') {
-        format!("\"{}\"", s.replace('"', "\"\""))
-    } else {
-        s.to_string()
+/// Print each merged cell containing `pattern` as family:row:column: value
+fn cmd_grep(
+    roots: &[PathBuf],
+    pattern: &str,
+    options: &ValueSearchOptions,
+) -> da_core::Result<()> {
+    let scan_result = scan_directory(roots)?;
+    let hits = search_values(&scan_result, pattern, options)?;
+
+    if hits.is_empty() {
+        println!("No values found matching '{}'", pattern);
+        return Ok(());
     }
+
+    for hit in &hits {
+        let row = hit.row_id.map(|id| id.to_string()).unwrap_or_else(|| "-".to_string());
+        println!("{}:{}:{}: {}", hit.family, row, hit.column, hit.value.to_string_value());
+    }
+    if options.limit == Some(hits.len()) {
+        println!("(stopped after {} matches)", hits.len());
+    }
+
+    Ok(())
 }
 
 fn cmd_search(roots: &[PathBuf], pattern: &str) -> da_core::Result<()> {
@@ -711,67 +2029,86 @@ fn cmd_search(roots: &[PathBuf], pattern: &str) -> da_core::Result<()> {
     Ok(())
 }
 
+/// What `filter` matches rows against
+enum FilterQuery {
+    /// One column's value, as a substring or compared with `op`
+    Column {
+        column: String,
+        value: String,
+        op: Option<String>,
+    },
+    /// A query string parsed by `FilterExpr::parse`
+    Expr(String),
+}
+
+impl FilterQuery {
+    /// Build the filter expression and describe it for the output
+    fn to_expr(&self) -> da_core::Result<(FilterExpr, String)> {
+        match self {
+            FilterQuery::Column { column, value, op: Some(op) } => {
+                let number = value.trim().parse().map_err(|_| da_core::Error::TypeMismatch {
+                    column: column.clone(),
+                    value: value.clone(),
+                    expected: da_core::ColumnType::Float,
+                })?;
+                let expr = FilterExpr::Compare {
+                    column: column.clone(),
+                    op: op.parse()?,
+                    value: number,
+                };
+                Ok((expr, format!("{} {} {}", column, op.trim(), value)))
+            }
+            FilterQuery::Column { column, value, op: None } => {
+                let expr = FilterExpr::Contains {
+                    column: column.clone(),
+                    value: value.clone(),
+                };
+                Ok((expr, format!("{} contains '{}'", column, value)))
+            }
+            FilterQuery::Expr(query) => Ok((FilterExpr::parse(query)?, query.clone())),
+        }
+    }
+}
+
 fn cmd_filter(
     roots: &[PathBuf],
     family_name: &str,
-    column: &str,
-    value: &str,
+    query: &FilterQuery,
     limit: Option<usize>,
+    pretty: bool,
+    max_width: Option<usize>,
 ) -> da_core::Result<()> {
     let scan_result = scan_directory(roots)?;
 
     let family = scan_result
-        .find_family(family_name)
+        .find_family_ci(family_name)?
         .ok_or_else(|| da_core::Error::FamilyNotFound(family_name.to_string()))?;
 
-    let merged = merge_family(family)?;
-
-    // Find the column
-    let col = merged
-        .find_column(column)
-        .ok_or_else(|| da_core::Error::InvalidFamilyName(format!("column '{}' not found", column)))?;
-
-    let value_lower = value.to_lowercase();
+    let merged = merge(family)?;
 
-    // Filter rows where the column value contains the search string
+    let (expr, condition) = query.to_expr()?;
     let matching_rows: Vec<_> = merged
-        .rows
-        .iter()
-        .filter(|row| {
-            row.cells
-                .get(col.index)
-                .map(|c| c.value.to_string_value().to_lowercase().contains(&value_lower))
-                .unwrap_or(false)
-        })
+        .filter_expr(&expr)?
+        .into_iter()
+        .map(|index| &merged.rows[index])
         .collect();
 
     if matching_rows.is_empty() {
-        println!("No rows found where {} contains '{}'", column, value);
+        println!("No rows found where {}", condition);
         return Ok(());
     }
 
-    println!(
-        "Found {} rows where {} contains '{}':\n",
-        matching_rows.len(),
-        column,
-        value
-    );
+    println!("Found {} rows where {}:\n", matching_rows.len(), condition);
 
-    // Print header
-    let header: Vec<&str> = merged.columns.iter().map(|c| c.name.as_str()).collect();
-    println!("{}", header.join("\t"));
-    println!("{}", "-".repeat(header.len() * 12));
-
-    // Print matching rows
+    // Print header and matching rows
+    let header: Vec<String> = merged.columns.iter().map(|c| c.name.clone()).collect();
     let row_limit = limit.unwrap_or(matching_rows.len());
-    for row in matching_rows.iter().take(row_limit) {
-        let values: Vec<String> = row
-            .cells
-            .iter()
-            .map(|c| c.value.to_string_value())
-            .collect();
-        println!("{}", values.join("\t"));
-    }
+    let rows: Vec<Vec<String>> = matching_rows
+        .iter()
+        .take(row_limit)
+        .map(|row| row.cells.iter().map(|c| c.value.to_string_value()).collect())
+        .collect();
+    print_rows(&header, &rows, pretty, max_width);
 
     if matching_rows.len() > row_limit {
         println!("... ({} more rows)", matching_rows.len() - row_limit);
@@ -780,62 +2117,159 @@ fn cmd_filter(
     Ok(())
 }
 
-fn cmd_validate(roots: &[PathBuf], patch_path: &PathBuf) -> da_core::Result<()> {
+fn cmd_stats(roots: &[PathBuf], family_name: &str, column: &str) -> da_core::Result<()> {
+    let scan_result = scan_directory(roots)?;
+
+    let family = scan_result
+        .find_family_ci(family_name)?
+        .ok_or_else(|| da_core::Error::FamilyNotFound(family_name.to_string()))?;
+
+    let merged = merge(family)?;
+
+    let col = merged
+        .find_column_ci(column)?
+        .ok_or_else(|| da_core::Error::ColumnNotFound(column.to_string()))?;
+    let stats = merged.column_stats(col.index);
+
+    println!("Family: {}", merged.family_name);
+    println!("Column: {}", col.name);
+    println!("Rows: {}", merged.rows.len());
+    println!("  Integers: {}", stats.integers);
+    println!("  Floats:   {}", stats.floats);
+    println!("  Strings:  {}", stats.strings);
+    println!("  Empty:    {}", stats.empty);
+
+    match (stats.min, stats.max, stats.mean, stats.median) {
+        (Some(min), Some(max), Some(mean), Some(median)) => {
+            println!("Min:    {}", min);
+            println!("Max:    {}", max);
+            println!("Mean:   {:.4}", mean);
+            println!("Median: {}", median);
+        }
+        _ => println!("No numeric values"),
+    }
+
+    Ok(())
+}
+
+fn cmd_columns(roots: &[PathBuf], family_name: &str, decimal: bool) -> da_core::Result<()> {
+    let scan_result = scan_directory(roots)?;
+
+    let family = scan_result
+        .find_family_ci(family_name)?
+        .ok_or_else(|| da_core::Error::FamilyNotFound(family_name.to_string()))?;
+
+    let merged = merge(family)?;
+    let names = merged.column_names();
+    let width = names.len().saturating_sub(1).to_string().len();
+
+    println!("Family: {} ({} columns)", merged.family_name, names.len());
+    for (index, name) in names.iter().enumerate() {
+        let label = CellValue::parse_hex(name).and_then(|v| v.as_hex());
+        match label {
+            Some(id) if decimal => println!("  {:>width$}  {} ({})", index, name, id),
+            _ => println!("  {:>width$}  {}", index, name),
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate a patch, returning whether it can be applied
+fn cmd_validate(
+    roots: &[PathBuf],
+    patch_path: &PathBuf,
+    check_types: bool,
+    json: bool,
+) -> da_core::Result<bool> {
     // Load the patch file
     let patch = PatchFile::load(patch_path)?;
-    println!("Validating patch for family '{}' with {} edits\n", patch.family, patch.edits.len());
+    if !json {
+        println!(
+            "Validating patch for family '{}' with {} edits and {} row operations\n",
+            patch.family,
+            patch.edits.len(),
+            patch.row_ops.len()
+        );
+    }
 
     // Scan and find the family
     let scan_result = scan_directory(roots)?;
     let family = match scan_result.find_family(&patch.family) {
         Some(f) => f,
+        None if json => return Err(da_core::Error::FamilyNotFound(patch.family.clone())),
         None => {
             println!("INVALID: Family '{}' not found", patch.family);
-            return Ok(());
+            return Ok(false);
         }
     };
 
     // Merge the family
-    let merged = merge_family(family)?;
-
-    // Validate each edit
-    let mut valid_count = 0;
-    let mut invalid_count = 0;
-
-    for edit in &patch.edits {
-        let row_exists = merged.rows.iter().any(|r| r.id == Some(edit.row_id));
-        let col_exists = merged.columns.iter().any(|c| c.name == edit.column);
-
-        if !row_exists {
-            println!("INVALID: Row ID {} not found", edit.row_id);
-            invalid_count += 1;
-        } else if !col_exists {
-            println!("INVALID: Column '{}' not found (row {})", edit.column, edit.row_id);
-            invalid_count += 1;
-        } else {
-            // Find provenance
-            if let Some(row) = merged.find_row(edit.row_id) {
-                if let Some(col) = merged.find_column(&edit.column) {
-                    let source = &row.cells[col.index].source;
-                    let current = &row.cells[col.index].value;
-                    println!(
-                        "OK: Row {}, {} = '{}' -> '{}' (source: {})",
-                        edit.row_id,
-                        edit.column,
-                        current,
-                        edit.value,
-                        source.file_name().unwrap_or_default().to_string_lossy()
-                    );
-                }
+    let merged = merge(family)?;
+
+    let report = validate_patch(&merged, &patch, check_types);
+    if json {
+        let value = validation_json(&patch.family, &report)?;
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(report.is_valid());
+    }
+
+    for (edit, result) in patch.edits.iter().zip(&report.edits) {
+        if let Some(error) = &result.error {
+            println!("INVALID: {} (row {}, column '{}')", error, edit.row_id, edit.column);
+            continue;
+        }
+
+        // Find provenance
+        let cell = (merged.find_row(edit.row_id), merged.find_column(&edit.column));
+        if let (Some(row), Some(col)) = cell {
+            let source = &row.cells[col.index].source;
+            let current = &row.cells[col.index].value;
+            println!(
+                "OK: Row {}, {} = '{}' -> '{}' (source: {})",
+                edit.row_id,
+                edit.column,
+                current,
+                edit.value,
+                source.file_name().unwrap_or_default().to_string_lossy()
+            );
+        }
+        if let Some(ty) = result.type_mismatch {
+            println!(
+                "WARNING: Row {}, {} = '{}' does not fit the column's {:?} type",
+                edit.row_id, edit.column, edit.value, ty
+            );
+        }
+    }
+
+    for (op, result) in patch.row_ops.iter().zip(&report.row_ops) {
+        match (op, &result.error) {
+            (_, Some(error)) => println!("INVALID: {} ({})", error, row_op_name(op)),
+            (RowOp::DeleteRow { row_id }, None) => println!("OK: Delete row {}", row_id),
+            (RowOp::InsertRow { row_id, values, .. }, None) => {
+                println!("OK: Insert row {} ({} values)", row_id, values.len())
             }
-            valid_count += 1;
         }
     }
 
+    for (op, result) in patch.column_ops.iter().zip(&report.column_ops) {
+        match (op, &result.error) {
+            (_, Some(error)) => println!("INVALID: {} ({})", error, column_op_name(op)),
+            (ColumnOp::Rename { from, to }, None) => println!("OK: Rename {} -> {}", from, to),
+            (ColumnOp::Reorder { order }, None) => println!("OK: Reorder {}", order.join(",")),
+        }
+    }
+
+    let invalid_count = report.error_count();
+    let valid_count = report.checked_count() - invalid_count;
+
     println!();
     println!("Validation complete:");
     println!("  {} valid edits", valid_count);
     println!("  {} invalid edits", invalid_count);
+    if check_types {
+        println!("  {} type warnings", report.warning_count());
+    }
 
     if invalid_count > 0 {
         println!("\nPatch has errors and cannot be applied cleanly.");
@@ -843,6 +2277,204 @@ fn cmd_validate(roots: &[PathBuf], patch_path: &PathBuf) -> da_core::Result<()>
         println!("\nPatch is valid and ready to apply.");
     }
 
+    Ok(invalid_count == 0)
+}
+
+/// Round-trip a file through the parser and writer, returning whether every
+/// cell survived
+fn cmd_verify(path: &Path) -> da_core::Result<bool> {
+    let report = verify_roundtrip(path)?;
+
+    for warning in &report.warnings {
+        let line = warning.line.map(|l| format!(":{}", l)).unwrap_or_default();
+        println!("WARNING: {}{}: {}", path.display(), line, warning.message);
+    }
+    for difference in &report.differences {
+        let row = match difference.row_id {
+            Some(id) => format!("Row {}", id),
+            None => format!("Row #{}", difference.row + 1),
+        };
+        println!(
+            "CHANGED: {}, {}: '{}' -> '{}'",
+            row, difference.column, difference.before, difference.after
+        );
+    }
+
+    println!();
+    println!("Bytes: {}", if report.bytes_match { "identical" } else { "different" });
+    println!("Changed cells: {}", report.differences.len());
+    if report.cells_match() && !report.bytes_match {
+        println!("\nOnly quoting or line endings differ; every cell reads back the same.");
+    }
+
+    Ok(report.cells_match())
+}
+
+/// The validation report as JSON, with the family, the counts and the
+/// overall result added, and a `valid` flag on every edit and operation
+fn validation_json(family: &str, report: &ValidationReport) -> da_core::Result<serde_json::Value> {
+    let mut value = serde_json::to_value(report)?;
+    if let serde_json::Value::Object(object) = &mut value {
+        for key in ["edits", "row_ops", "column_ops"] {
+            let Some(serde_json::Value::Array(items)) = object.get_mut(key) else {
+                continue;
+            };
+            for item in items.iter_mut().filter_map(|i| i.as_object_mut()) {
+                let valid = !item.contains_key("error");
+                item.insert("valid".to_string(), valid.into());
+            }
+        }
+        object.insert("family".to_string(), family.into());
+        object.insert("valid".to_string(), report.is_valid().into());
+        object.insert("checked".to_string(), report.checked_count().into());
+        object.insert("invalid".to_string(), report.error_count().into());
+        object.insert("warnings".to_string(), report.warning_count().into());
+    }
+    Ok(value)
+}
+
+/// Short description of a row operation for validation messages
+fn row_op_name(op: &RowOp) -> &'static str {
+    match op {
+        RowOp::DeleteRow { .. } => "delete",
+        RowOp::InsertRow { .. } => "insert",
+    }
+}
+
+fn column_op_name(op: &ColumnOp) -> &'static str {
+    match op {
+        ColumnOp::Rename { .. } => "rename",
+        ColumnOp::Reorder { .. } => "reorder",
+    }
+}
+
+fn cmd_check(roots: &[PathBuf], family_name: Option<&str>) -> da_core::Result<()> {
+    let scan_result = scan_directory(roots)?;
+
+    let families: Vec<&Family> = match family_name {
+        Some(name) => vec![scan_result
+            .find_family_ci(name)?
+            .ok_or_else(|| da_core::Error::FamilyNotFound(name.to_string()))?],
+        None => scan_result.families.iter().collect(),
+    };
+
+    let mut with_problems = 0;
+    for family in &families {
+        let problems = check_family(family)?;
+        if problems.is_empty() {
+            println!("{}: OK", family.name);
+            continue;
+        }
+
+        with_problems += 1;
+        println!("{}: {} problems", family.name, problems.len());
+        for problem in &problems {
+            println!("  {}", problem);
+        }
+    }
+
+    println!("\n{} families checked, {} with problems", families.len(), with_problems);
+
+    Ok(())
+}
+
+/// Describe each problem found while merging a family
+fn check_family(family: &Family) -> da_core::Result<Vec<String>> {
+    let (_, report) = merge_with_report(family)?;
+    let file_names = |paths: &[PathBuf]| -> String {
+        paths
+            .iter()
+            .map(|p| p.file_name().unwrap_or_default().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut problems = Vec::new();
+    if let Some(mismatch) = &report.id_column_mismatch {
+        problems.push(format!(
+            "ID column is keyed in {} but not in {}",
+            file_names(&mismatch.keyed),
+            file_names(&mismatch.unkeyed)
+        ));
+    }
+    for divergence in &report.type_divergences {
+        let types: Vec<String> = divergence
+            .types
+            .iter()
+            .map(|(path, ty)| format!("{:?} in {}", ty, path.file_name().unwrap_or_default().to_string_lossy()))
+            .collect();
+        problems.push(format!("column '{}' has mixed types: {}", divergence.column, types.join(", ")));
+    }
+    for warning in &report.warnings {
+        problems.push(match warning {
+            MergeWarning::DuplicateColumn { source, column, indices } => format!(
+                "column '{}' appears {} times in {}; only the last is merged",
+                column,
+                indices.len(),
+                file_names(std::slice::from_ref(source))
+            ),
+            MergeWarning::ColumnMoved { column, base_index, source, index } => format!(
+                "column '{}' is column {} in {} but column {} in the first file",
+                column,
+                index,
+                file_names(std::slice::from_ref(source)),
+                base_index
+            ),
+        });
+    }
+
+    Ok(problems)
+}
+
+fn cmd_diff(left: &[PathBuf], right: &[PathBuf], family_name: &str, json: bool) -> da_core::Result<()> {
+    let mut merged = Vec::new();
+    for roots in [left, right] {
+        let scan_result = scan_directory(roots)?;
+        let family = scan_result
+            .find_family_ci(family_name)?
+            .ok_or_else(|| da_core::Error::FamilyNotFound(family_name.to_string()))?;
+        merged.push(merge(family)?);
+    }
+
+    let diff = diff_tables(&merged[0], &merged[1]);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+
+    if diff.is_empty() {
+        println!("No differences in '{}'", family_name);
+        return Ok(());
+    }
+
+    println!("Diff for '{}':\n", family_name);
+
+    if !diff.added_columns.is_empty() {
+        println!("Columns added: {}", diff.added_columns.join(", "));
+    }
+    if !diff.removed_columns.is_empty() {
+        println!("Columns removed: {}", diff.removed_columns.join(", "));
+    }
+
+    let format_ids = |ids: &[i64]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ");
+    if !diff.added_rows.is_empty() {
+        println!("Rows added ({}): {}", diff.added_rows.len(), format_ids(&diff.added_rows));
+    }
+    if !diff.removed_rows.is_empty() {
+        println!("Rows removed ({}): {}", diff.removed_rows.len(), format_ids(&diff.removed_rows));
+    }
+
+    if !diff.changed_cells.is_empty() {
+        println!("\nChanged cells ({}):", diff.changed_cells.len());
+        for change in &diff.changed_cells {
+            println!(
+                "  Row {}, {}: '{}' -> '{}'",
+                change.row_id, change.column, change.old_value, change.new_value
+            );
+        }
+    }
+
     Ok(())
 }
 
@@ -862,6 +2494,19 @@ fn cmd_history(history_path: &PathBuf, family: Option<&str>) -> da_core::Result<
                     println!("History for '{}' ({} entries):\n", family_name, entries.len());
                     for (i, entry) in entries.iter().enumerate().rev() {
                         println!("{}. {}", i + 1, entry.timestamp.format("%Y-%m-%d %H:%M:%S"));
+                        if let Some(label) = &entry.label {
+                            println!("   Marker: {}", label);
+                        }
+                        if entry.is_marker() {
+                            println!();
+                            continue;
+                        }
+                        if let Some(description) = entry.description() {
+                            println!("   {}", description);
+                        }
+                        if let Some(author) = &entry.patch.author {
+                            println!("   Author: {}", author);
+                        }
                         println!("   {} edits applied", entry.patch.edits.len());
                         println!("   Output: {}", entry.output_dir.display());
                         for file in &entry.output_files {
@@ -883,9 +2528,10 @@ fn cmd_history(history_path: &PathBuf, family: Option<&str>) -> da_core::Result<
                     println!("{}: {} patches applied", family_name, entries.len());
                     if let Some(last) = entries.last() {
                         println!(
-                            "  Last: {} ({} edits)",
+                            "  Last: {} ({} edits){}",
                             last.timestamp.format("%Y-%m-%d %H:%M:%S"),
-                            last.patch.edits.len()
+                            last.patch.edits.len(),
+                            last.description().map(|d| format!(": {}", d)).unwrap_or_default()
                         );
                     }
                 }
@@ -896,62 +2542,680 @@ fn cmd_history(history_path: &PathBuf, family: Option<&str>) -> da_core::Result<
     Ok(())
 }
 
+fn cmd_history_mark(history_path: &PathBuf, family: &str, label: &str) -> da_core::Result<()> {
+    let mut history = HistoryFile::load(history_path)?;
+    history.add_entry(create_marker_entry(family, label));
+    history.save(history_path)?;
+
+    println!("Marked '{}' for '{}' in {}", label, family, history_path.display());
+
+    Ok(())
+}
+
 fn cmd_undo(
     roots: &[PathBuf],
     history_path: &PathBuf,
     family_name: &str,
-    output_dir: &PathBuf,
+    output_dir: &Path,
+    steps: Option<usize>,
+    until: Option<DateTime<Utc>>,
+    force: bool,
 ) -> da_core::Result<()> {
     let mut history = HistoryFile::load(history_path)?;
 
-    let last_entry = match history.get_last_entry(family_name) {
-        Some(entry) => entry.clone(),
+    let target = match until {
+        Some(until) => until,
         None => {
-            println!("No history to undo for family '{}'", family_name);
-            return Ok(());
+            let entries = history.get_family_history(family_name).map_or(&[][..], Vec::as_slice);
+            let keep = entries.len().saturating_sub(steps.unwrap_or(1));
+            match keep {
+                0 => DateTime::<Utc>::MIN_UTC,
+                n => entries[n - 1].timestamp,
+            }
         }
     };
 
-    println!("Undoing last patch for '{}':", family_name);
-    println!("  Applied: {}", last_entry.timestamp.format("%Y-%m-%d %H:%M:%S"));
-    println!("  {} edits to undo\n", last_entry.patch.edits.len());
+    let undone = history.entries_since(family_name, target);
+    if undone.is_empty() {
+        println!("No history to undo for family '{}'", family_name);
+        return Ok(());
+    }
+
+    // Markers changed no files, so undoing only markers just drops them from history
+    if undone.iter().all(|e| e.is_marker()) {
+        let labels: Vec<String> = undone
+            .iter()
+            .rev()
+            .map(|e| format!("'{}'", e.label.as_deref().unwrap_or_default()))
+            .collect();
+        for _ in 0..labels.len() {
+            history.pop_last_entry(family_name);
+        }
+        history.save(history_path)?;
+        println!(
+            "Removed marker {} for '{}'. No files changed.",
+            labels.join(", "),
+            family_name
+        );
+        return Ok(());
+    }
+
+    // Undoing rewrites the patched files, losing anything changed since
+    let drifted = history.drifted_since(family_name, target);
+    if !drifted.is_empty() {
+        if !force {
+            eprintln!("Undoing would overwrite these changes; pass --force to undo anyway.");
+            return Err(da_core::Error::SourceDrift(drifted));
+        }
+        eprintln!("Warning: {}\n", da_core::Error::SourceDrift(drifted));
+    }
+
+    println!("Undoing {} entries for '{}':", undone.len(), family_name);
+    for entry in undone.iter().rev() {
+        match &entry.label {
+            Some(label) if entry.is_marker() => println!(
+                "  {}  marker '{}'",
+                entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                label
+            ),
+            _ => println!(
+                "  {}  {} edits",
+                entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                entry.patch.edits.len()
+            ),
+        }
+    }
+    println!();
 
-    // To undo, we need to re-export the original files (without the patch)
     let scan_result = scan_directory(roots)?;
     let family = scan_result
-        .find_family(family_name)
+        .find_family_ci(family_name)?
         .ok_or_else(|| da_core::Error::FamilyNotFound(family_name.to_string()))?;
 
-    let merged = merge_family(family)?;
+    let result = history.undo_to_with_options(family, target, output_dir, &merge_options())?;
+    history.save(history_path)?;
 
-    // Export the original (unpatched) source files
-    // We need to identify which source files were modified
-    let empty_patch = PatchFile::new(family_name);
-    let _result = export_with_edits(&merged, &empty_patch, output_dir)?;
+    println!("Restored files:");
+    for file in &result.files_written {
+        println!("  {}", file.display());
+    }
+    if !result.errors.is_empty() {
+        println!("\nErrors:");
+        for (path, err) in &result.errors {
+            println!("  {}: {}", path.display(), err);
+        }
+    }
 
-    // Actually, we need to re-export only the files that were modified
-    // For a true undo, let's just copy the original source files
-    println!("Re-exporting original files to {}:", output_dir.display());
+    println!("\nUndo complete. Run `redo` to re-apply the patches.");
 
-    // Use the files that were in the last patch's output
-    for output_file in &last_entry.output_files {
-        if let Some(file_name) = output_file.file_name() {
-            // Find the original source file
-            for member in &family.members {
-                if member.path.file_name() == Some(file_name) {
-                    let dest = output_dir.join(file_name);
-                    std::fs::copy(&member.path, &dest)?;
-                    println!("  Restored: {}", dest.display());
-                }
-            }
+    Ok(())
+}
+
+/// Re-apply the most recently undone patch and move it back into the history
+///
+/// Patches that were applied in place are applied in place again (with
+/// backups); others are re-exported, replacing the restored files.
+fn cmd_redo(
+    roots: &[PathBuf],
+    history_path: &PathBuf,
+    family_name: &str,
+    output_dir: Option<&PathBuf>,
+) -> da_core::Result<()> {
+    let mut history = HistoryFile::load(history_path)?;
+
+    let entry = match history.get_last_redo(family_name) {
+        Some(entry) => entry.clone(),
+        None => {
+            println!("Nothing to redo for family '{}'", family_name);
+            return Ok(());
         }
+    };
+
+    if entry.is_marker() {
+        history.redo_last_entry(family_name);
+        history.save(history_path)?;
+        println!(
+            "Restored marker '{}' for '{}'. No files changed.",
+            entry.label.as_deref().unwrap_or_default(),
+            family_name
+        );
+        return Ok(());
     }
 
-    // Remove the entry from history
-    history.pop_last_entry(family_name);
+    println!("Redoing patch for '{}':", family_name);
+    println!("  Applied: {}", entry.timestamp.format("%Y-%m-%d %H:%M:%S"));
+    println!("  {} edits to re-apply\n", entry.patch.edits.len());
+
+    let scan_result = scan_directory(roots)?;
+    let family = scan_result
+        .find_family_ci(family_name)?
+        .ok_or_else(|| da_core::Error::FamilyNotFound(family_name.to_string()))?;
+    let merged = merge(family)?;
+
+    let result = if entry.reverse.is_some() {
+        let options = ExportOptions {
+            backup: true,
+            ..export_options(false)
+        };
+        apply_in_place(&merged, &entry.patch, &options)?
+    } else {
+        let options = ExportOptions {
+            overwrite: true,
+            ..export_options(false)
+        };
+        let output_dir = output_dir.unwrap_or(&entry.output_dir);
+        export_with_edits_with_options(&merged, &entry.patch, output_dir, &options)?
+    };
+
+    for file in &result.files_written {
+        println!("  Written: {}", file.display());
+    }
+    print_export_result(&result);
+
+    history.redo_last_entry(family_name);
     history.save(history_path)?;
 
-    println!("\nUndo complete. History entry removed.");
+    println!("\nRedo complete. History entry restored.");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Create an empty scratch directory for one test
+    ///
+    /// The process ID and a counter keep parallel tests apart.
+    fn scratch_dir(name: &str) -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir()
+            .join(format!("da-cli-{}-{}-{}", name, std::process::id(), n));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_patch_in_place_with_yes() {
+        let dir = scratch_dir("in-place");
+        let root = dir.join("override");
+        fs::create_dir_all(&root).unwrap();
+
+        let original = "ID,Name,Value\n1,foo,100\n2,bar,200\n";
+        let source = root.join("items.csv");
+        fs::write(&source, original).unwrap();
+
+        let mut patch = PatchFile::new("items");
+        patch.add_edit(Edit::new(2, "Value", "250"));
+        let patch_path = dir.join("patch.json");
+        patch.save(&patch_path).unwrap();
+
+        let history_path = dir.join("history.json");
+        let roots = std::slice::from_ref(&root);
+        let backup = ExportOptions { backup: true, ..Default::default() };
+        cmd_patch_in_place(roots, &patch_path, &history_path, true, &backup).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&source).unwrap(),
+            "ID,Name,Value\n1,foo,100\n2,bar,250\n"
+        );
+        assert_eq!(fs::read_to_string(da_core::backup_path(&source)).unwrap(), original);
+
+        // The recorded reverse patch restores the original file
+        let history = HistoryFile::load(&history_path).unwrap();
+        let entry = history.get_last_entry("items").unwrap();
+        assert_eq!(entry.output_files, vec![source.clone()]);
+        let reverse = entry.reverse.as_ref().unwrap();
+
+        let family = scan_directory(&[&root]).unwrap();
+        let merged = merge(family.find_family("items").unwrap()).unwrap();
+        apply_in_place(&merged, reverse, &ExportOptions::default()).unwrap();
+        assert_eq!(fs::read_to_string(&source).unwrap(), original);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_patch_in_place_refuses_failed_validation() {
+        let dir = scratch_dir("in-place-bad");
+        let root = dir.join("override");
+        fs::create_dir_all(&root).unwrap();
+
+        let original = "ID,Name\n1,foo\n";
+        let source = root.join("items.csv");
+        fs::write(&source, original).unwrap();
+
+        let mut patch = PatchFile::new("items");
+        patch.add_edit(Edit::new(1, "Name", "bar"));
+        patch.add_edit(Edit::new(1, "Missing", "x"));
+        let patch_path = dir.join("patch.json");
+        patch.save(&patch_path).unwrap();
+
+        let history_path = dir.join("history.json");
+        let roots = std::slice::from_ref(&root);
+        let backup = ExportOptions { backup: true, ..Default::default() };
+        assert!(cmd_patch_in_place(roots, &patch_path, &history_path, true, &backup).is_err());
+        assert_eq!(fs::read_to_string(&source).unwrap(), original);
+        assert!(!da_core::backup_path(&source).exists());
+        assert!(!history_path.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_validate_json_report() {
+        let dir = scratch_dir("validate");
+        let root = dir.join("override");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("items.csv"), "ID,Name,Value\n1,foo,100\n").unwrap();
+
+        let mut patch = PatchFile::new("items");
+        patch.add_edit(Edit::new(1, "Value", "high"));
+        patch.add_edit(Edit::new(1, "Missing", "x"));
+        let patch_path = dir.join("patch.json");
+        patch.save(&patch_path).unwrap();
+
+        let roots = std::slice::from_ref(&root);
+        assert!(!cmd_validate(roots, &patch_path, true, true).unwrap());
+
+        let merged = merge(scan_directory(roots).unwrap().find_family("items").unwrap());
+        let report = validate_patch(&merged.unwrap(), &patch, true);
+        let value = validation_json("items", &report).unwrap();
+        assert_eq!(value["family"], "items");
+        assert_eq!(value["valid"], false);
+        assert_eq!((value["checked"].as_u64(), value["invalid"].as_u64()), (Some(2), Some(1)));
+        assert_eq!(value["warnings"], 1);
+        assert_eq!(value["edits"][0]["valid"], true);
+        assert_eq!(value["edits"][0]["type_mismatch"], "integer");
+        assert_eq!(value["edits"][1]["valid"], false);
+        assert_eq!(value["edits"][1]["error"], "Column not found: Missing");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_batch_routes_entries_and_records_history() {
+        let dir = scratch_dir("batch");
+        let root = dir.join("override");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("items.csv"), "ID,Name\n1,foo\n").unwrap();
+        fs::write(root.join("spells.csv"), "ID,Name\n1,fire\n").unwrap();
+
+        let mut items = PatchFile::new("items");
+        items.add_edit(Edit::new(1, "Name", "bar"));
+        items.save(dir.join("items.json")).unwrap();
+        let mut spells = PatchFile::new("spells");
+        spells.add_edit(Edit::new(1, "Name", "ice"));
+        spells.save(dir.join("spells.json")).unwrap();
+
+        // A bare path and an entry with its own output dir and history
+        let json = serde_json::json!({
+            "roots": [root],
+            "output_dir": dir.join("out"),
+            "patches": [
+                dir.join("items.json"),
+                {
+                    "patch": dir.join("spells.json"),
+                    "output_dir": dir.join("mod"),
+                    "history_file": dir.join("history.json"),
+                },
+            ],
+        });
+        let batch_path = dir.join("batch.json");
+        fs::write(&batch_path, json.to_string()).unwrap();
+        cmd_batch(&batch_path, false).unwrap();
+
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(dir.join("out").join("items.csv")), "ID,Name\n1,bar\n");
+        assert_eq!(read(dir.join("mod").join("spells.csv")), "ID,Name\n1,ice\n");
+        assert!(!dir.join("out").join("spells.csv").exists());
+
+        let history = HistoryFile::load(dir.join("history.json")).unwrap();
+        assert!(history.get_last_entry("items").is_none());
+        let entry = history.get_last_entry("spells").unwrap();
+        assert_eq!(entry.output_dir, dir.join("mod"));
+        assert_eq!(entry.snapshot[0].old_value, "fire");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_history_mark_and_undo_marker() {
+        let dir = scratch_dir("mark");
+        let history_path = dir.join("history.json");
+
+        let mut patch = PatchFile::new("items");
+        patch.add_edit(Edit::new(1, "Name", "bar"));
+        let csv = "ID,Name\n1,foo\n";
+        let table = da_core::parser::parse_csv_str(csv, "items.csv").unwrap();
+        let merged = da_core::merge_tables("items", vec![table]).unwrap();
+        let mut history = HistoryFile::new();
+        history.add_entry(create_history_entry(&merged, &patch, vec![], dir.join("exports")));
+        history.save(&history_path).unwrap();
+
+        cmd_history_mark(&history_path, "items", "baseline").unwrap();
+
+        let history = HistoryFile::load(&history_path).unwrap();
+        let marker = history.get_last_entry("items").unwrap();
+        assert_eq!(marker.label.as_deref(), Some("baseline"));
+        assert!(marker.patch.edits.is_empty());
+        assert_eq!(history.total_entries(), 2);
+
+        // Undoing the marker needs no scan and leaves the earlier patch in place
+        let missing = [dir.join("missing")];
+        cmd_undo(&missing, &history_path, "items", &dir.join("out"), None, None, false).unwrap();
+
+        let history = HistoryFile::load(&history_path).unwrap();
+        assert_eq!(history.total_entries(), 1);
+        assert_eq!(history.get_last_entry("items").unwrap().patch.edits.len(), 1);
+        assert!(!dir.join("out").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_undo_then_redo_patch() {
+        let dir = scratch_dir("redo");
+        let root = dir.join("override");
+        let output = dir.join("exports");
+        fs::create_dir_all(&root).unwrap();
+
+        let original = "ID,Name,Value\n1,foo,100\n2,bar,200\n";
+        fs::write(root.join("items.csv"), original).unwrap();
+
+        let mut patch = PatchFile::new("items");
+        patch.add_edit(Edit::new(2, "Value", "250"));
+        let patch_path = dir.join("patch.json");
+        patch.save(&patch_path).unwrap();
+
+        let history_path = dir.join("history.json");
+        let roots = std::slice::from_ref(&root);
+        cmd_patch(roots, &patch_path, &output, Some(&history_path), &ExportOptions::default())
+            .unwrap();
+        let patched = fs::read_to_string(output.join("items.csv")).unwrap();
+
+        cmd_undo(roots, &history_path, "items", &output, None, None, false).unwrap();
+        assert_eq!(fs::read_to_string(output.join("items.csv")).unwrap(), original);
+        let history = HistoryFile::load(&history_path).unwrap();
+        assert_eq!(history.total_entries(), 0);
+        assert!(history.get_last_redo("items").is_some());
+
+        cmd_redo(roots, &history_path, "items", None).unwrap();
+        assert_eq!(fs::read_to_string(output.join("items.csv")).unwrap(), patched);
+        let history = HistoryFile::load(&history_path).unwrap();
+        let redone = &history.get_last_entry("items").unwrap().patch.edits;
+        assert_eq!(redone.len(), 1);
+        assert_eq!((redone[0].row_id, redone[0].value.as_str()), (2, "250"));
+        assert!(history.get_last_redo("items").is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_undo_steps_reverts_stacked_in_place_patches() {
+        let dir = scratch_dir("undo-steps");
+        let root = dir.join("override");
+        fs::create_dir_all(&root).unwrap();
+
+        let original = "ID,Name,Value\n1,foo,100\n2,bar,200\n";
+        let source = root.join("items.csv");
+        fs::write(&source, original).unwrap();
+
+        let history_path = dir.join("history.json");
+        let roots = std::slice::from_ref(&root);
+        let defaults = ExportOptions::default();
+        for (row_id, value) in [(1, "150"), (2, "250")] {
+            let mut patch = PatchFile::new("items");
+            patch.add_edit(Edit::new(row_id, "Value", value));
+            let patch_path = dir.join("patch.json");
+            patch.save(&patch_path).unwrap();
+            cmd_patch_in_place(roots, &patch_path, &history_path, true, &defaults).unwrap();
+        }
+        cmd_history_mark(&history_path, "items", "both").unwrap();
+        assert_eq!(
+            fs::read_to_string(&source).unwrap(),
+            "ID,Name,Value\n1,foo,150\n2,bar,250\n"
+        );
+
+        cmd_undo(roots, &history_path, "items", &dir.join("out"), Some(3), None, false).unwrap();
+        assert_eq!(fs::read_to_string(&source).unwrap(), original);
+
+        let history = HistoryFile::load(&history_path).unwrap();
+        assert_eq!(history.total_entries(), 0);
+        assert_eq!(history.redo["items"].len(), 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_undo_refuses_files_changed_since_patch() {
+        let dir = scratch_dir("undo-drift");
+        let root = dir.join("override");
+        fs::create_dir_all(&root).unwrap();
+        let source = root.join("items.csv");
+        fs::write(&source, "ID,Name,Value\n1,foo,100\n2,bar,200\n").unwrap();
+
+        let mut patch = PatchFile::new("items");
+        patch.add_edit(Edit::new(1, "Value", "150"));
+        let patch_path = dir.join("patch.json");
+        patch.save(&patch_path).unwrap();
+        let history_path = dir.join("history.json");
+        let roots = std::slice::from_ref(&root);
+        let defaults = ExportOptions::default();
+        cmd_patch_in_place(roots, &patch_path, &history_path, true, &defaults).unwrap();
+
+        // A later hand edit that undo would throw away
+        let edited = "ID,Name,Value\n1,foo,150\n2,bar,999\n";
+        fs::write(&source, edited).unwrap();
+
+        let out = dir.join("out");
+        let err = cmd_undo(roots, &history_path, "items", &out, None, None, false).unwrap_err();
+        assert!(matches!(&err, da_core::Error::SourceDrift(files) if *files == [source.clone()]));
+        assert_eq!(fs::read_to_string(&source).unwrap(), edited);
+        assert_eq!(HistoryFile::load(&history_path).unwrap().total_entries(), 1);
+
+        cmd_undo(roots, &history_path, "items", &out, None, None, true).unwrap();
+        assert_eq!(
+            fs::read_to_string(&source).unwrap(),
+            "ID,Name,Value\n1,foo,100\n2,bar,999\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_show_only_conflicts_or_changed() {
+        let dir = scratch_dir("conflicts");
+        fs::write(dir.join("items.csv"), "ID,Name,Value\n1,foo,100\n2,bar,200\n3,baz,300\n").unwrap();
+        fs::write(dir.join("items_ep1.csv"), "ID,Name,Value\n2,bar,250\n3,baz,300\n").unwrap();
+
+        let scan_result = scan_directory(&[&dir]).unwrap();
+        let family = scan_result.find_family("items").unwrap();
+        let text = show_family(family, TextRenderOptions::default(), ShowRows::Conflicts, &[])
+            .unwrap();
+        assert_eq!(text, "ID  Name  Value\n--  ----  -----\n2   bar   250*\n");
+
+        // Row 3 conflicts with nothing, but ep1 still provides its cells
+        let text = show_family(family, TextRenderOptions::default(), ShowRows::Changed, &[])
+            .unwrap();
+        assert_eq!(
+            text,
+            "ID  Name  Value\n--  ----  -----\n2   bar*  250*\n3   baz*  300*\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invert_writes_reverse_patch() {
+        let dir = scratch_dir("invert");
+        let root = dir.join("2da");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("items.csv"), "ID,Name,Value\n1,foo,100\n2,bar,200\n").unwrap();
+
+        let mut patch = PatchFile::new("items");
+        patch.add_edit(Edit::new(2, "Value", "250"));
+        patch.add_row_op(RowOp::InsertRow {
+            row_id: 3,
+            values: HashMap::from([("Name".to_string(), "baz".to_string())]),
+            file: None,
+        });
+        let patch_path = dir.join("patch.json");
+        patch.save(&patch_path).unwrap();
+
+        let reverse_path = dir.join("reverse.json");
+        cmd_invert(std::slice::from_ref(&root), &patch_path, &reverse_path).unwrap();
+
+        // Applying the patch and then its reverse gives back the original
+        let roots = std::slice::from_ref(&root);
+        let history_path = dir.join("history.json");
+        let defaults = ExportOptions::default();
+        cmd_patch_in_place(roots, &patch_path, &history_path, true, &defaults).unwrap();
+        cmd_patch_in_place(roots, &reverse_path, &history_path, true, &defaults).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("items.csv")).unwrap(),
+            "ID,Name,Value\n1,foo,100\n2,bar,200\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_show_matches_names_ignoring_case() {
+        let dir = scratch_dir("case");
+        fs::write(dir.join("Items.csv"), "ID,Name,Value\n1,foo,100\n").unwrap();
+
+        let scan_result = scan_directory(&[&dir]).unwrap();
+        let family = scan_result.find_family_ci("items").unwrap().unwrap();
+        let opts = TextRenderOptions {
+            columns: Some(vec!["id".to_string(), "VALUE".to_string()]),
+            ..Default::default()
+        };
+        let text = show_family(family, opts, ShowRows::All, &[]).unwrap();
+        assert_eq!(text, "ID  Value\n--  -----\n1   100\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let key = |s: &str| s.parse::<SortKey>().unwrap();
+        assert_eq!(key("Cost:desc"), SortKey { column: "Cost".to_string(), ascending: false });
+        assert_eq!(key("A:B"), SortKey { column: "A:B".to_string(), ascending: true });
+        assert!(":asc".parse::<SortKey>().is_err());
+
+        let csv = "ID,Tier,Cost\n1,b,5\n2,a,40\n3,b,100\n4,a,5\n5,b,\n";
+        let table = da_core::parser::parse_csv_str(csv, "items.csv").unwrap();
+        let merged = da_core::merge_tables("items", vec![table]).unwrap();
+        let ids = |t: &ResolvedTable| t.rows.iter().map(|r| r.id.unwrap()).collect::<Vec<_>>();
+
+        // Numeric, so 100 comes before 40 and 5; empty cells stay last
+        let mut sorted = merged.clone();
+        sort_rows(&mut sorted, &[key("cost:desc")]).unwrap();
+        assert_eq!(ids(&sorted), vec![3, 2, 1, 4, 5]);
+
+        // Tier first, then Cost within each tier
+        let mut sorted = merged.clone();
+        sort_rows(&mut sorted, &[key("Tier"), key("Cost:desc")]).unwrap();
+        assert_eq!(ids(&sorted), vec![2, 4, 3, 1, 5]);
+
+        let mut sorted = merged;
+        let err = sort_rows(&mut sorted, &[key("Weight")]).unwrap_err();
+        assert!(matches!(err, da_core::Error::ColumnNotFound(_)));
+    }
+
+    #[test]
+    fn test_merge_summary_counts() {
+        let dir = scratch_dir("merge");
+        fs::write(dir.join("items.csv"), "ID,Name,Value\n1,foo,100\n2,bar,200\n").unwrap();
+        fs::write(dir.join("items_ep1.csv"), "ID,Name,Value,Icon\n2,,250,x\n3,baz,300,y\n").unwrap();
+
+        let scan_result = scan_directory(&[&dir]).unwrap();
+        let lines = merge_summary(scan_result.find_family("items").unwrap()).unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                "Family: items".to_string(),
+                "Sources (in merge order):".to_string(),
+                format!("  1. {}: 2 rows, 4 cells", dir.join("items.csv").display()),
+                format!("  2. {}: 2 rows, 7 cells", dir.join("items_ep1.csv").display()),
+                "Columns: 4".to_string(),
+                "Rows: 3".to_string(),
+                "Conflicts: 1".to_string(),
+                "Type divergences: 0".to_string(),
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_files_later_files_override() {
+        let dir = scratch_dir("merge-files");
+        let base = dir.join("base.csv");
+        let overlay = dir.join("override.csv");
+        fs::write(&base, "ID,Name,Value\n1,foo,100\n2,bar,200\n").unwrap();
+        fs::write(&overlay, "ID,Name,Value\n2,,250\n3,baz,300\n").unwrap();
+
+        let merged = merge_files(&[base.clone(), overlay.clone()]).unwrap();
+        assert_eq!(merged.family_name, "base");
+        assert_eq!(merged.sources, vec![base.clone(), overlay.clone()]);
+        let row = merged.find_row(2).unwrap();
+        assert_eq!(row.cells[1].value.as_str(), Some("bar"));
+        assert_eq!(row.cells[2].value.as_i64(), Some(250));
+        assert_eq!(row.cells[2].source, overlay);
+
+        let output = dir.join("merged.csv");
+        let format = ExportFormat {
+            format: "csv".to_string(),
+            compact: false,
+            columns: None,
+            line_ending: LineEnding::Lf,
+        };
+        cmd_merge_files(&[overlay, base], Some(&output), &format).unwrap();
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "ID,Name,Value\n1,foo,100\n2,bar,200\n3,baz,300\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_rejects_output_encoding_for_json() {
+        let format = ExportFormat {
+            format: "json".to_string(),
+            compact: false,
+            columns: None,
+            line_ending: LineEnding::Lf,
+        };
+        let encoding = OutputEncoding::for_label("windows-1252", false).unwrap();
+        let dir = scratch_dir("export-encoding");
+        let output = dir.join("items.json");
+        let result = cmd_export(&[], "items", &format, &output, Some(encoding), None, &[]);
+        assert!(matches!(result, Err(da_core::Error::Export(_))));
+        assert!(!output.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_reports_mixed_id_columns() {
+        let dir = scratch_dir("check");
+        fs::write(dir.join("items.csv"), "ID,Name\n1,foo\n").unwrap();
+        fs::write(dir.join("items_ep1.csv"), "Label,Name\nextra,bar\n").unwrap();
+
+        let scan_result = scan_directory(&[&dir]).unwrap();
+        let problems = check_family(scan_result.find_family("items").unwrap()).unwrap();
+        assert_eq!(
+            problems,
+            vec!["ID column is keyed in items.csv but not in items_ep1.csv".to_string()]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
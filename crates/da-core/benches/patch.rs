@@ -0,0 +1,37 @@
+//! Patch validation and application on a large table with many edits
+//!
+//! Run with `cargo bench -p da-core --bench patch`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use da_core::parser::parse_csv_str;
+use da_core::{apply_patch, merge_tables, validate_patch, Edit, PatchFile};
+use std::fmt::Write;
+
+const ROWS: i64 = 20_000;
+const EDITS: i64 = 1_000;
+
+fn bench_patch(c: &mut Criterion) {
+    let mut csv = String::from("ID,Label,Cost\n");
+    for id in 0..ROWS {
+        writeln!(csv, "{},spell_{},{}", id, id, id % 100).unwrap();
+    }
+    let table = parse_csv_str(&csv, "spells.csv").unwrap();
+    let merged = merge_tables("spells", vec![table]).unwrap();
+
+    // Spread the edits over the whole table, so a linear lookup would
+    // scan most of it for each one
+    let mut patch = PatchFile::new("spells");
+    for i in 0..EDITS {
+        patch.add_edit(Edit::new(ROWS - 1 - i * (ROWS / EDITS), "Cost", "5"));
+    }
+
+    c.bench_function("validate_patch 1k edits on 20k rows", |b| {
+        b.iter(|| validate_patch(&merged, &patch, false))
+    });
+    c.bench_function("apply_patch 1k edits on 20k rows", |b| {
+        b.iter(|| apply_patch(&merged, &patch).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_patch);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+//! Directory scan over a synthetic tree of 10k CSV files
+//!
+//! Run with `cargo bench -p da-core --bench scan`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use da_core::scan_directory;
+use std::fs;
+use std::path::PathBuf;
+
+const FAMILIES: usize = 500;
+const VARIANTS: usize = 20;
+
+fn build_tree() -> PathBuf {
+    let root = std::env::temp_dir().join(format!("da_bench_scan_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+
+    for f in 0..FAMILIES {
+        let dir = root.join(format!("module_{}", f % 25));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(format!("table_{}.csv", f)), "ID,Value\n1,1\n").unwrap();
+        for v in 1..VARIANTS {
+            fs::write(dir.join(format!("table_{}_mod{}.csv", f, v)), "ID,Value\n1,1\n").unwrap();
+        }
+    }
+
+    root
+}
+
+fn bench_scan(c: &mut Criterion) {
+    let root = build_tree();
+
+    c.bench_function("scan_directory 10k files", |b| {
+        b.iter(|| scan_directory(&[&root]).unwrap())
+    });
+
+    let _ = fs::remove_dir_all(&root);
+}
+
+criterion_group!(benches, bench_scan);
+criterion_main!(benches);
@@ -0,0 +1,151 @@
+//! Cache of merged tables for interactive sessions
+//!
+//! Merging a family parses every member file. A `MergeCache` keeps the
+//! last merged table of each family together with a hash of its member
+//! files' paths, sizes and modification times, so merging the same family
+//! again only parses the files when one of them has changed.
+
+use crate::error::{Error, Result};
+use crate::merger::{merge_family_with_options, MergeOptions, ResolvedTable};
+use crate::scanner::Family;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+
+/// Merged tables by family name, each with the hash it was merged at
+#[derive(Debug, Clone, Default)]
+pub struct MergeCache {
+    entries: HashMap<String, (u64, ResolvedTable)>,
+    hits: usize,
+    misses: usize,
+}
+
+impl MergeCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of merges answered from the cache
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Number of merges that had to parse the member files
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Number of families in the cache
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether the cache holds no families
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop every cached table
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Merge a family, reusing the cached table if its files are unchanged
+///
+/// The member files are checked on every call, so an edit made since the
+/// last merge is always picked up. A new merge replaces the family's
+/// previous entry.
+pub fn merge_family_cached<'a>(
+    family: &Family,
+    cache: &'a mut MergeCache,
+) -> Result<&'a ResolvedTable> {
+    merge_family_cached_with_options(family, cache, &MergeOptions::default())
+}
+
+/// Merge a family using the given options, reusing the cached table if
+/// its files and the options are unchanged
+pub fn merge_family_cached_with_options<'a>(
+    family: &Family,
+    cache: &'a mut MergeCache,
+    options: &MergeOptions,
+) -> Result<&'a ResolvedTable> {
+    let hash = family_hash(family, options)?;
+
+    let fresh = cache
+        .entries
+        .get(&family.name)
+        .is_some_and(|(cached, _)| *cached == hash);
+    if fresh {
+        cache.hits += 1;
+    } else {
+        let (table, _) = merge_family_with_options(family, options)?;
+        cache.misses += 1;
+        cache.entries.insert(family.name.clone(), (hash, table));
+    }
+
+    Ok(&cache.entries[&family.name].1)
+}
+
+/// Hash a family's member paths and their current sizes and modification
+/// times, along with the merge options
+fn family_hash(family: &Family, options: &MergeOptions) -> Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    family.name.hash(&mut hasher);
+    serde_json::to_string(options)?.hash(&mut hasher);
+    for member in &family.members {
+        member.path.hash(&mut hasher);
+        member.suffix.hash(&mut hasher);
+        // Archive members can't change underneath us, but hash their bytes
+        // so two scans of different archives never share an entry
+        if let Some(bytes) = &member.contents {
+            bytes.hash(&mut hasher);
+            continue;
+        }
+        let metadata = fs::metadata(&member.path).map_err(|e| Error::FileRead {
+            path: member.path.clone(),
+            source: e,
+        })?;
+        metadata.len().hash(&mut hasher);
+        metadata.modified().ok().hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::scan_directory;
+    use crate::table::CellValue;
+    use crate::test_util::scratch_dir;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_merge_cache_reuses_unchanged_families() {
+        let dir = scratch_dir("cache");
+        let path = dir.join("items.csv");
+        fs::write(&path, "ID,Name\n1,a\n").unwrap();
+
+        let scan = scan_directory(&[&dir]).unwrap();
+        let family = scan.find_family("items").unwrap();
+        let mut cache = MergeCache::new();
+
+        merge_family_cached(family, &mut cache).unwrap();
+        let table = merge_family_cached(family, &mut cache).unwrap();
+        assert_eq!(table.rows[0].cells[1].value, CellValue::String("a".to_string()));
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        // A changed file is parsed again
+        fs::write(&path, "ID,Name\n1,b\n").unwrap();
+        let later = SystemTime::now() + Duration::from_secs(60);
+        fs::File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+        let table = merge_family_cached(family, &mut cache).unwrap();
+        assert_eq!(table.rows[0].cells[1].value, CellValue::String("b".to_string()));
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+        assert_eq!(cache.len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+//! CSV output for parsed and merged tables
+//!
+//! All CSV written by this crate goes through the csv crate's writer, so
+//! fields are quoted the same way everywhere: only when they hold a comma,
+//! a double quote or a line break, with quotes doubled inside. Lines end
+//! in `\n` unless a `LineEnding` says otherwise.
+
+use crate::error::Result;
+use crate::merger::ResolvedTable;
+use crate::table::Table;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+/// How written lines end
+///
+/// Line breaks inside quoted fields are written as they are either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    /// `\n`, the default so exports are the same on every platform
+    #[default]
+    Lf,
+    /// `\r\n`, for Windows tools that expect it
+    Crlf,
+}
+
+impl LineEnding {
+    /// The characters ending each line
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+/// Create a CSV writer with the settings every export uses
+///
+/// Rows may differ in length, as they can in the files being exported.
+pub(crate) fn csv_writer<W: Write>(writer: W, line_ending: LineEnding) -> csv::Writer<W> {
+    let terminator = match line_ending {
+        LineEnding::Lf => csv::Terminator::Any(b'\n'),
+        LineEnding::Crlf => csv::Terminator::CRLF,
+    };
+    csv::WriterBuilder::new()
+        .flexible(true)
+        .quote_style(csv::QuoteStyle::Necessary)
+        .terminator(terminator)
+        .from_writer(writer)
+}
+
+/// Write a parsed table as CSV: the header, then every row
+pub fn write_csv<W: Write>(writer: W, table: &Table) -> Result<()> {
+    let mut writer = csv_writer(writer, LineEnding::Lf);
+    writer
+        .write_record(table.columns.iter().map(|c| &c.name))
+        .map_err(io::Error::from)?;
+    for row in &table.rows {
+        writer
+            .write_record(row.cells.iter().map(|c| c.to_string_value()))
+            .map_err(io::Error::from)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write a merged table as CSV: the header, then every row, without
+/// provenance
+pub fn write_resolved<W: Write>(writer: W, table: &ResolvedTable) -> Result<()> {
+    write_resolved_with_line_ending(writer, table, LineEnding::Lf)
+}
+
+/// Write a merged table as CSV, ending lines with `line_ending`
+pub fn write_resolved_with_line_ending<W: Write>(
+    writer: W,
+    table: &ResolvedTable,
+    line_ending: LineEnding,
+) -> Result<()> {
+    let mut writer = csv_writer(writer, line_ending);
+    writer
+        .write_record(table.columns.iter().map(|c| &c.name))
+        .map_err(io::Error::from)?;
+    for row in &table.rows {
+        writer
+            .write_record(row.cells.iter().map(|c| c.value.to_string_value()))
+            .map_err(io::Error::from)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Quote one field the way `csv_writer` would inside a record
+///
+/// For records assembled field by field, such as a copied line with one
+/// edited cell. An empty field stays empty.
+pub(crate) fn escape_field(field: &str) -> String {
+    if field.is_empty() {
+        return String::new();
+    }
+    let mut writer = csv::WriterBuilder::new()
+        .buffer_capacity(field.len() + 3)
+        .from_writer(Vec::new());
+    // Writing to memory can't fail; the closing quote is only written with
+    // the record's line ending, which is dropped again
+    let _ = writer.write_record([field]);
+    let mut bytes = writer.into_inner().unwrap_or_default();
+    bytes.pop();
+    String::from_utf8(bytes).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merger::merge_tables;
+    use crate::parser::parse_csv_str;
+
+    #[test]
+    fn test_escape_field() {
+        assert_eq!(escape_field(""), "");
+        assert_eq!(escape_field("simple"), "simple");
+        assert_eq!(escape_field("with,comma"), "\"with,comma\"");
+        assert_eq!(escape_field("with\"quote"), "\"with\"\"quote\"");
+        assert_eq!(escape_field("with\nnewline"), "\"with\nnewline\"");
+        assert_eq!(escape_field("with\rreturn"), "\"with\rreturn\"");
+        assert_eq!(escape_field("0xC4FDA9ED"), "0xC4FDA9ED");
+    }
+
+    #[test]
+    fn test_write_csv_matches_simple_input() {
+        let csv = "ID,Name,Cost\n1,fire,1.5\n2,,3\n";
+        let table = parse_csv_str(csv, "test.csv").unwrap();
+
+        let mut out = Vec::new();
+        write_csv(&mut out, &table).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), csv);
+
+        let merged = merge_tables("test", vec![table]).unwrap();
+        let mut out = Vec::new();
+        write_resolved(&mut out, &merged).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), csv);
+    }
+
+    #[test]
+    fn test_write_csv_quotes_edge_cases() {
+        let csv = "ID,\"Label, full\"\n1,\"say \"\"hi\"\"\"\n2,\"two\nlines\"\n";
+        let table = parse_csv_str(csv, "test.csv").unwrap();
+
+        let mut out = Vec::new();
+        write_csv(&mut out, &table).unwrap();
+        assert_eq!(String::from_utf8(out.clone()).unwrap(), csv);
+
+        let reparsed = parse_csv_str(std::str::from_utf8(&out).unwrap(), "test.csv").unwrap();
+        assert_eq!(reparsed.columns, table.columns);
+        assert_eq!(reparsed.rows, table.rows);
+    }
+
+    #[test]
+    fn test_write_resolved_line_endings() {
+        let csv = "ID,Name\n1,fire\n2,\"two\nlines\"\n";
+        let merged = merge_tables("test", vec![parse_csv_str(csv, "test.csv").unwrap()]).unwrap();
+
+        let mut lf = Vec::new();
+        write_resolved_with_line_ending(&mut lf, &merged, LineEnding::Lf).unwrap();
+        assert_eq!(lf, csv.as_bytes());
+
+        // Only record ends change; the break inside the quoted field stays
+        let mut crlf = Vec::new();
+        write_resolved_with_line_ending(&mut crlf, &merged, LineEnding::Crlf).unwrap();
+        assert_eq!(crlf, b"ID,Name\r\n1,fire\r\n2,\"two\nlines\"\r\n");
+    }
+}
@@ -0,0 +1,165 @@
+//! Row- and cell-level comparison of merged tables
+
+use crate::merger::ResolvedTable;
+use crate::table::CellValue;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A cell whose value differs between two tables
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CellChange {
+    /// Row ID
+    pub row_id: i64,
+    /// Column name
+    pub column: String,
+    /// Value in the old table
+    pub old_value: CellValue,
+    /// Value in the new table
+    pub new_value: CellValue,
+}
+
+/// Differences between two merged tables
+///
+/// Rows are matched by ID; rows without an ID cannot be matched and are
+/// ignored. Cells are only compared in columns present on both sides.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TableDiff {
+    /// Columns only in the new table
+    pub added_columns: Vec<String>,
+    /// Columns only in the old table
+    pub removed_columns: Vec<String>,
+    /// Row IDs only in the new table
+    pub added_rows: Vec<i64>,
+    /// Row IDs only in the old table
+    pub removed_rows: Vec<i64>,
+    /// Cells that differ in rows present in both tables
+    pub changed_cells: Vec<CellChange>,
+}
+
+impl TableDiff {
+    /// Check whether the tables are equivalent
+    pub fn is_empty(&self) -> bool {
+        self.added_columns.is_empty()
+            && self.removed_columns.is_empty()
+            && self.added_rows.is_empty()
+            && self.removed_rows.is_empty()
+            && self.changed_cells.is_empty()
+    }
+}
+
+/// Compare two merged tables, treating `a` as old and `b` as new
+pub fn diff_tables(a: &ResolvedTable, b: &ResolvedTable) -> TableDiff {
+    let mut diff = TableDiff::default();
+
+    // Columns present on both sides: name -> (index in a, index in b)
+    let mut shared_columns: Vec<(&str, usize, usize)> = Vec::new();
+    for col in &a.columns {
+        match b.find_column(&col.name) {
+            Some(other) => shared_columns.push((col.name.as_str(), col.index, other.index)),
+            None => diff.removed_columns.push(col.name.clone()),
+        }
+    }
+    for col in &b.columns {
+        if a.find_column(&col.name).is_none() {
+            diff.added_columns.push(col.name.clone());
+        }
+    }
+
+    let rows_a: BTreeMap<i64, usize> = keyed_rows(a);
+    let rows_b: BTreeMap<i64, usize> = keyed_rows(b);
+
+    for (&id, &idx_a) in &rows_a {
+        let idx_b = match rows_b.get(&id) {
+            Some(&idx) => idx,
+            None => {
+                diff.removed_rows.push(id);
+                continue;
+            }
+        };
+
+        let row_a = &a.rows[idx_a];
+        let row_b = &b.rows[idx_b];
+        for &(name, col_a, col_b) in &shared_columns {
+            let old_value = row_a.cells.get(col_a).map(|c| &c.value);
+            let new_value = row_b.cells.get(col_b).map(|c| &c.value);
+            if old_value != new_value {
+                diff.changed_cells.push(CellChange {
+                    row_id: id,
+                    column: name.to_string(),
+                    old_value: old_value.cloned().unwrap_or(CellValue::Empty),
+                    new_value: new_value.cloned().unwrap_or(CellValue::Empty),
+                });
+            }
+        }
+    }
+
+    diff.added_rows = rows_b
+        .keys()
+        .filter(|id| !rows_a.contains_key(id))
+        .copied()
+        .collect();
+
+    diff
+}
+
+/// Map row IDs to row indices, skipping rows without an ID
+fn keyed_rows(table: &ResolvedTable) -> BTreeMap<i64, usize> {
+    table
+        .rows
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, row)| row.id.map(|id| (id, idx)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merger::merge_tables;
+    use crate::parser::parse_csv_str;
+
+    fn resolve(csv: &str) -> ResolvedTable {
+        merge_tables("test", vec![parse_csv_str(csv, "test.csv").unwrap()]).unwrap()
+    }
+
+    #[test]
+    fn test_diff_identical_tables() {
+        let a = resolve("ID,Name\n1,foo\n");
+        let b = resolve("ID,Name\n1,foo\n");
+
+        assert!(diff_tables(&a, &b).is_empty());
+    }
+
+    #[test]
+    fn test_diff_rows_and_cells() {
+        let a = resolve("ID,Name,Value\n1,foo,100\n2,bar,200\n");
+        let b = resolve("ID,Name,Value\n1,foo,150\n3,baz,300\n");
+
+        let diff = diff_tables(&a, &b);
+        assert_eq!(diff.added_rows, vec![3]);
+        assert_eq!(diff.removed_rows, vec![2]);
+        assert_eq!(
+            diff.changed_cells,
+            vec![CellChange {
+                row_id: 1,
+                column: "Value".to_string(),
+                old_value: CellValue::Integer(100),
+                new_value: CellValue::Integer(150),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diff_columns() {
+        let a = resolve("ID,Name,Old\n1,foo,x\n");
+        let b = resolve("ID,New,Name\n1,y,FOO\n");
+
+        let diff = diff_tables(&a, &b);
+        assert_eq!(diff.added_columns, vec!["New".to_string()]);
+        assert_eq!(diff.removed_columns, vec!["Old".to_string()]);
+
+        // Shared columns are matched by name, not position
+        assert_eq!(diff.changed_cells.len(), 1);
+        assert_eq!(diff.changed_cells[0].column, "Name");
+    }
+}
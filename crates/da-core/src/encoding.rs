@@ -0,0 +1,94 @@
+//! Text encodings for exported files
+//!
+//! Tables are held as UTF-8 internally. Exports can be transcoded to a
+//! legacy encoding such as Windows-1252 for tools that require it.
+
+use crate::error::{Error, Result};
+use encoding_rs::{EncoderResult, Encoding, UTF_8};
+
+/// Byte written for characters the target encoding can't represent
+const REPLACEMENT: u8 = b'?';
+
+/// The encoding exported text is written in
+#[derive(Debug, Clone, Copy)]
+pub struct OutputEncoding {
+    /// Target encoding
+    pub encoding: &'static Encoding,
+    /// Write `?` for characters the encoding lacks instead of failing
+    pub replace_unmappable: bool,
+}
+
+impl OutputEncoding {
+    /// Look up an encoding by a WHATWG label such as `windows-1252` or `latin1`
+    pub fn for_label(label: &str, replace_unmappable: bool) -> Result<Self> {
+        let encoding = Encoding::for_label(label.trim().as_bytes())
+            .ok_or_else(|| Error::UnknownEncoding(label.to_string()))?;
+        Ok(Self {
+            encoding: encoding.output_encoding(),
+            replace_unmappable,
+        })
+    }
+
+    /// Transcode UTF-8 text to this encoding
+    ///
+    /// Fails with `Error::UnmappableCharacter` on the first character the
+    /// encoding can't represent, unless `replace_unmappable` is set.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        if self.encoding == UTF_8 {
+            return Ok(text.as_bytes().to_vec());
+        }
+
+        let mut encoder = self.encoding.new_encoder();
+        let mut out = Vec::with_capacity(text.len());
+        let mut rest = text;
+        loop {
+            let max = encoder
+                .max_buffer_length_from_utf8_without_replacement(rest.len())
+                .unwrap_or(rest.len() * 4 + 16);
+            let start = out.len();
+            out.resize(start + max, 0);
+            let (result, read, written) =
+                encoder.encode_from_utf8_without_replacement(rest, &mut out[start..], true);
+            out.truncate(start + written);
+            rest = &rest[read..];
+
+            match result {
+                EncoderResult::InputEmpty => return Ok(out),
+                EncoderResult::OutputFull => {}
+                EncoderResult::Unmappable(ch) => {
+                    if !self.replace_unmappable {
+                        return Err(Error::UnmappableCharacter {
+                            ch,
+                            encoding: self.encoding.name(),
+                        });
+                    }
+                    out.push(REPLACEMENT);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_windows_1252() {
+        let encoding = OutputEncoding::for_label("windows-1252", false).unwrap();
+        assert_eq!(encoding.encode("Café €5").unwrap(), b"Caf\xe9 \x805");
+
+        assert!(matches!(
+            encoding.encode("a\u{4e00}b"),
+            Err(Error::UnmappableCharacter { ch: '\u{4e00}', .. })
+        ));
+
+        let replacing = OutputEncoding::for_label("cp1252", true).unwrap();
+        assert_eq!(replacing.encode("a\u{4e00}b").unwrap(), b"a?b");
+
+        assert!(matches!(
+            OutputEncoding::for_label("no-such-encoding", false),
+            Err(Error::UnknownEncoding(_))
+        ));
+    }
+}
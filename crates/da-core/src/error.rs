@@ -1,5 +1,6 @@
 //! Error types for da-core
 
+use crate::table::ColumnType;
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -17,9 +18,13 @@ pub enum Error {
         source: std::io::Error,
     },
 
-    /// Failed to parse CSV
-    #[error("failed to parse CSV '{path}': {message}")]
-    CsvParse { path: PathBuf, message: String },
+    /// Failed to parse CSV, at `line` (1-based) when it is known
+    #[error("{path}{}: {message}", line.map(|l| format!(":{}", l)).unwrap_or_default())]
+    CsvParse {
+        path: PathBuf,
+        line: Option<u64>,
+        message: String,
+    },
 
     /// CSV parsing error from the csv crate
     #[error("CSV error in '{path}': {source}")]
@@ -29,6 +34,18 @@ pub enum Error {
         source: csv::Error,
     },
 
+    /// Failed to read a zip archive or one of its entries
+    #[error("failed to read archive '{path}': {source}")]
+    Archive {
+        path: PathBuf,
+        #[source]
+        source: zip::result::ZipError,
+    },
+
+    /// A zip archive entry is bigger than the scan allows
+    #[error("archive entry '{path}' is larger than {limit} bytes")]
+    ArchiveEntryTooLarge { path: PathBuf, limit: u64 },
+
     /// Directory traversal error
     #[error("failed to traverse directory: {0}")]
     WalkDir(#[from] walkdir::Error),
@@ -53,11 +70,90 @@ pub enum Error {
     #[error("duplicate row ID {id} in {path}")]
     DuplicateRowId { id: i64, path: PathBuf },
 
+    /// No row with this ID in the table
+    #[error("row ID {0} not found")]
+    RowNotFound(i64),
+
+    /// No column with this name in the table
+    #[error("column '{0}' not found")]
+    ColumnNotFound(String),
+
+    /// A bulk edit's pattern isn't a valid regex
+    #[error("invalid regex: {0}")]
+    InvalidRegex(#[from] regex::Error),
+
+    /// A scan include or exclude pattern isn't a valid glob
+    #[error("invalid glob: {0}")]
+    InvalidGlob(#[from] globset::Error),
+
+    /// A numeric filter operator isn't one of <, <=, >, >=, == or !=
+    #[error("unknown comparison '{0}'; expected <, <=, >, >=, == or !=")]
+    InvalidComparison(String),
+
+    /// A filter query string couldn't be parsed
+    #[error("invalid filter query: {0}")]
+    InvalidQuery(String),
+
+    /// A row selection such as `100-150,200` couldn't be parsed
+    #[error("invalid row selection: {0}")]
+    InvalidRowSelection(String),
+
+    /// A name matches several others when case is ignored
+    #[error("'{name}' is ambiguous; it matches {}", candidates.join(", "))]
+    AmbiguousName {
+        name: String,
+        candidates: Vec<String>,
+    },
+
+    /// A value doesn't fit the type inferred for its column
+    #[error("value '{value}' does not fit column '{column}' of type {expected:?}")]
+    TypeMismatch {
+        column: String,
+        value: String,
+        expected: ColumnType,
+    },
+
+    /// A table could not be written in the requested format
+    #[error("export failed: {0}")]
+    Export(String),
+
+    /// Export would replace files that already exist in the output directory
+    #[error("refusing to overwrite existing files: {}", display_paths(.0))]
+    OutputExists(Vec<PathBuf>),
+
+    /// An in-place write would replace read-only source files
+    #[error("refusing to overwrite read-only files: {}", display_paths(.0))]
+    ReadOnlyTarget(Vec<PathBuf>),
+
+    /// Files changed after the history entries being undone wrote them
+    #[error("files changed since they were patched: {}", display_paths(.0))]
+    SourceDrift(Vec<PathBuf>),
+
+    /// No encoding with this label
+    #[error("unknown encoding: {0}")]
+    UnknownEncoding(String),
+
+    /// A character can't be represented in the output encoding
+    #[error("character '{ch}' cannot be encoded as {encoding}")]
+    UnmappableCharacter { ch: char, encoding: &'static str },
+
     /// IO error
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    /// SQLite error
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
     /// JSON serialization error
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 }
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,360 @@
+//! Row filters combining per-column predicates
+//!
+//! A `FilterExpr` is built directly or parsed from a query string such as
+//! `Category=weapon & (Tier>=3 | !Name=test)`:
+//!
+//! - `Column=text` matches cells containing `text`, ignoring case
+//! - `Column<n`, `<=`, `>`, `>=`, `==` and `!=` compare the cell's number
+//!   to `n`; empty and text cells never match
+//! - `&` (and), `|` (or) and a leading `!` (not) combine predicates, with
+//!   `!` binding tightest and `&` before `|`; parentheses group
+//!
+//! Column names and values are trimmed. A value containing `&`, `|` or
+//! `)` can be written in double quotes.
+//!
+//! A `RowSelection` picks rows by ID instead, from a list of IDs and
+//! inclusive ranges such as `100-150,200,305`.
+
+use crate::error::{Error, Result};
+use crate::merger::{CompareOp, ResolvedRow, ResolvedTable};
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::ops::RangeInclusive;
+use std::str::CharIndices;
+
+/// A boolean combination of per-column row predicates
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterExpr {
+    /// The column's text contains `value`, ignoring case
+    Contains {
+        /// Column name
+        column: String,
+        /// Text to look for
+        value: String,
+    },
+    /// The column holds a number that compares to `value`
+    Compare {
+        /// Column name
+        column: String,
+        /// Comparison to apply
+        op: CompareOp,
+        /// Number to compare against
+        value: f64,
+    },
+    /// Both sides match
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    /// Either side matches
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+    /// The inner expression doesn't match
+    Not(Box<FilterExpr>),
+}
+
+impl FilterExpr {
+    /// Parse a query string
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut parser = Parser {
+            query,
+            chars: query.char_indices().peekable(),
+        };
+        let expr = parser.or()?;
+        parser.skip_whitespace();
+        match parser.chars.peek() {
+            None => Ok(expr),
+            Some(&(pos, c)) => Err(invalid(format!("unexpected '{}' at {}", c, pos))),
+        }
+    }
+
+    /// Get the indices of the rows of `table` this expression matches
+    ///
+    /// Columns are looked up ignoring case if there is no exact match, and
+    /// a column the table doesn't have is an error.
+    pub fn matching_rows(&self, table: &ResolvedTable) -> Result<Vec<usize>> {
+        let mut columns = HashMap::new();
+        self.resolve_columns(table, &mut columns)?;
+
+        Ok(table
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| self.matches(row, &columns))
+            .map(|(index, _)| index)
+            .collect())
+    }
+
+    /// Map every column name in the expression to its index
+    fn resolve_columns<'a>(
+        &'a self,
+        table: &ResolvedTable,
+        columns: &mut HashMap<&'a str, usize>,
+    ) -> Result<()> {
+        match self {
+            FilterExpr::Contains { column, .. } | FilterExpr::Compare { column, .. } => {
+                let col = table
+                    .find_column_ci(column)?
+                    .ok_or_else(|| Error::ColumnNotFound(column.clone()))?;
+                columns.insert(column, col.index);
+                Ok(())
+            }
+            FilterExpr::And(left, right) | FilterExpr::Or(left, right) => {
+                left.resolve_columns(table, columns)?;
+                right.resolve_columns(table, columns)
+            }
+            FilterExpr::Not(inner) => inner.resolve_columns(table, columns),
+        }
+    }
+
+    /// Evaluate the expression against one row
+    fn matches(&self, row: &ResolvedRow, columns: &HashMap<&str, usize>) -> bool {
+        let cell = |column: &str| row.cells.get(columns[column]).map(|c| &c.value);
+        match self {
+            FilterExpr::Contains { column, value } => cell(column).is_some_and(|v| {
+                v.to_string_value()
+                    .to_lowercase()
+                    .contains(&value.to_lowercase())
+            }),
+            FilterExpr::Compare { column, op, value } => cell(column)
+                .and_then(|v| v.as_f64())
+                .is_some_and(|n| op.matches(n, *value)),
+            FilterExpr::And(left, right) => {
+                left.matches(row, columns) && right.matches(row, columns)
+            }
+            FilterExpr::Or(left, right) => left.matches(row, columns) || right.matches(row, columns),
+            FilterExpr::Not(inner) => !inner.matches(row, columns),
+        }
+    }
+}
+
+/// Recursive descent parser over a query string
+struct Parser<'a> {
+    query: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    /// `and ('|' and)*`
+    fn or(&mut self) -> Result<FilterExpr> {
+        let mut expr = self.and()?;
+        while self.eat('|') {
+            expr = FilterExpr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    /// `unary ('&' unary)*`
+    fn and(&mut self) -> Result<FilterExpr> {
+        let mut expr = self.unary()?;
+        while self.eat('&') {
+            expr = FilterExpr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    /// `'!' unary | '(' or ')' | predicate`
+    fn unary(&mut self) -> Result<FilterExpr> {
+        if self.eat('!') {
+            return Ok(FilterExpr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat('(') {
+            let expr = self.or()?;
+            if !self.eat(')') {
+                return Err(invalid("missing ')'".to_string()));
+            }
+            return Ok(expr);
+        }
+        self.predicate()
+    }
+
+    /// `column op value`
+    fn predicate(&mut self) -> Result<FilterExpr> {
+        self.skip_whitespace();
+        let column = self
+            .take_while(|c| !matches!(c, '<' | '>' | '=' | '!' | '&' | '|' | '(' | ')'))
+            .trim()
+            .to_string();
+        if column.is_empty() {
+            return Err(invalid("expected a column name".to_string()));
+        }
+
+        let op = self.take_while(|c| matches!(c, '<' | '>' | '=' | '!'));
+        let value = self.value()?;
+
+        match op {
+            "" => return Err(invalid(format!("expected an operator after '{}'", column))),
+            "=" => return Ok(FilterExpr::Contains { column, value }),
+            _ => {}
+        }
+        let op: CompareOp = op.parse()?;
+        let value = value
+            .parse()
+            .map_err(|_| invalid(format!("'{}' is not a number", value)))?;
+        Ok(FilterExpr::Compare { column, op, value })
+    }
+
+    /// A quoted string, or the text up to the next `&`, `|` or `)`, trimmed
+    fn value(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        if self.eat('"') {
+            let value = self.take_while(|c| c != '"').to_string();
+            if !self.eat('"') {
+                return Err(invalid("unterminated quoted value".to_string()));
+            }
+            return Ok(value);
+        }
+        Ok(self.take_while(|c| !matches!(c, '&' | '|' | ')')).trim().to_string())
+    }
+
+    /// Consume `c` (after any whitespace) if it comes next
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        self.chars.next_if(|&(_, next)| next == c).is_some()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    /// Consume characters while `keep` holds, returning them
+    fn take_while(&mut self, keep: impl Fn(char) -> bool) -> &'a str {
+        let start = self.chars.peek().map_or(self.query.len(), |&(pos, _)| pos);
+        while self.chars.next_if(|&(_, c)| keep(c)).is_some() {}
+        let end = self.chars.peek().map_or(self.query.len(), |&(pos, _)| pos);
+        &self.query[start..end]
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidQuery(message)
+}
+
+/// Row IDs picked by ID and by inclusive range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowSelection {
+    ranges: Vec<RangeInclusive<i64>>,
+}
+
+impl RowSelection {
+    /// Check whether a row ID is selected
+    pub fn contains(&self, id: i64) -> bool {
+        self.ranges.iter().any(|range| range.contains(&id))
+    }
+}
+
+/// Parse a row selection such as `100-150,200,305`
+///
+/// Items are separated by commas and are either an ID or two IDs joined by
+/// `-`, the first no larger than the second. Whitespace around items is
+/// ignored. A selection must pick at least one ID.
+pub fn parse_row_selection(spec: &str) -> Result<RowSelection> {
+    let invalid = |message: String| Error::InvalidRowSelection(message);
+    let id = |s: &str| {
+        s.trim()
+            .parse::<i64>()
+            .map_err(|_| invalid(format!("'{}' is not a row ID", s.trim())))
+    };
+
+    let mut ranges = Vec::new();
+    for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        // Skip a leading minus so negative IDs aren't read as ranges
+        let dash = item.char_indices().skip(1).find(|&(_, c)| c == '-');
+        let range = match dash {
+            Some((dash, _)) => {
+                let (start, end) = (id(&item[..dash])?, id(&item[dash + 1..])?);
+                if start > end {
+                    return Err(invalid(format!("range '{}' runs backwards", item)));
+                }
+                start..=end
+            }
+            None => {
+                let id = id(item)?;
+                id..=id
+            }
+        };
+        ranges.push(range);
+    }
+
+    if ranges.is_empty() {
+        return Err(invalid("no rows selected".to_string()));
+    }
+    Ok(RowSelection { ranges })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merger::merge_tables;
+    use crate::parser::parse_csv_str;
+
+    fn weapons() -> ResolvedTable {
+        let csv = "ID,Category,Tier,Name\n\
+                   1,weapon,3,Sword\n\
+                   2,weapon,1,Dagger\n\
+                   3,armor,4,Plate\n\
+                   4,weapon,****,Bow\n\
+                   5,ring,5,Band\n";
+        merge_tables("items", vec![parse_csv_str(csv, "items.csv").unwrap()]).unwrap()
+    }
+
+    #[test]
+    fn test_filter_and_expression() {
+        let table = weapons();
+        let expr = FilterExpr::parse("Category=weapon & Tier>=3").unwrap();
+        assert_eq!(
+            expr,
+            FilterExpr::And(
+                Box::new(FilterExpr::Contains {
+                    column: "Category".to_string(),
+                    value: "weapon".to_string(),
+                }),
+                Box::new(FilterExpr::Compare {
+                    column: "Tier".to_string(),
+                    op: CompareOp::GreaterOrEqual,
+                    value: 3.0,
+                }),
+            )
+        );
+        assert_eq!(table.filter_expr(&expr).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn test_filter_or_and_not_expressions() {
+        let table = weapons();
+        let expr = FilterExpr::parse("category=ARMOR | Tier == 5").unwrap();
+        assert_eq!(table.filter_expr(&expr).unwrap(), vec![2, 4]);
+
+        // '&' binds tighter than '|'; '!' negates the group
+        let expr = FilterExpr::parse("!(Category=weapon) | Tier<2 & Name=\"dag\"").unwrap();
+        assert_eq!(table.filter_expr(&expr).unwrap(), vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn test_row_selection() {
+        let selection = parse_row_selection(" 2-3, 5 ,-1").unwrap();
+        let picked: Vec<i64> = (-2..=7).filter(|&id| selection.contains(id)).collect();
+        assert_eq!(picked, vec![-1, 2, 3, 5]);
+
+        let mut table = weapons();
+        table.select_rows(&parse_row_selection("2-3,5,100-150").unwrap());
+        let ids: Vec<i64> = table.rows.iter().filter_map(|r| r.id).collect();
+        assert_eq!(ids, vec![2, 3, 5]);
+
+        for spec in ["", " , ", "3-1", "a", "1-", "1-2-3", "é", "é-2", "1-é", "-é"] {
+            let result = parse_row_selection(spec);
+            assert!(matches!(result, Err(Error::InvalidRowSelection(_))), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_filter_query_errors() {
+        let table = weapons();
+        for query in ["Tier>>3", "Tier>high", "(Tier>1", "=weapon", "Tier>1 )"] {
+            let result = FilterExpr::parse(query);
+            assert!(
+                matches!(result, Err(Error::InvalidQuery(_) | Error::InvalidComparison(_))),
+                "{}",
+                query
+            );
+        }
+        let expr = FilterExpr::parse("Weight>1").unwrap();
+        assert!(matches!(table.filter_expr(&expr), Err(Error::ColumnNotFound(_))));
+    }
+}
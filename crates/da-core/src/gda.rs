@@ -0,0 +1,278 @@
+//! Export of merged tables to the game's binary 2DA format (GDA)
+//!
+//! A GDA file is a GFF V4.0 container of type `G2DA`:
+//!
+//! - `gtop` (top-level struct): a list of `colm` structs and a list of `rows` structs
+//! - `colm`: the column's name hash (`UINT32`) and its value type (`UINT8`)
+//! - `rows`: one field per column, in column order
+//!
+//! Column names are stored as CRC32 hashes of the lowercased name. Names that
+//! already are hashes (e.g. `0xC4FDA9ED`) are written as their numeric value.
+
+use crate::error::{Error, Result};
+use crate::merger::ResolvedTable;
+use crate::table::{CellValue, ColumnType};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// GFF field labels used by G2DA files
+const LABEL_COLUMN_LIST: u32 = 10000;
+const LABEL_ROW_LIST: u32 = 10001;
+const LABEL_COLUMN_HASH: u32 = 10002;
+const LABEL_COLUMN_TYPE: u32 = 10999;
+const LABEL_FIRST_CELL: u32 = 10005;
+
+/// GFF field types
+const GFF_UINT8: u16 = 0;
+const GFF_UINT32: u16 = 4;
+const GFF_INT32: u16 = 5;
+const GFF_FLOAT32: u16 = 8;
+const GFF_ECSTRING: u16 = 14;
+
+/// GFF field flags
+const FLAG_LIST: u16 = 0x8000;
+const FLAG_STRUCT: u16 = 0x4000;
+
+/// G2DA column type codes
+const GDA_INT: u8 = 0;
+const GDA_FLOAT: u8 = 1;
+const GDA_STRING: u8 = 3;
+
+/// Indices of the list element structs in the struct array (`gtop` is 0)
+const STRUCT_COLUMN: u16 = 1;
+const STRUCT_ROW: u16 = 2;
+
+const HEADER_SIZE: usize = 28;
+const STRUCT_DEF_SIZE: usize = 16;
+const FIELD_DEF_SIZE: usize = 12;
+const COLUMN_STRUCT_SIZE: usize = 5;
+
+/// Write a merged table as a GDA file at `path`
+pub fn export_2da<P: AsRef<Path>>(table: &ResolvedTable, path: P) -> Result<()> {
+    let file = File::create(path.as_ref())?;
+    let mut writer = BufWriter::new(file);
+    write_2da(table, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write a merged table in GDA format to any writer
+pub fn write_2da<W: Write>(table: &ResolvedTable, writer: &mut W) -> Result<()> {
+    let column_count = table.columns.len();
+
+    let mut hashes = Vec::with_capacity(column_count);
+    for col in &table.columns {
+        hashes.push(column_hash(&col.name)?);
+    }
+    let types: Vec<ColumnType> = (0..column_count).map(|idx| column_type(table, idx)).collect();
+
+    // Struct and field definitions
+    let field_count = 4 + column_count;
+    let data_offset = HEADER_SIZE + 3 * STRUCT_DEF_SIZE + field_count * FIELD_DEF_SIZE;
+    let row_struct_size = 4 * column_count;
+
+    let mut out = Vec::new();
+    out.extend_from_slice(b"GFF V4.0PC  G2DAV0.2");
+    put_u32(&mut out, 3);
+    put_u32(&mut out, data_offset as u32);
+
+    // Struct array: fourcc, field count, offset of first field, instance size
+    let struct_defs: [(&[u8; 4], usize, usize, usize); 3] = [
+        (b"gtop", 2, 0, 8),
+        (b"colm", 2, 2, COLUMN_STRUCT_SIZE),
+        (b"rows", column_count, 4, row_struct_size),
+    ];
+    for (fourcc, fields, first_field, size) in struct_defs {
+        out.extend_from_slice(fourcc);
+        put_u32(&mut out, fields as u32);
+        put_u32(
+            &mut out,
+            (HEADER_SIZE + 3 * STRUCT_DEF_SIZE + first_field * FIELD_DEF_SIZE) as u32,
+        );
+        put_u32(&mut out, size as u32);
+    }
+
+    // Field array: label, type, flags, offset within the struct instance
+    put_field(&mut out, LABEL_COLUMN_LIST, STRUCT_COLUMN, FLAG_LIST | FLAG_STRUCT, 0);
+    put_field(&mut out, LABEL_ROW_LIST, STRUCT_ROW, FLAG_LIST | FLAG_STRUCT, 4);
+    put_field(&mut out, LABEL_COLUMN_HASH, GFF_UINT32, 0, 0);
+    put_field(&mut out, LABEL_COLUMN_TYPE, GFF_UINT8, 0, 4);
+    for (idx, ty) in types.iter().enumerate() {
+        let gff_type = match ty {
+            ColumnType::Integer | ColumnType::Bool | ColumnType::Empty => GFF_INT32,
+            ColumnType::Float => GFF_FLOAT32,
+            ColumnType::String => GFF_ECSTRING,
+        };
+        put_field(&mut out, LABEL_FIRST_CELL + idx as u32, gff_type, 0, 4 * idx as u32);
+    }
+    debug_assert_eq!(out.len(), data_offset);
+
+    // Data block; offsets stored in it are relative to its start
+    let mut data = Vec::new();
+    let column_list_offset = 8;
+    let row_list_offset = column_list_offset + 4 + column_count * COLUMN_STRUCT_SIZE;
+    let strings_offset = row_list_offset + 4 + table.rows.len() * row_struct_size;
+
+    put_u32(&mut data, column_list_offset as u32);
+    put_u32(&mut data, row_list_offset as u32);
+
+    put_u32(&mut data, column_count as u32);
+    for (hash, ty) in hashes.iter().zip(&types) {
+        put_u32(&mut data, *hash);
+        data.push(match ty {
+            ColumnType::Integer | ColumnType::Bool | ColumnType::Empty => GDA_INT,
+            ColumnType::Float => GDA_FLOAT,
+            ColumnType::String => GDA_STRING,
+        });
+    }
+
+    let mut strings = Vec::new();
+    put_u32(&mut data, table.rows.len() as u32);
+    for row in &table.rows {
+        for (idx, ty) in types.iter().enumerate() {
+            let value = row.cells.get(idx).map(|c| &c.value).unwrap_or(&CellValue::Empty);
+            match ty {
+                ColumnType::Integer | ColumnType::Bool | ColumnType::Empty => {
+                    let v = match value {
+                        CellValue::Integer(i) => *i,
+                        number @ CellValue::Number { .. } => number.as_i64().unwrap_or(0),
+                        _ => 0,
+                    };
+                    let v = i32::try_from(v).map_err(|_| {
+                        Error::Export(format!(
+                            "value {} in column '{}' does not fit a 2DA integer",
+                            v, table.columns[idx].name
+                        ))
+                    })?;
+                    data.extend_from_slice(&v.to_le_bytes());
+                }
+                ColumnType::Float => {
+                    let v = match value {
+                        CellValue::Integer(i) => *i as f32,
+                        CellValue::Float(f) | CellValue::Number { value: f, .. } => *f as f32,
+                        _ => 0.0,
+                    };
+                    data.extend_from_slice(&v.to_le_bytes());
+                }
+                ColumnType::String => {
+                    put_u32(&mut data, (strings_offset + strings.len()) as u32);
+                    put_string(&mut strings, &value.to_string_value());
+                }
+            }
+        }
+    }
+    data.extend_from_slice(&strings);
+
+    out.extend_from_slice(&data);
+    writer.write_all(&out)?;
+    Ok(())
+}
+
+/// Get the GDA label hash for a column name
+fn column_hash(name: &str) -> Result<u32> {
+    if let Some(hex) = name.strip_prefix("0x").or_else(|| name.strip_prefix("0X")) {
+        return u32::from_str_radix(hex, 16)
+            .map_err(|_| Error::Export(format!("invalid hex column name '{}'", name)));
+    }
+    Ok(crc32(name.to_lowercase().as_bytes()))
+}
+
+/// Pick the narrowest GDA type that holds every value in a column
+fn column_type(table: &ResolvedTable, idx: usize) -> ColumnType {
+    let values = table.rows.iter().filter_map(|r| r.cells.get(idx)).map(|c| &c.value);
+    match ColumnType::infer(values) {
+        ColumnType::Empty => ColumnType::Integer,
+        ty => ty,
+    }
+}
+
+/// CRC-32 (IEEE), as used for GDA column name hashes
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_field(buf: &mut Vec<u8>, label: u32, field_type: u16, flags: u16, offset: u32) {
+    put_u32(buf, label);
+    buf.extend_from_slice(&field_type.to_le_bytes());
+    buf.extend_from_slice(&flags.to_le_bytes());
+    put_u32(buf, offset);
+}
+
+/// Append a GFF string: a character count followed by UTF-16LE code units
+fn put_string(buf: &mut Vec<u8>, s: &str) {
+    let units: Vec<u16> = s.encode_utf16().collect();
+    put_u32(buf, units.len() as u32);
+    for unit in units {
+        buf.extend_from_slice(&unit.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merger::merge_tables;
+    use crate::parser::parse_csv_str;
+
+    fn read_u32(buf: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(buf[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_column_hash_hex_name() {
+        assert_eq!(column_hash("0xC4FDA9ED").unwrap(), 0xC4FD_A9ED);
+        assert_eq!(column_hash("ID").unwrap(), crc32(b"id"));
+        assert!(column_hash("0xZZ").is_err());
+    }
+
+    #[test]
+    fn test_write_2da_layout() {
+        let csv = "ID,0xC4FDA9ED,Scale,Label\n1,10,0.5,abc\n2,,1,\n";
+        let table = merge_tables("test", vec![parse_csv_str(csv, "test.csv").unwrap()]).unwrap();
+
+        let mut buf = Vec::new();
+        write_2da(&table, &mut buf).unwrap();
+
+        assert_eq!(&buf[0..20], b"GFF V4.0PC  G2DAV0.2");
+        assert_eq!(read_u32(&buf, 20), 3);
+        let data = read_u32(&buf, 24) as usize;
+        assert_eq!(data, HEADER_SIZE + 3 * STRUCT_DEF_SIZE + 8 * FIELD_DEF_SIZE);
+
+        // Column list: hashes and types
+        let columns = data + read_u32(&buf, data) as usize;
+        assert_eq!(read_u32(&buf, columns), 4);
+        assert_eq!(read_u32(&buf, columns + 4 + 5), 0xC4FD_A9ED);
+        let types: Vec<u8> = (0..4).map(|i| buf[columns + 4 + i * 5 + 4]).collect();
+        assert_eq!(types, vec![GDA_INT, GDA_INT, GDA_FLOAT, GDA_STRING]);
+
+        // Row list: second row has an empty int (0) and a float column widened from "1"
+        let rows = data + read_u32(&buf, data + 4) as usize;
+        assert_eq!(read_u32(&buf, rows), 2);
+        let row2 = rows + 4 + 16;
+        assert_eq!(read_u32(&buf, row2), 2);
+        assert_eq!(read_u32(&buf, row2 + 4), 0);
+        assert_eq!(f32::from_le_bytes(buf[row2 + 8..row2 + 12].try_into().unwrap()), 1.0);
+
+        // First row's string points at a UTF-16 "abc"
+        let label = data + read_u32(&buf, rows + 4 + 12) as usize;
+        assert_eq!(read_u32(&buf, label), 3);
+        assert_eq!(&buf[label + 4..label + 10], &[b'a', 0, b'b', 0, b'c', 0]);
+    }
+}
@@ -3,11 +3,16 @@
 //! Tracks which patches have been applied to allow undo operations.
 
 use crate::error::{Error, Result};
-use crate::patch::PatchFile;
+use crate::merger::{merge_family_with_options, MergeOptions, ResolvedTable};
+use crate::patch::{
+    apply_in_place, export_with_edits_with_options, Edit, ExportOptions, PatchFile,
+};
+use crate::scanner::Family;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 /// A record of a patch that was applied
@@ -23,6 +28,59 @@ pub struct HistoryEntry {
     pub output_files: Vec<PathBuf>,
     /// Output directory used
     pub output_dir: PathBuf,
+    /// Patch that reverts this one, recorded when source files were edited in place
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverse: Option<PatchFile>,
+    /// Label of a marker entry that records a checkpoint without edits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Merged values of the edited cells before the patch was applied
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub snapshot: Vec<CellSnapshot>,
+    /// Content hash of each written file right after the patch, to tell
+    /// whether the file changed since (see `file_hash`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub file_hashes: BTreeMap<PathBuf, String>,
+}
+
+/// The value a cell held before a recorded patch edited it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CellSnapshot {
+    /// Row ID
+    pub row_id: i64,
+    /// Column name, as the patch's edit gives it
+    pub column: String,
+    /// Merged value of the cell before the edit
+    pub old_value: String,
+}
+
+impl HistoryEntry {
+    /// The description of the recorded patch, if it has one
+    pub fn description(&self) -> Option<&str> {
+        self.patch.description.as_deref()
+    }
+
+    /// Whether this entry is a marker that changed no files
+    pub fn is_marker(&self) -> bool {
+        self.label.is_some() && self.patch.edits.is_empty() && self.patch.row_ops.is_empty()
+    }
+
+    /// Get the patch that undoes this entry
+    ///
+    /// This is the recorded reverse patch of an in-place entry. Otherwise it
+    /// is built from the snapshot, putting every edited cell back to its old
+    /// value, so it needs neither the original files nor a fresh merge. Row
+    /// operations are only undone by a recorded reverse patch.
+    pub fn inverse_patch(&self) -> PatchFile {
+        if let Some(reverse) = &self.reverse {
+            return reverse.clone();
+        }
+        let mut inverse = PatchFile::new(self.family.clone());
+        for cell in &self.snapshot {
+            inverse.add_edit(Edit::new(cell.row_id, cell.column.clone(), cell.old_value.clone()));
+        }
+        inverse
+    }
 }
 
 /// History file containing all applied patches
@@ -30,6 +88,9 @@ pub struct HistoryEntry {
 pub struct HistoryFile {
     /// History entries grouped by family name
     pub entries: HashMap<String, Vec<HistoryEntry>>,
+    /// Undone entries that can be redone, grouped by family name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub redo: HashMap<String, Vec<HistoryEntry>>,
 }
 
 impl HistoryFile {
@@ -39,6 +100,9 @@ impl HistoryFile {
     }
 
     /// Load history from a file, or create empty if not exists
+    ///
+    /// A temporary file left by an interrupted `save` is ignored; the next
+    /// save overwrites it.
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref();
         if !path.exists() {
@@ -53,14 +117,27 @@ impl HistoryFile {
     }
 
     /// Save history to a file
+    ///
+    /// The history is written to a temporary file next to `path` and then
+    /// renamed over it, so a crash mid-write leaves the previous history
+    /// intact.
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
         let content = serde_json::to_string_pretty(self)?;
-        fs::write(path, content)?;
+        let temp = temp_path(path);
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&temp, path)?;
         Ok(())
     }
 
     /// Add an entry to the history
+    ///
+    /// A new entry discards the family's redo stack.
     pub fn add_entry(&mut self, entry: HistoryEntry) {
+        self.redo.remove(&entry.family);
         self.entries
             .entry(entry.family.clone())
             .or_default()
@@ -77,9 +154,185 @@ impl HistoryFile {
         self.entries.get(family).and_then(|v| v.last())
     }
 
+    /// Get the entries for a family recorded after `timestamp`, oldest first
+    pub fn entries_since(&self, family: &str, timestamp: DateTime<Utc>) -> &[HistoryEntry] {
+        match self.entries.get(family) {
+            Some(entries) => {
+                let start = entries.partition_point(|e| e.timestamp <= timestamp);
+                &entries[start..]
+            }
+            None => &[],
+        }
+    }
+
     /// Remove and return the last entry for a family (for undo)
+    ///
+    /// The entry is kept on the family's redo stack.
     pub fn pop_last_entry(&mut self, family: &str) -> Option<HistoryEntry> {
-        self.entries.get_mut(family).and_then(|v| v.pop())
+        let entry = self.entries.get_mut(family).and_then(|v| v.pop())?;
+        self.redo
+            .entry(family.to_string())
+            .or_default()
+            .push(entry.clone());
+        Some(entry)
+    }
+
+    /// Get the entry that `redo_last_entry` would restore for a family
+    pub fn get_last_redo(&self, family: &str) -> Option<&HistoryEntry> {
+        self.redo.get(family).and_then(|v| v.last())
+    }
+
+    /// Move the most recently undone entry for a family back into its history
+    ///
+    /// Callers re-apply the returned entry's patch.
+    pub fn redo_last_entry(&mut self, family: &str) -> Option<HistoryEntry> {
+        let stack = self.redo.get_mut(family)?;
+        let entry = stack.pop()?;
+        if stack.is_empty() {
+            self.redo.remove(family);
+        }
+        self.entries
+            .entry(family.to_string())
+            .or_default()
+            .push(entry.clone());
+        Some(entry)
+    }
+
+    /// Undo every entry for a family recorded after `timestamp`
+    ///
+    /// In-place patches are reverted newest first by applying their reverse
+    /// patches to the source files. Files written by undone exports are
+    /// restored in `output_dir`: re-exported from the newest remaining entry
+    /// that also wrote them, or copied from the source files otherwise. The
+    /// undone entries move to the redo stack.
+    pub fn undo_to(
+        &mut self,
+        family: &Family,
+        timestamp: DateTime<Utc>,
+        output_dir: &Path,
+    ) -> Result<UndoResult> {
+        self.undo_to_with_options(family, timestamp, output_dir, &MergeOptions::default())
+    }
+
+    /// Undo every entry for a family recorded after `timestamp`, merging
+    /// and parsing the family's files with the given options
+    pub fn undo_to_with_options(
+        &mut self,
+        family: &Family,
+        timestamp: DateTime<Utc>,
+        output_dir: &Path,
+        merge: &MergeOptions,
+    ) -> Result<UndoResult> {
+        let undone: Vec<HistoryEntry> = self
+            .entries_since(&family.name, timestamp)
+            .iter()
+            .rev()
+            .cloned()
+            .collect();
+        let mut result = UndoResult {
+            undone: Vec::new(),
+            files_written: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        for entry in &undone {
+            if let Some(reverse) = &entry.reverse {
+                let (merged, _) = merge_family_with_options(family, merge)?;
+                let options = ExportOptions {
+                    backup: true,
+                    parse: merge.parse.clone(),
+                    ..Default::default()
+                };
+                let export = apply_in_place(&merged, reverse, &options)?;
+                result.files_written.extend(export.files_written);
+                result.errors.extend(export.errors);
+            }
+        }
+
+        let restored: HashSet<&std::ffi::OsStr> = undone
+            .iter()
+            .filter(|e| e.reverse.is_none())
+            .flat_map(|e| e.output_files.iter().filter_map(|p| p.file_name()))
+            .collect();
+
+        if !restored.is_empty() {
+            fs::create_dir_all(output_dir)?;
+            let history = self
+                .entries
+                .get(&family.name)
+                .map_or(&[][..], Vec::as_slice);
+            let kept = &history[..history.len() - undone.len()];
+            let exports: Vec<&HistoryEntry> = kept
+                .iter()
+                .filter(|e| e.reverse.is_none() && !e.is_marker())
+                .collect();
+
+            // Exports are not cumulative, so each file holds the output of the
+            // newest export that wrote it. Re-export from the oldest remaining
+            // entry that wrote a restored file onwards, so newer entries win.
+            let first = exports.iter().position(|e| {
+                e.output_files
+                    .iter()
+                    .any(|p| p.file_name().is_some_and(|n| restored.contains(n)))
+            });
+
+            let (merged, _) = merge_family_with_options(family, merge)?;
+            let mut covered = HashSet::new();
+            if let Some(first) = first {
+                let options = ExportOptions {
+                    overwrite: true,
+                    parse: merge.parse.clone(),
+                    ..Default::default()
+                };
+                for entry in &exports[first..] {
+                    let export =
+                        export_with_edits_with_options(&merged, &entry.patch, output_dir, &options)?;
+                    for file in export.files_written {
+                        covered.insert(file.file_name().unwrap_or_default().to_os_string());
+                        if !result.files_written.contains(&file) {
+                            result.files_written.push(file);
+                        }
+                    }
+                    result.errors.extend(export.errors);
+                }
+            }
+
+            for source in &merged.sources {
+                let Some(file_name) = source.file_name() else {
+                    continue;
+                };
+                if restored.contains(file_name) && !covered.contains(file_name) {
+                    let dest = output_dir.join(file_name);
+                    fs::copy(source, &dest)?;
+                    result.files_written.push(dest);
+                }
+            }
+        }
+
+        for _ in &undone {
+            self.pop_last_entry(&family.name);
+        }
+        result.undone = undone;
+        Ok(result)
+    }
+
+    /// Find files that changed after the entries recorded since `timestamp`
+    /// wrote them
+    ///
+    /// Each file is compared with the hash of the newest of those entries
+    /// that wrote it, since older ones are expected to differ. Undoing
+    /// would overwrite the changes in these files. Missing files and
+    /// entries recorded without hashes are not reported.
+    pub fn drifted_since(&self, family: &str, timestamp: DateTime<Utc>) -> Vec<PathBuf> {
+        let mut expected: BTreeMap<&PathBuf, &String> = BTreeMap::new();
+        for entry in self.entries_since(family, timestamp) {
+            expected.extend(&entry.file_hashes);
+        }
+        expected
+            .into_iter()
+            .filter(|(path, hash)| file_hash(path).is_ok_and(|current| current != **hash))
+            .map(|(path, _)| path.clone())
+            .collect()
     }
 
     /// Get all families that have history
@@ -93,8 +346,24 @@ impl HistoryFile {
     }
 }
 
+/// Result of undoing history entries with [`HistoryFile::undo_to`]
+#[derive(Debug, Clone)]
+pub struct UndoResult {
+    /// Entries that were undone, newest first
+    pub undone: Vec<HistoryEntry>,
+    /// Files that were reverted, re-exported or restored
+    pub files_written: Vec<PathBuf>,
+    /// Errors encountered (source path, error message)
+    pub errors: Vec<(PathBuf, String)>,
+}
+
 /// Create a history entry from a successful patch application
+///
+/// `table` is the family's merged table before the patch was applied; the
+/// entry's snapshot records the values of the cells the patch edits. The
+/// output files are hashed as they are now, so call this after writing them.
 pub fn create_history_entry(
+    table: &ResolvedTable,
     patch: &PatchFile,
     output_files: Vec<PathBuf>,
     output_dir: PathBuf,
@@ -103,15 +372,102 @@ pub fn create_history_entry(
         timestamp: Utc::now(),
         family: patch.family.clone(),
         patch: patch.clone(),
-        output_files,
         output_dir,
+        reverse: None,
+        label: None,
+        snapshot: snapshot_edits(table, patch),
+        file_hashes: output_files
+            .iter()
+            .filter_map(|path| Some((path.clone(), file_hash(path).ok()?)))
+            .collect(),
+        output_files,
     }
 }
 
+/// Hash a file's contents, as recorded in `HistoryEntry::file_hashes`
+///
+/// Uses 64-bit FNV-1a, which gives the same hash on every platform and
+/// build, written as 16 hex digits.
+pub fn file_hash(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).map_err(|e| Error::FileRead {
+        path: path.to_path_buf(),
+        source: e,
+    })?;
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    Ok(format!("{:016x}", hash))
+}
+
+/// Record the merged value of every cell a patch edits
+///
+/// Bulk edits are expanded against `table`. Each cell is recorded once,
+/// and edits of rows or columns the table doesn't have are skipped.
+pub fn snapshot_edits(table: &ResolvedTable, patch: &PatchFile) -> Vec<CellSnapshot> {
+    let bulk_edits: Vec<Edit> = patch
+        .bulk_edits
+        .iter()
+        .filter_map(|bulk| bulk.expand(table).ok())
+        .flatten()
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut snapshot = Vec::new();
+    for edit in patch.edits.iter().chain(&bulk_edits) {
+        if !seen.insert((edit.row_id, edit.column.as_str())) {
+            continue;
+        }
+        let (Some(row), Some(col)) = (table.find_row(edit.row_id), table.find_column(&edit.column))
+        else {
+            continue;
+        };
+        snapshot.push(CellSnapshot {
+            row_id: edit.row_id,
+            column: edit.column.clone(),
+            old_value: row.cells[col.index].value.to_string_value(),
+        });
+    }
+    snapshot
+}
+
+/// Create a marker entry that labels the current state of a family
+///
+/// The entry carries an empty patch, so undoing it changes no files.
+pub fn create_marker_entry(family: &str, label: &str) -> HistoryEntry {
+    HistoryEntry {
+        timestamp: Utc::now(),
+        family: family.to_string(),
+        patch: PatchFile::new(family),
+        output_files: Vec::new(),
+        output_dir: PathBuf::new(),
+        reverse: None,
+        label: Some(label.to_string()),
+        snapshot: Vec::new(),
+        file_hashes: BTreeMap::new(),
+    }
+}
+
+/// Get the temporary file `HistoryFile::save` writes before renaming it
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::patch::Edit;
+    use crate::merger::{merge_family, merge_tables};
+    use crate::parser::parse_csv_str;
+    use crate::patch::export_with_edits;
+    use crate::scanner::scan_directory;
+    use crate::test_util::scratch_dir;
+    use chrono::Duration;
+
+    fn table() -> ResolvedTable {
+        let csv = "ID,col,Value\n1,old,100\n2,other,200\n";
+        merge_tables("test_family", vec![parse_csv_str(csv, "test.csv").unwrap()]).unwrap()
+    }
 
     #[test]
     fn test_history_add_and_get() {
@@ -121,6 +477,7 @@ mod tests {
         patch.add_edit(Edit::new(1, "col", "val"));
 
         let entry = create_history_entry(
+            &table(),
             &patch,
             vec![PathBuf::from("output.csv")],
             PathBuf::from("exports"),
@@ -133,6 +490,57 @@ mod tests {
         assert!(history.get_last_entry("test_family").is_some());
     }
 
+    #[test]
+    fn test_snapshot_records_pre_edit_values() {
+        let table = table();
+        let mut patch = PatchFile::new("test_family");
+        patch.description = Some("Retune values".to_string());
+        patch.add_edit(Edit::new(1, "col", "new"));
+        patch.add_edit(Edit::new(2, "Value", "250"));
+        patch.add_edit(Edit::new(1, "col", "newer"));
+        patch.add_edit(Edit::new(9, "col", "missing row"));
+
+        let entry = create_history_entry(&table, &patch, vec![], PathBuf::from("exports"));
+        let snapshot: Vec<_> = entry
+            .snapshot
+            .iter()
+            .map(|c| (c.row_id, c.column.as_str(), c.old_value.as_str()))
+            .collect();
+        assert_eq!(snapshot, vec![(1, "col", "old"), (2, "Value", "200")]);
+
+        // The snapshot and description survive a save; the snapshot gives
+        // the inverse edits
+        let json = serde_json::to_string(&entry).unwrap();
+        let entry: HistoryEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(entry.description(), Some("Retune values"));
+        let inverse = entry.inverse_patch();
+        let edits: Vec<_> = inverse.edits.iter().map(|e| (e.row_id, e.value.as_str())).collect();
+        assert_eq!(edits, vec![(1, "old"), (2, "200")]);
+    }
+
+    #[test]
+    fn test_snapshot_undoes_in_place_patch() {
+        let dir = scratch_dir("snapshot");
+        let source = dir.join("items.csv");
+        let original = "ID,Name,Value\n1,foo,100\n2,bar,200\n";
+        fs::write(&source, original).unwrap();
+
+        let merge = || merge_family(scan_directory(&[&dir]).unwrap().find_family("items").unwrap());
+        let merged = merge().unwrap();
+        let mut patch = PatchFile::new("items");
+        patch.add_edit(Edit::new(2, "Value", "250"));
+        patch.add_edit(Edit::new(1, "Name", "baz"));
+        let entry = create_history_entry(&merged, &patch, vec![], dir.clone());
+
+        let options = ExportOptions::default();
+        apply_in_place(&merged, &patch, &options).unwrap();
+        assert_ne!(fs::read_to_string(&source).unwrap(), original);
+        apply_in_place(&merge().unwrap(), &entry.inverse_patch(), &options).unwrap();
+        assert_eq!(fs::read_to_string(&source).unwrap(), original);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_history_pop() {
         let mut history = HistoryFile::new();
@@ -141,6 +549,7 @@ mod tests {
         patch.add_edit(Edit::new(1, "col", "val"));
 
         let entry = create_history_entry(
+            &table(),
             &patch,
             vec![PathBuf::from("output.csv")],
             PathBuf::from("exports"),
@@ -152,4 +561,153 @@ mod tests {
         assert!(popped.is_some());
         assert_eq!(history.total_entries(), 0);
     }
+
+    #[test]
+    fn test_marker_entry_round_trip() {
+        let mut history = HistoryFile::new();
+
+        let mut patch = PatchFile::new("test_family");
+        patch.add_edit(Edit::new(1, "col", "val"));
+        history.add_entry(create_history_entry(&table(), &patch, vec![], "exports".into()));
+        history.add_entry(create_marker_entry("test_family", "baseline"));
+
+        let json = serde_json::to_string(&history).unwrap();
+        let mut history: HistoryFile = serde_json::from_str(&json).unwrap();
+
+        let marker = history.pop_last_entry("test_family").unwrap();
+        assert!(marker.is_marker());
+        assert_eq!(marker.label.as_deref(), Some("baseline"));
+        assert!(marker.output_files.is_empty());
+
+        let last = history.get_last_entry("test_family").unwrap();
+        assert!(!last.is_marker());
+        assert_eq!(last.patch.edits.len(), 1);
+    }
+
+    #[test]
+    fn test_undo_then_redo_restores_patch() {
+        let mut history = HistoryFile::new();
+
+        let mut patch = PatchFile::new("test_family");
+        patch.add_edit(Edit::new(1, "col", "val"));
+        history.add_entry(create_history_entry(
+            &table(),
+            &patch,
+            vec![PathBuf::from("output.csv")],
+            PathBuf::from("exports"),
+        ));
+
+        history.pop_last_entry("test_family").unwrap();
+        assert_eq!(history.total_entries(), 0);
+        assert!(history.get_last_redo("test_family").is_some());
+
+        // The redo stack survives a save and load
+        let json = serde_json::to_string(&history).unwrap();
+        let mut history: HistoryFile = serde_json::from_str(&json).unwrap();
+
+        let redone = history.redo_last_entry("test_family").unwrap();
+        assert_eq!(redone.patch.edits.len(), 1);
+        assert_eq!(redone.patch.edits[0].value, "val");
+        assert_eq!(history.total_entries(), 1);
+        assert!(history.redo_last_entry("test_family").is_none());
+        assert!(history.redo.is_empty());
+    }
+
+    #[test]
+    fn test_interrupted_save_keeps_history() {
+        let dir = scratch_dir("history");
+        let path = dir.join("history.json");
+
+        let mut history = HistoryFile::new();
+        history.add_entry(create_marker_entry("items", "first"));
+        history.save(&path).unwrap();
+        assert!(!temp_path(&path).exists());
+
+        // A save that died mid-write leaves a truncated temporary file
+        fs::write(temp_path(&path), "{\"entries\": {\"ite").unwrap();
+        let loaded = HistoryFile::load(&path).unwrap();
+        assert_eq!(loaded.get_last_entry("items").unwrap().label.as_deref(), Some("first"));
+
+        // The next save replaces both
+        history.add_entry(create_marker_entry("items", "second"));
+        history.save(&path).unwrap();
+        assert!(!temp_path(&path).exists());
+        let loaded = HistoryFile::load(&path).unwrap();
+        assert_eq!(loaded.get_family_history("items").unwrap().len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_new_entry_clears_redo() {
+        let mut history = HistoryFile::new();
+        history.add_entry(create_marker_entry("test_family", "one"));
+        history.pop_last_entry("test_family");
+
+        history.add_entry(create_marker_entry("test_family", "two"));
+        assert!(history.get_last_redo("test_family").is_none());
+    }
+
+    #[test]
+    fn test_undo_to_reverts_two_stacked_patches() {
+        let dir = scratch_dir("undo-to");
+        let root = dir.join("override");
+        let output = dir.join("exports");
+        fs::create_dir_all(&root).unwrap();
+
+        let base = "ID,Name,Value\n1,foo,100\n2,bar,200\n";
+        let variant = "ID,Name,Value\n3,baz,300\n";
+        fs::write(root.join("items.csv"), base).unwrap();
+        fs::write(root.join("items_ep1.csv"), variant).unwrap();
+
+        let scan_result = scan_directory(&[&root]).unwrap();
+        let family = scan_result.find_family("items").unwrap();
+        let merged = merge_family(family).unwrap();
+
+        let start = Utc::now();
+        let mut history = HistoryFile::new();
+        let mut record = |edit: Edit, offset: i64| {
+            let mut patch = PatchFile::new("items");
+            patch.add_edit(edit);
+            let result = export_with_edits_with_options(
+                &merged,
+                &patch,
+                &output,
+                &ExportOptions {
+                    overwrite: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let mut entry =
+                create_history_entry(&merged, &patch, result.files_written, output.clone());
+            entry.timestamp = start + Duration::seconds(offset);
+            history.add_entry(entry);
+        };
+        record(Edit::new(1, "Value", "150"), 0);
+        record(Edit::new(3, "Value", "350"), 1);
+        record(Edit::new(2, "Value", "250"), 2);
+
+        let result = history.undo_to(family, start, &output).unwrap();
+        assert_eq!(result.undone.len(), 2);
+        assert!(result.errors.is_empty());
+
+        // items.csv goes back to the first patch's export, items_ep1.csv to the source
+        let mut first = PatchFile::new("items");
+        first.add_edit(Edit::new(1, "Value", "150"));
+        let expected = dir.join("expected");
+        export_with_edits(&merged, &first, &expected).unwrap();
+        assert_eq!(
+            fs::read_to_string(output.join("items.csv")).unwrap(),
+            fs::read_to_string(expected.join("items.csv")).unwrap()
+        );
+        assert_eq!(fs::read_to_string(output.join("items_ep1.csv")).unwrap(), variant);
+
+        assert_eq!(history.total_entries(), 1);
+        assert!(history.entries_since("items", start).is_empty());
+        let next_redo = history.get_last_redo("items").unwrap();
+        assert_eq!(next_redo.patch.edits[0].row_id, 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
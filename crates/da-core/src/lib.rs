@@ -1,25 +1,78 @@
 //! da-core: Core library for parsing and merging Dragon Age 2DA tables
 //!
 //! This library provides functionality to:
-//! - Scan directories for CSV files (exported from 2DA format)
+//! - Scan directories or zip archives for CSV files (exported from 2DA format)
 //! - Parse CSV files into structured tables
 //! - Group files into "families" based on naming conventions
+//! - Search the merged cell values of every family
 //! - Merge family members with provenance tracking
+//! - Cache merged tables until their source files change
+//! - Compare merged tables row by row and cell by cell
+//! - Render merged tables as aligned plain text
 //! - Apply patches (edits) and export modified source files
+//! - Write parsed and merged tables back out as CSV
+//! - Check which cells a file would lose in a parse and write round trip
+//! - Export merged tables to the game's binary 2DA (GDA) format
+//! - Export merged tables to a SQLite database
 //! - Track patch history for undo support
 
+pub mod cache;
+pub mod csv_writer;
+pub mod diff;
+pub mod encoding;
 pub mod error;
+pub mod filter;
+pub mod gda;
 pub mod history;
 pub mod merger;
 pub mod parser;
 pub mod patch;
+pub mod render;
+pub mod roundtrip;
 pub mod scanner;
+pub mod search;
+pub mod sqlite;
 pub mod table;
+#[cfg(test)]
+mod test_util;
 
+pub use cache::{merge_family_cached, merge_family_cached_with_options, MergeCache};
+pub use csv_writer::{write_csv, write_resolved, write_resolved_with_line_ending, LineEnding};
+pub use diff::{diff_tables, CellChange, TableDiff};
+pub use encoding::OutputEncoding;
 pub use error::{Error, Result};
-pub use history::{create_history_entry, HistoryEntry, HistoryFile};
-pub use merger::{merge_family, ResolvedCell, ResolvedRow, ResolvedTable};
-pub use parser::parse_csv;
-pub use patch::{apply_patch, export_with_edits, BatchFile, Edit, ExportResult, PatchFile, PatchResult};
-pub use scanner::{scan_directory, Family, FamilyMember};
-pub use table::{CellValue, Column, Row, Table};
+pub use filter::{parse_row_selection, FilterExpr, RowSelection};
+pub use gda::{export_2da, write_2da};
+pub use history::{
+    create_history_entry, create_marker_entry, file_hash, snapshot_edits, CellSnapshot,
+    HistoryEntry, HistoryFile, UndoResult,
+};
+pub use merger::{
+    check_column_names, check_id_columns, merge_all, merge_all_with_options, merge_family,
+    merge_family_from, merge_family_from_with_options, merge_family_with_options,
+    merge_family_with_progress, merge_family_with_report, merge_tables, merge_tables_with_options,
+    merge_tables_with_report, ColumnStats, CompareOp, EmptyOverride, IdColumnMismatch,
+    MergeConflict, MergeOptions, MergeProgress, MergeReport, MergeWarning, ResolvedCell,
+    ResolvedRow, ResolvedTable, TypeDivergence, CLEAR_SENTINEL,
+};
+pub use parser::{
+    parse_csv, parse_csv_streaming, parse_csv_streaming_with_options, parse_csv_with_options,
+    EmptyIdPolicy, IdColumn, ParseOptions, RowErrorPolicy, RowIter, MAX_INDEXED_COLUMNS,
+};
+pub use patch::{
+    apply_in_place, apply_patch, backup_path, export_multi_with_edits, export_with_edits,
+    export_with_edits_with_options, invert_patch, preview_edit, validate_patch, BatchEntry,
+    BatchFile, BulkEdit, ColumnOp, ColumnOpValidation, Edit, EditValidation, ExportOptions,
+    ExportResult, MultiPatchFile, PatchFile, PatchResult, RowOp, RowOpValidation, ValidationReport,
+};
+pub use render::{render_aligned, TextRenderOptions};
+pub use roundtrip::{verify_roundtrip, RoundtripDifference, RoundtripReport};
+pub use scanner::{
+    scan_directory, scan_directory_with_options, scan_directory_with_progress, scan_zip,
+    scan_zip_reader, scan_zip_with_options, search_families, ExtraColumns, Family, FamilyMember,
+    FamilySearchHit, MissingColumn, Rescan, ScanOptions, ScanProgress, ScanResult, ShapeReport,
+    DEFAULT_MAX_ARCHIVE_ENTRY_SIZE, DEFAULT_SUFFIXES,
+};
+pub use search::{search_values, ValueHit, ValueSearchOptions};
+pub use sqlite::export_sqlite;
+pub use table::{CellValue, Column, ColumnType, ParseWarning, ParseWarningKind, Row, Table};
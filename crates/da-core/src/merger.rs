@@ -1,12 +1,17 @@
 //! Merge engine for combining family tables with provenance tracking
 
 use crate::error::{Error, Result};
-use crate::parser::parse_csv;
+use crate::filter::{FilterExpr, RowSelection};
+use crate::parser::ParseOptions;
 use crate::scanner::Family;
-use crate::table::{CellValue, Column, Table};
+use crate::table::{find_ignoring_case, CellValue, Column, ColumnType, Table};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashSet};
-use std::path::PathBuf;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
 
 /// A merged table with provenance information for each cell
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +24,12 @@ pub struct ResolvedTable {
     pub rows: Vec<ResolvedRow>,
     /// Files that contributed to this table, in merge order
     pub sources: Vec<PathBuf>,
+    /// Index of the column holding the row IDs, as in the first file
+    #[serde(default)]
+    pub id_column: usize,
+    /// Row ID -> index into `rows`, built on the first lookup
+    #[serde(skip)]
+    row_index: OnceLock<HashMap<i64, usize>>,
 }
 
 impl ResolvedTable {
@@ -32,14 +43,56 @@ impl ResolvedTable {
         self.rows.len()
     }
 
+    /// Get the column names, in column order
+    pub fn column_names(&self) -> Vec<&str> {
+        self.columns.iter().map(|c| c.name.as_str()).collect()
+    }
+
     /// Find a column by name
     pub fn find_column(&self, name: &str) -> Option<&Column> {
         self.columns.iter().find(|c| c.name == name)
     }
 
+    /// Find a column by name, ignoring case if there is no exact match
+    pub fn find_column_ci(&self, name: &str) -> Result<Option<&Column>> {
+        find_ignoring_case(&self.columns, name, |c| &c.name)
+    }
+
     /// Find a row by ID
+    ///
+    /// The first lookup builds an index of every row ID, so later lookups
+    /// take constant time.
     pub fn find_row(&self, id: i64) -> Option<&ResolvedRow> {
-        self.rows.iter().find(|r| r.id == Some(id))
+        self.row_position(id).map(|index| &self.rows[index])
+    }
+
+    /// Get the index in `rows` of the row with this ID
+    ///
+    /// Uses the same index as `find_row`. After editing `rows` directly,
+    /// call `invalidate_row_index`: moved and removed rows are handled
+    /// without it, but rows added since the index was built are not found.
+    pub fn row_position(&self, id: i64) -> Option<usize> {
+        let index = self.row_index.get_or_init(|| {
+            let mut index = HashMap::with_capacity(self.rows.len());
+            for (i, row) in self.rows.iter().enumerate() {
+                if let Some(id) = row.id {
+                    index.entry(id).or_insert(i);
+                }
+            }
+            index
+        });
+        match index.get(&id) {
+            Some(&i) if self.rows.get(i).is_some_and(|r| r.id == Some(id)) => Some(i),
+            Some(_) => self.rows.iter().position(|r| r.id == Some(id)),
+            None => None,
+        }
+    }
+
+    /// Drop the row ID index after adding, removing or reordering `rows`
+    ///
+    /// The methods of this type that change the rows do this themselves.
+    pub fn invalidate_row_index(&mut self) {
+        self.row_index = OnceLock::new();
     }
 
     /// Get provenance for a specific cell
@@ -49,12 +102,351 @@ impl ResolvedTable {
             .and_then(|r| r.cells.get(col_idx))
             .map(|c| &c.source)
     }
+
+    /// Get up to `count` rows starting at index `start`
+    ///
+    /// The slice is shorter (or empty) when the range runs past the end.
+    pub fn row_slice(&self, start: usize, count: usize) -> &[ResolvedRow] {
+        let start = start.min(self.rows.len());
+        let end = start.saturating_add(count).min(self.rows.len());
+        &self.rows[start..end]
+    }
+
+    /// Get the distinct files that provided any cell of a row, in merge order
+    pub fn row_sources(&self, row_id: i64) -> Vec<&PathBuf> {
+        let Some(row) = self.find_row(row_id) else {
+            return Vec::new();
+        };
+        self.sources
+            .iter()
+            .filter(|source| row.cells.iter().any(|c| &c.source == *source))
+            .collect()
+    }
+
+    /// Count the cells each source file won, for every file in `sources`
+    ///
+    /// The ID cells of keyed rows aren't counted, as every file that
+    /// mentions a row repeats its ID. Files that won nothing count 0.
+    pub fn source_contribution(&self) -> HashMap<PathBuf, usize> {
+        let mut counts: HashMap<PathBuf, usize> =
+            self.sources.iter().map(|s| (s.clone(), 0)).collect();
+        for row in &self.rows {
+            for (index, cell) in row.cells.iter().enumerate() {
+                if row.id.is_some() && index == self.id_column {
+                    continue;
+                }
+                *counts.entry(cell.source.clone()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Get the files in `sources` that won no cells, in merge order
+    ///
+    /// Such a file only repeats values that are already set, or is
+    /// overridden everywhere by later files.
+    pub fn unused_sources(&self) -> Vec<&PathBuf> {
+        let counts = self.source_contribution();
+        self.sources.iter().filter(|s| counts[*s] == 0).collect()
+    }
+
+    /// Get every cell of a keyed row whose value didn't come from the
+    /// first file in merge order (normally the base file)
+    ///
+    /// Cells of rows a variant added are included, as the base has no
+    /// value for them. The ID column is left out, as a row's ID is the
+    /// same in every file.
+    pub fn overridden_cells(&self) -> Vec<(i64, &str, &PathBuf)> {
+        let Some(base) = self.sources.first() else {
+            return Vec::new();
+        };
+        self.rows
+            .iter()
+            .filter_map(|row| Some((row.id?, row)))
+            .flat_map(|(id, row)| {
+                let columns = self.columns.iter().filter(|c| c.index != self.id_column);
+                columns.filter_map(move |col| {
+                    let cell = row.cells.get(col.index)?;
+                    (cell.source != *base).then_some((id, col.name.as_str(), &cell.source))
+                })
+            })
+            .collect()
+    }
+
+    /// Infer the type of a column from its merged values
+    pub fn infer_column_type(&self, col_index: usize) -> ColumnType {
+        ColumnType::infer(
+            self.rows
+                .iter()
+                .filter_map(|r| r.cells.get(col_index).map(|c| &c.value)),
+        )
+    }
+
+    /// Convert each row to a JSON object mapping column names to values
+    ///
+    /// Keys follow the column order. Numbers stay numbers, empty cells are
+    /// `null`, and provenance is left out.
+    pub fn rows_as_json_objects(&self) -> Vec<serde_json::Value> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let object = self
+                    .columns
+                    .iter()
+                    .map(|col| {
+                        let value = match row.cells.get(col.index).map(|c| &c.value) {
+                            Some(CellValue::Integer(i)) => serde_json::Value::from(*i),
+                            Some(CellValue::Float(f)) => serde_json::Value::from(*f),
+                            Some(value @ CellValue::Number { value: f, .. }) => value
+                                .as_i64()
+                                .map_or_else(|| serde_json::Value::from(*f), Into::into),
+                            Some(CellValue::String(s) | CellValue::Hex { text: s, .. }) => {
+                                serde_json::Value::from(s.as_str())
+                            }
+                            Some(CellValue::Empty) | None => serde_json::Value::Null,
+                        };
+                        (col.name.clone(), value)
+                    })
+                    .collect();
+                serde_json::Value::Object(object)
+            })
+            .collect()
+    }
+
+    /// Convert the table to JSON with each cell tagged by its type
+    ///
+    /// The derived serialization writes cells as `{"Integer": 5}`; this
+    /// gives `{"type": "int", "value": 5}` instead, which is easier to
+    /// consume from JavaScript. The types are `int`, `float`, `string`,
+    /// `hex` (its text, as a JS number can't hold every `u64`) and `empty`
+    /// (with a `null` value). Each row's cells follow `columns`; provenance
+    /// is left out.
+    pub fn to_typed_json(&self) -> serde_json::Value {
+        let rows: Vec<serde_json::Value> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let cells: Vec<serde_json::Value> = self
+                    .columns
+                    .iter()
+                    .map(|col| {
+                        let (kind, value) = match row.cells.get(col.index).map(|c| &c.value) {
+                            Some(CellValue::Integer(i)) => ("int", serde_json::Value::from(*i)),
+                            Some(CellValue::Float(f)) => ("float", serde_json::Value::from(*f)),
+                            Some(value @ CellValue::Number { value: f, .. }) => {
+                                match value.as_i64() {
+                                    Some(i) => ("int", serde_json::Value::from(i)),
+                                    None => ("float", serde_json::Value::from(*f)),
+                                }
+                            }
+                            Some(CellValue::String(s)) => {
+                                ("string", serde_json::Value::from(s.as_str()))
+                            }
+                            Some(CellValue::Hex { text, .. }) => {
+                                ("hex", serde_json::Value::from(text.as_str()))
+                            }
+                            Some(CellValue::Empty) | None => ("empty", serde_json::Value::Null),
+                        };
+                        serde_json::json!({ "type": kind, "value": value })
+                    })
+                    .collect();
+                serde_json::json!({ "id": row.id, "cells": cells })
+            })
+            .collect();
+
+        let columns: Vec<&str> = self.columns.iter().map(|c| c.name.as_str()).collect();
+        serde_json::json!({
+            "family": self.family_name,
+            "columns": columns,
+            "rows": rows,
+        })
+    }
+
+    /// Count a column's cells by type and aggregate its numbers
+    ///
+    /// Strings such as `****` sentinels are counted but left out of the
+    /// aggregates, which are `None` when the column holds no numbers.
+    pub fn column_stats(&self, col_index: usize) -> ColumnStats {
+        let mut stats = ColumnStats::default();
+        let mut numbers = Vec::new();
+        for row in &self.rows {
+            let value = row.cells.get(col_index).map_or(&CellValue::Empty, |c| &c.value);
+            match value {
+                CellValue::Integer(_) => stats.integers += 1,
+                CellValue::Number { .. } if value.as_i64().is_some() => stats.integers += 1,
+                CellValue::Float(_) | CellValue::Number { .. } => stats.floats += 1,
+                CellValue::String(_) => stats.strings += 1,
+                CellValue::Hex { .. } => stats.hex += 1,
+                CellValue::Empty => stats.empty += 1,
+            }
+            numbers.extend(value.as_f64());
+        }
+
+        if numbers.is_empty() {
+            return stats;
+        }
+        numbers.sort_by(f64::total_cmp);
+        let mid = numbers.len() / 2;
+        stats.min = numbers.first().copied();
+        stats.max = numbers.last().copied();
+        stats.mean = Some(numbers.iter().sum::<f64>() / numbers.len() as f64);
+        stats.median = Some(if numbers.len() % 2 == 0 {
+            (numbers[mid - 1] + numbers[mid]) / 2.0
+        } else {
+            numbers[mid]
+        });
+        stats
+    }
+
+    /// Get the indices of the rows whose number in a column compares to `value`
+    ///
+    /// Integer and float cells compare by value; empty and text cells never
+    /// match, not even `!=`.
+    pub fn filter_numeric(&self, col_index: usize, op: CompareOp, value: f64) -> Vec<usize> {
+        self.rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| {
+                row.cells
+                    .get(col_index)
+                    .and_then(|c| c.value.as_f64())
+                    .is_some_and(|n| op.matches(n, value))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Get the indices of the rows a filter expression matches
+    ///
+    /// Fails if the expression names a column the table doesn't have.
+    pub fn filter_expr(&self, expr: &FilterExpr) -> Result<Vec<usize>> {
+        expr.matching_rows(self)
+    }
+
+    /// Keep only the keyed rows whose ID is in `selection`
+    ///
+    /// IDs the selection names that the table lacks are ignored, and rows
+    /// without an ID are dropped.
+    pub fn select_rows(&mut self, selection: &RowSelection) {
+        self.rows
+            .retain(|row| row.id.is_some_and(|id| selection.contains(id)));
+        self.invalidate_row_index();
+    }
+
+    /// Reorder rows by the values in one column
+    ///
+    /// With `numeric` set, numbers compare by value and any non-numeric
+    /// strings follow them in text order; otherwise every value compares as
+    /// text. Empty cells always go last, in either direction. The sort is
+    /// stable, so rows with equal keys keep their relative order.
+    pub fn sort_by_column(&mut self, col_index: usize, ascending: bool, numeric: bool) {
+        self.rows.sort_by(|a, b| {
+            let a = a.cells.get(col_index).map_or(&CellValue::Empty, |c| &c.value);
+            let b = b.cells.get(col_index).map_or(&CellValue::Empty, |c| &c.value);
+
+            match (a.is_empty(), b.is_empty()) {
+                (true, true) => return Ordering::Equal,
+                (true, false) => return Ordering::Greater,
+                (false, true) => return Ordering::Less,
+                (false, false) => {}
+            }
+
+            let ord = if numeric {
+                match (a.as_f64(), b.as_f64()) {
+                    (Some(x), Some(y)) => x.total_cmp(&y),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => a.to_string_value().cmp(&b.to_string_value()),
+                }
+            } else {
+                a.to_string_value().cmp(&b.to_string_value())
+            };
+
+            if ascending {
+                ord
+            } else {
+                ord.reverse()
+            }
+        });
+        self.invalidate_row_index();
+    }
+}
+
+/// Summary of one column of a merged table
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColumnStats {
+    /// Cells holding integers
+    pub integers: usize,
+    /// Cells holding floats
+    pub floats: usize,
+    /// Cells holding non-numeric text
+    pub strings: usize,
+    /// Cells holding hex values, which are left out of the aggregates
+    pub hex: usize,
+    /// Empty cells
+    pub empty: usize,
+    /// Smallest number in the column
+    pub min: Option<f64>,
+    /// Largest number in the column
+    pub max: Option<f64>,
+    /// Mean of the numbers in the column
+    pub mean: Option<f64>,
+    /// Median of the numbers in the column
+    pub median: Option<f64>,
+}
+
+/// Comparison used by `ResolvedTable::filter_numeric`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompareOp {
+    /// `<`
+    Less,
+    /// `<=`
+    LessOrEqual,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterOrEqual,
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+}
+
+impl CompareOp {
+    /// Check whether `left op right` holds
+    pub fn matches(self, left: f64, right: f64) -> bool {
+        match self {
+            CompareOp::Less => left < right,
+            CompareOp::LessOrEqual => left <= right,
+            CompareOp::Greater => left > right,
+            CompareOp::GreaterOrEqual => left >= right,
+            CompareOp::Equal => left == right,
+            CompareOp::NotEqual => left != right,
+        }
+    }
+}
+
+impl FromStr for CompareOp {
+    type Err = Error;
+
+    /// Parse an operator written as `<`, `<=`, `>`, `>=`, `==` or `!=`
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "<" => Ok(CompareOp::Less),
+            "<=" => Ok(CompareOp::LessOrEqual),
+            ">" => Ok(CompareOp::Greater),
+            ">=" => Ok(CompareOp::GreaterOrEqual),
+            "==" => Ok(CompareOp::Equal),
+            "!=" => Ok(CompareOp::NotEqual),
+            other => Err(Error::InvalidComparison(other.to_string())),
+        }
+    }
 }
 
 /// A row in the resolved table
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResolvedRow {
-    /// Row ID (from first column if numeric)
+    /// Row ID (from the ID column if numeric)
     pub id: Option<i64>,
     /// Cells with provenance
     pub cells: Vec<ResolvedCell>,
@@ -75,28 +467,285 @@ impl ResolvedCell {
     }
 }
 
+/// A cell where a later file overrode a different non-empty value
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeConflict {
+    /// Row ID
+    pub row_id: i64,
+    /// Column name
+    pub column: String,
+    /// The value that was overridden
+    pub losing_value: CellValue,
+    /// The file that provided the overridden value
+    pub losing_source: PathBuf,
+    /// The value that won
+    pub winning_value: CellValue,
+    /// The file that provided the winning value
+    pub winning_source: PathBuf,
+}
+
+/// A column typed as a string in some source files and as numbers in others
+///
+/// Integer and float columns are compatible and aren't reported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypeDivergence {
+    /// Column name
+    pub column: String,
+    /// Type of the column in each source file with a non-empty value, in merge order
+    pub types: Vec<(PathBuf, ColumnType)>,
+}
+
+/// Source files that disagree on whether their ID column holds row IDs
+///
+/// Rows from keyed files merge by ID while rows from unkeyed files are
+/// appended, so mixing the two rarely gives the intended table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdColumnMismatch {
+    /// Files with at least one numeric row ID, in merge order
+    pub keyed: Vec<PathBuf>,
+    /// Files with rows but no numeric row IDs, in merge order
+    pub unkeyed: Vec<PathBuf>,
+}
+
+/// A problem with the source files' columns that can corrupt a merge
+///
+/// Columns are matched by name alone, so hex labels that collided after
+/// truncation, or a file that repeats a name, silently mix unrelated data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MergeWarning {
+    /// A file has several columns with the same name; only the last of
+    /// them is merged
+    DuplicateColumn {
+        /// File with the repeated name
+        source: PathBuf,
+        /// The repeated column name
+        column: String,
+        /// 0-based indices of the columns with that name
+        indices: Vec<usize>,
+    },
+    /// A file with as many columns as the first file has a shared column
+    /// in a different place, which suggests the names mean different things
+    ColumnMoved {
+        /// Column name
+        column: String,
+        /// Index of the column in the first file
+        base_index: usize,
+        /// File where the column sits elsewhere
+        source: PathBuf,
+        /// Index of the column in that file
+        index: usize,
+    },
+}
+
+/// Diagnostics collected while merging
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergeReport {
+    /// Cells where a later file overrode a different non-empty value
+    pub conflicts: Vec<MergeConflict>,
+    /// Columns whose type differs between source files
+    pub type_divergences: Vec<TypeDivergence>,
+    /// Keyed and unkeyed source files mixed in one family
+    #[serde(default)]
+    pub id_column_mismatch: Option<IdColumnMismatch>,
+    /// Column layouts that suggest names colliding across or within files
+    #[serde(default)]
+    pub warnings: Vec<MergeWarning>,
+}
+
+/// Options controlling how family members are merged
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MergeOptions {
+    /// Variant suffixes in load order; later suffixes override earlier ones
+    ///
+    /// Unlisted variants load before all listed ones, alphabetically by
+    /// suffix. An empty list keeps the plain alphabetical order.
+    pub precedence: Vec<String>,
+    /// What an empty cell in a later file does to the value it overrides
+    pub empty_override: EmptyOverride,
+    /// Keep rows in the order they are first seen across the files instead
+    /// of putting keyed rows first in ID order; rows are still merged by ID
+    pub preserve_order: bool,
+    /// Columns whose first non-empty value for a row is never overridden,
+    /// such as a descriptive `Label`
+    ///
+    /// Later files can still fill a protected cell left empty and give
+    /// values to rows they add. Names are matched exactly.
+    pub protected_columns: Vec<String>,
+    /// How each member file is parsed
+    pub parse: ParseOptions,
+}
+
+/// How empty cells are treated when a later file overrides an earlier one
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyOverride {
+    /// Empty cells never override; the earlier value is kept
+    #[default]
+    Ignore,
+    /// An empty cell clears the earlier value, if the later file has the
+    /// column at all
+    Clear,
+    /// Empty cells never override, but a cell holding exactly this text
+    /// clears the earlier value (see `CLEAR_SENTINEL`)
+    Sentinel(String),
+}
+
+/// Conventional cell text for `EmptyOverride::Sentinel`
+pub const CLEAR_SENTINEL: &str = "__CLEAR__";
+
+impl EmptyOverride {
+    /// The sentinel mode with the conventional `__CLEAR__` text
+    pub fn sentinel() -> Self {
+        EmptyOverride::Sentinel(CLEAR_SENTINEL.to_string())
+    }
+}
+
 /// Merge a family of tables into a single resolved table
 pub fn merge_family(family: &Family) -> Result<ResolvedTable> {
+    merge_family_with_report(family).map(|(table, _)| table)
+}
+
+/// Merge a family and report value conflicts and column type divergences
+pub fn merge_family_with_report(family: &Family) -> Result<(ResolvedTable, MergeReport)> {
+    merge_family_with_options(family, &MergeOptions::default())
+}
+
+/// Merge a family, reusing tables the caller has already parsed
+///
+/// Each member takes the table in `tables` whose `source_path` is the
+/// member's path; members without one are parsed as `merge_family` would.
+/// Tables belonging to no member are ignored. The result, provenance
+/// included, is the same as parsing every file afresh, as long as the
+/// tables were parsed with default options (or with `options.parse` for
+/// `merge_family_from_with_options`).
+pub fn merge_family_from(family: &Family, tables: Vec<Table>) -> Result<ResolvedTable> {
+    merge_family_from_with_options(family, tables, &MergeOptions::default()).map(|(table, _)| table)
+}
+
+/// Merge a family from already-parsed tables using the given options
+pub fn merge_family_from_with_options(
+    family: &Family,
+    tables: Vec<Table>,
+    options: &MergeOptions,
+) -> Result<(ResolvedTable, MergeReport)> {
+    if family.members.is_empty() {
+        return Err(Error::FamilyNotFound(family.name.clone()));
+    }
+
+    let mut parsed: HashMap<PathBuf, Table> =
+        tables.into_iter().map(|t| (t.source_path.clone(), t)).collect();
+    let mut ordered = Vec::with_capacity(family.members.len());
+    for member in family.ordered_members(&options.precedence) {
+        let table = match parsed.remove(&member.path) {
+            Some(table) => table,
+            None => member.parse(&options.parse)?,
+        };
+        ordered.push(table);
+    }
+
+    merge_tables_with_options(&family.name, ordered, options)
+}
+
+/// Merge several families in parallel
+///
+/// The results are in the same order as `families`, and a family that
+/// fails to merge doesn't stop the others.
+pub fn merge_all(families: &[Family]) -> Vec<Result<ResolvedTable>> {
+    merge_all_with_options(families, &MergeOptions::default())
+}
+
+/// Merge several families in parallel using the given options
+pub fn merge_all_with_options(
+    families: &[Family],
+    options: &MergeOptions,
+) -> Vec<Result<ResolvedTable>> {
+    families
+        .par_iter()
+        .map(|family| merge_family_with_options(family, options).map(|(table, _)| table))
+        .collect()
+}
+
+/// Progress of a running merge, passed to the callback of
+/// `merge_family_with_progress`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergeProgress<'a> {
+    /// File that was just parsed
+    pub file: &'a Path,
+    /// Member files parsed so far, including `file`
+    pub parsed: usize,
+    /// Member files in the family
+    pub total: usize,
+}
+
+/// Merge a family using the given options, collecting a merge report
+pub fn merge_family_with_options(
+    family: &Family,
+    options: &MergeOptions,
+) -> Result<(ResolvedTable, MergeReport)> {
+    merge_family_with_progress(family, options, |_| {})
+}
+
+/// Merge a family using the given options, reporting each parsed file
+pub fn merge_family_with_progress(
+    family: &Family,
+    options: &MergeOptions,
+    mut progress: impl FnMut(MergeProgress),
+) -> Result<(ResolvedTable, MergeReport)> {
     if family.members.is_empty() {
         return Err(Error::FamilyNotFound(family.name.clone()));
     }
 
     // Parse all member files
+    let total = family.members.len();
     let mut tables: Vec<Table> = Vec::new();
-    for member in &family.members {
-        let table = parse_csv(&member.path)?;
+    for member in family.ordered_members(&options.precedence) {
+        let table = member.parse(&options.parse)?;
         tables.push(table);
+        progress(MergeProgress {
+            file: &member.path,
+            parsed: tables.len(),
+            total,
+        });
     }
 
-    merge_tables(&family.name, tables)
+    merge_tables_with_options(&family.name, tables, options)
 }
 
 /// Merge multiple tables into a resolved table
+///
+/// Keyed rows come first, in ID order. Rows without an ID follow, sorted by
+/// source path and then by their position within that file.
 pub fn merge_tables(family_name: &str, tables: Vec<Table>) -> Result<ResolvedTable> {
+    merge_tables_with_report(family_name, tables).map(|(table, _)| table)
+}
+
+/// Merge multiple tables into a resolved table, collecting a merge report
+pub fn merge_tables_with_report(
+    family_name: &str,
+    tables: Vec<Table>,
+) -> Result<(ResolvedTable, MergeReport)> {
+    merge_tables_with_options(family_name, tables, &MergeOptions::default())
+}
+
+/// Merge multiple tables using the given options, collecting a merge report
+///
+/// The tables are merged in the order given, so `options.precedence` has no
+/// effect here; `merge_family_with_options` applies it when ordering files.
+pub fn merge_tables_with_options(
+    family_name: &str,
+    tables: Vec<Table>,
+    options: &MergeOptions,
+) -> Result<(ResolvedTable, MergeReport)> {
     if tables.is_empty() {
         return Err(Error::FamilyNotFound(family_name.to_string()));
     }
 
+    let type_divergences = find_type_divergences(&tables);
+    let id_column_mismatch = check_id_columns(&tables);
+    let warnings = check_column_names(&tables);
+
     // Build unified column list (union of all columns)
     let mut column_names: Vec<String> = Vec::new();
     let mut seen_columns: HashSet<String> = HashSet::new();
@@ -121,11 +770,23 @@ pub fn merge_tables(family_name: &str, tables: Vec<Table>) -> Result<ResolvedTab
         .iter()
         .map(|c| (c.name.as_str(), c.index))
         .collect();
+    let id_column = tables[0]
+        .columns
+        .get(tables[0].id_column)
+        .and_then(|c| col_index.get(c.name.as_str()).copied())
+        .unwrap_or(0);
+    let protected: Vec<bool> = columns
+        .iter()
+        .map(|c| options.protected_columns.contains(&c.name))
+        .collect();
 
     // Merge rows by ID
     // Using BTreeMap for deterministic ordering
     let mut rows_by_id: BTreeMap<i64, Vec<ResolvedCell>> = BTreeMap::new();
-    let mut rows_without_id: Vec<(Vec<ResolvedCell>, PathBuf)> = Vec::new();
+    let mut rows_without_id: Vec<(PathBuf, usize, Vec<ResolvedCell>)> = Vec::new();
+    let mut conflicts: Vec<MergeConflict> = Vec::new();
+    // Each row's first appearance, for `preserve_order`
+    let mut first_seen: Vec<RowSlot> = Vec::new();
 
     let sources: Vec<PathBuf> = tables.iter().map(|t| t.source_path.clone()).collect();
 
@@ -137,19 +798,30 @@ pub fn merge_tables(family_name: &str, tables: Vec<Table>) -> Result<ResolvedTab
             .map(|c| (c.name.as_str(), c.index))
             .collect();
 
-        for row in &table.rows {
+        for (row_idx, row) in table.rows.iter().enumerate() {
             // Create a resolved row with all columns
             let mut resolved_cells: Vec<ResolvedCell> = columns
                 .iter()
                 .map(|_| ResolvedCell::new(CellValue::Empty, table.source_path.clone()))
                 .collect();
 
+            // Which cells replace an existing row's values
+            let mut overrides = vec![false; columns.len()];
+
             // Fill in values from this row
             for (col_name, &unified_idx) in &col_index {
                 if let Some(&table_idx) = table_col_map.get(col_name) {
                     if let Some(cell) = row.cells.get(table_idx) {
+                        let (value, overrides_cell) = match &options.empty_override {
+                            EmptyOverride::Sentinel(text) if cell.as_str() == Some(text) => {
+                                (CellValue::Empty, true)
+                            }
+                            EmptyOverride::Clear => (cell.clone(), true),
+                            _ => (cell.clone(), !cell.is_empty()),
+                        };
+                        overrides[unified_idx] = overrides_cell;
                         resolved_cells[unified_idx] =
-                            ResolvedCell::new(cell.clone(), table.source_path.clone());
+                            ResolvedCell::new(value, table.source_path.clone());
                     }
                 }
             }
@@ -158,50 +830,216 @@ pub fn merge_tables(family_name: &str, tables: Vec<Table>) -> Result<ResolvedTab
                 Some(id) => {
                     // Merge with existing row or insert new
                     if let Some(existing) = rows_by_id.get_mut(&id) {
-                        // Override non-empty cells
+                        // Override non-empty cells, and empty ones the
+                        // empty_override policy lets through, unless the
+                        // column is protected and already has a value
                         for (i, new_cell) in resolved_cells.into_iter().enumerate() {
-                            if !new_cell.value.is_empty() {
+                            let old_cell = &existing[i];
+                            if overrides[i] && (!protected[i] || old_cell.value.is_empty()) {
+                                if !old_cell.value.is_empty() && old_cell.value != new_cell.value {
+                                    conflicts.push(MergeConflict {
+                                        row_id: id,
+                                        column: columns[i].name.clone(),
+                                        losing_value: old_cell.value.clone(),
+                                        losing_source: old_cell.source.clone(),
+                                        winning_value: new_cell.value.clone(),
+                                        winning_source: new_cell.source.clone(),
+                                    });
+                                }
                                 existing[i] = new_cell;
                             }
                         }
                     } else {
                         rows_by_id.insert(id, resolved_cells);
+                        first_seen.push(RowSlot::Keyed(id));
                     }
                 }
                 None => {
                     // No ID - append as separate row
-                    rows_without_id.push((resolved_cells, table.source_path.clone()));
+                    first_seen.push(RowSlot::Unkeyed(rows_without_id.len()));
+                    rows_without_id.push((table.source_path.clone(), row_idx, resolved_cells));
                 }
             }
         }
     }
 
     // Convert to final row format
-    let mut rows: Vec<ResolvedRow> = rows_by_id
-        .into_iter()
-        .map(|(id, cells)| ResolvedRow {
-            id: Some(id),
-            cells,
-        })
-        .collect();
+    let rows: Vec<ResolvedRow> = if options.preserve_order {
+        let mut unkeyed: Vec<Option<Vec<ResolvedCell>>> =
+            rows_without_id.into_iter().map(|(_, _, cells)| Some(cells)).collect();
+        first_seen
+            .into_iter()
+            .filter_map(|slot| match slot {
+                RowSlot::Keyed(id) => {
+                    rows_by_id.remove(&id).map(|cells| ResolvedRow { id: Some(id), cells })
+                }
+                RowSlot::Unkeyed(index) => {
+                    unkeyed[index].take().map(|cells| ResolvedRow { id: None, cells })
+                }
+            })
+            .collect()
+    } else {
+        let mut rows: Vec<ResolvedRow> = rows_by_id
+            .into_iter()
+            .map(|(id, cells)| ResolvedRow {
+                id: Some(id),
+                cells,
+            })
+            .collect();
 
-    // Append rows without IDs
-    for (cells, _source) in rows_without_id {
-        rows.push(ResolvedRow { id: None, cells });
-    }
+        // Append rows without IDs, ordered by source path and then by their
+        // position in the file, so the result doesn't depend on table order
+        rows_without_id.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+        for (_source, _row_idx, cells) in rows_without_id {
+            rows.push(ResolvedRow { id: None, cells });
+        }
+        rows
+    };
 
-    Ok(ResolvedTable {
+    let table = ResolvedTable {
         family_name: family_name.to_string(),
         columns,
         rows,
         sources,
-    })
+        id_column,
+        row_index: OnceLock::new(),
+    };
+
+    let report = MergeReport {
+        conflicts,
+        type_divergences,
+        id_column_mismatch,
+        warnings,
+    };
+
+    Ok((table, report))
+}
+
+/// Where a merged row first appeared: its ID, or its index among the rows
+/// without one
+enum RowSlot {
+    Keyed(i64),
+    Unkeyed(usize),
+}
+
+/// Check that the tables agree on whether their ID column holds row IDs
+///
+/// Tables without rows are ignored. Returns `None` when every table is keyed
+/// or every table is unkeyed.
+pub fn check_id_columns(tables: &[Table]) -> Option<IdColumnMismatch> {
+    let mut keyed = Vec::new();
+    let mut unkeyed = Vec::new();
+
+    for table in tables.iter().filter(|t| !t.rows.is_empty()) {
+        if table.rows.iter().any(|r| r.id.is_some()) {
+            keyed.push(table.source_path.clone());
+        } else {
+            unkeyed.push(table.source_path.clone());
+        }
+    }
+
+    if keyed.is_empty() || unkeyed.is_empty() {
+        return None;
+    }
+    Some(IdColumnMismatch { keyed, unkeyed })
+}
+
+/// Find repeated column names within a table, and shared columns that
+/// moved between tables of the same width
+pub fn check_column_names(tables: &[Table]) -> Vec<MergeWarning> {
+    let mut warnings = Vec::new();
+
+    for table in tables {
+        let mut by_name: Vec<(&str, Vec<usize>)> = Vec::new();
+        for col in &table.columns {
+            match by_name.iter_mut().find(|(name, _)| *name == col.name) {
+                Some((_, indices)) => indices.push(col.index),
+                None => by_name.push((col.name.as_str(), vec![col.index])),
+            }
+        }
+        warnings.extend(
+            by_name
+                .into_iter()
+                .filter(|(_, indices)| indices.len() > 1)
+                .map(|(name, indices)| MergeWarning::DuplicateColumn {
+                    source: table.source_path.clone(),
+                    column: name.to_string(),
+                    indices,
+                }),
+        );
+    }
+
+    // Files that only add columns are normal; a same-width file with its
+    // columns in another order is not
+    let Some(base) = tables.first() else {
+        return warnings;
+    };
+    for table in tables.iter().skip(1) {
+        if table.columns.len() != base.columns.len() {
+            continue;
+        }
+        for col in &table.columns {
+            let moved = base
+                .columns
+                .iter()
+                .find(|c| c.name == col.name)
+                .filter(|c| c.index != col.index);
+            if let Some(base_col) = moved {
+                warnings.push(MergeWarning::ColumnMoved {
+                    column: col.name.clone(),
+                    base_index: base_col.index,
+                    source: table.source_path.clone(),
+                    index: col.index,
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Find columns that are strings in some tables and numeric in others
+fn find_type_divergences(tables: &[Table]) -> Vec<TypeDivergence> {
+    // Column name -> per-table types, keeping first-seen column order
+    let mut by_column: Vec<(&str, Vec<(PathBuf, ColumnType)>)> = Vec::new();
+
+    for table in tables {
+        for col in &table.columns {
+            let values = table.rows.iter().filter_map(|r| r.cells.get(col.index));
+            let ty = ColumnType::infer(values);
+            if ty == ColumnType::Empty {
+                continue;
+            }
+
+            let entry = match by_column.iter_mut().find(|(name, _)| *name == col.name) {
+                Some((_, types)) => types,
+                None => {
+                    by_column.push((col.name.as_str(), Vec::new()));
+                    &mut by_column.last_mut().unwrap().1
+                }
+            };
+            entry.push((table.source_path.clone(), ty));
+        }
+    }
+
+    by_column
+        .into_iter()
+        .filter(|(_, types)| {
+            types.iter().any(|(_, t)| t.is_numeric())
+                && types.iter().any(|(_, t)| *t == ColumnType::String)
+        })
+        .map(|(name, types)| TypeDivergence {
+            column: name.to_string(),
+            types,
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::parse_csv_str;
+    use crate::parser::{parse_csv_str, parse_csv_str_with_options, ParseOptions};
+    use crate::test_util::scratch_dir;
 
     #[test]
     fn test_merge_single_table() {
@@ -298,4 +1136,565 @@ mod tests {
         assert_eq!(row.cells[1].value, CellValue::Integer(100));
         assert_eq!(row.cells[1].source, PathBuf::from("base.csv"));
     }
+
+    #[test]
+    fn test_merge_reports_conflicts() {
+        let base = "ID,Name,Value\n1,foo,100\n2,bar,200\n";
+        let overlay = "ID,Name,Value\n1,FOO,100\n2,,\n";
+
+        let base_table = parse_csv_str(base, "base.csv").unwrap();
+        let overlay_table = parse_csv_str(overlay, "overlay.csv").unwrap();
+
+        let (result, report) =
+            merge_tables_with_report("test", vec![base_table, overlay_table]).unwrap();
+        let conflicts = report.conflicts;
+
+        // Only the differing non-empty Name cell conflicts; equal values and
+        // empty overlay cells do not
+        assert_eq!(conflicts.len(), 1);
+        let conflict = &conflicts[0];
+        assert_eq!(conflict.row_id, 1);
+        assert_eq!(conflict.column, "Name");
+        assert_eq!(conflict.losing_value, CellValue::String("foo".to_string()));
+        assert_eq!(conflict.losing_source, PathBuf::from("base.csv"));
+        assert_eq!(conflict.winning_value, CellValue::String("FOO".to_string()));
+        assert_eq!(conflict.winning_source, PathBuf::from("overlay.csv"));
+
+        assert_eq!(result.find_row(1).unwrap().cells[1].value, conflict.winning_value);
+    }
+
+    #[test]
+    fn test_merge_reports_type_divergence() {
+        let base = "ID,Label,Scale\n1,100,1\n2,200,2\n";
+        let overlay = "ID,Label,Scale\n3,abc,1.5\n";
+
+        let base_table = parse_csv_str(base, "base.csv").unwrap();
+        let overlay_table = parse_csv_str(overlay, "overlay.csv").unwrap();
+
+        let (_, report) =
+            merge_tables_with_report("test", vec![base_table, overlay_table]).unwrap();
+
+        // Label mixes integers and strings; Scale only widens to float
+        assert!(report.conflicts.is_empty());
+        assert_eq!(
+            report.type_divergences,
+            vec![TypeDivergence {
+                column: "Label".to_string(),
+                types: vec![
+                    (PathBuf::from("base.csv"), ColumnType::Integer),
+                    (PathBuf::from("overlay.csv"), ColumnType::String),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_merge_warns_about_column_collisions() {
+        let base = "ID,0x0000,Name,0x0000\n1,a,foo,b\n";
+        let overlay = "ID,Name,0x0000,Cost\n1,bar,c,5\n";
+        let wider = "ID,Cost,Name,0x0000,Extra\n2,3,baz,d,x\n";
+        let tables = vec![
+            parse_csv_str(base, "base.csv").unwrap(),
+            parse_csv_str(overlay, "overlay.csv").unwrap(),
+            parse_csv_str(wider, "wider.csv").unwrap(),
+        ];
+
+        let (_, report) = merge_tables_with_report("test", tables).unwrap();
+
+        // Only the same-width overlay is compared with the base layout
+        assert_eq!(
+            report.warnings,
+            vec![
+                MergeWarning::DuplicateColumn {
+                    source: PathBuf::from("base.csv"),
+                    column: "0x0000".to_string(),
+                    indices: vec![1, 3],
+                },
+                MergeWarning::ColumnMoved {
+                    column: "Name".to_string(),
+                    base_index: 2,
+                    source: PathBuf::from("overlay.csv"),
+                    index: 1,
+                },
+                MergeWarning::ColumnMoved {
+                    column: "0x0000".to_string(),
+                    base_index: 1,
+                    source: PathBuf::from("overlay.csv"),
+                    index: 2,
+                },
+            ]
+        );
+
+        let clean = parse_csv_str("ID,Name\n1,foo\n", "clean.csv").unwrap();
+        let (_, report) = merge_tables_with_report("test", vec![clean]).unwrap();
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_merge_reports_mixed_id_columns() {
+        let base = "ID,Name\n1,foo\n2,bar\n";
+        let overlay = "Label,Name\nfirst,baz\n";
+
+        let base_table = parse_csv_str(base, "base.csv").unwrap();
+        let overlay_table = parse_csv_str(overlay, "overlay.csv").unwrap();
+        let empty_table = parse_csv_str("Label,Name\n", "empty.csv").unwrap();
+
+        let (_, report) = merge_tables_with_report(
+            "test",
+            vec![base_table.clone(), overlay_table, empty_table],
+        )
+        .unwrap();
+
+        assert_eq!(
+            report.id_column_mismatch,
+            Some(IdColumnMismatch {
+                keyed: vec![PathBuf::from("base.csv")],
+                unkeyed: vec![PathBuf::from("overlay.csv")],
+            })
+        );
+        assert_eq!(check_id_columns(&[base_table]), None);
+    }
+
+    #[test]
+    fn test_unkeyed_rows_order_is_stable() {
+        let a = "Name,Value\nfoo,1\nbar,2\n";
+        let b = "Name,Value\nbaz,3\n";
+
+        let names = |tables: Vec<Table>| -> Vec<String> {
+            merge_tables("test", tables)
+                .unwrap()
+                .rows
+                .iter()
+                .map(|r| r.cells[0].value.to_string_value())
+                .collect()
+        };
+
+        let forward = names(vec![
+            parse_csv_str(a, "a.csv").unwrap(),
+            parse_csv_str(b, "b.csv").unwrap(),
+        ]);
+        let reversed = names(vec![
+            parse_csv_str(b, "b.csv").unwrap(),
+            parse_csv_str(a, "a.csv").unwrap(),
+        ]);
+
+        assert_eq!(forward, vec!["foo", "bar", "baz"]);
+        assert_eq!(reversed, forward);
+    }
+
+    #[test]
+    fn test_merge_all_keeps_order_and_errors_apart() {
+        use crate::scanner::scan_directory;
+        use std::fs;
+
+        let dir = scratch_dir("merge-all");
+        for name in ["armor", "items", "spells", "weapons"] {
+            fs::write(dir.join(format!("{}.csv", name)), format!("ID,Name\n1,{}\n", name))
+                .unwrap();
+        }
+        fs::write(dir.join("spells_ep1.csv"), "ID,Name\n2,\"open\n").unwrap();
+
+        let scan = scan_directory(&[&dir]).unwrap();
+        let results = merge_all(&scan.families);
+        assert_eq!(results.len(), scan.families.len());
+        for (family, result) in scan.families.iter().zip(&results) {
+            match family.name.as_str() {
+                "spells" => assert!(matches!(result, Err(Error::CsvParse { .. }))),
+                name => {
+                    let table = result.as_ref().unwrap();
+                    assert_eq!(table.family_name, name);
+                    assert_eq!(table.rows[0].cells[1].value.to_string_value(), name);
+                }
+            }
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_precedence_overrides_alphabetical_order() {
+        use crate::scanner::scan_directory;
+        use std::fs;
+
+        let dir = scratch_dir("precedence");
+        fs::write(dir.join("items.csv"), "ID,Name\n1,base\n").unwrap();
+        fs::write(dir.join("items_drk.csv"), "ID,Name\n1,drk\n").unwrap();
+        fs::write(dir.join("items_ep1.csv"), "ID,Name\n1,ep1\n").unwrap();
+
+        let scan = scan_directory(&[&dir]).unwrap();
+        let family = scan.find_family("items").unwrap();
+
+        // Alphabetically ep1 loads last and wins
+        let merged = merge_family(family).unwrap();
+        assert_eq!(merged.rows[0].cells[1].value.to_string_value(), "ep1");
+
+        // Listing drk after ep1 makes drk the higher precedence
+        let options = MergeOptions {
+            precedence: vec!["ep1".to_string(), "drk".to_string()],
+            ..Default::default()
+        };
+        let mut parsed = Vec::new();
+        let (merged, _) = merge_family_with_progress(family, &options, |p| {
+            parsed.push((p.file.to_path_buf(), p.parsed, p.total));
+        })
+        .unwrap();
+        assert_eq!(merged.rows[0].cells[1].value.to_string_value(), "drk");
+        assert_eq!(merged.sources[2], dir.join("items_drk.csv"));
+        assert_eq!(parsed.last(), Some(&(dir.join("items_drk.csv"), 3, 3)));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_family_from_matches_merge_family() {
+        use crate::parser::parse_csv;
+        use crate::scanner::scan_directory;
+        use std::fs;
+
+        let dir = scratch_dir("merge-from");
+        fs::write(dir.join("items.csv"), "ID,Name,Cost\n1,a,5\n2,b,6\n").unwrap();
+        fs::write(dir.join("items_drk.csv"), "ID,Name\n2,B\n3,c\n").unwrap();
+        fs::write(dir.join("items_ep1.csv"), "ID,Cost\n1,9\n").unwrap();
+
+        let scan = scan_directory(&[&dir]).unwrap();
+        let family = scan.find_family("items").unwrap();
+        let json = |t: &ResolvedTable| serde_json::to_value(t).unwrap();
+        let expected = json(&merge_family(family).unwrap());
+
+        // Tables in any order, and with some left out, merge the same
+        let all: Vec<Table> =
+            family.members.iter().rev().map(|m| parse_csv(&m.path).unwrap()).collect();
+        assert_eq!(json(&merge_family_from(family, all).unwrap()), expected);
+        let some = vec![parse_csv(dir.join("items_ep1.csv")).unwrap()];
+        assert_eq!(json(&merge_family_from(family, some).unwrap()), expected);
+
+        let options = MergeOptions {
+            precedence: vec!["ep1".to_string(), "drk".to_string()],
+            ..Default::default()
+        };
+        let tables = family.members.iter().map(|m| parse_csv(&m.path).unwrap()).collect();
+        let (from, _) = merge_family_from_with_options(family, tables, &options).unwrap();
+        let (fresh, _) = merge_family_with_options(family, &options).unwrap();
+        assert_eq!(json(&from), json(&fresh));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_family_with_parse_options() {
+        use crate::parser::IdColumn;
+        use crate::scanner::scan_directory;
+        use std::fs;
+
+        let dir = scratch_dir("merge-parse");
+        fs::write(dir.join("items.csv"), "# base\nName,ID\na,0x0001\nb,0x0002\n").unwrap();
+        fs::write(dir.join("items_ep1.csv"), "Name,ID\nA,1\nc,3\n").unwrap();
+
+        let scan = scan_directory(&[&dir]).unwrap();
+        let family = scan.find_family("items").unwrap();
+        let options = MergeOptions {
+            parse: ParseOptions {
+                comment: Some(b'#'),
+                hex_values: true,
+                id_column: IdColumn::Name("ID".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // Hex and decimal IDs for the same row merge into one
+        let (merged, _) = merge_family_with_options(family, &options).unwrap();
+        let ids: Vec<_> = merged.rows.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![Some(1), Some(2), Some(3)]);
+        assert_eq!(merged.id_column, 1);
+        assert_eq!(merged.rows[0].cells[0].value, CellValue::String("A".to_string()));
+        assert_eq!(family.merged_row_count_with_options(&options).unwrap(), 3);
+
+        // The defaults read the comment as the base header and key no rows
+        assert_eq!(family.merged_row_count().unwrap(), 5);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sort_by_column_numeric_with_empties() {
+        let csv = "ID,Cost\n1,10\n2,\n3,2\n4,10\n5,\n6,-1\n";
+        let mut table = merge_tables("test", vec![parse_csv_str(csv, "test.csv").unwrap()]).unwrap();
+        let ids = |t: &ResolvedTable| t.rows.iter().map(|r| r.id.unwrap()).collect::<Vec<_>>();
+
+        table.sort_by_column(1, true, true);
+        assert_eq!(ids(&table), vec![6, 3, 1, 4, 2, 5]);
+
+        table.sort_by_column(1, false, true);
+        assert_eq!(ids(&table), vec![1, 4, 3, 6, 2, 5]);
+
+        // Lexical order puts "10" before "2"
+        table.sort_by_column(1, true, false);
+        assert_eq!(ids(&table), vec![6, 1, 4, 3, 2, 5]);
+    }
+
+    #[test]
+    fn test_row_sources() {
+        let base = parse_csv_str("ID,Name,Value\n1,foo,100\n2,bar,200\n", "base.csv").unwrap();
+        let ep1 = parse_csv_str("ID,Name,Value\n1,,150\n", "ep1.csv").unwrap();
+        let drk = parse_csv_str("ID,Name,Value\n1,,175\n", "drk.csv").unwrap();
+        let merged = merge_tables("test", vec![base, ep1, drk]).unwrap();
+
+        // ep1's value is overridden by drk, so it provides no cell
+        assert_eq!(
+            merged.row_sources(1),
+            vec![&PathBuf::from("base.csv"), &PathBuf::from("drk.csv")]
+        );
+        assert_eq!(merged.row_sources(2), vec![&PathBuf::from("base.csv")]);
+        assert!(merged.row_sources(3).is_empty());
+    }
+
+    #[test]
+    fn test_overridden_cells() {
+        let base = parse_csv_str("ID,Name,Value\n1,foo,100\n2,bar,200\n", "base.csv").unwrap();
+        let ep1 = parse_csv_str("ID,Name,Value\n2,,250\n", "ep1.csv").unwrap();
+        let merged = merge_tables("test", vec![base, ep1]).unwrap();
+
+        assert_eq!(
+            merged.overridden_cells(),
+            vec![(2, "Value", &PathBuf::from("ep1.csv"))]
+        );
+    }
+
+    #[test]
+    fn test_row_index_lookups() {
+        let csv = "ID,Name\n30,c\n10,a\n20,b\n,none\n";
+        let mut table = merge_tables("test", vec![parse_csv_str(csv, "t.csv").unwrap()]).unwrap();
+        let name = |table: &ResolvedTable, id| {
+            table.find_row(id).map(|r| r.cells[1].value.to_string_value())
+        };
+
+        assert_eq!(table.row_position(10), Some(0));
+        assert_eq!(name(&table, 30).as_deref(), Some("c"));
+        assert!(table.find_row(40).is_none());
+
+        // Sorting and selecting rebuild the index
+        table.sort_by_column(1, true, false);
+        assert_eq!(table.row_position(30), Some(2));
+        table.select_rows(&crate::filter::parse_row_selection("20-30").unwrap());
+        assert_eq!(table.row_position(30), Some(1));
+        assert!(table.find_row(10).is_none());
+
+        // Rows moved or removed behind the index's back are still handled
+        table.rows.swap(0, 1);
+        assert_eq!(name(&table, 20).as_deref(), Some("b"));
+        table.rows.remove(0);
+        assert!(table.find_row(30).is_none());
+
+        table.rows.push(ResolvedRow {
+            id: Some(50),
+            cells: vec![],
+        });
+        table.invalidate_row_index();
+        assert_eq!(table.row_position(50), Some(1));
+    }
+
+    #[test]
+    fn test_preserve_order_keeps_first_seen_positions() {
+        let base = "ID,Name\n5,five\n,gap\n2,two\n";
+        let overlay = "ID,Name\n9,nine\n2,TWO\n,tail\n";
+        let tables = || {
+            vec![
+                parse_csv_str(base, "base.csv").unwrap(),
+                parse_csv_str(overlay, "overlay.csv").unwrap(),
+            ]
+        };
+        let names = |table: &ResolvedTable| -> Vec<String> {
+            table.rows.iter().map(|r| r.cells[1].value.to_string_value()).collect()
+        };
+
+        let options = MergeOptions {
+            preserve_order: true,
+            ..Default::default()
+        };
+        let (table, _) = merge_tables_with_options("test", tables(), &options).unwrap();
+        assert_eq!(names(&table), vec!["five", "gap", "TWO", "nine", "tail"]);
+        let ids: Vec<Option<i64>> = table.rows.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![Some(5), None, Some(2), Some(9), None]);
+
+        let table = merge_tables("test", tables()).unwrap();
+        assert_eq!(names(&table), vec!["TWO", "five", "nine", "gap", "tail"]);
+    }
+
+    #[test]
+    fn test_protected_columns_keep_first_value() {
+        let base = "ID,Label,Cost\n1,FIRE_BOLT,10\n2,,20\n";
+        let overlay = "ID,Label,Cost\n1,FIREBOLT_V2,15\n2,ICE_SHARD,25\n3,WIND,30\n";
+        let tables = vec![
+            parse_csv_str(base, "base.csv").unwrap(),
+            parse_csv_str(overlay, "overlay.csv").unwrap(),
+        ];
+        let options = MergeOptions {
+            protected_columns: vec!["Label".to_string()],
+            ..Default::default()
+        };
+        let (table, report) = merge_tables_with_options("test", tables, &options).unwrap();
+        let cells = |id: i64| -> Vec<String> {
+            let row = table.find_row(id).unwrap();
+            row.cells.iter().map(|c| c.value.to_string_value()).collect()
+        };
+
+        // The label survives while the numeric tweak applies
+        assert_eq!(cells(1), vec!["1", "FIRE_BOLT", "15"]);
+        assert_eq!(table.find_row(1).unwrap().cells[1].source, PathBuf::from("base.csv"));
+        // Empty labels are filled, and new rows keep their own
+        assert_eq!(cells(2), vec!["2", "ICE_SHARD", "25"]);
+        assert_eq!(cells(3), vec!["3", "WIND", "30"]);
+        assert!(report.conflicts.iter().all(|c| c.column == "Cost"));
+    }
+
+    #[test]
+    fn test_empty_override_policies() {
+        let merge = |empty_override: EmptyOverride, patch: &str| {
+            let base = parse_csv_str("ID,Name,Value\n1,foo,100\n", "base.csv").unwrap();
+            let ep1 = parse_csv_str(patch, "ep1.csv").unwrap();
+            let options = MergeOptions {
+                empty_override,
+                ..Default::default()
+            };
+            let (merged, report) =
+                merge_tables_with_options("test", vec![base, ep1], &options).unwrap();
+            (merged.rows[0].cells[2].clone(), report.conflicts.len())
+        };
+        let empty = "ID,Name,Value\n1,bar,\n";
+        let sentinel = "ID,Name,Value\n1,bar,__CLEAR__\n";
+
+        let (cell, conflicts) = merge(EmptyOverride::Ignore, empty);
+        assert_eq!((cell.value, conflicts), (CellValue::Integer(100), 1));
+        assert_eq!(cell.source, PathBuf::from("base.csv"));
+
+        let (cell, conflicts) = merge(EmptyOverride::Clear, empty);
+        assert_eq!((cell.value, conflicts), (CellValue::Empty, 2));
+        assert_eq!(cell.source, PathBuf::from("ep1.csv"));
+
+        // Clearing needs the column; a file without it keeps the value
+        let (cell, _) = merge(EmptyOverride::Clear, "ID,Name\n1,bar\n");
+        assert_eq!(cell.value, CellValue::Integer(100));
+
+        let (cell, _) = merge(EmptyOverride::sentinel(), empty);
+        assert_eq!(cell.value, CellValue::Integer(100));
+        let (cell, conflicts) = merge(EmptyOverride::sentinel(), sentinel);
+        assert_eq!((cell.value, conflicts), (CellValue::Empty, 2));
+        assert_eq!(cell.source, PathBuf::from("ep1.csv"));
+
+        // Without the sentinel mode the text is an ordinary value
+        let (cell, _) = merge(EmptyOverride::Ignore, sentinel);
+        assert_eq!(cell.value, CellValue::String(CLEAR_SENTINEL.to_string()));
+    }
+
+    #[test]
+    fn test_source_contribution() {
+        let base = parse_csv_str("ID,Name,Value\n1,foo,100\n2,bar,200\n", "base.csv").unwrap();
+        let ep1 = parse_csv_str("ID,Name,Value\n1,,150\n2,baz,\n", "ep1.csv").unwrap();
+        let drk = parse_csv_str("ID,Name,Value\n1,,\n", "drk.csv").unwrap();
+        let merged = merge_tables("test", vec![base, ep1, drk]).unwrap();
+
+        let counts = merged.source_contribution();
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts[&PathBuf::from("base.csv")], 2);
+        assert_eq!(counts[&PathBuf::from("ep1.csv")], 2);
+        assert_eq!(counts[&PathBuf::from("drk.csv")], 0);
+        assert_eq!(merged.unused_sources(), vec![&PathBuf::from("drk.csv")]);
+    }
+
+    #[test]
+    fn test_row_slice_clamps_to_table() {
+        let csv = "ID,Name\n1,a\n2,b\n3,c\n";
+        let table = merge_tables("test", vec![parse_csv_str(csv, "test.csv").unwrap()]).unwrap();
+        let ids = |rows: &[ResolvedRow]| rows.iter().map(|r| r.id.unwrap()).collect::<Vec<_>>();
+
+        assert_eq!(ids(table.row_slice(1, 2)), vec![2, 3]);
+        assert_eq!(ids(table.row_slice(2, 10)), vec![3]);
+        assert!(table.row_slice(5, 2).is_empty());
+        assert_eq!(ids(table.row_slice(0, usize::MAX)), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_column_names_are_unified_in_order() {
+        let base = parse_csv_str("ID,Name\n1,a\n", "base.csv").unwrap();
+        let ep1 = parse_csv_str("ID,0xC4FDA9ED,Name\n2,x,b\n", "ep1.csv").unwrap();
+        let merged = merge_tables("test", vec![base, ep1]).unwrap();
+        assert_eq!(merged.column_names(), vec!["ID", "Name", "0xC4FDA9ED"]);
+    }
+
+    #[test]
+    fn test_column_stats() {
+        let csv = "ID,Cost,Label\n1,4,a\n2,****,b\n3,1.5,c\n4,,d\n5,10,e\n";
+        let table = merge_tables("test", vec![parse_csv_str(csv, "test.csv").unwrap()]).unwrap();
+
+        let stats = table.column_stats(1);
+        assert_eq!((stats.integers, stats.floats, stats.strings, stats.empty), (2, 1, 1, 1));
+        assert_eq!(stats.min, Some(1.5));
+        assert_eq!(stats.max, Some(10.0));
+        assert_eq!(stats.mean, Some(15.5 / 3.0));
+        assert_eq!(stats.median, Some(4.0));
+
+        let stats = table.column_stats(0);
+        assert_eq!(stats.median, Some(3.0));
+
+        let stats = table.column_stats(2);
+        assert_eq!(stats.strings, 5);
+        assert_eq!(stats.mean, None);
+    }
+
+    #[test]
+    fn test_filter_numeric_skips_text_and_empty() {
+        let csv = "ID,Cost\n1,4\n2,****\n3,1.5\n4,\n5,10\n6,4.0\n";
+        let table = merge_tables("test", vec![parse_csv_str(csv, "test.csv").unwrap()]).unwrap();
+
+        assert_eq!(table.filter_numeric(1, CompareOp::Greater, 2.0), vec![0, 4, 5]);
+        assert_eq!(table.filter_numeric(1, CompareOp::Equal, 4.0), vec![0, 5]);
+        assert_eq!(table.filter_numeric(1, CompareOp::NotEqual, 4.0), vec![2, 4]);
+        assert_eq!("<=".parse::<CompareOp>().unwrap(), CompareOp::LessOrEqual);
+        assert!(matches!("=~".parse::<CompareOp>(), Err(Error::InvalidComparison(_))));
+    }
+
+    #[test]
+    fn test_rows_as_json_objects() {
+        let csv = "Name,ID,Scale\nfoo,1,1.5\nbar,2,\n";
+        let table = merge_tables("test", vec![parse_csv_str(csv, "test.csv").unwrap()]).unwrap();
+        let rows: Vec<String> = table
+            .rows_as_json_objects()
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+
+        assert_eq!(
+            rows,
+            vec![
+                r#"{"Name":"foo","ID":1,"Scale":1.5}"#,
+                r#"{"Name":"bar","ID":2,"Scale":null}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typed_json_tags_every_cell_type() {
+        let csv = "ID,Count,Scale,Name,Label,Note\n1,5,1.5,fire,0x1F,\n,,,x,,\n";
+        let options = ParseOptions {
+            hex_values: true,
+            ..Default::default()
+        };
+        let table = parse_csv_str_with_options(csv, "test.csv", &options).unwrap();
+        let merged = merge_tables("spells", vec![table]).unwrap();
+
+        assert_eq!(
+            merged.to_typed_json().to_string(),
+            concat!(
+                r#"{"family":"spells","columns":["ID","Count","Scale","Name","Label","Note"],"#,
+                r#""rows":[{"id":1,"cells":[{"type":"int","value":1},{"type":"int","value":5},"#,
+                r#"{"type":"float","value":1.5},{"type":"string","value":"fire"},"#,
+                r#"{"type":"hex","value":"0x1F"},{"type":"empty","value":null}]},"#,
+                r#"{"id":null,"cells":[{"type":"empty","value":null},"#,
+                r#"{"type":"empty","value":null},{"type":"empty","value":null},"#,
+                r#"{"type":"string","value":"x"},{"type":"empty","value":null},"#,
+                r#"{"type":"empty","value":null}]}]}"#,
+            )
+        );
+    }
 }
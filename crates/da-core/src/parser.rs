@@ -1,150 +1,695 @@
 //! CSV parser for 2DA table files
 
 use crate::error::{Error, Result};
-use crate::table::{CellValue, Column, Row, Table};
+use crate::table::{
+    find_ignoring_case, CellValue, Column, ParseWarning, ParseWarningKind, Row, Table,
+};
+use encoding_rs::{Encoding, UTF_8};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
 use std::fs::File;
-use std::io::BufReader;
-use std::path::Path;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Byte order mark some tools write at the start of UTF-8 files
+pub(crate) const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// How to treat rows whose ID cell is empty
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyIdPolicy {
+    /// Keep the row without an ID; the merge appends it as a separate row
+    #[default]
+    Unkeyed,
+    /// Fail the parse with an error naming the row
+    ErrorOut,
+    /// Give the row ID 0
+    ///
+    /// The row then merges like any other keyed row, so it collides with a
+    /// real row 0 in the same family: whichever file comes later overrides
+    /// the other's non-empty cells.
+    TreatAsZero,
+}
+
+/// Column holding the row IDs
+///
+/// Written in JSON as a column index (`1`) or a column name (`"RowID"`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IdColumn {
+    /// 0-based column position, after indexed headers are placed
+    Index(usize),
+    /// Column name, matched exactly and then ignoring case
+    Name(String),
+}
+
+impl Default for IdColumn {
+    fn default() -> Self {
+        IdColumn::Index(0)
+    }
+}
+
+/// What to do with a row that can't be read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowErrorPolicy {
+    /// Fail the whole parse
+    #[default]
+    Fail,
+    /// Leave the row out and record it in `Table::parse_warnings`
+    Skip,
+}
+
+/// Options controlling how CSV files are parsed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParseOptions {
+    /// How to treat rows with an empty ID cell
+    pub empty_id_policy: EmptyIdPolicy,
+    /// Skip lines starting with this byte (e.g. `b'#'`); comment lines above
+    /// the header are kept in `Table::leading_comments`
+    pub comment: Option<u8>,
+    /// Fail with `Error::DuplicateRowId` when an ID appears twice in one
+    /// file; otherwise the repeat is recorded in `Table::warnings`
+    pub error_on_duplicate_ids: bool,
+    /// Header fields carry their column position as `<n>:<name>`
+    /// (e.g. `0:ID,2:Cost`); positions without a header become unnamed
+    /// empty columns
+    pub indexed_headers: bool,
+    /// Label of the encoding files are written in (e.g. `windows-1252`);
+    /// UTF-8 if `None`. Only ASCII-compatible encodings are supported
+    pub encoding: Option<String>,
+    /// Read `0x`-prefixed cells as `CellValue::Hex` instead of strings
+    pub hex_values: bool,
+    /// Report integer columns holding only `0` and `1` as
+    /// `ColumnType::Bool` in `Table::column_types`
+    pub bool_columns: bool,
+    /// What to do with malformed rows, such as invalid UTF-8, a quote left
+    /// open, or an empty or repeated ID the options above reject
+    pub on_error: RowErrorPolicy,
+    /// Column the row IDs are read from; the first column by default
+    pub id_column: IdColumn,
+}
 
 /// Parse a CSV file into a Table
 pub fn parse_csv<P: AsRef<Path>>(path: P) -> Result<Table> {
+    parse_csv_with_options(path, &ParseOptions::default())
+}
+
+/// Parse a CSV file into a Table using the given options
+pub fn parse_csv_with_options<P: AsRef<Path>>(path: P, options: &ParseOptions) -> Result<Table> {
     let path = path.as_ref();
     let file = File::open(path).map_err(|e| Error::FileRead {
         path: path.to_path_buf(),
         source: e,
     })?;
 
-    let reader = BufReader::new(file);
-    let mut csv_reader = csv::ReaderBuilder::new()
-        .has_headers(true)
-        .flexible(true) // Allow varying number of fields
-        .from_reader(reader);
+    parse_reader(file, path.to_path_buf(), options)
+}
 
-    // Parse headers into columns
-    let headers = csv_reader.headers().map_err(|e| Error::Csv {
+/// Parse CSV from a string (useful for testing)
+pub fn parse_csv_str(content: &str, source_name: &str) -> Result<Table> {
+    parse_csv_str_with_options(content, source_name, &ParseOptions::default())
+}
+
+/// Parse CSV from a string using the given options
+pub fn parse_csv_str_with_options(
+    content: &str,
+    source_name: &str,
+    options: &ParseOptions,
+) -> Result<Table> {
+    parse_csv_bytes_with_options(content.as_bytes(), source_name, options)
+}
+
+/// Parse CSV from raw bytes, such as a file read out of an archive
+///
+/// Unlike `parse_csv_str`, the bytes needn't be UTF-8; they are decoded
+/// the same way a file on disk would be.
+pub fn parse_csv_bytes_with_options<P: AsRef<Path>>(
+    content: &[u8],
+    source_name: P,
+    options: &ParseOptions,
+) -> Result<Table> {
+    parse_reader(content, source_name.as_ref().to_path_buf(), options)
+}
+
+/// Parse a CSV file row by row without loading the whole table
+pub fn parse_csv_streaming<P: AsRef<Path>>(path: P) -> Result<RowIter<File>> {
+    parse_csv_streaming_with_options(path, &ParseOptions::default())
+}
+
+/// Parse a CSV file row by row using the given options
+pub fn parse_csv_streaming_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &ParseOptions,
+) -> Result<RowIter<File>> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|e| Error::FileRead {
         path: path.to_path_buf(),
         source: e,
     })?;
 
-    let columns: Vec<Column> = headers
-        .iter()
-        .enumerate()
-        .map(|(i, name)| Column::new(name.to_string(), i))
-        .collect();
+    RowIter::from_reader(file, path, options)
+}
 
-    if columns.is_empty() {
-        return Err(Error::CsvParse {
-            path: path.to_path_buf(),
-            message: "no columns found in CSV".to_string(),
-        });
-    }
+/// Parse CSV data from any reader, attributing errors to `path`
+fn parse_reader<R: Read>(reader: R, path: PathBuf, options: &ParseOptions) -> Result<Table> {
+    let mut iter = RowIter::from_reader(reader, path, options)?;
+    let rows = iter.by_ref().collect::<Result<Vec<Row>>>()?;
 
-    // Parse rows
-    let mut rows = Vec::new();
-    for (row_idx, result) in csv_reader.records().enumerate() {
-        let record = result.map_err(|e| Error::Csv {
-            path: path.to_path_buf(),
-            source: e,
+    let mut table = Table {
+        columns: iter.columns,
+        rows,
+        source_path: iter.path,
+        id_column: iter.id_column,
+        leading_comments: iter.leading_comments,
+        column_types: Vec::new(),
+        warnings: iter.warnings,
+        parse_warnings: iter.parse_warnings,
+    };
+    table.column_types = if options.bool_columns {
+        table.infer_column_types_with_bools()
+    } else {
+        table.infer_column_types()
+    };
+    Ok(table)
+}
+
+/// Iterator over the rows of a CSV table, yielding one `Row` at a time
+///
+/// The header is read up front, so the columns are available before
+/// the first row is pulled.
+pub struct RowIter<R: Read> {
+    reader: csv::Reader<QuoteTracker<BufReader<R>>>,
+    /// Raw fields of the current record, decoded as each row is built
+    byte_record: csv::ByteRecord,
+    encoding: Option<&'static Encoding>,
+    columns: Vec<Column>,
+    /// Column index of each field, when headers are indexed
+    positions: Option<Vec<usize>>,
+    /// Column the row IDs are read from
+    id_column: usize,
+    path: PathBuf,
+    leading_comments: Vec<String>,
+    /// Lines before the ones the csv reader sees
+    line_offset: u64,
+    warnings: Vec<ParseWarning>,
+    parse_warnings: Vec<ParseWarning>,
+    on_error: RowErrorPolicy,
+    hex_values: bool,
+    empty_id_policy: EmptyIdPolicy,
+    error_on_duplicate_ids: bool,
+    seen_ids: HashSet<i64>,
+    row_idx: usize,
+}
+
+impl<R: Read> RowIter<R> {
+    /// Read the header from `reader`, attributing errors to `path`
+    pub fn from_reader<P: AsRef<Path>>(reader: R, path: P, options: &ParseOptions) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let encoding = input_encoding(options, &path)?;
+        let mut reader = BufReader::new(reader);
+
+        if reader.fill_buf()?.starts_with(UTF8_BOM) {
+            reader.consume(UTF8_BOM.len());
+        }
+
+        let leading_comments = match options.comment {
+            Some(marker) => read_leading_comments(&mut reader, marker, encoding)?,
+            None => Vec::new(),
+        };
+
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .flexible(true) // Allow varying number of fields
+            .comment(options.comment)
+            .from_reader(QuoteTracker::new(reader, options.comment));
+
+        // The csv reader counts lines from the end of the leading comments
+        let line_offset = leading_comments.len() as u64;
+
+        // Parse headers into columns
+        let csv_error = |e| csv_error(&path, e, line_offset);
+        let headers = match encoding {
+            Some(encoding) => {
+                &decode_record(csv_reader.byte_headers().map_err(csv_error)?, encoding)
+            }
+            None => csv_reader.headers().map_err(csv_error)?,
+        };
+
+        let (columns, positions) = if options.indexed_headers {
+            let (columns, positions) = indexed_columns(headers, &path, line_offset + 1)?;
+            (columns, Some(positions))
+        } else {
+            let columns = headers
+                .iter()
+                .enumerate()
+                .map(|(i, name)| Column::new(name.to_string(), i))
+                .collect();
+            (columns, None)
+        };
+
+        if columns.is_empty() {
+            return Err(Error::CsvParse {
+                path,
+                line: None,
+                message: "no columns found in CSV".to_string(),
+            });
+        }
+
+        let id_column = match &options.id_column {
+            IdColumn::Index(index) => columns.get(*index).map(|c| c.index),
+            IdColumn::Name(name) => {
+                find_ignoring_case(&columns, name, |c| &c.name)?.map(|c| c.index)
+            }
+        };
+        let id_column = id_column.ok_or_else(|| Error::CsvParse {
+            path: path.clone(),
+            line: Some(line_offset + 1),
+            message: match &options.id_column {
+                IdColumn::Index(index) => format!("no column {} to read IDs from", index),
+                IdColumn::Name(name) => format!("no column '{}' to read IDs from", name),
+            },
         })?;
 
-        let cells: Vec<CellValue> = record.iter().map(CellValue::parse).collect();
+        Ok(Self {
+            reader: csv_reader,
+            byte_record: csv::ByteRecord::new(),
+            encoding,
+            columns,
+            positions,
+            id_column,
+            path,
+            leading_comments,
+            line_offset,
+            warnings: Vec::new(),
+            parse_warnings: Vec::new(),
+            on_error: options.on_error,
+            hex_values: options.hex_values,
+            empty_id_policy: options.empty_id_policy,
+            error_on_duplicate_ids: options.error_on_duplicate_ids,
+            seen_ids: HashSet::new(),
+            row_idx: 0,
+        })
+    }
 
-        // Extract ID from first column if it's an integer
-        let id = cells.first().and_then(|c| match c {
-            CellValue::Integer(i) => Some(*i),
-            _ => None,
-        });
+    /// Columns from the header row
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Index of the column the row IDs are read from
+    pub fn id_column(&self) -> usize {
+        self.id_column
+    }
+
+    /// Path used for error messages
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Comment lines found above the header
+    pub fn leading_comments(&self) -> &[String] {
+        &self.leading_comments
+    }
+
+    /// Problems found in the rows read so far that didn't stop the parse
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    /// Rows skipped so far under `RowErrorPolicy::Skip`
+    pub fn parse_warnings(&self) -> &[ParseWarning] {
+        &self.parse_warnings
+    }
+
+    /// Line the current record starts on
+    fn line(&self) -> Option<u64> {
+        self.byte_record.position().map(|p| p.line() + self.line_offset)
+    }
+
+    /// Describe a problem with the current record
+    fn warning(&self, kind: ParseWarningKind, message: String) -> ParseWarning {
+        let raw: Vec<_> = self.byte_record.iter().map(String::from_utf8_lossy).collect();
+        ParseWarning {
+            kind,
+            line: self.line(),
+            raw: raw.join(","),
+            message,
+        }
+    }
+
+    /// Read the next record, keeping its raw fields in `byte_record`
+    fn read_record(&mut self) -> Result<bool> {
+        let found = self
+            .reader
+            .read_byte_record(&mut self.byte_record)
+            .map_err(|e| csv_error(&self.path, e, self.line_offset))?;
+        if !found {
+            return Ok(false);
+        }
+        self.row_idx += 1;
+
+        // A quote left open swallows the rest of the file into the last
+        // field without an error; only that record ends inside quotes with
+        // all of the input consumed
+        let tracker = self.reader.get_ref();
+        if tracker.in_quotes() && self.reader.position().byte() == tracker.consumed() {
+            return Err(Error::CsvParse {
+                path: self.path.clone(),
+                line: self.line(),
+                message: "unterminated quoted field".to_string(),
+            });
+        }
+        Ok(true)
+    }
+
+    /// Turn the current record into a row
+    fn build_row(&mut self) -> Result<Row> {
+        let mut cells = Vec::with_capacity(self.byte_record.len());
+        for (i, field) in self.byte_record.iter().enumerate() {
+            let text = match self.encoding {
+                Some(encoding) => encoding.decode_without_bom_handling(field).0,
+                None => Cow::Borrowed(std::str::from_utf8(field).map_err(|_| Error::CsvParse {
+                    path: self.path.clone(),
+                    line: self.line(),
+                    message: format!("invalid UTF-8 in field {}", i + 1),
+                })?),
+            };
+            let value = self.hex_values.then(|| CellValue::parse_hex(&text)).flatten();
+            cells.push(value.unwrap_or_else(|| CellValue::parse(&text)));
+        }
+
+        // Move fields to their indexed positions; extra fields stay at the
+        // end so the length check below still catches them
+        if let Some(positions) = &self.positions {
+            let mut placed = vec![CellValue::Empty; self.columns.len()];
+            let extra = cells.split_off(positions.len().min(cells.len()));
+            for (value, &pos) in cells.into_iter().zip(positions) {
+                placed[pos] = value;
+            }
+            placed.extend(extra);
+            cells = placed;
+        }
+
+        // Extract ID from the ID column if it's an integer, decimal or hex
+        let id = match cells.get(self.id_column) {
+            Some(CellValue::Integer(i)) => Some(*i),
+            Some(number @ CellValue::Number { .. }) => number.as_i64(),
+            Some(CellValue::Hex { value, .. }) => i64::try_from(*value).ok(),
+            Some(CellValue::Empty) | None => match self.empty_id_policy {
+                EmptyIdPolicy::Unkeyed => None,
+                EmptyIdPolicy::TreatAsZero => Some(0),
+                EmptyIdPolicy::ErrorOut => {
+                    return Err(Error::CsvParse {
+                        path: self.path.clone(),
+                        line: self.line(),
+                        message: format!("row {} has an empty ID", self.row_idx),
+                    });
+                }
+            },
+            Some(_) => None,
+        };
+
+        if let Some(id) = id {
+            if !self.seen_ids.insert(id) {
+                if self.error_on_duplicate_ids {
+                    return Err(Error::DuplicateRowId {
+                        id,
+                        path: self.path.clone(),
+                    });
+                }
+                let message = format!("row {} repeats ID {}", self.row_idx, id);
+                self.warnings.push(self.warning(ParseWarningKind::DuplicateId, message));
+            }
+        }
 
         // Pad with empty cells if row is shorter than header
         let mut padded_cells = cells;
-        while padded_cells.len() < columns.len() {
+        while padded_cells.len() < self.columns.len() {
             padded_cells.push(CellValue::Empty);
         }
 
         // Warn if row is longer than header (truncate)
-        if padded_cells.len() > columns.len() {
-            eprintln!(
-                "Warning: row {} in {} has more cells than columns, truncating",
-                row_idx + 1,
-                path.display()
+        if padded_cells.len() > self.columns.len() {
+            let message = format!(
+                "row {} has {} cells but only {} columns; truncated",
+                self.row_idx,
+                padded_cells.len(),
+                self.columns.len()
             );
-            padded_cells.truncate(columns.len());
+            self.warnings.push(self.warning(ParseWarningKind::ExtraCells, message));
+            padded_cells.truncate(self.columns.len());
         }
 
-        rows.push(Row::new(id, padded_cells));
+        Ok(Row::new(id, padded_cells))
     }
+}
 
-    Ok(Table {
-        columns,
-        rows,
-        source_path: path.to_path_buf(),
-    })
+impl<R: Read> Iterator for RowIter<R> {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let row = match self.read_record() {
+                Ok(true) => self.build_row(),
+                Ok(false) => return None,
+                Err(e) => Err(e),
+            };
+            match row {
+                // Errors without a record (e.g. I/O) can't be skipped past
+                Err(error)
+                    if self.on_error == RowErrorPolicy::Skip
+                        && !matches!(error, Error::Csv { .. }) =>
+                {
+                    let message = match error {
+                        Error::CsvParse { message, .. } => message,
+                        other => other.to_string(),
+                    };
+                    let warning = self.warning(ParseWarningKind::SkippedRow, message);
+                    self.parse_warnings.push(warning);
+                }
+                row => return Some(row),
+            }
+        }
+    }
 }
 
-/// Parse CSV from a string (useful for testing)
-pub fn parse_csv_str(content: &str, source_name: &str) -> Result<Table> {
-    let mut csv_reader = csv::ReaderBuilder::new()
-        .has_headers(true)
-        .flexible(true)
-        .from_reader(content.as_bytes());
+/// Where the CSV quoting stands after some input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QuoteState {
+    RecordStart,
+    FieldStart,
+    Unquoted,
+    Quoted,
+    /// A quote inside a quoted field: the field's end, or half of `""`
+    QuoteInQuoted,
+    Comment,
+}
 
-    let path = std::path::PathBuf::from(source_name);
+impl QuoteState {
+    /// The state after `byte`, following the csv reader's rules
+    fn step(self, byte: u8, comment: Option<u8>) -> Self {
+        use QuoteState::*;
+        let terminator = byte == b'\n' || byte == b'\r';
+        match self {
+            RecordStart if terminator => RecordStart,
+            RecordStart if comment == Some(byte) => Comment,
+            RecordStart | FieldStart if byte == b'"' => Quoted,
+            Quoted if byte == b'"' => QuoteInQuoted,
+            Quoted => Quoted,
+            QuoteInQuoted if byte == b'"' => Quoted,
+            Comment if terminator => RecordStart,
+            Comment => Comment,
+            _ if terminator => RecordStart,
+            _ if byte == b',' => FieldStart,
+            _ => Unquoted,
+        }
+    }
+}
 
-    // Parse headers into columns
-    let headers = csv_reader.headers().map_err(|e| Error::Csv {
-        path: path.clone(),
-        source: e,
-    })?;
+/// Reader that follows the quoting of the bytes the csv reader pulls
+/// through it, since the csv reader ends a quoted field at the end of the
+/// input without saying it was never closed
+struct QuoteTracker<R> {
+    inner: R,
+    comment: Option<u8>,
+    state: QuoteState,
+    consumed: u64,
+}
 
-    let columns: Vec<Column> = headers
-        .iter()
-        .enumerate()
-        .map(|(i, name)| Column::new(name.to_string(), i))
-        .collect();
+impl<R> QuoteTracker<R> {
+    fn new(inner: R, comment: Option<u8>) -> Self {
+        Self {
+            inner,
+            comment,
+            state: QuoteState::RecordStart,
+            consumed: 0,
+        }
+    }
 
-    if columns.is_empty() {
-        return Err(Error::CsvParse {
-            path: path.clone(),
-            message: "no columns found in CSV".to_string(),
-        });
+    /// Whether the input read so far ends inside a quoted field
+    fn in_quotes(&self) -> bool {
+        self.state == QuoteState::Quoted
     }
 
-    // Parse rows
-    let mut rows = Vec::new();
-    for result in csv_reader.records() {
-        let record = result.map_err(|e| Error::Csv {
-            path: path.clone(),
-            source: e,
-        })?;
+    /// Bytes read so far
+    fn consumed(&self) -> u64 {
+        self.consumed
+    }
+}
 
-        let cells: Vec<CellValue> = record.iter().map(CellValue::parse).collect();
+impl<R: Read> Read for QuoteTracker<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        for &byte in &buf[..read] {
+            self.state = self.state.step(byte, self.comment);
+        }
+        self.consumed += read as u64;
+        Ok(read)
+    }
+}
 
-        let id = cells.first().and_then(|c| match c {
-            CellValue::Integer(i) => Some(*i),
-            _ => None,
-        });
+/// Wrap an error from the csv crate, keeping the line it occurred on
+///
+/// `line_offset` counts lines consumed before the csv reader started.
+pub(crate) fn csv_error(path: &Path, error: csv::Error, line_offset: u64) -> Error {
+    let Some(line) = error.position().map(|p| p.line() + line_offset) else {
+        return Error::Csv {
+            path: path.to_path_buf(),
+            source: error,
+        };
+    };
+    let message = match error.kind() {
+        csv::ErrorKind::Utf8 { err, .. } => {
+            format!("invalid UTF-8 in field {}", err.field() + 1)
+        }
+        csv::ErrorKind::UnequalLengths {
+            expected_len, len, ..
+        } => format!("expected {} fields, found {}", expected_len, len),
+        _ => error.to_string(),
+    };
+    Error::CsvParse {
+        path: path.to_path_buf(),
+        line: Some(line),
+        message,
+    }
+}
 
-        let mut padded_cells = cells;
-        while padded_cells.len() < columns.len() {
-            padded_cells.push(CellValue::Empty);
+/// Highest column count an indexed header may ask for; the gaps between
+/// indexes become real columns, so a stray huge index would otherwise
+/// allocate without bound
+pub const MAX_INDEXED_COLUMNS: usize = 65_536;
+
+/// Build columns from `<n>:<name>` header fields, returning the column
+/// index of each field
+fn indexed_columns(
+    headers: &csv::StringRecord,
+    path: &Path,
+    line: u64,
+) -> Result<(Vec<Column>, Vec<usize>)> {
+    let error = |message: String| Error::CsvParse {
+        path: path.to_path_buf(),
+        line: Some(line),
+        message,
+    };
+
+    let mut names: BTreeMap<usize, &str> = BTreeMap::new();
+    let mut positions = Vec::new();
+    for field in headers {
+        let (index, name) = field
+            .split_once(':')
+            .and_then(|(index, name)| Some((index.trim().parse::<usize>().ok()?, name)))
+            .ok_or_else(|| error(format!("header '{}' has no '<n>:' index", field)))?;
+        if index >= MAX_INDEXED_COLUMNS {
+            return Err(error(format!(
+                "column index {} in header '{}' is over the limit of {}",
+                index,
+                field,
+                MAX_INDEXED_COLUMNS - 1
+            )));
         }
-        if padded_cells.len() > columns.len() {
-            padded_cells.truncate(columns.len());
+        if names.insert(index, name).is_some() {
+            return Err(error(format!(
+                "column index {} appears twice in the header",
+                index
+            )));
         }
+        positions.push(index);
+    }
+
+    let count = match names.keys().next_back() {
+        Some(last) => last
+            .checked_add(1)
+            .ok_or_else(|| error(format!("column index {} is too large", last)))?,
+        None => 0,
+    };
+    let columns = (0..count)
+        .map(|i| Column::new(names.get(&i).copied().unwrap_or_default().to_string(), i))
+        .collect();
+
+    Ok((columns, positions))
+}
+
+/// Look up the encoding named in `options`
+///
+/// Returns `None` for UTF-8, which the CSV reader handles itself.
+pub(crate) fn input_encoding(
+    options: &ParseOptions,
+    path: &Path,
+) -> Result<Option<&'static Encoding>> {
+    let Some(label) = &options.encoding else {
+        return Ok(None);
+    };
+    let encoding = Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| Error::UnknownEncoding(label.clone()))?;
+    if !encoding.is_ascii_compatible() {
+        return Err(Error::CsvParse {
+            path: path.to_path_buf(),
+            line: None,
+            message: format!("{} is not supported for CSV input", encoding.name()),
+        });
+    }
+    Ok((encoding != UTF_8).then_some(encoding))
+}
+
+/// Decode each field of a raw record
+fn decode_record(record: &csv::ByteRecord, encoding: &'static Encoding) -> csv::StringRecord {
+    record
+        .iter()
+        .map(|field| encoding.decode_without_bom_handling(field).0)
+        .collect()
+}
 
-        rows.push(Row::new(id, padded_cells));
+/// Consume the comment lines at the start of the input
+fn read_leading_comments<R: BufRead>(
+    reader: &mut R,
+    marker: u8,
+    encoding: Option<&'static Encoding>,
+) -> Result<Vec<String>> {
+    let mut comments = Vec::new();
+
+    while reader.fill_buf()?.first() == Some(&marker) {
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line)?;
+        while matches!(line.last(), Some(b'\n' | b'\r')) {
+            line.pop();
+        }
+        let comment = match encoding {
+            Some(encoding) => encoding.decode_without_bom_handling(&line).0,
+            None => String::from_utf8_lossy(&line),
+        };
+        comments.push(comment.into_owned());
     }
 
-    Ok(Table {
-        columns,
-        rows,
-        source_path: path,
-    })
+    Ok(comments)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::table::ColumnType;
 
     #[test]
     fn test_parse_simple_csv() {
@@ -180,6 +725,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_parse_with_floats() {
         let csv = "ID,Value\n1,3.14\n2,-2.5\n";
         let table = parse_csv_str(csv, "test.csv").unwrap();
@@ -188,6 +734,90 @@ mod tests {
         assert_eq!(table.rows[1].cells[1], CellValue::Float(-2.5));
     }
 
+    #[test]
+    fn test_hex_values_round_trip() {
+        let csv = "ID,Guid,Label\n1,0xABCD,0x\n2,0x00c4FDA9ED,0xZZ\n";
+        let options = ParseOptions {
+            hex_values: true,
+            ..Default::default()
+        };
+        let table = parse_csv_str_with_options(csv, "test.csv", &options).unwrap();
+
+        assert_eq!(table.rows[0].cells[1].as_hex(), Some(0xABCD));
+        assert_eq!(table.rows[0].cells[1].to_string_value(), "0xABCD");
+        assert_eq!(table.rows[1].cells[1].as_hex(), Some(0xC4FD_A9ED));
+        assert_eq!(table.rows[1].cells[1].to_string_value(), "0x00c4FDA9ED");
+        assert_eq!(table.rows[0].cells[2], CellValue::String("0x".to_string()));
+        assert_eq!(table.rows[1].cells[2], CellValue::String("0xZZ".to_string()));
+        assert_eq!(table.column_types[1], ColumnType::String);
+
+        // Off by default
+        let table = parse_csv_str(csv, "test.csv").unwrap();
+        assert_eq!(table.rows[0].cells[1], CellValue::String("0xABCD".to_string()));
+    }
+
+    #[test]
+    fn test_hex_ids_merge_on_their_value() {
+        let options = ParseOptions {
+            hex_values: true,
+            ..Default::default()
+        };
+        let base = "ID,Name,Cost\n0x0001,fire,10\n0x0002,ice,5\n";
+        let base = parse_csv_str_with_options(base, "base.csv", &options).unwrap();
+        assert_eq!(base.rows.iter().map(|r| r.id).collect::<Vec<_>>(), vec![Some(1), Some(2)]);
+
+        let ep1 = "ID,Name,Cost\n0x02,,8\n0xFFFFFFFFFFFFFFFF,huge,1\n";
+        let ep1 = parse_csv_str_with_options(ep1, "ep1.csv", &options).unwrap();
+        assert_eq!(ep1.rows[1].id, None);
+
+        let merged = crate::merger::merge_tables("test", vec![base, ep1]).unwrap();
+        assert_eq!(merged.rows.len(), 3);
+        let ice = merged.find_row(2).unwrap();
+        assert_eq!(ice.cells[1].value, CellValue::String("ice".to_string()));
+        assert_eq!(ice.cells[2].value, CellValue::Integer(8));
+        assert_eq!(ice.cells[2].source, PathBuf::from("ep1.csv"));
+
+        // Without the option hex IDs are text, so the rows stay unkeyed
+        let table = parse_csv_str("ID,Name\n0x0001,fire\n", "test.csv").unwrap();
+        assert_eq!(table.rows[0].id, None);
+    }
+
+    #[test]
+    fn test_parse_infers_column_types() {
+        let csv = "ID,Value,Label,Unused\n1,2.75,a,\n2,3,7,\n";
+        let table = parse_csv_str(csv, "test.csv").unwrap();
+
+        assert_eq!(
+            table.column_types,
+            vec![
+                ColumnType::Integer,
+                ColumnType::Float,
+                ColumnType::String,
+                ColumnType::Empty
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_infers_bool_columns_when_asked() {
+        let csv = "ID,Hidden,Cost,Flag\n1,1,2,0\n2,0,5,\n";
+        let table = parse_csv_str(csv, "test.csv").unwrap();
+        assert_eq!(table.column_types[1], ColumnType::Integer);
+
+        let options = ParseOptions {
+            bool_columns: true,
+            ..Default::default()
+        };
+        let table = parse_csv_str_with_options(csv, "test.csv", &options).unwrap();
+        assert_eq!(
+            table.column_types,
+            vec![ColumnType::Integer, ColumnType::Bool, ColumnType::Integer, ColumnType::Bool]
+        );
+        assert_eq!(table.rows[0].cells[1], CellValue::Integer(1));
+        assert!(ColumnType::Bool.accepts(&CellValue::Integer(0)));
+        assert!(!ColumnType::Bool.accepts(&CellValue::Integer(2)));
+    }
+
     #[test]
     fn test_parse_non_integer_id() {
         let csv = "Name,Value\nfoo,100\nbar,200\n";
@@ -197,4 +827,353 @@ mod tests {
         assert_eq!(table.rows[0].id, None);
         assert_eq!(table.rows[1].id, None);
     }
+
+    const EMPTY_ID_CSV: &str = "ID,Name\n1,foo\n,fallback\n";
+
+    #[test]
+    fn test_empty_id_unkeyed() {
+        let table = parse_csv_str(EMPTY_ID_CSV, "test.csv").unwrap();
+
+        assert_eq!(table.rows[0].id, Some(1));
+        assert_eq!(table.rows[1].id, None);
+    }
+
+    #[test]
+    fn test_empty_id_error_out() {
+        let options = ParseOptions {
+            empty_id_policy: EmptyIdPolicy::ErrorOut,
+            ..Default::default()
+        };
+        let err = parse_csv_str_with_options(EMPTY_ID_CSV, "test.csv", &options).unwrap_err();
+
+        assert!(err.to_string().contains("row 2 has an empty ID"));
+    }
+
+    #[test]
+    fn test_empty_id_treat_as_zero() {
+        let options = ParseOptions {
+            empty_id_policy: EmptyIdPolicy::TreatAsZero,
+            ..Default::default()
+        };
+        let table = parse_csv_str_with_options(EMPTY_ID_CSV, "test.csv", &options).unwrap();
+
+        assert_eq!(table.rows[1].id, Some(0));
+        assert_eq!(table.rows[1].cells[0], CellValue::Empty);
+    }
+
+    #[test]
+    fn test_empty_id_policy_ignores_string_ids() {
+        let options = ParseOptions {
+            empty_id_policy: EmptyIdPolicy::ErrorOut,
+            ..Default::default()
+        };
+        let table =
+            parse_csv_str_with_options("Name,Value\nfoo,1\n", "test.csv", &options).unwrap();
+
+        assert_eq!(table.rows[0].id, None);
+    }
+
+    #[test]
+    fn test_id_column_by_index_or_name() {
+        let csv = "Label,RowID,Cost\nfire,3,10\nice,x,5\nwind,1,7\n";
+        for id_column in [IdColumn::Index(1), IdColumn::Name("rowid".to_string())] {
+            let options = ParseOptions {
+                id_column,
+                ..Default::default()
+            };
+            let table = parse_csv_str_with_options(csv, "test.csv", &options).unwrap();
+            let ids: Vec<_> = table.rows.iter().map(|r| r.id).collect();
+            assert_eq!(ids, vec![Some(3), None, Some(1)]);
+            assert_eq!(table.id_column, 1);
+        }
+
+        // Rows merge on the IDs from the chosen column
+        let options = ParseOptions {
+            id_column: IdColumn::Index(1),
+            ..Default::default()
+        };
+        let base = parse_csv_str_with_options(csv, "base.csv", &options).unwrap();
+        let ep1 = parse_csv_str_with_options("Label,RowID,Cost\n,3,12\n", "ep1.csv", &options)
+            .unwrap();
+        let merged = crate::merger::merge_tables("test", vec![base, ep1]).unwrap();
+        let fire = merged.find_row(3).unwrap();
+        assert_eq!(fire.cells[0].value, CellValue::String("fire".to_string()));
+        assert_eq!(fire.cells[2].value, CellValue::Integer(12));
+
+        let options = ParseOptions {
+            id_column: IdColumn::Name("Missing".to_string()),
+            ..Default::default()
+        };
+        let err = parse_csv_str_with_options(csv, "test.csv", &options).unwrap_err();
+        assert_eq!(err.to_string(), "test.csv:1: no column 'Missing' to read IDs from");
+    }
+
+    #[test]
+    fn test_leading_comments_kept() {
+        let csv = "# Licensed under MIT\r\n#  keep  spacing\nID,Name\n1,foo\n";
+        let options = ParseOptions {
+            comment: Some(b'#'),
+            ..Default::default()
+        };
+        let table = parse_csv_str_with_options(csv, "test.csv", &options).unwrap();
+
+        assert_eq!(
+            table.leading_comments,
+            vec![
+                "# Licensed under MIT".to_string(),
+                "#  keep  spacing".to_string()
+            ]
+        );
+        assert_eq!(table.columns[0].name, "ID");
+        assert_eq!(table.rows.len(), 1);
+    }
+
+    #[test]
+    fn test_utf8_bom_stripped() {
+        let table = parse_csv_str("\u{feff}ID,Name\n1,foo\n", "test.csv").unwrap();
+        assert_eq!(table.find_column("ID").map(|c| c.index), Some(0));
+        assert_eq!(table.rows[0].id, Some(1));
+
+        // The BOM doesn't hide a comment on the first line
+        let options = ParseOptions {
+            comment: Some(b'#'),
+            ..Default::default()
+        };
+        let csv = "\u{feff}# header\nID,Name\n1,foo\n";
+        let table = parse_csv_str_with_options(csv, "test.csv", &options).unwrap();
+        assert_eq!(table.leading_comments, vec!["# header".to_string()]);
+        assert_eq!(table.columns[0].name, "ID");
+    }
+
+    #[test]
+    fn test_windows_1252_input() {
+        let csv = b"ID,Name\n1,Caf\xe9\n2,\x93quoted\x94\n";
+        let options = ParseOptions {
+            encoding: Some("windows-1252".to_string()),
+            ..Default::default()
+        };
+        let table = parse_reader(&csv[..], PathBuf::from("test.csv"), &options).unwrap();
+        assert_eq!(table.rows[0].cells[1], CellValue::String("Café".to_string()));
+        assert_eq!(
+            table.rows[1].cells[1],
+            CellValue::String("\u{201c}quoted\u{201d}".to_string())
+        );
+
+        // Without the option the bytes aren't valid UTF-8
+        let options = ParseOptions::default();
+        assert!(parse_reader(&csv[..], PathBuf::from("test.csv"), &options).is_err());
+
+        let options = ParseOptions {
+            encoding: Some("utf-16le".to_string()),
+            ..Default::default()
+        };
+        assert!(parse_reader(&csv[..], PathBuf::from("test.csv"), &options).is_err());
+    }
+
+    #[test]
+    fn test_streaming_sums_column_without_collecting() {
+        let mut csv = String::from("ID,Name,Cost\n");
+        for i in 0..100_000 {
+            csv.push_str(&format!("{},spell_{},{}\n", i, i, i % 10));
+        }
+
+        let rows =
+            RowIter::from_reader(csv.as_bytes(), "spells.csv", &ParseOptions::default()).unwrap();
+        assert_eq!(rows.columns()[2].name, "Cost");
+
+        let mut count = 0;
+        let mut total = 0;
+        for row in rows {
+            if let CellValue::Integer(cost) = row.unwrap().cells[2] {
+                total += cost;
+            }
+            count += 1;
+        }
+
+        assert_eq!(count, 100_000);
+        assert_eq!(total, 450_000);
+    }
+
+    #[test]
+    fn test_streaming_error_names_source() {
+        let options = ParseOptions {
+            empty_id_policy: EmptyIdPolicy::ErrorOut,
+            ..Default::default()
+        };
+        let mut rows = RowIter::from_reader(EMPTY_ID_CSV.as_bytes(), "test.csv", &options).unwrap();
+
+        assert_eq!(rows.next().unwrap().unwrap().id, Some(1));
+        let err = rows.next().unwrap().unwrap_err();
+        assert!(err.to_string().contains("test.csv"));
+        assert!(err.to_string().contains("row 2 has an empty ID"));
+    }
+
+    #[test]
+    fn test_unterminated_quote_reports_line() {
+        let csv = "# header comment\nID,Name\n1,foo\n2,\"bar\n3,baz\n";
+        let options = ParseOptions {
+            comment: Some(b'#'),
+            ..Default::default()
+        };
+        let err = parse_csv_str_with_options(csv, "test.csv", &options).unwrap_err();
+
+        assert!(matches!(err, Error::CsvParse { line: Some(4), .. }));
+        assert_eq!(err.to_string(), "test.csv:4: unterminated quoted field");
+
+        // Also without a final line break
+        let err = parse_csv_str("ID,Name\n1,\"open", "test.csv").unwrap_err();
+        assert_eq!(err.to_string(), "test.csv:2: unterminated quoted field");
+    }
+
+    #[test]
+    fn test_closed_quote_ending_in_line_break() {
+        let csv = "ID,Name\n1,\"two\nlines\n\"\n2,\"a \"\"b\"\"\r\n\"";
+        let table = parse_csv_str(csv, "test.csv").unwrap();
+
+        // Cells are trimmed, so only the inner line break is left
+        let expected = ["two\nlines", "a \"b\""];
+        for (row, expected) in table.rows.iter().zip(expected) {
+            assert_eq!(row.cells[1], CellValue::String(expected.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_skip_malformed_rows() {
+        let csv: &[u8] = b"ID,Name\n1,foo\n2,b\xffr\n,empty\n4,baz\n";
+        let options = ParseOptions {
+            empty_id_policy: EmptyIdPolicy::ErrorOut,
+            on_error: RowErrorPolicy::Skip,
+            ..Default::default()
+        };
+        let table = parse_reader(csv, PathBuf::from("test.csv"), &options).unwrap();
+
+        let ids: Vec<_> = table.rows.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![Some(1), Some(4)]);
+        assert_eq!(
+            table.parse_warnings,
+            vec![
+                ParseWarning {
+                    kind: ParseWarningKind::SkippedRow,
+                    line: Some(3),
+                    raw: "2,b\u{fffd}r".to_string(),
+                    message: "invalid UTF-8 in field 2".to_string(),
+                },
+                ParseWarning {
+                    kind: ParseWarningKind::SkippedRow,
+                    line: Some(4),
+                    raw: ",empty".to_string(),
+                    message: "row 3 has an empty ID".to_string(),
+                },
+            ]
+        );
+
+        // The default still fails on the first bad row
+        let options = ParseOptions::default();
+        let err = parse_reader(csv, PathBuf::from("test.csv"), &options).unwrap_err();
+        assert_eq!(err.to_string(), "test.csv:3: invalid UTF-8 in field 2");
+    }
+
+    const DUPLICATE_ID_CSV: &str = "ID,Name\n1,foo\n2,bar\n1,baz\n";
+
+    #[test]
+    fn test_duplicate_id_strict() {
+        let options = ParseOptions {
+            error_on_duplicate_ids: true,
+            ..Default::default()
+        };
+        let err = parse_csv_str_with_options(DUPLICATE_ID_CSV, "test.csv", &options).unwrap_err();
+
+        assert!(matches!(err, Error::DuplicateRowId { id: 1, .. }));
+    }
+
+    #[test]
+    fn test_duplicate_id_lenient_warns() {
+        let table = parse_csv_str(DUPLICATE_ID_CSV, "test.csv").unwrap();
+
+        assert_eq!(table.rows.len(), 3);
+        assert_eq!(
+            table.warnings,
+            vec![ParseWarning {
+                kind: ParseWarningKind::DuplicateId,
+                line: Some(4),
+                raw: "1,baz".to_string(),
+                message: "row 3 repeats ID 1".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_ragged_rows_are_recorded_not_printed() {
+        let csv = "ID,Name,Cost\n1,fire\n2,ice,5,extra,more\n3,acid,7\n";
+        let table = parse_csv_str(csv, "test.csv").unwrap();
+
+        // Short rows are padded silently; long rows are truncated with a
+        // warning on the table rather than on stderr
+        assert_eq!(table.rows[0].cells[2], CellValue::Empty);
+        assert_eq!(table.rows[1].cells.len(), 3);
+        assert!(table.parse_warnings.is_empty());
+        assert_eq!(
+            table.warnings,
+            vec![ParseWarning {
+                kind: ParseWarningKind::ExtraCells,
+                line: Some(3),
+                raw: "2,ice,5,extra,more".to_string(),
+                message: "row 2 has 5 cells but only 3 columns; truncated".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_indexed_headers_fill_gaps() {
+        let options = ParseOptions {
+            indexed_headers: true,
+            ..Default::default()
+        };
+        let csv = "0:ID,2:Cost\n1,10\n2,20\n";
+        let table = parse_csv_str_with_options(csv, "test.csv", &options).unwrap();
+
+        let names: Vec<&str> = table.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["ID", "", "Cost"]);
+        assert_eq!(table.rows[1].id, Some(2));
+        assert_eq!(
+            table.rows[1].cells,
+            vec![
+                CellValue::Integer(2),
+                CellValue::Empty,
+                CellValue::Integer(20)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_indexed_headers_reorder_and_reject_missing_index() {
+        let options = ParseOptions {
+            indexed_headers: true,
+            ..Default::default()
+        };
+        let table =
+            parse_csv_str_with_options("1:Name,0:ID\nfoo,7\n", "test.csv", &options).unwrap();
+        assert_eq!(table.columns[0].name, "ID");
+        assert_eq!(table.rows[0].id, Some(7));
+
+        let err =
+            parse_csv_str_with_options("0:ID,Name\n1,foo\n", "test.csv", &options).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("header 'Name' has no '<n>:' index"));
+    }
+
+    #[test]
+    fn test_indexed_headers_reject_huge_index() {
+        let options = ParseOptions {
+            indexed_headers: true,
+            ..Default::default()
+        };
+        for header in ["1000000000:ID", "18446744073709551615:ID"] {
+            let csv = format!("{}\n1\n", header);
+            let err = parse_csv_str_with_options(&csv, "test.csv", &options).unwrap_err();
+            assert!(matches!(err, Error::CsvParse { line: Some(1), .. }));
+            assert!(err.to_string().contains("is over the limit of 65535"), "{}", err);
+        }
+    }
 }
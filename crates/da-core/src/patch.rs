@@ -5,16 +5,38 @@
 //! - Patch file format (JSON) for storing edits
 //! - Export functionality that writes edits back to source files
 
+use crate::csv_writer::{escape_field, LineEnding};
+use crate::encoding::OutputEncoding;
 use crate::error::{Error, Result};
-use crate::merger::ResolvedTable;
-use crate::parser::parse_csv;
+use crate::merger::{merge_family_with_options, MergeOptions, ResolvedTable};
+use crate::parser::{
+    csv_error, input_encoding, parse_csv_streaming_with_options, parse_csv_with_options,
+    EmptyIdPolicy, ParseOptions, UTF8_BOM,
+};
+use crate::scanner::ScanResult;
+use crate::table::{CellValue, Column, ColumnType, Table};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs::{self, File};
-use std::io::{BufWriter, Write};
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 /// A single edit to a cell
+///
+/// An empty (or whitespace-only) `value` clears the cell: it resolves to
+/// `CellValue::Empty` and is exported as an empty field, or as
+/// `ExportOptions::empty_as` if that is set. Set `literal` to keep the
+/// value verbatim as a string instead, which for an empty value exports an
+/// explicit `""` field whatever `empty_as` says. CSV can't tell `""` from
+/// an empty field when reading, so the exported file reads back as `Empty`
+/// either way; the quotes only keep the intent visible in the file.
+///
+/// With `expected_old` set, the edit only applies while the cell still
+/// holds that value; if the source files changed since the patch was
+/// written, the edit is reported as stale instead.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Edit {
     /// Row ID (must match a row in the table)
@@ -23,6 +45,12 @@ pub struct Edit {
     pub column: String,
     /// New value as a string
     pub value: String,
+    /// Keep `value` as a literal string instead of detecting its type
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub literal: bool,
+    /// Value the cell must currently hold for the edit to apply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_old: Option<String>,
 }
 
 impl Edit {
@@ -32,7 +60,243 @@ impl Edit {
             row_id,
             column: column.into(),
             value: value.into(),
+            literal: false,
+            expected_old: None,
+        }
+    }
+
+    /// Require the cell to hold `old` before this edit applies
+    pub fn with_expected_old(mut self, old: impl Into<String>) -> Self {
+        self.expected_old = Some(old.into());
+        self
+    }
+
+    /// Describe how the cell differs from `expected_old`, if it does
+    ///
+    /// Values compare after type detection, so `1.50` matches `1.5`.
+    pub fn stale_reason(&self, current: &CellValue) -> Option<String> {
+        let expected = self.expected_old.as_deref()?;
+        (CellValue::parse(expected) != *current).then(|| {
+            format!(
+                "Stale edit: expected '{}', found '{}'",
+                expected,
+                current.to_string_value()
+            )
+        })
+    }
+
+    /// Create an edit whose value is kept verbatim as a string
+    pub fn new_literal(row_id: i64, column: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            literal: true,
+            ..Self::new(row_id, column, value)
+        }
+    }
+
+    /// Get the cell value this edit produces
+    pub fn cell_value(&self) -> CellValue {
+        if self.literal {
+            CellValue::String(self.value.clone())
+        } else {
+            CellValue::parse(&self.value)
+        }
+    }
+
+    /// Get the CSV field written for this edit on export, writing a
+    /// cleared cell as `empty_as`
+    fn export_field(&self, empty_as: Option<&str>) -> String {
+        if self.literal && self.value.is_empty() {
+            "\"\"".to_string()
+        } else if !self.literal && self.value.trim().is_empty() {
+            empty_as.map(escape_field).unwrap_or_default()
+        } else {
+            escape_field(&self.value)
+        }
+    }
+}
+
+/// A row-level operation in a patch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum RowOp {
+    /// Remove a row from every source file that defines it
+    DeleteRow {
+        /// Row ID (must match a row in the table)
+        row_id: i64,
+    },
+    /// Add a new row; columns missing from `values` are left empty
+    InsertRow {
+        /// Row ID (must not already exist in the table)
+        row_id: i64,
+        /// Cell values by column name
+        values: HashMap<String, String>,
+        /// Source file name to write the row into (defaults to the base file)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file: Option<String>,
+    },
+}
+
+impl RowOp {
+    /// Get the row ID this operation targets
+    pub fn row_id(&self) -> i64 {
+        match self {
+            RowOp::DeleteRow { row_id } | RowOp::InsertRow { row_id, .. } => *row_id,
+        }
+    }
+}
+
+/// A column-level operation in a patch
+///
+/// Column operations run in patch order after the edits and row
+/// operations, which keep referring to columns by their original names.
+/// On export they rewrite the header and every data record of each source
+/// file that has the affected columns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ColumnOp {
+    /// Give a column a new name
+    Rename {
+        /// Current column name
+        from: String,
+        /// New column name (must not already exist)
+        to: String,
+    },
+    /// Put the columns in a new order
+    Reorder {
+        /// Every column name exactly once, starting with the ID column
+        order: Vec<String>,
+    },
+}
+
+/// Check column operations in order against a table's column names
+///
+/// Returns one result per operation; a failed operation leaves the names
+/// unchanged for the ones after it. Also returns the final names.
+fn check_column_ops(
+    table: &ResolvedTable,
+    ops: &[ColumnOp],
+) -> (Vec<Option<String>>, Vec<String>) {
+    let mut names: Vec<String> = table.columns.iter().map(|c| c.name.clone()).collect();
+    let id = table.id_column;
+    let errors = ops
+        .iter()
+        .map(|op| {
+            let error = match op {
+                ColumnOp::Rename { from, .. } if !names.contains(from) => {
+                    format!("Column '{}' not found", from)
+                }
+                ColumnOp::Rename { from, to } if from != to && names.contains(to) => {
+                    format!("Column '{}' already exists", to)
+                }
+                ColumnOp::Rename { from, to } => {
+                    if let Some(name) = names.iter_mut().find(|n| *n == from) {
+                        name.clone_from(to);
+                    }
+                    return None;
+                }
+                ColumnOp::Reorder { order } => {
+                    let listed: HashSet<&String> = order.iter().collect();
+                    if let Some(name) = order.iter().find(|n| !names.contains(n)) {
+                        format!("Column '{}' not found", name)
+                    } else if listed.len() != order.len() {
+                        "Reorder lists a column more than once".to_string()
+                    } else if let Some(name) = names.iter().find(|n| !listed.contains(n)) {
+                        format!("Reorder leaves out column '{}'", name)
+                    } else if order.get(id) != names.get(id) {
+                        "Reorder must keep the ID column in place".to_string()
+                    } else {
+                        names.clone_from(order);
+                        return None;
+                    }
+                }
+            };
+            Some(error)
+        })
+        .collect();
+    (errors, names)
+}
+
+/// Work out a source file's columns after the column operations
+///
+/// Returns the new layout as (index in the file, name) pairs in output
+/// order, or `None` if the operations leave the file's header unchanged.
+/// Operations are assumed valid for the merged table; a reorder moves the
+/// file's columns into the listed order, skipping columns it lacks.
+fn column_layout(columns: &[Column], ops: &[ColumnOp]) -> Option<Vec<(usize, String)>> {
+    let mut layout: Vec<(usize, String)> =
+        columns.iter().map(|c| (c.index, c.name.clone())).collect();
+    for op in ops {
+        match op {
+            ColumnOp::Rename { from, to } => {
+                if let Some((_, name)) = layout.iter_mut().find(|(_, n)| n == from) {
+                    name.clone_from(to);
+                }
+            }
+            ColumnOp::Reorder { order } => {
+                layout.sort_by_key(|(_, n)| order.iter().position(|o| o == n));
+            }
+        }
+    }
+
+    let unchanged = layout
+        .iter()
+        .zip(columns)
+        .all(|((index, name), c)| *index == c.index && *name == c.name);
+    (!unchanged).then_some(layout)
+}
+
+/// Put a record's fields into the order of a column layout
+///
+/// `field_of_column` maps a column index to its field in the record.
+/// Missing trailing fields come out empty.
+fn arrange_fields(
+    fields: &[String],
+    layout: &[(usize, String)],
+    field_of_column: impl Fn(usize) -> usize,
+) -> Vec<String> {
+    layout
+        .iter()
+        .map(|(index, _)| fields.get(field_of_column(*index)).cloned().unwrap_or_default())
+        .collect()
+}
+
+/// A regex replacement applied to one column of every matching row
+///
+/// At apply time it expands into one `Edit` per keyed row whose cell
+/// contains a match, replacing every match in the cell's text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BulkEdit {
+    /// Column to edit
+    pub column: String,
+    /// Regex searched for in each cell's text
+    pub match_regex: String,
+    /// Replacement text; `$1` or `${name}` insert capture groups
+    pub replacement: String,
+}
+
+impl BulkEdit {
+    /// Expand into concrete edits against the merged table
+    ///
+    /// Fails if the regex is invalid or the column doesn't exist. Rows
+    /// without an ID can't be edited and are skipped.
+    pub fn expand(&self, table: &ResolvedTable) -> Result<Vec<Edit>> {
+        let regex = Regex::new(&self.match_regex)?;
+        let col = table
+            .find_column(&self.column)
+            .ok_or_else(|| Error::ColumnNotFound(self.column.clone()))?;
+
+        let mut edits = Vec::new();
+        for row in &table.rows {
+            let (Some(row_id), Some(cell)) = (row.id, row.cells.get(col.index)) else {
+                continue;
+            };
+            let text = cell.value.to_string_value();
+            if regex.is_match(&text) {
+                let value = regex.replace_all(&text, self.replacement.as_str());
+                edits.push(Edit::new(row_id, self.column.clone(), value));
+            }
         }
+        Ok(edits)
     }
 }
 
@@ -41,8 +305,30 @@ impl Edit {
 pub struct PatchFile {
     /// Family name this patch applies to
     pub family: String,
+    /// What the patch is for, shown in the history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Who wrote the patch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
     /// List of edits
     pub edits: Vec<Edit>,
+    /// List of row operations (absent in older patch files)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub row_ops: Vec<RowOp>,
+    /// Values for columns that an inserted row leaves out, by column name
+    ///
+    /// A value given in the `InsertRow` itself always wins; columns with
+    /// neither are left empty. Only rows inserted by this patch are
+    /// filled, existing rows and merged values are never touched.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub column_defaults: HashMap<String, String>,
+    /// Regex replacements expanded into edits at apply time
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bulk_edits: Vec<BulkEdit>,
+    /// Column renames and reorders, applied after everything else
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub column_ops: Vec<ColumnOp>,
 }
 
 impl PatchFile {
@@ -50,8 +336,32 @@ impl PatchFile {
     pub fn new(family: impl Into<String>) -> Self {
         Self {
             family: family.into(),
+            description: None,
+            author: None,
             edits: Vec::new(),
+            row_ops: Vec::new(),
+            column_defaults: HashMap::new(),
+            bulk_edits: Vec::new(),
+            column_ops: Vec::new(),
+        }
+    }
+
+    /// Copy of this patch with its bulk edits expanded into plain edits
+    ///
+    /// The expanded edits come before the patch's own edits, so an explicit
+    /// edit to the same cell wins.
+    pub fn expand_bulk_edits(&self, table: &ResolvedTable) -> Result<PatchFile> {
+        let mut edits = Vec::new();
+        for bulk in &self.bulk_edits {
+            edits.extend(bulk.expand(table)?);
         }
+        edits.extend(self.edits.iter().cloned());
+
+        Ok(PatchFile {
+            edits,
+            bulk_edits: Vec::new(),
+            ..self.clone()
+        })
     }
 
     /// Add an edit to the patch
@@ -59,6 +369,16 @@ impl PatchFile {
         self.edits.push(edit);
     }
 
+    /// Add a row operation to the patch
+    pub fn add_row_op(&mut self, op: RowOp) {
+        self.row_ops.push(op);
+    }
+
+    /// Add a column operation to the patch
+    pub fn add_column_op(&mut self, op: ColumnOp) {
+        self.column_ops.push(op);
+    }
+
     /// Load a patch file from JSON
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let content = fs::read_to_string(path.as_ref()).map_err(|e| Error::FileRead {
@@ -84,7 +404,63 @@ pub struct BatchFile {
     /// Output directory for exports
     pub output_dir: PathBuf,
     /// List of patch files to apply
-    pub patches: Vec<PathBuf>,
+    pub patches: Vec<BatchEntry>,
+}
+
+/// One patch of a batch file
+///
+/// Written either as a bare path or as an object that also sets where this
+/// patch is exported and which history file records it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BatchEntry {
+    /// A patch file exported to the batch's output directory
+    Path(PathBuf),
+    /// A patch file with its own settings
+    Detailed {
+        /// Path to the patch file
+        patch: PathBuf,
+        /// Output directory, instead of the batch's
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output_dir: Option<PathBuf>,
+        /// History file to record the patch in once it is exported
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        history_file: Option<PathBuf>,
+    },
+}
+
+impl BatchEntry {
+    /// Get the path to the patch file
+    pub fn patch(&self) -> &Path {
+        match self {
+            BatchEntry::Path(patch) | BatchEntry::Detailed { patch, .. } => patch,
+        }
+    }
+
+    /// Get the directory this patch is exported to, given the batch's
+    pub fn output_dir<'a>(&'a self, default: &'a Path) -> &'a Path {
+        match self {
+            BatchEntry::Detailed {
+                output_dir: Some(dir),
+                ..
+            } => dir,
+            _ => default,
+        }
+    }
+
+    /// Get the history file this patch is recorded in, if any
+    pub fn history_file(&self) -> Option<&Path> {
+        match self {
+            BatchEntry::Detailed { history_file, .. } => history_file.as_deref(),
+            BatchEntry::Path(_) => None,
+        }
+    }
+}
+
+impl From<PathBuf> for BatchEntry {
+    fn from(patch: PathBuf) -> Self {
+        BatchEntry::Path(patch)
+    }
 }
 
 impl BatchFile {
@@ -105,6 +481,41 @@ impl BatchFile {
     }
 }
 
+/// Patches for several families, applied together
+///
+/// Stored as `{"patches": [...]}`, each entry a complete single-family
+/// patch. `load` also accepts a plain single-family patch file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MultiPatchFile {
+    /// One patch per family
+    pub patches: Vec<PatchFile>,
+}
+
+impl MultiPatchFile {
+    /// Load a multi-family patch, or a single-family patch as one entry
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(path.as_ref()).map_err(|e| Error::FileRead {
+            path: path.as_ref().to_path_buf(),
+            source: e,
+        })?;
+        let value: serde_json::Value = serde_json::from_str(&content)?;
+        if value.get("patches").is_some() {
+            serde_json::from_value(value).map_err(Error::Json)
+        } else {
+            Ok(Self {
+                patches: vec![serde_json::from_value(value)?],
+            })
+        }
+    }
+
+    /// Save the multi-family patch to JSON
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
 /// Result of applying a patch - tracks which source files were modified
 #[derive(Debug, Clone)]
 pub struct PatchResult {
@@ -116,6 +527,26 @@ pub struct PatchResult {
     pub modified_sources: HashMap<PathBuf, Vec<i64>>,
     /// Edits that failed (row not found, column not found, etc.)
     pub failed_edits: Vec<(Edit, String)>,
+    /// Number of rows deleted
+    pub rows_deleted: usize,
+    /// Number of rows inserted
+    pub rows_inserted: usize,
+    /// Row operations that failed (row not found, row already exists, etc.)
+    pub failed_row_ops: Vec<(RowOp, String)>,
+    /// Number of rows each bulk edit matched, in patch order
+    pub bulk_matches: Vec<(BulkEdit, usize)>,
+    /// Column operations that failed (column not found, incomplete order, etc.)
+    pub failed_column_ops: Vec<(ColumnOp, String)>,
+}
+
+impl PatchResult {
+    /// Bulk edits whose regex matched no rows
+    pub fn unmatched_bulk_edits(&self) -> impl Iterator<Item = &BulkEdit> {
+        self.bulk_matches
+            .iter()
+            .filter(|(_, count)| *count == 0)
+            .map(|(bulk, _)| bulk)
+    }
 }
 
 /// Apply a patch to a resolved table and track which source files are affected
@@ -125,11 +556,23 @@ pub fn apply_patch(table: &ResolvedTable, patch: &PatchFile) -> Result<PatchResu
         edits_applied: 0,
         modified_sources: HashMap::new(),
         failed_edits: Vec::new(),
+        rows_deleted: 0,
+        rows_inserted: 0,
+        failed_row_ops: Vec::new(),
+        bulk_matches: Vec::new(),
+        failed_column_ops: Vec::new(),
     };
 
-    for edit in &patch.edits {
+    let mut bulk_edits = Vec::new();
+    for bulk in &patch.bulk_edits {
+        let edits = bulk.expand(table)?;
+        result.bulk_matches.push((bulk.clone(), edits.len()));
+        bulk_edits.extend(edits);
+    }
+
+    for edit in bulk_edits.iter().chain(&patch.edits) {
         // Find the row by ID
-        let row_idx = table.rows.iter().position(|r| r.id == Some(edit.row_id));
+        let row_idx = table.row_position(edit.row_id);
 
         let row_idx = match row_idx {
             Some(idx) => idx,
@@ -157,7 +600,12 @@ pub fn apply_patch(table: &ResolvedTable, patch: &PatchFile) -> Result<PatchResu
         };
 
         // Get the source file for this cell
-        let source = &table.rows[row_idx].cells[col_idx].source;
+        let cell = &table.rows[row_idx].cells[col_idx];
+        if let Some(reason) = edit.stale_reason(&cell.value) {
+            result.failed_edits.push((edit.clone(), reason));
+            continue;
+        }
+        let source = &cell.source;
 
         // Track this modification
         result
@@ -169,176 +617,2395 @@ pub fn apply_patch(table: &ResolvedTable, patch: &PatchFile) -> Result<PatchResu
         result.edits_applied += 1;
     }
 
-    Ok(result)
-}
+    for op in &patch.row_ops {
+        match op {
+            RowOp::DeleteRow { row_id } => {
+                let row = match table.find_row(*row_id) {
+                    Some(row) => row,
+                    None => {
+                        result
+                            .failed_row_ops
+                            .push((op.clone(), format!("Row ID {} not found", row_id)));
+                        continue;
+                    }
+                };
 
-/// Export modified source files with edits applied
-///
-/// This reads the original source files, applies the relevant edits,
-/// and writes new copies to the output directory.
-pub fn export_with_edits<P: AsRef<Path>>(
-    table: &ResolvedTable,
-    patch: &PatchFile,
-    output_dir: P,
-) -> Result<ExportResult> {
-    let output_dir = output_dir.as_ref();
+                // Every file that provided a cell of this row loses it
+                let sources: HashSet<&PathBuf> = row.cells.iter().map(|c| &c.source).collect();
+                for source in sources {
+                    result
+                        .modified_sources
+                        .entry(source.clone())
+                        .or_default()
+                        .push(*row_id);
+                }
 
-    // Create output directory if it doesn't exist
-    fs::create_dir_all(output_dir)?;
+                result.rows_deleted += 1;
+            }
+            RowOp::InsertRow {
+                row_id,
+                values,
+                file,
+            } => {
+                if table.find_row(*row_id).is_some() {
+                    result
+                        .failed_row_ops
+                        .push((op.clone(), format!("Row ID {} already exists", row_id)));
+                    continue;
+                }
 
-    // Group edits by source file
-    let mut edits_by_source: HashMap<PathBuf, Vec<&Edit>> = HashMap::new();
+                if let Some(column) = values.keys().find(|c| table.find_column(c).is_none()) {
+                    result
+                        .failed_row_ops
+                        .push((op.clone(), format!("Column '{}' not found", column)));
+                    continue;
+                }
 
-    for edit in &patch.edits {
-        // Find the row and get its source file for the edited column
-        if let Some(row) = table.rows.iter().find(|r| r.id == Some(edit.row_id)) {
-            if let Some(col) = table.columns.iter().find(|c| c.name == edit.column) {
-                let source = &row.cells[col.index].source;
-                edits_by_source
+                let source = match insert_target(table, file.as_deref()) {
+                    Some(source) => source,
+                    None => {
+                        result.failed_row_ops.push((
+                            op.clone(),
+                            format!("Source file '{}' not found", file.as_deref().unwrap_or("")),
+                        ));
+                        continue;
+                    }
+                };
+
+                result
+                    .modified_sources
                     .entry(source.clone())
                     .or_default()
-                    .push(edit);
+                    .push(*row_id);
+
+                result.rows_inserted += 1;
             }
         }
     }
 
-    let mut result = ExportResult {
-        files_written: Vec::new(),
-        edits_applied: 0,
-        errors: Vec::new(),
-    };
-
-    // Process each source file that has edits
-    for (source_path, edits) in edits_by_source {
-        match export_single_file(&source_path, &edits, output_dir) {
-            Ok(output_path) => {
-                result.edits_applied += edits.len();
-                result.files_written.push(output_path);
-            }
-            Err(e) => {
-                result.errors.push((source_path, e.to_string()));
-            }
+    let (errors, _) = check_column_ops(table, &patch.column_ops);
+    for (op, error) in patch.column_ops.iter().zip(errors) {
+        if let Some(error) = error {
+            result.failed_column_ops.push((op.clone(), error));
         }
     }
 
     Ok(result)
 }
 
-/// Export a single source file with edits applied
-fn export_single_file(
-    source_path: &Path,
-    edits: &[&Edit],
-    output_dir: &Path,
-) -> Result<PathBuf> {
-    // Parse the original file
-    let original = parse_csv(source_path)?;
+/// Build the patch that undoes `patch` on the table it was applied to
+///
+/// Edits are reverted to the cell values in `table`, inserted rows are
+/// deleted, and deleted rows are inserted again with their merged values
+/// into the file that provided their ID. Column operations are undone in
+/// reverse order, and the inverse refers to columns by their new names.
+/// Edits and operations that `apply_patch` would reject have no inverse
+/// and are skipped; if any column operation fails, none are inverted.
+pub fn invert_patch(table: &ResolvedTable, patch: &PatchFile) -> PatchFile {
+    let mut inverse = PatchFile::new(patch.family.clone());
+
+    let (errors, final_names) = check_column_ops(table, &patch.column_ops);
+    let column_ops_apply = errors.iter().all(Option::is_none);
+    let renamed = |name: &str| -> String {
+        match table.find_column(name) {
+            Some(col) if column_ops_apply => final_names[col.index].clone(),
+            _ => name.to_string(),
+        }
+    };
 
-    // Build a map of edits: (row_id, column_name) -> new_value
-    let edit_map: HashMap<(i64, &str), &str> = edits
+    let deleted: HashSet<i64> = patch
+        .row_ops
         .iter()
-        .map(|e| ((e.row_id, e.column.as_str()), e.value.as_str()))
+        .filter(|op| matches!(op, RowOp::DeleteRow { .. }))
+        .map(RowOp::row_id)
         .collect();
 
-    // Build column name -> index map
-    let col_indices: HashMap<&str, usize> = original
-        .columns
+    // One restoring edit per cell; a deleted row gets its values back
+    // through the re-insert instead
+    let mut seen = HashSet::new();
+    let bulk_edits: Vec<Edit> = patch
+        .bulk_edits
         .iter()
-        .map(|c| (c.name.as_str(), c.index))
+        .filter_map(|bulk| bulk.expand(table).ok())
+        .flatten()
         .collect();
+    for edit in patch.edits.iter().chain(&bulk_edits) {
+        if deleted.contains(&edit.row_id) || !seen.insert((edit.row_id, edit.column.as_str())) {
+            continue;
+        }
+        let (Some(row), Some(col)) = (table.find_row(edit.row_id), table.find_column(&edit.column))
+        else {
+            continue;
+        };
+        let original = row.cells[col.index].value.to_string_value();
+        inverse.add_edit(Edit::new(edit.row_id, renamed(&edit.column), original));
+    }
 
-    // Determine output path
-    let file_name = source_path
-        .file_name()
-        .ok_or_else(|| Error::InvalidFamilyName("Invalid source path".to_string()))?;
-    let output_path = output_dir.join(file_name);
+    for op in &patch.row_ops {
+        match op {
+            RowOp::DeleteRow { row_id } => {
+                let Some(row) = table.find_row(*row_id) else {
+                    continue;
+                };
+                let values = table
+                    .columns
+                    .iter()
+                    .filter(|c| c.index != table.id_column)
+                    .filter_map(|c| {
+                        let value = &row.cells[c.index].value;
+                        (!value.is_empty()).then(|| (renamed(&c.name), value.to_string_value()))
+                    })
+                    .collect();
+                let file = row.cells[table.id_column]
+                    .source
+                    .file_name()
+                    .map(|f| f.to_string_lossy().into_owned());
+                inverse.add_row_op(RowOp::InsertRow {
+                    row_id: *row_id,
+                    values,
+                    file,
+                });
+            }
+            RowOp::InsertRow { row_id, .. } => {
+                if table.find_row(*row_id).is_none() {
+                    inverse.add_row_op(RowOp::DeleteRow { row_id: *row_id });
+                }
+            }
+        }
+    }
 
-    // Write the modified CSV
-    let file = File::create(&output_path)?;
-    let mut writer = BufWriter::new(file);
+    if column_ops_apply {
+        // Replay the operations to know each one's starting names
+        let mut names: Vec<String> = table.columns.iter().map(|c| c.name.clone()).collect();
+        let mut undo = Vec::new();
+        for op in &patch.column_ops {
+            match op {
+                ColumnOp::Rename { from, to } => {
+                    if let Some(name) = names.iter_mut().find(|n| *n == from) {
+                        name.clone_from(to);
+                    }
+                    undo.push(ColumnOp::Rename {
+                        from: to.clone(),
+                        to: from.clone(),
+                    });
+                }
+                ColumnOp::Reorder { order } => {
+                    undo.push(ColumnOp::Reorder {
+                        order: std::mem::replace(&mut names, order.clone()),
+                    });
+                }
+            }
+        }
+        undo.reverse();
+        inverse.column_ops = undo;
+    }
 
-    // Write header
-    let header: Vec<&str> = original.columns.iter().map(|c| c.name.as_str()).collect();
-    writeln!(writer, "{}", header.join(","))?;
+    inverse
+}
 
-    // Write rows with edits applied
-    for row in &original.rows {
-        let mut cells: Vec<String> = row
-            .cells
+/// Result of validating one edit of a patch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditValidation {
+    /// Index of the edit in the patch
+    pub index: usize,
+    /// Why the edit cannot be applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Inferred type of the column, set when the new value does not fit it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_mismatch: Option<ColumnType>,
+}
+
+/// Result of validating one row operation of a patch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RowOpValidation {
+    /// Index of the row operation in the patch
+    pub index: usize,
+    /// Why the operation cannot be applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of validating one column operation of a patch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnOpValidation {
+    /// Index of the column operation in the patch
+    pub index: usize,
+    /// Why the operation cannot be applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Per-edit and per-operation results of validating a patch
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// One result per edit, in patch order
+    pub edits: Vec<EditValidation>,
+    /// One result per row operation, in patch order
+    pub row_ops: Vec<RowOpValidation>,
+    /// One result per column operation, in patch order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub column_ops: Vec<ColumnOpValidation>,
+}
+
+impl ValidationReport {
+    /// Check whether every edit and row or column operation can be applied
+    ///
+    /// Type mismatches are warnings and don't make a patch invalid.
+    pub fn is_valid(&self) -> bool {
+        self.first_error().is_none()
+    }
+
+    /// Get the first error, edits before row operations before column operations
+    pub fn first_error(&self) -> Option<&str> {
+        self.edits
             .iter()
-            .map(|c| c.to_string_value())
-            .collect();
+            .filter_map(|e| e.error.as_deref())
+            .chain(self.row_ops.iter().filter_map(|o| o.error.as_deref()))
+            .chain(self.column_ops.iter().filter_map(|o| o.error.as_deref()))
+            .next()
+    }
 
-        // Apply any edits for this row
-        if let Some(row_id) = row.id {
-            for (col_name, &col_idx) in &col_indices {
-                if let Some(&new_value) = edit_map.get(&(row_id, col_name)) {
-                    if col_idx < cells.len() {
-                        cells[col_idx] = new_value.to_string();
+    /// Count the edits and row and column operations checked
+    pub fn checked_count(&self) -> usize {
+        self.edits.len() + self.row_ops.len() + self.column_ops.len()
+    }
+
+    /// Count the edits and row and column operations that cannot be applied
+    pub fn error_count(&self) -> usize {
+        self.edits.iter().filter(|e| e.error.is_some()).count()
+            + self.row_ops.iter().filter(|o| o.error.is_some()).count()
+            + self.column_ops.iter().filter(|o| o.error.is_some()).count()
+    }
+
+    /// Count the edits whose value does not fit the column type
+    pub fn warning_count(&self) -> usize {
+        self.edits.iter().filter(|e| e.type_mismatch.is_some()).count()
+    }
+}
+
+/// Check that every edit and operation of a patch can be applied
+///
+/// Edits need an existing row and column, deletes an existing row, and
+/// inserts a new row ID with known columns. Renames need an existing
+/// column and a free name, and reorders must list every column once with
+/// the ID column first. With `check_types` set, each edit's value is also
+/// checked against the type inferred from the column's merged values, so
+/// text written into a numeric column is reported as a type mismatch.
+pub fn validate_patch(
+    table: &ResolvedTable,
+    patch: &PatchFile,
+    check_types: bool,
+) -> ValidationReport {
+    let mut column_types: HashMap<usize, ColumnType> = HashMap::new();
+    let mut report = ValidationReport::default();
+
+    for (index, edit) in patch.edits.iter().enumerate() {
+        let mut result = EditValidation {
+            index,
+            error: None,
+            type_mismatch: None,
+        };
+        match (table.find_row(edit.row_id), table.find_column(&edit.column)) {
+            (None, _) => result.error = Some(format!("Row not found: {}", edit.row_id)),
+            (_, None) => result.error = Some(format!("Column not found: {}", edit.column)),
+            (Some(row), Some(col)) => {
+                result.error = edit.stale_reason(&row.cells[col.index].value);
+                if check_types {
+                    let ty = *column_types
+                        .entry(col.index)
+                        .or_insert_with(|| table.infer_column_type(col.index));
+                    if !ty.accepts(&edit.cell_value()) {
+                        result.type_mismatch = Some(ty);
                     }
                 }
             }
         }
+        report.edits.push(result);
+    }
 
-        // Escape and write
-        let escaped: Vec<String> = cells.iter().map(|c| escape_csv(c)).collect();
-        writeln!(writer, "{}", escaped.join(","))?;
+    for (index, op) in patch.row_ops.iter().enumerate() {
+        let error = match op {
+            RowOp::DeleteRow { row_id } if table.find_row(*row_id).is_none() => {
+                Some(format!("Row not found: {}", row_id))
+            }
+            RowOp::InsertRow { row_id, .. } if table.find_row(*row_id).is_some() => {
+                Some(format!("Row already exists: {}", row_id))
+            }
+            RowOp::InsertRow { values, .. } => values
+                .keys()
+                .find(|c| table.find_column(c).is_none())
+                .map(|c| format!("Column not found: {}", c)),
+            RowOp::DeleteRow { .. } => None,
+        };
+        report.row_ops.push(RowOpValidation { index, error });
     }
 
-    Ok(output_path)
-}
+    let (errors, _) = check_column_ops(table, &patch.column_ops);
+    for (index, error) in errors.into_iter().enumerate() {
+        report.column_ops.push(ColumnOpValidation { index, error });
+    }
 
-/// Result of exporting with edits
-#[derive(Debug, Clone)]
-pub struct ExportResult {
-    /// Files that were written
-    pub files_written: Vec<PathBuf>,
-    /// Total number of edits applied
-    pub edits_applied: usize,
-    /// Errors encountered (source path, error message)
-    pub errors: Vec<(PathBuf, String)>,
+    report
 }
 
-/// Escape a value for CSV output
-fn escape_csv(s: &str) -> String {
-    if s.contains(',') || s.contains('"') || s.contains('\n') || s.contains('\r') {
-        format!("\"{}\"", s.replace('"', "\"\""))
-    } else {
-        s.to_string()
+/// Get the value a cell would hold after an edit, without applying it
+///
+/// Fails if the edit's row or column doesn't exist, or if the new value
+/// doesn't fit the type inferred from the column's merged values.
+pub fn preview_edit(table: &ResolvedTable, edit: &Edit) -> Result<CellValue> {
+    if table.find_row(edit.row_id).is_none() {
+        return Err(Error::RowNotFound(edit.row_id));
     }
-}
+    let col = table
+        .find_column(&edit.column)
+        .ok_or_else(|| Error::ColumnNotFound(edit.column.clone()))?;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    let value = edit.cell_value();
+    let expected = table.infer_column_type(col.index);
+    if !expected.accepts(&value) {
+        return Err(Error::TypeMismatch {
+            column: edit.column.clone(),
+            value: edit.value.clone(),
+            expected,
+        });
+    }
+    Ok(value)
+}
 
-    #[test]
-    fn test_edit_creation() {
-        let edit = Edit::new(42, "Name", "NewValue");
-        assert_eq!(edit.row_id, 42);
-        assert_eq!(edit.column, "Name");
-        assert_eq!(edit.value, "NewValue");
+/// Find the source file an inserted row should be written into
+///
+/// Without an explicit file name this is the first source in merge order,
+/// which is the base file whenever the family has one.
+fn insert_target<'a>(table: &'a ResolvedTable, file: Option<&str>) -> Option<&'a PathBuf> {
+    match file {
+        Some(name) => table
+            .sources
+            .iter()
+            .find(|s| s.file_name().is_some_and(|f| f == name)),
+        None => table.sources.first(),
     }
+}
 
-    #[test]
-    fn test_patch_file_serialization() {
-        let mut patch = PatchFile::new("test_family");
-        patch.add_edit(Edit::new(1, "Col1", "Value1"));
-        patch.add_edit(Edit::new(2, "Col2", "Value2"));
+/// Changes to write into a single source file
+#[derive(Default)]
+struct SourceChanges<'a> {
+    /// Cell edits whose winning value lives in this file
+    edits: Vec<&'a Edit>,
+    /// Row IDs to drop from this file
+    deleted_rows: HashSet<i64>,
+    /// New rows to add to this file, by row ID
+    inserted_rows: BTreeMap<i64, &'a HashMap<String, String>>,
+    /// Values for columns the inserted rows leave out
+    column_defaults: Option<&'a HashMap<String, String>>,
+    /// Column operations that change this file's header
+    column_ops: &'a [ColumnOp],
+}
 
-        let json = serde_json::to_string_pretty(&patch).unwrap();
-        let loaded: PatchFile = serde_json::from_str(&json).unwrap();
+/// Options controlling how modified source files are written
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Options used to re-read the original source files
+    pub parse: ParseOptions,
+    /// Write the source file's leading comment lines back above the header
+    pub preserve_comments: bool,
+    /// Replace files that already exist in the output directory
+    pub overwrite: bool,
+    /// Copy each file to `<name>.bak` before `apply_in_place` overwrites it
+    pub backup: bool,
+    /// Encoding to write files in (UTF-8 if `None`)
+    pub encoding: Option<OutputEncoding>,
+    /// Copy each source file byte for byte and rewrite only changed records,
+    /// keeping number formatting, quoting, line endings and comments
+    pub preserve_raw: bool,
+    /// Build every file in memory without touching the disk; the contents
+    /// are returned in `ExportResult::dry_run_files`
+    pub dry_run: bool,
+    /// How rewritten lines end; `preserve_raw` keeps each file's own
+    /// line endings instead
+    pub line_ending: LineEnding,
+    /// Text written for a cell an edit clears, such as `****`; an empty
+    /// field if `None`. Literal empty edits are still written as `""`
+    pub empty_as: Option<String>,
+}
 
-        assert_eq!(loaded.family, "test_family");
-        assert_eq!(loaded.edits.len(), 2);
-        assert_eq!(loaded.edits[0].row_id, 1);
-        assert_eq!(loaded.edits[1].column, "Col2");
+impl ExportOptions {
+    /// Options for minimal diffs against the original files
+    ///
+    /// Unchanged records, the header, comments and line endings are copied
+    /// verbatim, so only the edited, inserted and deleted lines differ.
+    pub fn fidelity() -> Self {
+        Self {
+            preserve_comments: true,
+            preserve_raw: true,
+            ..Default::default()
+        }
     }
+}
 
-    #[test]
-    fn test_escape_csv() {
-        assert_eq!(escape_csv("simple"), "simple");
-        assert_eq!(escape_csv("with,comma"), "\"with,comma\"");
-        assert_eq!(escape_csv("with\"quote"), "\"with\"\"quote\"");
-        assert_eq!(escape_csv("with\nnewline"), "\"with\nnewline\"");
+/// Export modified source files with edits applied
+///
+/// This reads the original source files, applies the relevant edits,
+/// and writes new copies to the output directory.
+pub fn export_with_edits<P: AsRef<Path>>(
+    table: &ResolvedTable,
+    patch: &PatchFile,
+    output_dir: P,
+) -> Result<ExportResult> {
+    export_with_edits_with_options(table, patch, output_dir, &ExportOptions::default())
+}
+
+/// Export modified source files with edits applied, using the given options
+pub fn export_with_edits_with_options<P: AsRef<Path>>(
+    table: &ResolvedTable,
+    patch: &PatchFile,
+    output_dir: P,
+    options: &ExportOptions,
+) -> Result<ExportResult> {
+    let output_dir = output_dir.as_ref();
+
+    // Create output directory if it doesn't exist
+    if !options.dry_run {
+        fs::create_dir_all(output_dir)?;
+    }
+
+    let patch = concrete_patch(table, patch)?;
+    let (changes_by_source, mut result) = collect_changes(table, &patch, options)?;
+    let targets = output_targets(changes_by_source, output_dir)?;
+    if !options.overwrite && !options.dry_run {
+        refuse_existing(&targets)?;
+    }
+
+    write_targets(targets, options, &mut result);
+    Ok(result)
+}
+
+/// Export modified source files for every family in a multi-family patch
+///
+/// Each family is looked up in `scan_result` and merged before anything
+/// is written, and existing outputs are checked across all families, so a
+/// missing family or a refused overwrite leaves the output directory
+/// untouched. The counts of all families are added up in one result.
+pub fn export_multi_with_edits<P: AsRef<Path>>(
+    scan_result: &ScanResult,
+    patches: &MultiPatchFile,
+    output_dir: P,
+    options: &ExportOptions,
+) -> Result<ExportResult> {
+    let output_dir = output_dir.as_ref();
+
+    let merge = MergeOptions {
+        parse: options.parse.clone(),
+        ..Default::default()
+    };
+    let mut seen = HashSet::new();
+    let tables = patches
+        .patches
+        .iter()
+        .map(|patch| {
+            if !seen.insert(patch.family.as_str()) {
+                return Err(Error::Export(format!(
+                    "family '{}' appears in more than one patch",
+                    patch.family
+                )));
+            }
+            let family = scan_result
+                .find_family(&patch.family)
+                .ok_or_else(|| Error::FamilyNotFound(patch.family.clone()))?;
+            merge_family_with_options(family, &merge).map(|(table, _)| table)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let concrete = tables
+        .iter()
+        .zip(&patches.patches)
+        .map(|(table, patch)| concrete_patch(table, patch))
+        .collect::<Result<Vec<_>>>()?;
+
+    if !options.dry_run {
+        fs::create_dir_all(output_dir)?;
+    }
+
+    let mut result = ExportResult::default();
+    let mut targets = Vec::new();
+    for (table, patch) in tables.iter().zip(&concrete) {
+        let (changes_by_source, family_result) = collect_changes(table, patch, options)?;
+        result.rows_deleted += family_result.rows_deleted;
+        result.rows_inserted += family_result.rows_inserted;
+        targets.extend(output_targets(changes_by_source, output_dir)?);
+    }
+    if !options.overwrite && !options.dry_run {
+        refuse_existing(&targets)?;
+    }
+
+    write_targets(targets, options, &mut result);
+    Ok(result)
+}
+
+/// The patch with its bulk edits expanded, borrowed if it has none
+fn concrete_patch<'a>(table: &ResolvedTable, patch: &'a PatchFile) -> Result<Cow<'a, PatchFile>> {
+    if patch.bulk_edits.is_empty() {
+        Ok(Cow::Borrowed(patch))
+    } else {
+        patch.expand_bulk_edits(table).map(Cow::Owned)
+    }
+}
+
+/// Pair each changed source file with its path in `output_dir`
+fn output_targets<'a>(
+    changes_by_source: HashMap<PathBuf, SourceChanges<'a>>,
+    output_dir: &Path,
+) -> Result<Vec<(PathBuf, SourceChanges<'a>, PathBuf)>> {
+    let mut targets = Vec::new();
+    for (source_path, changes) in changes_by_source {
+        let file_name = source_path
+            .file_name()
+            .ok_or_else(|| Error::InvalidFamilyName("Invalid source path".to_string()))?;
+        let output_path = output_dir.join(file_name);
+        targets.push((source_path, changes, output_path));
+    }
+    Ok(targets)
+}
+
+/// Fail with `Error::OutputExists` if any target is already there
+///
+/// Every target is checked before writing anything, so a refused export
+/// leaves the output directory untouched.
+fn refuse_existing(targets: &[(PathBuf, SourceChanges, PathBuf)]) -> Result<()> {
+    let mut existing: Vec<PathBuf> = targets
+        .iter()
+        .map(|(_, _, output_path)| output_path.clone())
+        .filter(|path| path.exists())
+        .collect();
+    if existing.is_empty() {
+        return Ok(());
+    }
+    existing.sort();
+    existing.dedup();
+    Err(Error::OutputExists(existing))
+}
+
+/// Write edits back into the source files themselves
+///
+/// Each modified file is overwritten at its original path. With
+/// `options.backup` set, every target is first copied to the first free
+/// backup path (see `backup_path`), so older backups are never replaced;
+/// nothing is written if any backup fails. Fails with
+/// `Error::ReadOnlyTarget` before touching anything if a target is
+/// read-only.
+pub fn apply_in_place(
+    table: &ResolvedTable,
+    patch: &PatchFile,
+    options: &ExportOptions,
+) -> Result<ExportResult> {
+    let patch = concrete_patch(table, patch)?;
+    let (changes_by_source, mut result) = collect_changes(table, &patch, options)?;
+
+    let targets: Vec<_> = changes_by_source
+        .into_iter()
+        .map(|(source_path, changes)| {
+            let output_path = source_path.clone();
+            (source_path, changes, output_path)
+        })
+        .collect();
+    refuse_read_only(&targets)?;
+
+    if options.backup && !options.dry_run {
+        for (source_path, _, _) in &targets {
+            let backup_path = free_backup_path(source_path);
+            fs::copy(source_path, &backup_path)?;
+            result.backups.push(backup_path);
+        }
+    }
+
+    write_targets(targets, options, &mut result);
+    Ok(result)
+}
+
+/// Fail with `Error::ReadOnlyTarget` if any target can't be written
+fn refuse_read_only(targets: &[(PathBuf, SourceChanges, PathBuf)]) -> Result<()> {
+    let mut read_only: Vec<PathBuf> = targets
+        .iter()
+        .map(|(_, _, output_path)| output_path.clone())
+        .filter(|path| fs::metadata(path).is_ok_and(|m| m.permissions().readonly()))
+        .collect();
+    if read_only.is_empty() {
+        return Ok(());
+    }
+    read_only.sort();
+    read_only.dedup();
+    Err(Error::ReadOnlyTarget(read_only))
+}
+
+/// Get the path a source file is first backed up to before an in-place
+/// write, `<name>.bak`
+///
+/// Later backups, made while that one exists, go to `<name>.bak.1`,
+/// `<name>.bak.2` and so on.
+pub fn backup_path(source: &Path) -> PathBuf {
+    let mut name = source.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    source.with_file_name(name)
+}
+
+/// Get the first backup path for `source` that doesn't exist yet
+fn free_backup_path(source: &Path) -> PathBuf {
+    let first = backup_path(source);
+    let mut path = first.clone();
+    let mut number = 0;
+    while path.exists() {
+        number += 1;
+        let mut name = first.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}", number));
+        path = first.with_file_name(name);
+    }
+    path
+}
+
+/// Group the edits and row operations of a patch by the source file they change
+fn collect_changes<'a>(
+    table: &ResolvedTable,
+    patch: &'a PatchFile,
+    options: &ExportOptions,
+) -> Result<(HashMap<PathBuf, SourceChanges<'a>>, ExportResult)> {
+    // A bad column operation would leave the family's files disagreeing
+    // on their columns, so it stops the whole export
+    let (errors, _) = check_column_ops(table, &patch.column_ops);
+    if let Some(error) = errors.into_iter().flatten().next() {
+        return Err(Error::Export(error));
+    }
+
+    // Group edits by source file
+    let mut changes_by_source: HashMap<PathBuf, SourceChanges> = HashMap::new();
+    let mut stale_edits = Vec::new();
+
+    for edit in &patch.edits {
+        // Find the row and get its source file for the edited column
+        if let Some(row) = table.find_row(edit.row_id) {
+            if let Some(col) = table.columns.iter().find(|c| c.name == edit.column) {
+                let cell = &row.cells[col.index];
+                if let Some(reason) = edit.stale_reason(&cell.value) {
+                    stale_edits.push((edit.clone(), reason));
+                    continue;
+                }
+                let source = &cell.source;
+                changes_by_source
+                    .entry(source.clone())
+                    .or_default()
+                    .edits
+                    .push(edit);
+            }
+        }
+    }
+
+    // A deleted row must be dropped from every file that defines it,
+    // otherwise the merge would bring it back from the untouched ones
+    let deleted_ids: HashSet<i64> = patch
+        .row_ops
+        .iter()
+        .filter(|op| matches!(op, RowOp::DeleteRow { .. }))
+        .map(RowOp::row_id)
+        .filter(|id| table.find_row(*id).is_some())
+        .collect();
+
+    if !deleted_ids.is_empty() {
+        for source in &table.sources {
+            let original = parse_csv_with_options(source, &options.parse)?;
+            let owned: HashSet<i64> = original
+                .rows
+                .iter()
+                .filter_map(|r| r.id)
+                .filter(|id| deleted_ids.contains(id))
+                .collect();
+
+            if !owned.is_empty() {
+                changes_by_source
+                    .entry(source.clone())
+                    .or_default()
+                    .deleted_rows
+                    .extend(owned);
+            }
+        }
+    }
+
+    // Inserted rows go into a single file; IDs already in the table are
+    // skipped. An ID inserted twice, or a value for a column the target
+    // file doesn't have, would be dropped silently, so it stops the export
+    let mut rows_inserted = 0;
+    let mut inserted_ids = HashSet::new();
+    let mut headers: HashMap<&PathBuf, Vec<Column>> = HashMap::new();
+    for op in &patch.row_ops {
+        if let RowOp::InsertRow {
+            row_id,
+            values,
+            file,
+        } = op
+        {
+            if !inserted_ids.insert(*row_id) {
+                return Err(Error::Export(format!(
+                    "row {} is inserted more than once",
+                    row_id
+                )));
+            }
+            if table.find_row(*row_id).is_some() {
+                continue;
+            }
+            if let Some(source) = insert_target(table, file.as_deref()) {
+                let header = match headers.entry(source) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry.insert(
+                        parse_csv_streaming_with_options(source, &options.parse)?
+                            .columns()
+                            .to_vec(),
+                    ),
+                };
+                if let Some(column) = values.keys().find(|k| !header.iter().any(|c| &c.name == *k))
+                {
+                    return Err(Error::Export(format!(
+                        "column '{}' of inserted row {} is not in {}",
+                        column,
+                        row_id,
+                        source.display()
+                    )));
+                }
+                let changes = changes_by_source.entry(source.clone()).or_default();
+                changes.inserted_rows.insert(*row_id, values);
+                changes.column_defaults = Some(&patch.column_defaults);
+                rows_inserted += 1;
+            }
+        }
+    }
+
+    // Column operations touch every file that has one of the columns
+    if !patch.column_ops.is_empty() {
+        for source in &table.sources {
+            let original = parse_csv_with_options(source, &options.parse)?;
+            if column_layout(&original.columns, &patch.column_ops).is_some() {
+                changes_by_source.entry(source.clone()).or_default().column_ops =
+                    &patch.column_ops;
+            }
+        }
+    }
+
+    let result = ExportResult {
+        files_written: Vec::new(),
+        edits_applied: 0,
+        rows_deleted: deleted_ids.len(),
+        rows_inserted,
+        backups: Vec::new(),
+        skipped_edits: stale_edits,
+        dry_run_files: HashMap::new(),
+        errors: Vec::new(),
+    };
+
+    Ok((changes_by_source, result))
+}
+
+/// Write each source's changes to its target path, recording the outcome
+///
+/// In a dry run the contents are kept in `result.dry_run_files` instead.
+fn write_targets(
+    targets: Vec<(PathBuf, SourceChanges, PathBuf)>,
+    options: &ExportOptions,
+    result: &mut ExportResult,
+) {
+    for (source_path, changes, output_path) in targets {
+        let written = export_single_file(&source_path, &changes, options).and_then(
+            |(text, bytes, skipped)| {
+                if options.dry_run {
+                    result.dry_run_files.insert(output_path.clone(), text);
+                } else {
+                    fs::write(&output_path, bytes)?;
+                }
+                Ok(skipped)
+            },
+        );
+        match written {
+            Ok(skipped) => {
+                result.edits_applied += changes.edits.len() - skipped.len();
+                let file_name = source_path.file_name().unwrap_or_default().to_string_lossy();
+                for edit in skipped {
+                    let reason = format!("Column '{}' not in {}", edit.column, file_name);
+                    result.skipped_edits.push((edit.clone(), reason));
+                }
+                if !options.dry_run {
+                    result.files_written.push(output_path);
+                }
+            }
+            Err(e) => {
+                result.errors.push((source_path, e.to_string()));
+            }
+        }
+    }
+}
+
+/// Build a single source file with edits applied and deleted rows dropped
+///
+/// Returns the file as text and as the bytes to write in the output
+/// encoding. Edits are placed by column name in the file's own column
+/// order, before column operations rename or move the columns. Edits for
+/// columns this file doesn't have are skipped and returned.
+fn export_single_file<'a>(
+    source_path: &Path,
+    changes: &SourceChanges<'a>,
+    options: &ExportOptions,
+) -> Result<(String, Vec<u8>, Vec<&'a Edit>)> {
+    // Parse the original file
+    let original = parse_csv_with_options(source_path, &options.parse)?;
+
+    // Build a map of edits: (row_id, column_name) -> edit
+    let edit_map: HashMap<(i64, &str), &Edit> = changes
+        .edits
+        .iter()
+        .map(|e| ((e.row_id, e.column.as_str()), *e))
+        .collect();
+
+    // Build column name -> index map
+    let col_indices: HashMap<&str, usize> = original
+        .columns
+        .iter()
+        .map(|c| (c.name.as_str(), c.index))
+        .collect();
+
+    let layout = column_layout(&original.columns, changes.column_ops);
+
+    // Build the file in memory, so an encoding failure leaves no partial file
+    let writer = if options.preserve_raw {
+        copy_raw_with_changes(
+            source_path,
+            &original,
+            changes,
+            &edit_map,
+            layout.as_deref(),
+            options,
+        )?
+    } else {
+        rewrite_with_changes(
+            &original,
+            changes,
+            &edit_map,
+            &col_indices,
+            layout.as_deref(),
+            options,
+        )?
+    };
+
+    let text = String::from_utf8_lossy(&writer).into_owned();
+    let bytes = match &options.encoding {
+        Some(encoding) => encoding.encode(&text)?,
+        None => writer,
+    };
+
+    let skipped = changes
+        .edits
+        .iter()
+        .filter(|e| !col_indices.contains_key(e.column.as_str()))
+        .copied()
+        .collect();
+    Ok((text, bytes, skipped))
+}
+
+/// Write a source file from its parsed rows with the changes applied
+///
+/// Cells are written back from their parsed values, so quoting is redone
+/// and padding around cells is trimmed.
+fn rewrite_with_changes(
+    original: &Table,
+    changes: &SourceChanges,
+    edit_map: &HashMap<(i64, &str), &Edit>,
+    col_indices: &HashMap<&str, usize>,
+    layout: Option<&[(usize, String)]>,
+    options: &ExportOptions,
+) -> Result<Vec<u8>> {
+    let mut writer: Vec<u8> = Vec::new();
+    let ending = options.line_ending.as_str();
+
+    if options.preserve_comments {
+        for comment in &original.leading_comments {
+            write!(writer, "{}{}", comment, ending)?;
+        }
+    }
+
+    // Write header
+    let header: Vec<String> = match layout {
+        Some(layout) => layout.iter().map(|(_, name)| escape_field(name)).collect(),
+        None => original.columns.iter().map(|c| escape_field(&c.name)).collect(),
+    };
+    write!(writer, "{}{}", header.join(","), ending)?;
+
+    // Inserted rows are written just before the first row with a higher ID,
+    // keeping the file in the same ID order the merge produces
+    let mut pending_inserts = changes.inserted_rows.iter().peekable();
+    let defaults = changes.column_defaults;
+
+    // Write rows with edits applied
+    for row in &original.rows {
+        if let Some(row_id) = row.id {
+            while let Some((&new_id, values)) = pending_inserts.next_if(|(&id, _)| id < row_id) {
+                let cells = inserted_row_cells(original, new_id, values, defaults, layout);
+                write!(writer, "{}{}", cells.join(","), ending)?;
+            }
+        }
+
+        if row.id.is_some_and(|id| changes.deleted_rows.contains(&id)) {
+            continue;
+        }
+
+        let mut cells: Vec<String> = row
+            .cells
+            .iter()
+            .map(|c| escape_field(&c.to_string_value()))
+            .collect();
+
+        // Apply any edits for this row
+        if let Some(row_id) = row.id {
+            for (col_name, &col_idx) in col_indices {
+                if let Some(edit) = edit_map.get(&(row_id, col_name)) {
+                    if col_idx < cells.len() {
+                        cells[col_idx] = edit.export_field(options.empty_as.as_deref());
+                    }
+                }
+            }
+        }
+
+        if let Some(layout) = layout {
+            cells = arrange_fields(&cells, layout, |index| index);
+        }
+
+        write!(writer, "{}{}", cells.join(","), ending)?;
+    }
+
+    for (&new_id, values) in pending_inserts {
+        let cells = inserted_row_cells(original, new_id, values, defaults, layout);
+        write!(writer, "{}{}", cells.join(","), ending)?;
+    }
+
+    Ok(writer)
+}
+
+/// Copy a source file byte for byte, rewriting only the changed records
+///
+/// The header, comments, blank lines and untouched records are kept as
+/// they are, including their line endings. Edited records keep their
+/// other fields' text and line ending; deleted records are dropped along
+/// with their line ending. Inserted rows use the file's line ending. A
+/// column layout rewrites the header, and every record too if it moves
+/// columns.
+fn copy_raw_with_changes(
+    source_path: &Path,
+    original: &Table,
+    changes: &SourceChanges,
+    edit_map: &HashMap<(i64, &str), &Edit>,
+    layout: Option<&[(usize, String)]>,
+    options: &ExportOptions,
+) -> Result<Vec<u8>> {
+    let mut source = fs::read(source_path).map_err(|e| Error::FileRead {
+        path: source_path.to_path_buf(),
+        source: e,
+    })?;
+    if let Some(encoding) = input_encoding(&options.parse, source_path)? {
+        source = encoding.decode_without_bom_handling(&source).0.into_owned().into_bytes();
+    }
+
+    // Keep a byte order mark, but out of the reader's way
+    let mut out = Vec::with_capacity(source.len());
+    let bom_len = if source.starts_with(UTF8_BOM) { UTF8_BOM.len() } else { 0 };
+    out.extend_from_slice(&source[..bom_len]);
+    let bytes = &source[bom_len..];
+
+    let line_ending: &[u8] = if bytes.windows(2).any(|w| w == b"\r\n") {
+        b"\r\n"
+    } else {
+        b"\n"
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .comment(options.parse.comment)
+        .from_reader(bytes);
+    let mut record = csv::StringRecord::new();
+
+    // Field position of each column, by column index
+    let mut field_of_column: Vec<usize> = (0..original.columns.len()).collect();
+    let mut header_read = false;
+
+    // Renames alone leave the data records as they are
+    let moved = layout.filter(|l| l.iter().enumerate().any(|(k, (index, _))| k != *index));
+    let terminator = |raw: &[u8]| -> usize {
+        raw.iter()
+            .rev()
+            .take_while(|b| matches!(b, b'\r' | b'\n'))
+            .count()
+    };
+
+    let mut pending_inserts = changes.inserted_rows.iter().peekable();
+    let mut copied = 0;
+
+    loop {
+        if !reader.read_record(&mut record).map_err(|e| csv_error(source_path, e, 0))? {
+            break;
+        }
+        let start = skip_ignored_lines(bytes, copied, options.parse.comment);
+        let mut end = reader.position().byte() as usize;
+        // The reader stops between the \r and \n of a CRLF
+        if bytes[..end].ends_with(b"\r") && bytes.get(end) == Some(&b'\n') {
+            end += 1;
+        }
+
+        // Comments and blank lines before the record
+        out.extend_from_slice(&bytes[copied..start]);
+        copied = end;
+        let raw = &bytes[start..end];
+
+        if !header_read {
+            header_read = true;
+            if options.parse.indexed_headers {
+                for (field, name) in record.iter().enumerate() {
+                    let index = name
+                        .split_once(':')
+                        .and_then(|(i, _)| i.trim().parse().ok());
+                    if let Some(index) = index.filter(|i: &usize| *i < field_of_column.len()) {
+                        field_of_column[index] = field;
+                    }
+                }
+            }
+            match layout {
+                Some(layout) => {
+                    let names: Vec<String> = layout
+                        .iter()
+                        .enumerate()
+                        .map(|(k, (_, name))| {
+                            if options.parse.indexed_headers {
+                                escape_field(&format!("{}:{}", k, name))
+                            } else {
+                                escape_field(name)
+                            }
+                        })
+                        .collect();
+                    out.extend_from_slice(names.join(",").as_bytes());
+                    out.extend_from_slice(&raw[raw.len() - terminator(raw)..]);
+                }
+                None => out.extend_from_slice(raw),
+            }
+            continue;
+        }
+
+        let id_field = record.get(field_of_column[original.id_column]).unwrap_or_default();
+        let id_value = options
+            .parse
+            .hex_values
+            .then(|| CellValue::parse_hex(id_field))
+            .flatten()
+            .unwrap_or_else(|| CellValue::parse(id_field));
+        let id = match id_value {
+            CellValue::Integer(i) => Some(i),
+            number @ CellValue::Number { .. } => number.as_i64(),
+            CellValue::Hex { value, .. } => i64::try_from(value).ok(),
+            CellValue::Empty if options.parse.empty_id_policy == EmptyIdPolicy::TreatAsZero => {
+                Some(0)
+            }
+            _ => None,
+        };
+
+        if let Some(row_id) = id {
+            while let Some((&new_id, values)) = pending_inserts.next_if(|(&id, _)| id < row_id) {
+                push_inserted_row(&mut out, original, new_id, values, changes, layout, line_ending);
+            }
+        }
+
+        if id.is_some_and(|id| changes.deleted_rows.contains(&id)) {
+            continue;
+        }
+
+        let row_edits: Vec<(usize, &Edit)> = match id {
+            Some(row_id) => original
+                .columns
+                .iter()
+                .filter_map(|c| {
+                    edit_map
+                        .get(&(row_id, c.name.as_str()))
+                        .map(|edit| (field_of_column[c.index], *edit))
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        if row_edits.is_empty() && moved.is_none() {
+            out.extend_from_slice(raw);
+            continue;
+        }
+
+        let mut fields: Vec<String> = record.iter().map(escape_field).collect();
+        for (field, edit) in row_edits {
+            if fields.len() <= field {
+                fields.resize(field + 1, String::new());
+            }
+            fields[field] = edit.export_field(options.empty_as.as_deref());
+        }
+        if let Some(layout) = moved {
+            fields = arrange_fields(&fields, layout, |index| field_of_column[index]);
+        }
+        out.extend_from_slice(fields.join(",").as_bytes());
+        out.extend_from_slice(&raw[raw.len() - terminator(raw)..]);
+    }
+
+    out.extend_from_slice(&bytes[copied..]);
+    for (&new_id, values) in pending_inserts {
+        push_inserted_row(&mut out, original, new_id, values, changes, layout, line_ending);
+    }
+
+    Ok(out)
+}
+
+/// Skip the blank lines and comment lines the CSV reader passes over,
+/// returning where the next record starts
+fn skip_ignored_lines(bytes: &[u8], mut pos: usize, comment: Option<u8>) -> usize {
+    loop {
+        match bytes.get(pos) {
+            Some(b'\r' | b'\n') => pos += 1,
+            Some(b) if Some(*b) == comment => {
+                pos = bytes[pos..]
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .map_or(bytes.len(), |i| pos + i + 1);
+            }
+            _ => return pos,
+        }
+    }
+}
+
+/// Append an inserted row with the given line ending, first ending the
+/// last line if the output doesn't
+fn push_inserted_row(
+    out: &mut Vec<u8>,
+    original: &Table,
+    row_id: i64,
+    values: &HashMap<String, String>,
+    changes: &SourceChanges,
+    layout: Option<&[(usize, String)]>,
+    line_ending: &[u8],
+) {
+    if !out.is_empty() && !out.ends_with(b"\n") {
+        out.extend_from_slice(line_ending);
+    }
+    let cells = inserted_row_cells(original, row_id, values, changes.column_defaults, layout);
+    out.extend_from_slice(cells.join(",").as_bytes());
+    out.extend_from_slice(line_ending);
+}
+
+/// Build the fields of a new row, taking the ID for the ID column,
+/// filling columns without a value from `defaults` and leaving the rest
+/// empty, in the order of `layout` if there is one
+fn inserted_row_cells(
+    original: &Table,
+    row_id: i64,
+    values: &HashMap<String, String>,
+    defaults: Option<&HashMap<String, String>>,
+    layout: Option<&[(usize, String)]>,
+) -> Vec<String> {
+    let cells: Vec<String> = original
+        .columns
+        .iter()
+        .map(|c| {
+            if c.index == original.id_column {
+                row_id.to_string()
+            } else {
+                values
+                    .get(&c.name)
+                    .or_else(|| defaults.and_then(|d| d.get(&c.name)))
+                    .map(|v| escape_field(v))
+                    .unwrap_or_default()
+            }
+        })
+        .collect();
+    match layout {
+        Some(layout) => arrange_fields(&cells, layout, |index| index),
+        None => cells,
+    }
+}
+
+/// Result of exporting with edits
+#[derive(Debug, Clone, Default)]
+pub struct ExportResult {
+    /// Files that were written
+    pub files_written: Vec<PathBuf>,
+    /// Total number of edits applied
+    pub edits_applied: usize,
+    /// Number of rows deleted
+    pub rows_deleted: usize,
+    /// Number of rows inserted
+    pub rows_inserted: usize,
+    /// Backup copies made before overwriting source files
+    pub backups: Vec<PathBuf>,
+    /// Edits not written because their cell no longer holds `expected_old`
+    /// or their column is missing from the target file
+    pub skipped_edits: Vec<(Edit, String)>,
+    /// Contents each file would have, by output path (dry runs only)
+    pub dry_run_files: HashMap<PathBuf, String>,
+    /// Errors encountered (source path, error message)
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merger::merge_tables;
+    use crate::parser::{parse_csv, parse_csv_str, IdColumn};
+    use crate::test_util::scratch_dir;
+
+    #[test]
+    fn test_edit_creation() {
+        let edit = Edit::new(42, "Name", "NewValue");
+        assert_eq!(edit.row_id, 42);
+        assert_eq!(edit.column, "Name");
+        assert_eq!(edit.value, "NewValue");
+    }
+
+    #[test]
+    fn test_patch_file_serialization() {
+        let mut patch = PatchFile::new("test_family");
+        patch.add_edit(Edit::new(1, "Col1", "Value1"));
+        patch.add_edit(Edit::new(2, "Col2", "Value2"));
+
+        let json = serde_json::to_string_pretty(&patch).unwrap();
+        let loaded: PatchFile = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.family, "test_family");
+        assert_eq!(loaded.edits.len(), 2);
+        assert_eq!(loaded.edits[0].row_id, 1);
+        assert_eq!(loaded.edits[1].column, "Col2");
+    }
+
+    #[test]
+    fn test_patch_without_row_ops_deserializes() {
+        let json = r#"{"family": "test", "edits": [{"row_id": 1, "column": "Name", "value": "x"}]}"#;
+        let patch: PatchFile = serde_json::from_str(json).unwrap();
+
+        assert_eq!(patch.edits.len(), 1);
+        assert!(patch.row_ops.is_empty());
+        assert_eq!(patch.description, None);
+
+        // Cell-only patches serialize without the new field
+        let out = serde_json::to_string(&patch).unwrap();
+        assert!(!out.contains("row_ops"));
+        assert!(!out.contains("description"));
+    }
+
+    #[test]
+    fn test_patch_description_and_author_round_trip() {
+        let mut patch = PatchFile::new("test");
+        patch.description = Some("Cheaper fire spells".to_string());
+        patch.author = Some("kai".to_string());
+
+        let json = serde_json::to_string(&patch).unwrap();
+        assert!(json.starts_with(r#"{"family":"test","description":"Cheaper fire spells","#));
+        let loaded: PatchFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.description.as_deref(), Some("Cheaper fire spells"));
+        assert_eq!(loaded.author.as_deref(), Some("kai"));
+    }
+
+    #[test]
+    fn test_row_op_serialization() {
+        let mut patch = PatchFile::new("test");
+        patch.add_row_op(RowOp::DeleteRow { row_id: 7 });
+
+        let json = serde_json::to_string(&patch).unwrap();
+        assert!(json.contains(r#"{"op":"delete_row","row_id":7}"#));
+
+        let loaded: PatchFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.row_ops, vec![RowOp::DeleteRow { row_id: 7 }]);
+    }
+
+    #[test]
+    fn test_apply_patch_delete_row() {
+        let table = parse_csv_str("ID,Name\n1,foo\n2,bar\n", "base.csv").unwrap();
+        let merged = merge_tables("test", vec![table]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_row_op(RowOp::DeleteRow { row_id: 2 });
+        patch.add_row_op(RowOp::DeleteRow { row_id: 99 });
+
+        let result = apply_patch(&merged, &patch).unwrap();
+        assert_eq!(result.rows_deleted, 1);
+        assert_eq!(result.failed_row_ops.len(), 1);
+        assert_eq!(result.failed_row_ops[0].0.row_id(), 99);
+        assert_eq!(result.modified_sources[&PathBuf::from("base.csv")], vec![2]);
+    }
+
+    #[test]
+    fn test_export_delete_row_from_all_sources() {
+        let dir = scratch_dir("delete-row");
+        let base_path = dir.join("test.csv");
+        let variant_path = dir.join("test_ep1.csv");
+        fs::write(&base_path, "ID,Name\n1,foo\n2,bar\n3,baz\n").unwrap();
+        fs::write(&variant_path, "ID,Name\n2,BAR\n").unwrap();
+
+        let merged = merge_tables(
+            "test",
+            vec![parse_csv(&base_path).unwrap(), parse_csv(&variant_path).unwrap()],
+        )
+        .unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_row_op(RowOp::DeleteRow { row_id: 2 });
+
+        let out_dir = dir.join("out");
+        let result = export_with_edits(&merged, &patch, &out_dir).unwrap();
+        assert_eq!(result.rows_deleted, 1);
+        assert_eq!(result.files_written.len(), 2);
+
+        let base_out = fs::read_to_string(out_dir.join("test.csv")).unwrap();
+        assert_eq!(base_out, "ID,Name\n1,foo\n3,baz\n");
+        let variant_out = fs::read_to_string(out_dir.join("test_ep1.csv")).unwrap();
+        assert_eq!(variant_out, "ID,Name\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_empty_edit_clears_cell() {
+        let edit = Edit::new(1, "Name", "");
+        assert_eq!(edit.cell_value(), CellValue::Empty);
+        assert_eq!(edit.export_field(None), "");
+        assert_eq!(edit.export_field(Some("****")), "****");
+
+        let literal = Edit::new_literal(1, "Name", "");
+        assert_eq!(literal.cell_value(), CellValue::String(String::new()));
+        assert_eq!(literal.export_field(None), "\"\"");
+        assert_eq!(literal.export_field(Some("****")), "\"\"");
+    }
+
+    #[test]
+    fn test_export_empty_edit_resolves_to_empty() {
+        let dir = scratch_dir("clear-cell");
+        let base_path = dir.join("test.csv");
+        fs::write(&base_path, "ID,Name,Value\n1,foo,100\n").unwrap();
+
+        let merged = merge_tables("test", vec![parse_csv(&base_path).unwrap()]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(1, "Name", ""));
+
+        let out_dir = dir.join("out");
+        export_with_edits(&merged, &patch, &out_dir).unwrap();
+
+        let exported = parse_csv(out_dir.join("test.csv")).unwrap();
+        assert_eq!(exported.rows[0].cells[1], CellValue::Empty);
+        assert_eq!(exported.rows[0].cells[2], CellValue::Integer(100));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_empty_edits_with_empty_as() {
+        let dir = scratch_dir("empty-as");
+        let base_path = dir.join("test.csv");
+        fs::write(&base_path, "ID,Name,Icon\n1,foo,fire.dds\n").unwrap();
+        let merged = merge_tables("test", vec![parse_csv(&base_path).unwrap()]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(1, "Name", ""));
+        patch.add_edit(Edit::new_literal(1, "Icon", ""));
+
+        for fidelity in [false, true] {
+            let options = ExportOptions {
+                empty_as: Some("****".to_string()),
+                ..if fidelity { ExportOptions::fidelity() } else { ExportOptions::default() }
+            };
+            let out_dir = dir.join(format!("out-{}", fidelity));
+            export_with_edits_with_options(&merged, &patch, &out_dir, &options).unwrap();
+
+            // The cleared cell takes the marker; the literal empty keeps its
+            // quotes, which read back as an empty cell
+            let path = out_dir.join("test.csv");
+            assert_eq!(fs::read_to_string(&path).unwrap(), "ID,Name,Icon\n1,****,\"\"\n");
+            let exported = parse_csv(&path).unwrap();
+            assert_eq!(exported.rows[0].cells[1], CellValue::String("****".to_string()));
+            assert_eq!(exported.rows[0].cells[2], CellValue::Empty);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_patch_insert_existing_row_fails() {
+        let table = parse_csv_str("ID,Name\n1,foo\n", "base.csv").unwrap();
+        let merged = merge_tables("test", vec![table]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_row_op(RowOp::InsertRow {
+            row_id: 1,
+            values: HashMap::new(),
+            file: None,
+        });
+        patch.add_row_op(RowOp::InsertRow {
+            row_id: 2,
+            values: HashMap::from([("Name".to_string(), "bar".to_string())]),
+            file: None,
+        });
+
+        let result = apply_patch(&merged, &patch).unwrap();
+        assert_eq!(result.rows_inserted, 1);
+        assert_eq!(result.failed_row_ops.len(), 1);
+        assert_eq!(result.failed_row_ops[0].0.row_id(), 1);
+    }
+
+    #[test]
+    fn test_export_insert_row_partial_columns() {
+        let dir = scratch_dir("insert-row");
+        let base_path = dir.join("test.csv");
+        let variant_path = dir.join("test_ep1.csv");
+        fs::write(&base_path, "ID,Name,Value\n1,foo,100\n5,baz,500\n").unwrap();
+        fs::write(&variant_path, "ID,Name,Value\n1,FOO,\n").unwrap();
+
+        let merged = merge_tables(
+            "test",
+            vec![parse_csv(&base_path).unwrap(), parse_csv(&variant_path).unwrap()],
+        )
+        .unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_row_op(RowOp::InsertRow {
+            row_id: 3,
+            values: HashMap::from([("Name".to_string(), "new, row".to_string())]),
+            file: None,
+        });
+
+        let out_dir = dir.join("out");
+        let result = export_with_edits(&merged, &patch, &out_dir).unwrap();
+        assert_eq!(result.rows_inserted, 1);
+        assert_eq!(result.files_written, vec![out_dir.join("test.csv")]);
+
+        let base_out = fs::read_to_string(out_dir.join("test.csv")).unwrap();
+        assert_eq!(
+            base_out,
+            "ID,Name,Value\n1,foo,100\n3,\"new, row\",\n5,baz,500\n"
+        );
+
+        let exported = parse_csv(out_dir.join("test.csv")).unwrap();
+        assert_eq!(exported.rows[1].id, Some(3));
+        assert_eq!(exported.rows[1].cells[2], CellValue::Empty);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_rejects_bad_row_inserts() {
+        let dir = scratch_dir("bad-insert");
+        let base_path = dir.join("test.csv");
+        let variant_path = dir.join("test_ep1.csv");
+        fs::write(&base_path, "ID,Name\n1,foo\n").unwrap();
+        fs::write(&variant_path, "ID,Name,Extra\n1,FOO,x\n").unwrap();
+        let merged = merge_tables(
+            "test",
+            vec![parse_csv(&base_path).unwrap(), parse_csv(&variant_path).unwrap()],
+        )
+        .unwrap();
+        let out_dir = dir.join("out");
+        let insert = |row_id, column: &str| RowOp::InsertRow {
+            row_id,
+            values: HashMap::from([(column.to_string(), "new".to_string())]),
+            file: None,
+        };
+
+        // Extra is in the merged table but not in test.csv, which gets the row
+        let mut patch = PatchFile::new("test");
+        patch.add_row_op(insert(2, "Extra"));
+        let err = export_with_edits(&merged, &patch, &out_dir).unwrap_err();
+        assert!(matches!(err, Error::Export(_)));
+        assert!(err.to_string().contains("column 'Extra' of inserted row 2"), "{}", err);
+
+        let mut patch = PatchFile::new("test");
+        patch.add_row_op(insert(2, "Name"));
+        patch.add_row_op(insert(2, "Name"));
+        let err = export_with_edits(&merged, &patch, &out_dir).unwrap_err();
+        assert!(matches!(err, Error::Export(_)));
+        assert!(err.to_string().contains("row 2 is inserted more than once"), "{}", err);
+        assert!(!out_dir.join("test.csv").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_insert_row_fills_column_defaults() {
+        let dir = scratch_dir("column-defaults");
+        let base_path = dir.join("test.csv");
+        fs::write(&base_path, "ID,Name,Value,Icon\n1,foo,100,a\n").unwrap();
+        let merged = merge_tables("test", vec![parse_csv(&base_path).unwrap()]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.column_defaults = HashMap::from([
+            ("Value".to_string(), "0".to_string()),
+            ("Name".to_string(), "unnamed".to_string()),
+        ]);
+        patch.add_row_op(RowOp::InsertRow {
+            row_id: 2,
+            values: HashMap::from([("Name".to_string(), "bar".to_string())]),
+            file: None,
+        });
+
+        // Defaults survive a save and load
+        let json = serde_json::to_string(&patch).unwrap();
+        let patch: PatchFile = serde_json::from_str(&json).unwrap();
+
+        let out_dir = dir.join("out");
+        export_with_edits(&merged, &patch, &out_dir).unwrap();
+        assert_eq!(
+            fs::read_to_string(out_dir.join("test.csv")).unwrap(),
+            "ID,Name,Value,Icon\n1,foo,100,a\n2,bar,0,\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_as_windows_1252() {
+        let dir = scratch_dir("encoding");
+        let base_path = dir.join("test.csv");
+        fs::write(&base_path, "ID,Name\n1,Café\n2,Ærin\n").unwrap();
+        let merged = merge_tables("test", vec![parse_csv(&base_path).unwrap()]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(2, "Name", "Éowyn"));
+
+        let options = ExportOptions {
+            encoding: Some(OutputEncoding::for_label("windows-1252", false).unwrap()),
+            ..Default::default()
+        };
+        let out_dir = dir.join("out");
+        export_with_edits_with_options(&merged, &patch, &out_dir, &options).unwrap();
+        assert_eq!(
+            fs::read(out_dir.join("test.csv")).unwrap(),
+            b"ID,Name\n1,Caf\xe9\n2,\xc9owyn\n"
+        );
+
+        // A character outside the encoding fails the file instead of corrupting it
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(1, "Name", "\u{4e00}"));
+        let out_dir = dir.join("unmappable");
+        let result = export_with_edits_with_options(&merged, &patch, &out_dir, &options).unwrap();
+        assert!(result.files_written.is_empty());
+        assert_eq!(result.errors.len(), 1);
+        assert!(!out_dir.join("test.csv").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_skips_edit_to_column_missing_from_owning_file() {
+        let dir = scratch_dir("missing-column");
+        let base_path = dir.join("test.csv");
+        let variant_path = dir.join("test_ep1.csv");
+        fs::write(&base_path, "ID,Name\n1,foo\n").unwrap();
+        fs::write(&variant_path, "ID,Icon,Name\n2,a,bar\n").unwrap();
+
+        let merged = merge_tables(
+            "test",
+            vec![parse_csv(&base_path).unwrap(), parse_csv(&variant_path).unwrap()],
+        )
+        .unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(1, "Icon", "x"));
+        patch.add_edit(Edit::new(1, "Name", "FOO"));
+        patch.add_edit(Edit::new(2, "Name", "BAR"));
+
+        let out_dir = dir.join("out");
+        let result = export_with_edits(&merged, &patch, &out_dir).unwrap();
+        assert_eq!(result.edits_applied, 2);
+        assert_eq!(result.skipped_edits.len(), 1);
+        assert_eq!(result.skipped_edits[0].0.row_id, 1);
+        assert_eq!(result.skipped_edits[0].1, "Column 'Icon' not in test.csv");
+
+        // Each file keeps its own column order
+        assert_eq!(
+            fs::read_to_string(out_dir.join("test.csv")).unwrap(),
+            "ID,Name\n1,FOO\n"
+        );
+        assert_eq!(
+            fs::read_to_string(out_dir.join("test_ep1.csv")).unwrap(),
+            "ID,Icon,Name\n2,a,BAR\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bulk_edit_capture_groups() {
+        let dir = scratch_dir("bulk");
+        let base_path = dir.join("test.csv");
+        fs::write(
+            &base_path,
+            "ID,Icon,Cost\n1,ico_fire_1,10\n2,ico_ice_2,20\n3,none,30\n4,ico_bolt_9,40\n",
+        )
+        .unwrap();
+        let merged = merge_tables("test", vec![parse_csv(&base_path).unwrap()]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.bulk_edits.push(BulkEdit {
+            column: "Icon".to_string(),
+            match_regex: r"^ico_(\w+)_(\d)$".to_string(),
+            replacement: "spell_${1}_v$2".to_string(),
+        });
+        patch.bulk_edits.push(BulkEdit {
+            column: "Cost".to_string(),
+            match_regex: "^999$".to_string(),
+            replacement: "0".to_string(),
+        });
+        // An explicit edit overrides the bulk edit on the same cell
+        patch.add_edit(Edit::new(4, "Icon", "custom"));
+
+        let result = apply_patch(&merged, &patch).unwrap();
+        assert_eq!(result.edits_applied, 4);
+        assert_eq!(result.bulk_matches[0].1, 3);
+        let unmatched: Vec<_> = result.unmatched_bulk_edits().map(|b| b.column.as_str()).collect();
+        assert_eq!(unmatched, vec!["Cost"]);
+
+        let out_dir = dir.join("out");
+        export_with_edits(&merged, &patch, &out_dir).unwrap();
+        assert_eq!(
+            fs::read_to_string(out_dir.join("test.csv")).unwrap(),
+            "ID,Icon,Cost\n1,spell_fire_v1,10\n2,spell_ice_v2,20\n3,none,30\n4,custom,40\n"
+        );
+
+        // The inverse restores every cell the bulk edit touched
+        let inverse = invert_patch(&merged, &patch);
+        assert_eq!(inverse.edits.len(), 3);
+
+        patch.bulk_edits[0].match_regex = "(".to_string();
+        assert!(matches!(apply_patch(&merged, &patch), Err(Error::InvalidRegex(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_expected_old_guards_stale_edits() {
+        let dir = scratch_dir("stale");
+        let base_path = dir.join("test.csv");
+        fs::write(&base_path, "ID,Name,Cost\n1,foo,1.50\n2,bar,20\n").unwrap();
+        let merged = merge_tables("test", vec![parse_csv(&base_path).unwrap()]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(1, "Cost", "2").with_expected_old("1.5"));
+        patch.add_edit(Edit::new(2, "Name", "baz").with_expected_old("qux"));
+
+        let result = apply_patch(&merged, &patch).unwrap();
+        assert_eq!(result.edits_applied, 1);
+        assert_eq!(result.failed_edits.len(), 1);
+        assert_eq!(result.failed_edits[0].1, "Stale edit: expected 'qux', found 'bar'");
+
+        let report = validate_patch(&merged, &patch, false);
+        assert_eq!(report.error_count(), 1);
+        assert_eq!(
+            report.edits[1].error.as_deref(),
+            Some("Stale edit: expected 'qux', found 'bar'")
+        );
+
+        let out_dir = dir.join("out");
+        let result = export_with_edits(&merged, &patch, &out_dir).unwrap();
+        assert_eq!(result.edits_applied, 1);
+        assert_eq!(result.skipped_edits.len(), 1);
+        assert_eq!(
+            fs::read_to_string(out_dir.join("test.csv")).unwrap(),
+            "ID,Name,Cost\n1,foo,2\n2,bar,20\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_column_rename_and_reorder() {
+        let dir = scratch_dir("column-ops");
+        let base_path = dir.join("spells.csv");
+        let variant_path = dir.join("spells_ep1.csv");
+        fs::write(&base_path, "ID,0x1A,Name,Cost\n1,3,fire,1.50\n2,4,ice,2\n").unwrap();
+        fs::write(&variant_path, "ID,Name\n3,frost\n").unwrap();
+        let merged = merge_tables(
+            "spells",
+            vec![parse_csv(&base_path).unwrap(), parse_csv(&variant_path).unwrap()],
+        )
+        .unwrap();
+
+        let mut patch = PatchFile::new("spells");
+        patch.add_edit(Edit::new(2, "0x1A", "5"));
+        patch.add_column_op(ColumnOp::Rename {
+            from: "0x1A".to_string(),
+            to: "Flags".to_string(),
+        });
+        patch.add_column_op(ColumnOp::Reorder {
+            order: vec!["ID".into(), "Flags".into(), "Cost".into(), "Name".into()],
+        });
+
+        let result = apply_patch(&merged, &patch).unwrap();
+        assert!(result.failed_column_ops.is_empty());
+        assert!(validate_patch(&merged, &patch, false).is_valid());
+
+        let out_dir = dir.join("out");
+        let result = export_with_edits(&merged, &patch, &out_dir).unwrap();
+        assert_eq!(result.edits_applied, 1);
+        assert_eq!(
+            fs::read_to_string(out_dir.join("spells.csv")).unwrap(),
+            "ID,Flags,Cost,Name\n1,3,1.50,fire\n2,5,2,ice\n"
+        );
+        // The variant has neither moved column, so its header stays as it is
+        assert!(!out_dir.join("spells_ep1.csv").exists());
+
+        let raw_dir = dir.join("raw");
+        let options = ExportOptions::fidelity();
+        export_with_edits_with_options(&merged, &patch, &raw_dir, &options).unwrap();
+        assert_eq!(
+            fs::read_to_string(raw_dir.join("spells.csv")).unwrap(),
+            "ID,Flags,Cost,Name\n1,3,1.50,fire\n2,5,2,ice\n"
+        );
+
+        // Undoing restores the original names and order
+        let inverse = invert_patch(&merged, &patch);
+        assert_eq!(inverse.edits[0].column, "Flags");
+        assert_eq!(
+            inverse.column_ops,
+            vec![
+                ColumnOp::Reorder {
+                    order: vec!["ID".into(), "Flags".into(), "Name".into(), "Cost".into()],
+                },
+                ColumnOp::Rename {
+                    from: "Flags".to_string(),
+                    to: "0x1A".to_string(),
+                },
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_column_ops_are_reported() {
+        let table = parse_csv_str("ID,Name,Cost\n1,fire,2\n", "spells.csv").unwrap();
+        let merged = merge_tables("spells", vec![table]).unwrap();
+
+        let mut patch = PatchFile::new("spells");
+        patch.add_column_op(ColumnOp::Rename {
+            from: "Label".to_string(),
+            to: "Title".to_string(),
+        });
+        patch.add_column_op(ColumnOp::Reorder {
+            order: vec!["ID".into(), "Cost".into()],
+        });
+        patch.add_column_op(ColumnOp::Reorder {
+            order: vec!["Name".into(), "ID".into(), "Cost".into()],
+        });
+
+        let result = apply_patch(&merged, &patch).unwrap();
+        let reasons: Vec<&str> = result.failed_column_ops.iter().map(|(_, r)| r.as_str()).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                "Column 'Label' not found",
+                "Reorder leaves out column 'Name'",
+                "Reorder must keep the ID column in place",
+            ]
+        );
+        assert_eq!(validate_patch(&merged, &patch, false).error_count(), 3);
+
+        let dir = scratch_dir("column-ops-invalid");
+        let err = export_with_edits(&merged, &patch, dir.join("out")).unwrap_err();
+        assert!(matches!(err, Error::Export(_)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_dry_run_writes_nothing() {
+        let dir = scratch_dir("dry-run");
+        let base_path = dir.join("test.csv");
+        fs::write(&base_path, "ID,Name\n1,foo\n2,bar\n").unwrap();
+        let merged = merge_tables("test", vec![parse_csv(&base_path).unwrap()]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(2, "Name", "baz"));
+        patch.add_edit(Edit::new(9, "Name", "missing row"));
+
+        let options = ExportOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let out_dir = dir.join("out");
+        let result = export_with_edits_with_options(&merged, &patch, &out_dir, &options).unwrap();
+        assert!(!out_dir.exists());
+        assert!(result.files_written.is_empty());
+        assert_eq!(result.edits_applied, 1);
+        assert_eq!(
+            result.dry_run_files.get(&out_dir.join("test.csv")).map(String::as_str),
+            Some("ID,Name\n1,foo\n2,baz\n")
+        );
+
+        let options = ExportOptions {
+            backup: true,
+            ..options
+        };
+        let result = apply_in_place(&merged, &patch, &options).unwrap();
+        assert!(result.backups.is_empty());
+        assert_eq!(result.dry_run_files.len(), 1);
+        assert_eq!(fs::read_to_string(&base_path).unwrap(), "ID,Name\n1,foo\n2,bar\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_line_endings() {
+        let dir = scratch_dir("line-endings");
+        let base_path = dir.join("test.csv");
+        fs::write(&base_path, "ID,Name\n1,foo\n2,bar\n").unwrap();
+        let merged = merge_tables("test", vec![parse_csv(&base_path).unwrap()]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(2, "Name", "baz"));
+
+        let export = |line_ending| {
+            let options = ExportOptions {
+                dry_run: true,
+                line_ending,
+                ..Default::default()
+            };
+            let out_dir = dir.join("out");
+            let mut result =
+                export_with_edits_with_options(&merged, &patch, &out_dir, &options).unwrap();
+            result.dry_run_files.remove(&out_dir.join("test.csv")).unwrap()
+        };
+
+        let lf = export(LineEnding::Lf);
+        assert_eq!(lf, "ID,Name\n1,foo\n2,baz\n");
+        assert_eq!(export(LineEnding::Crlf), lf.replace('\n', "\r\n"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_patch_with_id_in_second_column() {
+        let dir = scratch_dir("id-column");
+        let path = dir.join("test.csv");
+        fs::write(&path, "Name,ID,Cost\nfire,1,10\nice,3,30\n").unwrap();
+        let parse = ParseOptions {
+            id_column: IdColumn::Index(1),
+            ..Default::default()
+        };
+        let table = parse_csv_with_options(&path, &parse).unwrap();
+        let merged = merge_tables("test", vec![table]).unwrap();
+        assert_eq!(merged.id_column, 1);
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(1, "Cost", "15"));
+        patch.add_row_op(RowOp::DeleteRow { row_id: 3 });
+        patch.add_row_op(RowOp::InsertRow {
+            row_id: 2,
+            values: HashMap::from([
+                ("Name".to_string(), "wind".to_string()),
+                ("Cost".to_string(), "20".to_string()),
+            ]),
+            file: None,
+        });
+        let result = apply_patch(&merged, &patch).unwrap();
+        assert_eq!((result.rows_inserted, result.rows_deleted), (1, 1));
+
+        // The deleted row comes back with its name and cost, keyed by ID
+        let inverse = invert_patch(&merged, &patch);
+        let edits: Vec<_> = inverse.edits.iter().map(|e| (e.row_id, e.value.as_str())).collect();
+        assert_eq!(edits, vec![(1, "10")]);
+        assert_eq!(
+            inverse.row_ops,
+            vec![
+                RowOp::InsertRow {
+                    row_id: 3,
+                    values: HashMap::from([
+                        ("Name".to_string(), "ice".to_string()),
+                        ("Cost".to_string(), "30".to_string()),
+                    ]),
+                    file: Some("test.csv".to_string()),
+                },
+                RowOp::DeleteRow { row_id: 2 },
+            ]
+        );
+
+        let out_dir = dir.join("out");
+        for preserve_raw in [false, true] {
+            let options = ExportOptions {
+                dry_run: true,
+                preserve_raw,
+                parse: parse.clone(),
+                ..Default::default()
+            };
+            let mut result =
+                export_with_edits_with_options(&merged, &patch, &out_dir, &options).unwrap();
+            let written = result.dry_run_files.remove(&out_dir.join("test.csv")).unwrap();
+            assert_eq!(written, "Name,ID,Cost\nfire,1,15\nwind,2,20\n");
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_multi_family_patch() {
+        let dir = scratch_dir("multi");
+        let root = dir.join("2da");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("spells.csv"), "ID,Name\n1,fire\n2,ice\n").unwrap();
+        fs::write(root.join("items.csv"), "ID,Name\n1,sword\n").unwrap();
+        let scan_result = crate::scanner::scan_directory(&[&root]).unwrap();
+
+        // A single-family patch loads as one entry
+        let mut spells = PatchFile::new("spells");
+        spells.add_edit(Edit::new(2, "Name", "frost"));
+        spells.save(dir.join("single.json")).unwrap();
+        let loaded = MultiPatchFile::load(dir.join("single.json")).unwrap();
+        assert_eq!(loaded.patches.len(), 1);
+        assert_eq!(loaded.patches[0].family, "spells");
+
+        let mut items = PatchFile::new("items");
+        items.add_edit(Edit::new(1, "Name", "axe"));
+        items.add_row_op(RowOp::InsertRow {
+            row_id: 2,
+            values: HashMap::from([("Name".to_string(), "bow".to_string())]),
+            file: None,
+        });
+        let multi = MultiPatchFile {
+            patches: vec![spells, items],
+        };
+        multi.save(dir.join("multi.json")).unwrap();
+        let multi = MultiPatchFile::load(dir.join("multi.json")).unwrap();
+
+        let out_dir = dir.join("out");
+        let options = ExportOptions::default();
+        let result = export_multi_with_edits(&scan_result, &multi, &out_dir, &options).unwrap();
+        assert_eq!(result.files_written.len(), 2);
+        assert_eq!(result.edits_applied, 2);
+        assert_eq!(result.rows_inserted, 1);
+        assert_eq!(
+            fs::read_to_string(out_dir.join("spells.csv")).unwrap(),
+            "ID,Name\n1,fire\n2,frost\n"
+        );
+        assert_eq!(
+            fs::read_to_string(out_dir.join("items.csv")).unwrap(),
+            "ID,Name\n1,axe\n2,bow\n"
+        );
+
+        // A missing family fails before anything is written
+        let mut missing = multi.clone();
+        missing.patches.push(PatchFile::new("nope"));
+        let out_dir = dir.join("missing");
+        let err = export_multi_with_edits(&scan_result, &missing, &out_dir, &options).unwrap_err();
+        assert!(matches!(err, Error::FamilyNotFound(_)));
+        assert!(!out_dir.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Export `original` with `patch` under the fidelity preset and return the file
+    fn export_fidelity(
+        name: &str,
+        original: &[u8],
+        patch: &PatchFile,
+        parse: ParseOptions,
+    ) -> Vec<u8> {
+        let dir = scratch_dir(name);
+        let path = dir.join("test.csv");
+        fs::write(&path, original).unwrap();
+        let merged =
+            merge_tables("test", vec![parse_csv_with_options(&path, &parse).unwrap()]).unwrap();
+
+        let options = ExportOptions {
+            parse,
+            ..ExportOptions::fidelity()
+        };
+        let out_dir = dir.join("out");
+        let result = export_with_edits_with_options(&merged, patch, &out_dir, &options).unwrap();
+        assert!(result.errors.is_empty(), "{}: {:?}", name, result.errors);
+        let written = fs::read(out_dir.join("test.csv")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        written
+    }
+
+    #[test]
+    fn test_fidelity_changes_only_edited_lines() {
+        // (name, original, row, column, value, expected)
+        let fixtures: &[(&str, &str, i64, &str, &str, &str)] = &[
+            (
+                "crlf",
+                "ID,Name,Cost\r\n1,fire,1.50\r\n2,ice,2.0\r\n",
+                2,
+                "Name",
+                "frost",
+                "ID,Name,Cost\r\n1,fire,1.50\r\n2,frost,2.0\r\n",
+            ),
+            (
+                "quoted",
+                "ID,Label,Icon\n1,\"Fire, Greater\",****\n2,\"plain\",****\n",
+                2,
+                "Icon",
+                "ice.dds",
+                "ID,Label,Icon\n1,\"Fire, Greater\",****\n2,plain,ice.dds\n",
+            ),
+            (
+                "trailing-columns",
+                "ID,Name,,\n1,fire,,\n2,ice,,\n",
+                1,
+                "Name",
+                "blaze",
+                "ID,Name,,\n1,blaze,,\n2,ice,,\n",
+            ),
+            (
+                "short-record",
+                "ID,Name,Cost\n1,fire\n2,ice,3\n",
+                1,
+                "Cost",
+                "07",
+                "ID,Name,Cost\n1,fire,07\n2,ice,3\n",
+            ),
+            (
+                "no-final-newline",
+                "ID,Name\n1,fire\n2,ice",
+                2,
+                "Name",
+                "frost",
+                "ID,Name\n1,fire\n2,frost",
+            ),
+            (
+                "bom",
+                "\u{feff}ID,Name\n1,fire\n",
+                1,
+                "Name",
+                "blaze",
+                "\u{feff}ID,Name\n1,blaze\n",
+            ),
+            (
+                "mixed-endings",
+                "ID,Name\r\n1,fire\n2,ice\r\n",
+                1,
+                "Name",
+                "blaze",
+                "ID,Name\r\n1,blaze\n2,ice\r\n",
+            ),
+        ];
+
+        for &(name, original, row, column, value, expected) in fixtures {
+            let mut patch = PatchFile::new("test");
+            patch.add_edit(Edit::new(row, column, value));
+            let written =
+                export_fidelity(name, original.as_bytes(), &patch, ParseOptions::default());
+            assert_eq!(String::from_utf8(written).unwrap(), expected, "fixture {}", name);
+        }
+    }
+
+    #[test]
+    fn test_fidelity_keeps_comments_and_row_ops() {
+        let original = "# spells\r\nID,Name,Cost\r\n1,fire,1.50\r\n\
+                        # retired\r\n2,ice,2\r\n\r\n4,bolt,****\r\n";
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(4, "Cost", "5"));
+        patch.add_row_op(RowOp::DeleteRow { row_id: 2 });
+        patch.add_row_op(RowOp::InsertRow {
+            row_id: 3,
+            values: HashMap::from([("Name".to_string(), "wind".to_string())]),
+            file: None,
+        });
+        patch.add_row_op(RowOp::InsertRow {
+            row_id: 9,
+            values: HashMap::from([("Name".to_string(), "stone".to_string())]),
+            file: None,
+        });
+
+        let parse = ParseOptions {
+            comment: Some(b'#'),
+            ..Default::default()
+        };
+        let written = export_fidelity("fidelity-ops", original.as_bytes(), &patch, parse);
+        assert_eq!(
+            String::from_utf8(written).unwrap(),
+            "# spells\r\nID,Name,Cost\r\n1,fire,1.50\r\n# retired\r\n\
+             \r\n3,wind,\r\n4,bolt,5\r\n9,stone,\r\n"
+        );
+    }
+
+    #[test]
+    fn test_export_round_trips_multiline_and_comma_cells() {
+        let original = "ID,Name,\"Desc, full\"\n\
+                        1,\"Fire, Greater\",\"Burns.\nTwice, \"\"hot\"\"\"\n\
+                        2,ice,\"line one\r\nline two\"\n";
+        let dir = scratch_dir("multiline");
+        let base_path = dir.join("test.csv");
+        fs::write(&base_path, original).unwrap();
+        let table = parse_csv(&base_path).unwrap();
+        assert_eq!(
+            table.rows[0].cells[2].to_string_value(),
+            "Burns.\nTwice, \"hot\""
+        );
+        let merged = merge_tables("test", vec![table.clone()]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(2, "Name", "frost, \"deep\"\nwinter"));
+
+        for (name, options) in [
+            ("rewrite", ExportOptions::default()),
+            ("raw", ExportOptions::fidelity()),
+        ] {
+            let out_dir = dir.join(name);
+            let result = export_with_edits_with_options(&merged, &patch, &out_dir, &options);
+            assert!(result.unwrap().errors.is_empty(), "{}", name);
+
+            let exported = parse_csv(out_dir.join("test.csv")).unwrap();
+            assert_eq!(exported.columns, table.columns, "{}", name);
+            let mut expected = table.rows.clone();
+            expected[1].cells[1] = CellValue::String("frost, \"deep\"\nwinter".to_string());
+            assert_eq!(exported.rows, expected, "{}", name);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_edits_rows_keyed_by_hex_ids() {
+        let dir = scratch_dir("hex-ids");
+        let base_path = dir.join("test.csv");
+        fs::write(&base_path, "ID,Name\n0x0001,fire\n0x0002,ice\n").unwrap();
+        let parse = ParseOptions {
+            hex_values: true,
+            ..Default::default()
+        };
+        let table = parse_csv_with_options(&base_path, &parse).unwrap();
+        let merged = merge_tables("test", vec![table]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(2, "Name", "frost"));
+
+        for (name, options) in [
+            ("rewrite", ExportOptions::default()),
+            ("raw", ExportOptions::fidelity()),
+        ] {
+            let options = ExportOptions {
+                parse: parse.clone(),
+                ..options
+            };
+            let out_dir = dir.join(name);
+            let result = export_with_edits_with_options(&merged, &patch, &out_dir, &options);
+            assert_eq!(result.unwrap().edits_applied, 1, "{}", name);
+            assert_eq!(
+                fs::read_to_string(out_dir.join("test.csv")).unwrap(),
+                "ID,Name\n0x0001,fire\n0x0002,frost\n",
+                "{}",
+                name
+            );
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_preserves_leading_comments() {
+        let dir = scratch_dir("comments");
+        let base_path = dir.join("test.csv");
+        fs::write(&base_path, "# Copyright (c) Example Mod Team\nID,Name\n1,foo\n").unwrap();
+
+        let options = ExportOptions {
+            parse: ParseOptions {
+                comment: Some(b'#'),
+                ..Default::default()
+            },
+            preserve_comments: true,
+            ..Default::default()
+        };
+        let original = parse_csv_with_options(&base_path, &options.parse).unwrap();
+        let merged = merge_tables("test", vec![original]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(1, "Name", "bar"));
+
+        let out_dir = dir.join("out");
+        export_with_edits_with_options(&merged, &patch, &out_dir, &options).unwrap();
+
+        let exported = fs::read_to_string(out_dir.join("test.csv")).unwrap();
+        assert_eq!(exported, "# Copyright (c) Example Mod Team\nID,Name\n1,bar\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_refuses_to_overwrite_without_option() {
+        let dir = scratch_dir("overwrite");
+        let base_path = dir.join("test.csv");
+        fs::write(&base_path, "ID,Name\n1,foo\n").unwrap();
+        let merged = merge_tables("test", vec![parse_csv(&base_path).unwrap()]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(1, "Name", "bar"));
+
+        let out_dir = dir.join("out");
+        let existing = out_dir.join("test.csv");
+        fs::create_dir_all(&out_dir).unwrap();
+        fs::write(&existing, "keep me\n").unwrap();
+
+        match export_with_edits(&merged, &patch, &out_dir) {
+            Err(Error::OutputExists(paths)) => assert_eq!(paths, vec![existing.clone()]),
+            other => panic!("expected OutputExists, got {:?}", other),
+        }
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "keep me\n");
+
+        let options = ExportOptions {
+            overwrite: true,
+            ..Default::default()
+        };
+        let result = export_with_edits_with_options(&merged, &patch, &out_dir, &options).unwrap();
+        assert_eq!(result.files_written, vec![existing.clone()]);
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "ID,Name\n1,bar\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_in_place_with_backup_and_inverse() {
+        let dir = scratch_dir("in-place");
+        let base_path = dir.join("test.csv");
+        let variant_path = dir.join("test_ep1.csv");
+        let base = "ID,Name,Value\n1,foo,100\n2,bar,200\n";
+        let variant = "ID,Name,Value\n2,BAR,\n";
+        fs::write(&base_path, base).unwrap();
+        fs::write(&variant_path, variant).unwrap();
+
+        let merge = || {
+            merge_tables(
+                "test",
+                vec![parse_csv(&base_path).unwrap(), parse_csv(&variant_path).unwrap()],
+            )
+            .unwrap()
+        };
+        let merged = merge();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(1, "Value", "150"));
+        patch.add_edit(Edit::new(2, "Name", "baz"));
+        patch.add_row_op(RowOp::DeleteRow { row_id: 1 });
+        patch.add_row_op(RowOp::InsertRow {
+            row_id: 3,
+            values: HashMap::from([("Name".to_string(), "qux".to_string())]),
+            file: None,
+        });
+        let inverse = invert_patch(&merged, &patch);
+
+        let options = ExportOptions {
+            backup: true,
+            ..Default::default()
+        };
+        let result = apply_in_place(&merged, &patch, &options).unwrap();
+        assert!(result.errors.is_empty());
+        assert_eq!(result.backups.len(), 2);
+        assert_eq!(fs::read_to_string(backup_path(&base_path)).unwrap(), base);
+        assert_eq!(
+            fs::read_to_string(&base_path).unwrap(),
+            "ID,Name,Value\n2,bar,200\n3,qux,\n"
+        );
+        assert_eq!(fs::read_to_string(&variant_path).unwrap(), "ID,Name,Value\n2,baz,\n");
+        let patched = fs::read_to_string(&base_path).unwrap();
+
+        // A second backup leaves the first one alone
+        let result = apply_in_place(&merge(), &inverse, &options).unwrap();
+        assert_eq!(fs::read_to_string(&base_path).unwrap(), base);
+        assert_eq!(fs::read_to_string(&variant_path).unwrap(), variant);
+        assert_eq!(fs::read_to_string(backup_path(&base_path)).unwrap(), base);
+        let second = dir.join("test.csv.bak.1");
+        assert!(result.backups.contains(&second));
+        assert_eq!(fs::read_to_string(&second).unwrap(), patched);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_in_place_refuses_read_only_files() {
+        let dir = scratch_dir("in-place-read-only");
+        let path = dir.join("test.csv");
+        let original = "ID,Name\n1,foo\n";
+        fs::write(&path, original).unwrap();
+        let merged = merge_tables("test", vec![parse_csv(&path).unwrap()]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(1, "Name", "bar"));
+        let options = ExportOptions {
+            backup: true,
+            ..Default::default()
+        };
+
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions.clone()).unwrap();
+        let err = apply_in_place(&merged, &patch, &options).unwrap_err();
+        assert!(matches!(&err, Error::ReadOnlyTarget(paths) if *paths == [path.clone()]));
+        assert!(!backup_path(&path).exists());
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs::set_permissions(&path, permissions).unwrap();
+        apply_in_place(&merged, &patch, &options).unwrap();
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), original);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "ID,Name\n1,bar\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_validate_patch_reports_type_mismatch() {
+        let csv = "ID,Name,Cost,Scale\n1,foo,10,1.5\n2,bar,20,2\n";
+        let table = merge_tables("test", vec![parse_csv_str(csv, "test.csv").unwrap()]).unwrap();
+
+        let mut patch = PatchFile::new("test");
+        patch.add_edit(Edit::new(1, "Cost", "hello"));
+        patch.add_edit(Edit::new(1, "Scale", "3"));
+        patch.add_edit(Edit::new(2, "Name", "42"));
+        patch.add_edit(Edit::new(2, "Cost", ""));
+        patch.add_edit(Edit::new(9, "Cost", "1"));
+        // A literal number is written as a string, so it doesn't fit
+        patch.add_edit(Edit::new_literal(2, "Scale", "4"));
+        patch.add_row_op(RowOp::DeleteRow { row_id: 9 });
+
+        let report = validate_patch(&table, &patch, true);
+        let mismatches: Vec<_> = report.edits.iter().map(|e| e.type_mismatch).collect();
+        assert_eq!(
+            mismatches,
+            vec![
+                Some(ColumnType::Integer),
+                None,
+                None,
+                None,
+                None,
+                Some(ColumnType::Float)
+            ]
+        );
+        assert_eq!(report.edits[4].error.as_deref(), Some("Row not found: 9"));
+        assert_eq!(report.row_ops[0].error.as_deref(), Some("Row not found: 9"));
+        assert_eq!((report.error_count(), report.warning_count()), (2, 2));
+        assert!(!report.is_valid());
+
+        // Without type checks the mismatch is not reported
+        let report = validate_patch(&table, &patch, false);
+        assert_eq!(report.warning_count(), 0);
+    }
+
+    #[test]
+    fn test_preview_edit() {
+        let csv = "ID,Name,Cost\n1,foo,10\n2,bar,20\n";
+        let table = merge_tables("test", vec![parse_csv_str(csv, "test.csv").unwrap()]).unwrap();
+
+        let value = preview_edit(&table, &Edit::new(1, "Cost", " 15 ")).unwrap();
+        assert_eq!(value, CellValue::Integer(15));
+        let value = preview_edit(&table, &Edit::new_literal(2, "Name", "42")).unwrap();
+        assert_eq!(value, CellValue::String("42".to_string()));
+        assert_eq!(table.find_row(1).unwrap().cells[2].value, CellValue::Integer(10));
+
+        assert!(matches!(
+            preview_edit(&table, &Edit::new(1, "Cost", "cheap")),
+            Err(Error::TypeMismatch { expected: ColumnType::Integer, .. })
+        ));
+        assert!(matches!(
+            preview_edit(&table, &Edit::new(9, "Cost", "1")),
+            Err(Error::RowNotFound(9))
+        ));
+        assert!(matches!(
+            preview_edit(&table, &Edit::new(1, "Weight", "1")),
+            Err(Error::ColumnNotFound(_))
+        ));
     }
 }
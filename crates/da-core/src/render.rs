@@ -0,0 +1,329 @@
+//! Plain-text and Markdown rendering of merged tables
+
+use crate::merger::ResolvedTable;
+use crate::table::Column;
+use serde::{Deserialize, Serialize};
+
+/// Separator between rendered columns
+const COLUMN_GAP: &str = "  ";
+
+/// Options controlling how a table is rendered as text
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextRenderOptions {
+    /// Truncate cells wider than this many characters
+    pub max_column_width: Option<usize>,
+    /// Names of the columns to show, in table order (all if `None`)
+    pub columns: Option<Vec<String>>,
+    /// Maximum number of rows to show
+    pub limit: Option<usize>,
+    /// Show the last `limit` rows instead of the first
+    pub tail: bool,
+    /// Cells to highlight with a trailing `*`, by row ID and column name
+    pub marked_cells: Vec<(i64, String)>,
+}
+
+impl ResolvedTable {
+    /// Render the table as text with each column padded to its widest cell
+    ///
+    /// The header is followed by a dashed rule. When rows are left out by
+    /// `limit`, a line noting how many is added on the side they were cut.
+    pub fn to_aligned_string(&self, opts: &TextRenderOptions) -> String {
+        let columns = self.selected_columns(opts.columns.as_deref());
+
+        let shown = opts.limit.unwrap_or(self.rows.len()).min(self.rows.len());
+        let hidden = self.rows.len() - shown;
+        let rows = if opts.tail {
+            &self.rows[hidden..]
+        } else {
+            &self.rows[..shown]
+        };
+
+        let fit = |s: String| fit(s, opts.max_column_width);
+
+        let header: Vec<String> = columns.iter().map(|c| fit(c.name.clone())).collect();
+        let body: Vec<Vec<String>> = rows
+            .iter()
+            .map(|row| {
+                columns
+                    .iter()
+                    .map(|col| {
+                        let cell = fit(row
+                            .cells
+                            .get(col.index)
+                            .map(|c| c.value.to_string_value())
+                            .unwrap_or_default());
+                        let marked = row.id.is_some_and(|id| {
+                            opts.marked_cells
+                                .iter()
+                                .any(|(row_id, name)| *row_id == id && *name == col.name)
+                        });
+                        if marked {
+                            cell + "*"
+                        } else {
+                            cell
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let widths = column_widths(&header, &body);
+        let mut out = String::new();
+        push_header(&mut out, &header, &widths);
+
+        if hidden > 0 && opts.tail {
+            out.push_str(&format!("... ({} earlier rows)\n", hidden));
+        }
+        for cells in &body {
+            push_line(&mut out, cells, &widths);
+        }
+        if hidden > 0 && !opts.tail {
+            out.push_str(&format!("... ({} more rows)\n", hidden));
+        }
+
+        out
+    }
+
+    /// Render the table as a GitHub-flavored Markdown table
+    ///
+    /// Only the named columns are included, in table order (all if `None`).
+    /// Pipes in cell values are escaped and empty cells are left blank.
+    pub fn to_markdown(&self, columns: Option<&[String]>) -> String {
+        let columns = self.selected_columns(columns);
+
+        let mut out = String::new();
+        let header: Vec<String> = columns.iter().map(|c| escape_markdown(&c.name)).collect();
+        push_markdown_row(&mut out, &header);
+        let rule: Vec<String> = columns.iter().map(|_| "---".to_string()).collect();
+        push_markdown_row(&mut out, &rule);
+
+        for row in &self.rows {
+            let cells: Vec<String> = columns
+                .iter()
+                .map(|col| {
+                    row.cells
+                        .get(col.index)
+                        .map(|c| escape_markdown(&c.value.to_string_value()))
+                        .unwrap_or_default()
+                })
+                .collect();
+            push_markdown_row(&mut out, &cells);
+        }
+
+        out
+    }
+
+    /// Get the named columns in table order, or every column for `None`
+    fn selected_columns(&self, names: Option<&[String]>) -> Vec<&Column> {
+        self.columns
+            .iter()
+            .filter(|c| names.is_none_or(|names| names.contains(&c.name)))
+            .collect()
+    }
+}
+
+/// Render a header and rows of text with each column padded to its widest
+/// cell, as `ResolvedTable::to_aligned_string` does
+///
+/// For output that isn't a whole merged table, such as filter results or a
+/// single parsed file. Cells and header names wider than
+/// `max_column_width` are truncated.
+pub fn render_aligned(
+    header: &[String],
+    rows: &[Vec<String>],
+    max_column_width: Option<usize>,
+) -> String {
+    let header: Vec<String> = header.iter().map(|h| fit(h.clone(), max_column_width)).collect();
+    let body: Vec<Vec<String>> = rows
+        .iter()
+        .map(|cells| cells.iter().map(|c| fit(c.clone(), max_column_width)).collect())
+        .collect();
+
+    let widths = column_widths(&header, &body);
+    let mut out = String::new();
+    push_header(&mut out, &header, &widths);
+    for cells in &body {
+        push_line(&mut out, cells, &widths);
+    }
+    out
+}
+
+/// Get the width of each column's widest cell, header included
+fn column_widths(header: &[String], body: &[Vec<String>]) -> Vec<usize> {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for cells in body {
+        for (width, cell) in widths.iter_mut().zip(cells) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    widths
+}
+
+/// Append the header line and the dashed rule under it
+fn push_header(out: &mut String, header: &[String], widths: &[usize]) {
+    push_line(out, header, widths);
+    let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    push_line(out, &rule, widths);
+}
+
+/// Append one Markdown table row
+fn push_markdown_row(out: &mut String, cells: &[String]) {
+    out.push('|');
+    for cell in cells {
+        out.push(' ');
+        out.push_str(cell);
+        out.push_str(" |");
+    }
+    out.push('\n');
+}
+
+/// Escape a value for a Markdown table cell
+///
+/// Pipes would end the cell and line breaks the row.
+fn escape_markdown(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+/// Append one padded line, without trailing spaces after the last column
+fn push_line(out: &mut String, cells: &[String], widths: &[usize]) {
+    let mut line = String::new();
+    for (i, (cell, width)) in cells.iter().zip(widths).enumerate() {
+        if i > 0 {
+            line.push_str(COLUMN_GAP);
+        }
+        line.push_str(cell);
+        line.extend(std::iter::repeat_n(' ', width - cell.chars().count()));
+    }
+    out.push_str(line.trim_end());
+    out.push('\n');
+}
+
+/// Truncate `s` if it is wider than `max` characters
+fn fit(s: String, max: Option<usize>) -> String {
+    match max {
+        Some(max) if s.chars().count() > max => truncate(&s, max),
+        _ => s,
+    }
+}
+
+/// Shorten `s` to `max` characters, marking the cut with `~`
+fn truncate(s: &str, max: usize) -> String {
+    if max == 0 {
+        return String::new();
+    }
+    let mut short: String = s.chars().take(max - 1).collect();
+    short.push('~');
+    short
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merger::merge_tables;
+    use crate::parser::parse_csv_str;
+
+    fn table(csv: &str) -> ResolvedTable {
+        merge_tables("test", vec![parse_csv_str(csv, "test.csv").unwrap()]).unwrap()
+    }
+
+    #[test]
+    fn test_aligned_columns_of_differing_widths() {
+        let table = table("ID,Name,Cost\n1,fireball,12\n200,x,3\n");
+        let text = table.to_aligned_string(&TextRenderOptions::default());
+
+        assert_eq!(
+            text,
+            "ID   Name      Cost\n\
+             ---  --------  ----\n\
+             1    fireball  12\n\
+             200  x         3\n"
+        );
+    }
+
+    #[test]
+    fn test_selected_columns_width_and_limit() {
+        let table = table("ID,Name,Cost\n1,fireball,12\n2,x,3\n3,y,4\n");
+        let opts = TextRenderOptions {
+            max_column_width: Some(4),
+            columns: Some(vec!["Name".to_string()]),
+            limit: Some(2),
+            tail: false,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            table.to_aligned_string(&opts),
+            "Name\n----\nfir~\nx\n... (1 more rows)\n"
+        );
+
+        let opts = TextRenderOptions { tail: true, ..opts };
+        assert_eq!(
+            table.to_aligned_string(&opts),
+            "Name\n----\n... (1 earlier rows)\nx\ny\n"
+        );
+    }
+
+    #[test]
+    fn test_render_aligned_truncates_long_values() {
+        let header = vec!["ID".to_string(), "Description".to_string()];
+        let rows = vec![
+            vec!["1".to_string(), "A ball of fire".to_string()],
+            vec!["20".to_string(), "Ice".to_string()],
+        ];
+
+        assert_eq!(
+            render_aligned(&header, &rows, Some(6)),
+            "ID  Descr~
+--  ------
+1   A bal~
+20  Ice
+"
+        );
+        assert_eq!(
+            render_aligned(&header, &rows, None),
+            "ID  Description
+--  --------------
+1   A ball of fire
+20  Ice
+"
+        );
+    }
+
+    #[test]
+    fn test_marked_cells() {
+        let table = table("ID,Name,Cost\n1,fireball,12\n2,x,3\n");
+        let opts = TextRenderOptions {
+            max_column_width: Some(4),
+            marked_cells: vec![(1, "Name".to_string()), (2, "Cost".to_string())],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            table.to_aligned_string(&opts),
+            "ID  Name   Cost\n--  -----  ----\n1   fir~*  12\n2   x      3*\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_escapes_pipes_and_filters_columns() {
+        let table = table("ID,Name,Cost\n1,a|b,12\n2,,3\n");
+
+        assert_eq!(
+            table.to_markdown(None),
+            "| ID | Name | Cost |\n\
+             | --- | --- | --- |\n\
+             | 1 | a\\|b | 12 |\n\
+             | 2 |  | 3 |\n"
+        );
+
+        let columns = ["Cost".to_string(), "ID".to_string()];
+        assert_eq!(
+            table.to_markdown(Some(&columns)),
+            "| ID | Cost |\n| --- | --- |\n| 1 | 12 |\n| 2 | 3 |\n"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! Checking whether a file survives being parsed and written back
+//!
+//! Exports that don't copy source bytes (`ExportOptions::preserve_raw`)
+//! write every cell from its parsed value. Numbers keep their spelling,
+//! so `1.50` and `007` are written as read, but surrounding spaces are
+//! trimmed and quoting is redone. `verify_roundtrip` shows what that
+//! would change in a given file.
+
+use crate::csv_writer::write_csv;
+use crate::error::{Error, Result};
+use crate::parser::{csv_error, parse_csv_bytes_with_options, ParseOptions};
+use crate::table::ParseWarning;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What a parse and write round trip did to a file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundtripReport {
+    /// The file checked
+    pub path: PathBuf,
+    /// Whether the written file is byte for byte the original
+    pub bytes_match: bool,
+    /// Cells whose text changed, in row and then column order
+    pub differences: Vec<RoundtripDifference>,
+    /// Problems found while parsing, such as extra cells the writer drops
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl RoundtripReport {
+    /// Whether every cell reads back with the text it was written with
+    ///
+    /// The bytes can still differ, e.g. in quoting or line endings.
+    pub fn cells_match(&self) -> bool {
+        self.differences.is_empty() && self.warnings.is_empty()
+    }
+}
+
+/// A cell whose text changed in the round trip
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundtripDifference {
+    /// ID of the cell's row, if the row has one
+    pub row_id: Option<i64>,
+    /// Position of the row among the file's rows, from 0
+    pub row: usize,
+    /// Column name
+    pub column: String,
+    /// The cell as read from the file, unquoted
+    pub before: String,
+    /// The cell as read back from the written file
+    pub after: String,
+}
+
+/// Parse a file, write it back out as CSV in memory, and compare the two
+///
+/// The file is read with default `ParseOptions` and written the way a
+/// normal (non-fidelity) export writes it. Cells are compared by their
+/// unquoted text, so a cell that only loses needless quotes isn't listed,
+/// though `bytes_match` will be false.
+pub fn verify_roundtrip<P: AsRef<Path>>(path: P) -> Result<RoundtripReport> {
+    let path = path.as_ref();
+    let original = fs::read(path).map_err(|e| Error::FileRead {
+        path: path.to_path_buf(),
+        source: e,
+    })?;
+
+    let table = parse_csv_bytes_with_options(&original, path, &ParseOptions::default())?;
+    let mut written = Vec::new();
+    write_csv(&mut written, &table)?;
+
+    let before = read_fields(&original, path)?;
+    let after = read_fields(&written, path)?;
+    let field = |records: &[Vec<String>], row: usize, index: usize| {
+        records
+            .get(row)
+            .and_then(|fields| fields.get(index))
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    let mut differences = Vec::new();
+    for (row, parsed) in table.rows.iter().enumerate() {
+        for column in &table.columns {
+            let before = field(&before, row, column.index);
+            let after = field(&after, row, column.index);
+            if before != after {
+                differences.push(RoundtripDifference {
+                    row_id: parsed.id,
+                    row,
+                    column: column.name.clone(),
+                    before,
+                    after,
+                });
+            }
+        }
+    }
+
+    Ok(RoundtripReport {
+        path: path.to_path_buf(),
+        bytes_match: original == written,
+        differences,
+        warnings: table.warnings,
+    })
+}
+
+/// Read the unquoted fields of every record after the header
+fn read_fields(bytes: &[u8], path: &Path) -> Result<Vec<Vec<String>>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(bytes);
+    reader
+        .byte_records()
+        .map(|record| {
+            let record = record.map_err(|e| csv_error(path, e, 0))?;
+            Ok(record.iter().map(|f| String::from_utf8_lossy(f).into_owned()).collect())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch_dir;
+
+    fn verify(name: &str, contents: &str) -> RoundtripReport {
+        let dir = scratch_dir("roundtrip");
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        let report = verify_roundtrip(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        report
+    }
+
+    #[test]
+    fn test_roundtrip_of_normalized_file_is_exact() {
+        let report = verify("clean.csv", "ID,Name,Cost\n1,\"fire, big\",1.5\n2,,3\n3,x,1.0\n");
+
+        assert!(report.bytes_match);
+        assert!(report.cells_match());
+    }
+
+    #[test]
+    fn test_roundtrip_keeps_number_spellings() {
+        let report = verify("numbers.csv", "ID,Cost,Rate\n1,1.50,1e3\n2,007,+0.250\n");
+
+        assert!(report.bytes_match);
+        assert!(report.cells_match());
+    }
+
+    #[test]
+    fn test_roundtrip_lists_changed_cells() {
+        let report = verify(
+            "lossy.csv",
+            "ID,Name,Cost,Big\r\n1,\"fire\",1.50,12345678901234567890\r\n2, ice ,007,1e3\r\n",
+        );
+
+        assert!(!report.bytes_match);
+        let changes: Vec<_> = report
+            .differences
+            .iter()
+            .map(|d| (d.row_id, d.column.as_str(), d.before.as_str(), d.after.as_str()))
+            .collect();
+        // Needless quotes and CRLF only change the bytes, and numbers are
+        // kept as written; only the padding around ice is trimmed
+        assert_eq!(changes, vec![(Some(2), "Name", " ice ", "ice")]);
+        assert!(!report.cells_match());
+    }
+}
@@ -1,13 +1,26 @@
 //! Directory scanner for discovering and grouping 2DA CSV files
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::merger::{merge_family_with_options, MergeOptions};
+use crate::parser::{
+    parse_csv_bytes_with_options, parse_csv_streaming_with_options, parse_csv_with_options,
+    ParseOptions, RowIter,
+};
+use crate::table::{find_ignoring_case, Column, Table};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use serde::{Deserialize, Serialize};
+use rayon::prelude::*;
 use std::collections::BTreeMap;
-use std::path::{Path, PathBuf};
+use std::fs;
+use std::io::{Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
 use walkdir::WalkDir;
+use zip::ZipArchive;
 
 /// A family of related CSV files that should be merged together
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Family {
     /// Base name of the family (e.g., "abi_base")
     pub name: String,
@@ -28,15 +41,162 @@ impl Family {
             .filter(|m| m.suffix.is_some())
             .collect()
     }
+
+    /// Get the members in merge order for an explicit suffix precedence
+    ///
+    /// The base file comes first, then variants whose suffix isn't listed
+    /// (alphabetically), then the listed suffixes in `precedence` order.
+    /// Later members override earlier ones, so the last listed suffix wins.
+    pub fn ordered_members(&self, precedence: &[String]) -> Vec<&FamilyMember> {
+        let rank = |m: &FamilyMember| match &m.suffix {
+            None => (0, 0),
+            Some(suffix) => match precedence.iter().position(|p| p == suffix) {
+                Some(pos) => (2, pos),
+                None => (1, 0),
+            },
+        };
+
+        // Members are already base-first and alphabetical; the stable sort
+        // keeps that order within each rank
+        let mut members: Vec<&FamilyMember> = self.members.iter().collect();
+        members.sort_by_key(|m| rank(m));
+        members
+    }
+
+    /// Get the number of rows in the merged table
+    ///
+    /// Rows sharing an ID across members are counted once. This parses and
+    /// merges every member file, so it costs as much as `merge_family`.
+    pub fn merged_row_count(&self) -> Result<usize> {
+        self.merged_row_count_with_options(&MergeOptions::default())
+    }
+
+    /// Count the rows of the merged family, merging with the given options
+    pub fn merged_row_count_with_options(&self, options: &MergeOptions) -> Result<usize> {
+        merge_family_with_options(self, options).map(|(table, _)| table.row_count())
+    }
+
+    /// Compare the columns of every member against the base file
+    ///
+    /// Only each member's header is read. Members that disagree with the
+    /// base (or, without one, the first member) are still merged fine, but
+    /// it often means a file was exported from the wrong table or an old
+    /// version of it, so the report lists the differences instead of
+    /// failing. Column names are compared exactly.
+    pub fn validate_shape(&self, parse: &ParseOptions) -> Result<ShapeReport> {
+        let mut headers = Vec::with_capacity(self.members.len());
+        for member in &self.members {
+            let names: Vec<String> =
+                member.read_columns(parse)?.into_iter().map(|c| c.name).collect();
+            headers.push((member.path.clone(), names));
+        }
+
+        let mut report = ShapeReport::default();
+        let reference = self.members.iter().position(|m| m.suffix.is_none()).unwrap_or(0);
+        let Some((_, expected)) = headers.get(reference) else {
+            return Ok(report);
+        };
+
+        for column in expected {
+            let members: Vec<PathBuf> = headers
+                .iter()
+                .filter(|(_, names)| !names.contains(column))
+                .map(|(path, _)| path.clone())
+                .collect();
+            if !members.is_empty() {
+                report.missing.push(MissingColumn {
+                    column: column.clone(),
+                    members,
+                });
+            }
+        }
+        for (path, names) in &headers {
+            let columns: Vec<String> =
+                names.iter().filter(|n| !expected.contains(n)).cloned().collect();
+            if !columns.is_empty() {
+                report.extra.push(ExtraColumns {
+                    member: path.clone(),
+                    columns,
+                });
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// How the headers of a family's members differ, from
+/// `Family::validate_shape`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShapeReport {
+    /// Columns of the base file that some members lack
+    pub missing: Vec<MissingColumn>,
+    /// Members with columns the base file doesn't have
+    pub extra: Vec<ExtraColumns>,
+}
+
+impl ShapeReport {
+    /// Whether every member has exactly the base file's columns
+    pub fn is_consistent(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty()
+    }
+}
+
+/// A base file column that some members lack
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MissingColumn {
+    /// Column name
+    pub column: String,
+    /// Members without the column
+    pub members: Vec<PathBuf>,
+}
+
+/// A member's columns that the base file doesn't have
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtraColumns {
+    /// The member file
+    pub member: PathBuf,
+    /// Its extra columns, in file order
+    pub columns: Vec<String>,
 }
 
 /// A member of a family (single CSV file)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FamilyMember {
     /// Full path to the file
     pub path: PathBuf,
     /// Suffix (e.g., "kcc" for "abi_base_kcc.csv"), None for base file
     pub suffix: Option<String>,
+    /// Modification time when the file was scanned, if the platform has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<SystemTime>,
+    /// The file's bytes, for members read out of an archive by `scan_zip`
+    ///
+    /// `path` is then the archive path joined with the entry name and
+    /// doesn't exist on disk. Not serialized.
+    #[serde(skip)]
+    pub contents: Option<Arc<[u8]>>,
+}
+
+impl FamilyMember {
+    /// Parse this member into a table, from memory if it came from an
+    /// archive and from disk otherwise
+    pub fn parse(&self, options: &ParseOptions) -> Result<Table> {
+        match &self.contents {
+            Some(bytes) => parse_csv_bytes_with_options(bytes, &self.path, options),
+            None => parse_csv_with_options(&self.path, options),
+        }
+    }
+
+    /// Read just this member's header
+    pub fn read_columns(&self, options: &ParseOptions) -> Result<Vec<Column>> {
+        let columns = match &self.contents {
+            Some(bytes) => {
+                RowIter::from_reader(&bytes[..], &self.path, options)?.columns().to_vec()
+            }
+            None => parse_csv_streaming_with_options(&self.path, options)?.columns().to_vec(),
+        };
+        Ok(columns)
+    }
 }
 
 /// Result of scanning directories
@@ -48,6 +208,10 @@ pub struct ScanResult {
     pub families: Vec<Family>,
     /// Total number of files found
     pub total_files: usize,
+    /// Entries the scan skipped, such as symlink loops or unreadable
+    /// directories
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 impl ScanResult {
@@ -56,87 +220,449 @@ impl ScanResult {
         self.families.iter().find(|f| f.name == name)
     }
 
+    /// Find a family by name, ignoring case if there is no exact match
+    pub fn find_family_ci(&self, name: &str) -> Result<Option<&Family>> {
+        find_ignoring_case(&self.families, name, |f| &f.name)
+    }
+
     /// Get all family names
     pub fn family_names(&self) -> Vec<&str> {
         self.families.iter().map(|f| f.name.as_str()).collect()
     }
+
+    /// Find families whose name contains `pattern`, ignoring case
+    ///
+    /// Hits are in family order and say where in the name the pattern
+    /// matched. An empty pattern matches every family at offset 0.
+    pub fn search(&self, pattern: &str) -> Vec<FamilySearchHit<'_>> {
+        search_families(&self.families, pattern)
+    }
+
+    /// Scan the roots again, reusing the families whose files are unchanged
+    pub fn rescan<P: AsRef<Path>>(&self, roots: &[P]) -> Result<Rescan> {
+        self.rescan_with_options(roots, &ScanOptions::default())
+    }
+
+    /// Scan the roots again using the given options
+    ///
+    /// Only the directory walk and a metadata lookup per file are repeated.
+    /// A family is kept from this result when it has the same files with
+    /// the same modification times; otherwise it is rebuilt and listed in
+    /// `Rescan::changed`. Families whose files were all removed disappear.
+    pub fn rescan_with_options<P: AsRef<Path>>(
+        &self,
+        roots: &[P],
+        options: &ScanOptions,
+    ) -> Result<Rescan> {
+        let mut fresh = scan_directory_with_options(roots, options)?;
+
+        let mut changed = Vec::new();
+        for family in &mut fresh.families {
+            match self.find_family(&family.name) {
+                Some(cached) if cached == family => family.clone_from(cached),
+                _ => changed.push(family.name.clone()),
+            }
+        }
+        for family in &self.families {
+            if fresh.find_family(&family.name).is_none() {
+                changed.push(family.name.clone());
+            }
+        }
+        changed.sort();
+
+        Ok(Rescan {
+            result: fresh,
+            changed,
+        })
+    }
+}
+
+/// A family found by `ScanResult::search`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FamilySearchHit<'a> {
+    /// The matching family
+    pub family: &'a Family,
+    /// Byte offset of the first match in the family name
+    pub offset: usize,
+    /// Length in bytes of the matched part of the name
+    pub len: usize,
+}
+
+impl FamilySearchHit<'_> {
+    /// The part of the family name that matched
+    pub fn matched(&self) -> &str {
+        &self.family.name[self.offset..self.offset + self.len]
+    }
+}
+
+/// Find families whose name contains `pattern`, ignoring case
+///
+/// The same as `ScanResult::search`, for a list of families held elsewhere.
+pub fn search_families<'a>(families: &'a [Family], pattern: &str) -> Vec<FamilySearchHit<'a>> {
+    families
+        .iter()
+        .filter_map(|family| {
+            let (offset, len) = match_ignoring_case(&family.name, pattern)?;
+            Some(FamilySearchHit {
+                family,
+                offset,
+                len,
+            })
+        })
+        .collect()
+}
+
+/// Find the first case-insensitive occurrence of `pattern` in `name`, as a
+/// byte offset and length into `name`
+fn match_ignoring_case(name: &str, pattern: &str) -> Option<(usize, usize)> {
+    let pattern = pattern.to_lowercase();
+    if pattern.is_empty() {
+        return Some((0, 0));
+    }
+    name.char_indices().find_map(|(start, _)| {
+        let mut lowered = String::new();
+        for (i, c) in name[start..].char_indices() {
+            lowered.extend(c.to_lowercase());
+            if !pattern.starts_with(&lowered) {
+                return None;
+            }
+            if lowered.len() == pattern.len() {
+                return Some((start, i + c.len_utf8()));
+            }
+        }
+        None
+    })
+}
+
+/// Result of rescanning directories
+#[derive(Debug, Clone)]
+pub struct Rescan {
+    /// The new scan result
+    pub result: ScanResult,
+    /// Families that were added, removed, or had a file added, removed or
+    /// modified, sorted by name
+    pub changed: Vec<String>,
+}
+
+/// Known DLC/variant suffixes - these indicate a variant file
+pub const DEFAULT_SUFFIXES: &[&str] = &[
+    "drk", "ep1", "gib", "kcc", "lel", "mem", "shale", "str", "val", "vala", "toe", "hrm",
+    "ibmoobs", "gxa",
+];
+
+/// Default for `ScanOptions::max_archive_entry_size`, 64 MiB
+pub const DEFAULT_MAX_ARCHIVE_ENTRY_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Options controlling how directories are scanned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanOptions {
+    /// Filename suffixes that mark a variant file (without the leading underscore)
+    pub known_suffixes: Vec<String>,
+    /// Descend into dot-directories and pick up dotfiles
+    pub include_hidden: bool,
+    /// Glob patterns a file's path relative to its root must match one of
+    /// to be scanned; every file is scanned if empty
+    pub include: Vec<String>,
+    /// Glob patterns that skip a file, even if it matches `include`
+    pub exclude: Vec<String>,
+    /// How many directory levels below each root to descend; files directly
+    /// in a root are at depth 1. Unlimited if `None`
+    pub max_depth: Option<usize>,
+    /// Largest archive entry, in bytes, that `scan_zip` reads into memory;
+    /// a bigger CSV entry fails the scan
+    pub max_archive_entry_size: u64,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            known_suffixes: DEFAULT_SUFFIXES.iter().map(|s| s.to_string()).collect(),
+            include_hidden: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_depth: None,
+            max_archive_entry_size: DEFAULT_MAX_ARCHIVE_ENTRY_SIZE,
+        }
+    }
+}
+
+impl ScanOptions {
+    /// Load scan options from JSON; missing fields keep their defaults
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(path.as_ref()).map_err(|e| Error::FileRead {
+            path: path.as_ref().to_path_buf(),
+            source: e,
+        })?;
+        serde_json::from_str(&content).map_err(Error::Json)
+    }
+}
+
+/// Progress of a running scan, passed to the callback of
+/// `scan_directory_with_progress`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanProgress {
+    /// CSV files found so far
+    pub discovered: usize,
+    /// Files assigned to a family so far
+    pub processed: usize,
+    /// Whether the directory walk is over, making `discovered` the total
+    pub walk_done: bool,
 }
 
+/// Number of files classified in parallel between two progress reports
+const PROGRESS_CHUNK: usize = 256;
+
 /// Scan one or more directories for CSV files and group them into families
 pub fn scan_directory<P: AsRef<Path>>(roots: &[P]) -> Result<ScanResult> {
-    let mut file_map: BTreeMap<String, Vec<(PathBuf, Option<String>)>> = BTreeMap::new();
-    let mut total_files = 0;
+    scan_directory_with_options(roots, &ScanOptions::default())
+}
+
+/// Scan one or more directories using the given options
+pub fn scan_directory_with_options<P: AsRef<Path>>(
+    roots: &[P],
+    options: &ScanOptions,
+) -> Result<ScanResult> {
+    scan_directory_with_progress(roots, options, |_| {})
+}
+
+/// Scan one or more directories, reporting progress as files are found
+/// and classified
+///
+/// `progress` is always called on the calling thread, between batches of
+/// parallel work, and no lock is held while it runs.
+pub fn scan_directory_with_progress<P: AsRef<Path>>(
+    roots: &[P],
+    options: &ScanOptions,
+    mut progress: impl FnMut(ScanProgress),
+) -> Result<ScanResult> {
+    let include = build_glob_set(&options.include)?;
+    let exclude = build_glob_set(&options.exclude)?;
 
+    // Walking is inherently serial; collect CSV paths first so the per-file
+    // work can run in parallel
+    let mut paths = Vec::new();
+    let mut warnings = Vec::new();
     for root in roots {
         let root = root.as_ref();
-
-        for entry in WalkDir::new(root)
-            .follow_links(true)
+        let mut walker = WalkDir::new(root).follow_links(true);
+        if let Some(depth) = options.max_depth {
+            walker = walker.max_depth(depth);
+        }
+        for entry in walker
             .into_iter()
-            .filter_map(|e| e.ok())
+            .filter_entry(|e| options.include_hidden || e.depth() == 0 || !is_hidden(e))
+            .filter_map(|e| e.map_err(|e| warnings.push(walk_warning(&e))).ok())
         {
-            let path = entry.path();
+            let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            let wanted = entry.path().extension().is_some_and(|ext| ext == "csv")
+                && (include.is_empty() || include.is_match(relative))
+                && !exclude.is_match(relative);
+            if wanted {
+                paths.push(entry.into_path());
+                progress(ScanProgress {
+                    discovered: paths.len(),
+                    processed: 0,
+                    walk_done: false,
+                });
+            }
+        }
+    }
 
-            // Only process CSV files
-            if path.extension().is_some_and(|ext| ext == "csv") {
-                if let Some(file_name) = path.file_stem().and_then(|s| s.to_str()) {
-                    let (family_name, suffix) = extract_family_info(file_name);
+    let mut report = ScanProgress {
+        discovered: paths.len(),
+        processed: 0,
+        walk_done: true,
+    };
+    progress(report);
 
-                    file_map
-                        .entry(family_name)
-                        .or_default()
-                        .push((path.to_path_buf(), suffix));
+    let mut classified: Vec<(String, FamilyMember)> = Vec::with_capacity(paths.len());
+    for chunk in paths.chunks(PROGRESS_CHUNK) {
+        classified.par_extend(chunk.par_iter().filter_map(|path| {
+            let file_name = path.file_stem().and_then(|s| s.to_str())?;
+            let (family_name, suffix) = extract_family_info(file_name, &options.known_suffixes);
+            let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+            Some((
+                family_name,
+                FamilyMember {
+                    path: path.clone(),
+                    suffix,
+                    modified,
+                    contents: None,
+                },
+            ))
+        }));
+        report.processed += chunk.len();
+        progress(report);
+    }
 
-                    total_files += 1;
-                }
-            }
+    Ok(ScanResult {
+        roots: roots.iter().map(|r| r.as_ref().to_path_buf()).collect(),
+        total_files: classified.len(),
+        families: group_families(classified),
+        warnings,
+    })
+}
+
+/// Scan a zip archive for CSV entries and group them into families
+///
+/// Mods are often shipped as zips; this inspects one without extracting
+/// it. See `scan_zip_reader`.
+pub fn scan_zip<P: AsRef<Path>>(path: P) -> Result<ScanResult> {
+    scan_zip_with_options(path, &ScanOptions::default())
+}
+
+/// Scan a zip archive using the given options
+pub fn scan_zip_with_options<P: AsRef<Path>>(path: P, options: &ScanOptions) -> Result<ScanResult> {
+    let path = path.as_ref();
+    let file = fs::File::open(path).map_err(|e| Error::FileRead {
+        path: path.to_path_buf(),
+        source: e,
+    })?;
+    scan_zip_reader(file, path, options)
+}
+
+/// Scan a zip archive held by any seekable reader, such as an in-memory
+/// buffer
+///
+/// Every CSV entry is read into memory and kept in its member's
+/// `contents`, so merging the families doesn't touch the archive again.
+/// Member paths are `archive` joined with the entry name; `include`,
+/// `exclude` and `max_depth` apply to the entry name as they would to a
+/// path below a scanned root. Entries whose names escape the archive
+/// (absolute or with `..`) are skipped with a warning.
+pub fn scan_zip_reader<R: Read + Seek>(
+    reader: R,
+    archive: &Path,
+    options: &ScanOptions,
+) -> Result<ScanResult> {
+    let archive_error = |source| Error::Archive {
+        path: archive.to_path_buf(),
+        source,
+    };
+    let include = build_glob_set(&options.include)?;
+    let exclude = build_glob_set(&options.exclude)?;
+    let mut zip = ZipArchive::new(reader).map_err(archive_error)?;
+
+    let mut classified = Vec::new();
+    let mut warnings = Vec::new();
+    for index in 0..zip.len() {
+        let mut entry = zip.by_index(index).map_err(archive_error)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let Some(name) = entry.enclosed_name() else {
+            warnings.push(format!("Skipped {}: name escapes the archive", entry.name()));
+            continue;
+        };
+        let hidden = name.components().any(|c| match c {
+            Component::Normal(part) => part.to_str().is_some_and(|p| p.starts_with('.')),
+            _ => false,
+        });
+        let wanted = name.extension().is_some_and(|ext| ext == "csv")
+            && (options.include_hidden || !hidden)
+            && options.max_depth.is_none_or(|depth| name.components().count() <= depth)
+            && (include.is_empty() || include.is_match(&name))
+            && !exclude.is_match(&name);
+        let Some(file_name) = name.file_stem().and_then(|s| s.to_str()).filter(|_| wanted) else {
+            continue;
+        };
+        let (family_name, suffix) = extract_family_info(file_name, &options.known_suffixes);
+
+        // The declared size is only a hint, as the archive may lie about it
+        let limit = options.max_archive_entry_size;
+        let capacity = entry.size().min(limit).min(1024 * 1024);
+        let mut bytes = Vec::with_capacity(capacity as usize);
+        let read = entry.by_ref().take(limit.saturating_add(1)).read_to_end(&mut bytes);
+        read.map_err(|e| Error::FileRead {
+            path: archive.join(&name),
+            source: e,
+        })?;
+        if bytes.len() as u64 > limit {
+            return Err(Error::ArchiveEntryTooLarge {
+                path: archive.join(&name),
+                limit,
+            });
         }
+        classified.push((
+            family_name,
+            FamilyMember {
+                path: archive.join(&name),
+                suffix,
+                modified: None,
+                contents: Some(bytes.into()),
+            },
+        ));
+    }
+
+    Ok(ScanResult {
+        roots: vec![archive.to_path_buf()],
+        total_files: classified.len(),
+        families: group_families(classified),
+        warnings,
+    })
+}
+
+/// Group classified members into families, sorted by name
+fn group_families(classified: Vec<(String, FamilyMember)>) -> Vec<Family> {
+    let mut file_map: BTreeMap<String, Vec<FamilyMember>> = BTreeMap::new();
+    for (family_name, member) in classified {
+        file_map.entry(family_name).or_default().push(member);
     }
 
     // Convert to families
-    let families: Vec<Family> = file_map
+    file_map
         .into_iter()
         .map(|(name, mut members)| {
             // Sort members: base file first, then variants alphabetically
-            members.sort_by(|a, b| match (&a.1, &b.1) {
-                (None, None) => a.0.cmp(&b.0),
+            members.sort_by(|a, b| match (&a.suffix, &b.suffix) {
+                (None, None) => a.path.cmp(&b.path),
                 (None, Some(_)) => std::cmp::Ordering::Less,
                 (Some(_), None) => std::cmp::Ordering::Greater,
                 (Some(sa), Some(sb)) => sa.cmp(sb),
             });
 
-            let members = members
-                .into_iter()
-                .map(|(path, suffix)| FamilyMember { path, suffix })
-                .collect();
-
             Family { name, members }
         })
-        .collect();
+        .collect()
+}
 
-    Ok(ScanResult {
-        roots: roots.iter().map(|r| r.as_ref().to_path_buf()).collect(),
-        families,
-        total_files,
-    })
+/// Describe a directory entry the walk had to skip
+///
+/// Following symlinks, the walk refuses to enter a link that points back
+/// to one of its own ancestors, so a loop ends up here instead of hanging.
+fn walk_warning(error: &walkdir::Error) -> String {
+    let path = error.path().unwrap_or(Path::new("")).display();
+    match error.loop_ancestor() {
+        Some(ancestor) => format!("Skipped {}: symlink loop back to {}", path, ancestor.display()),
+        None => format!("Skipped {}: {}", path, error),
+    }
+}
+
+/// Compile glob patterns into one set
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    Ok(builder.build()?)
+}
+
+/// Check whether an entry's file name starts with a dot
+fn is_hidden(entry: &walkdir::DirEntry) -> bool {
+    entry.file_name().to_str().is_some_and(|name| name.starts_with('.'))
 }
 
 /// Extract family name and optional suffix from a filename
 ///
-/// Examples:
+/// Examples (with the default suffix list):
 /// - "abi_base" -> ("abi_base", None)
 /// - "abi_base_kcc" -> ("abi_base", Some("kcc"))
 /// - "achievements_ep1" -> ("achievements", Some("ep1"))
 /// - "ai_abilities_cond_str" -> ("ai_abilities_cond", Some("str"))
-fn extract_family_info(file_name: &str) -> (String, Option<String>) {
-    // Known DLC/variant suffixes - these indicate a variant file
-    const KNOWN_SUFFIXES: &[&str] = &[
-        "drk", "ep1", "gib", "kcc", "lel", "mem", "shale", "str", "val", "vala", "toe", "hrm",
-        "ibmoobs", "gxa",
-    ];
-
+fn extract_family_info(file_name: &str, known_suffixes: &[String]) -> (String, Option<String>) {
     // Try to find a known suffix at the end
-    for suffix in KNOWN_SUFFIXES {
+    for suffix in known_suffixes {
         let suffix_pattern = format!("_{}", suffix);
         if file_name.ends_with(&suffix_pattern) {
             let base = &file_name[..file_name.len() - suffix_pattern.len()];
@@ -167,24 +693,30 @@ fn looks_like_variant(file_name: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::merger::merge_family;
+    use crate::test_util::scratch_dir;
+
+    fn default_suffixes() -> Vec<String> {
+        ScanOptions::default().known_suffixes
+    }
 
     #[test]
     fn test_extract_family_base() {
-        let (family, suffix) = extract_family_info("abi_base");
+        let (family, suffix) = extract_family_info("abi_base", &default_suffixes());
         assert_eq!(family, "abi_base");
         assert_eq!(suffix, None);
     }
 
     #[test]
     fn test_extract_family_with_suffix() {
-        let (family, suffix) = extract_family_info("abi_base_kcc");
+        let (family, suffix) = extract_family_info("abi_base_kcc", &default_suffixes());
         assert_eq!(family, "abi_base");
         assert_eq!(suffix, Some("kcc".to_string()));
     }
 
     #[test]
     fn test_extract_family_achievements() {
-        let (family, suffix) = extract_family_info("achievements_ep1");
+        let (family, suffix) = extract_family_info("achievements_ep1", &default_suffixes());
         assert_eq!(family, "achievements");
         assert_eq!(suffix, Some("ep1".to_string()));
     }
@@ -192,7 +724,7 @@ mod tests {
     #[test]
     fn test_extract_family_unknown_suffix() {
         // Unknown suffix should be treated as part of the base name
-        let (family, suffix) = extract_family_info("some_table_xyz");
+        let (family, suffix) = extract_family_info("some_table_xyz", &default_suffixes());
         assert_eq!(family, "some_table_xyz");
         assert_eq!(suffix, None);
     }
@@ -204,4 +736,353 @@ mod tests {
         assert!(!looks_like_variant("no_suffix_here_toolong"));
         assert!(!looks_like_variant("single"));
     }
+
+    #[test]
+    fn test_merged_row_count_dedups_ids() {
+        let dir = scratch_dir("row-count");
+        fs::write(dir.join("items.csv"), "ID,Name\n1,a\n2,b\n3,c\n").unwrap();
+        fs::write(dir.join("items_ep1.csv"), "ID,Name\n2,B\n3,C\n4,d\n").unwrap();
+
+        let result = scan_directory(&[&dir]).unwrap();
+        let family = result.find_family("items").unwrap();
+
+        assert_eq!(family.merged_row_count().unwrap(), 4);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_validate_shape_flags_missing_and_extra_columns() {
+        let dir = scratch_dir("shape");
+        fs::write(dir.join("items.csv"), "ID,Name,Cost\n1,a,5\n").unwrap();
+        fs::write(dir.join("items_ep1.csv"), "ID,Name\n2,b\n").unwrap();
+        fs::write(dir.join("items_kcc.csv"), "ID,Name,Cost,Weight\n3,c,1,2\n").unwrap();
+        fs::write(dir.join("spells.csv"), "ID,Name\n1,fire\n").unwrap();
+        fs::write(dir.join("spells_ep1.csv"), "ID,Name\n2,ice\n").unwrap();
+
+        let result = scan_directory(&[&dir]).unwrap();
+        let parse = ParseOptions::default();
+        let report = result.find_family("items").unwrap().validate_shape(&parse).unwrap();
+
+        assert!(!report.is_consistent());
+        assert_eq!(
+            report.missing,
+            vec![MissingColumn {
+                column: "Cost".to_string(),
+                members: vec![dir.join("items_ep1.csv")],
+            }]
+        );
+        assert_eq!(
+            report.extra,
+            vec![ExtraColumns {
+                member: dir.join("items_kcc.csv"),
+                columns: vec!["Weight".to_string()],
+            }]
+        );
+
+        let report = result.find_family("spells").unwrap().validate_shape(&parse).unwrap();
+        assert!(report.is_consistent());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_search_reports_match_locations() {
+        let family = |name: &str, members: usize| Family {
+            name: name.to_string(),
+            members: (0..members)
+                .map(|i| FamilyMember {
+                    path: PathBuf::from(format!("{}_{}.csv", name, i)),
+                    suffix: None,
+                    modified: None,
+                    contents: None,
+                })
+                .collect(),
+        };
+        let result = ScanResult {
+            roots: Vec::new(),
+            families: vec![family("ABI_base", 2), family("items", 1), family("Über_abi", 1)],
+            total_files: 4,
+            warnings: Vec::new(),
+        };
+
+        let hits = result.search("abi");
+        let found: Vec<_> = hits
+            .iter()
+            .map(|h| (h.family.name.as_str(), h.family.members.len(), h.offset, h.matched()))
+            .collect();
+        assert_eq!(found, vec![("ABI_base", 2, 0, "ABI"), ("Über_abi", 1, 6, "abi")]);
+
+        assert_eq!(result.search("ÜBER")[0].matched(), "Über");
+        assert_eq!(result.search("").len(), 3);
+        assert!(result.search("missing").is_empty());
+    }
+
+    #[test]
+    fn test_extract_family_custom_suffixes() {
+        let suffixes = vec!["awakening".to_string(), "dlc3".to_string()];
+
+        let (family, suffix) = extract_family_info("abi_base_awakening", &suffixes);
+        assert_eq!(family, "abi_base");
+        assert_eq!(suffix, Some("awakening".to_string()));
+
+        // "str" is no longer a known suffix, so it stays part of the name
+        let (family, suffix) = extract_family_info("ai_abilities_cond_str", &suffixes);
+        assert_eq!(family, "ai_abilities_cond_str");
+        assert_eq!(suffix, None);
+    }
+
+    #[test]
+    fn test_scan_options_partial_json_keeps_defaults() {
+        let options: ScanOptions = serde_json::from_str("{}").unwrap();
+        assert_eq!(options.known_suffixes, default_suffixes());
+
+        let options: ScanOptions = serde_json::from_str(r#"{"known_suffixes": ["dlc3"]}"#).unwrap();
+        assert_eq!(options.known_suffixes, vec!["dlc3".to_string()]);
+    }
+
+    #[test]
+    fn test_scan_skips_hidden_entries_by_default() {
+        let dir = scratch_dir("hidden");
+        fs::create_dir_all(dir.join(".cache")).unwrap();
+        fs::write(dir.join("items.csv"), "ID,Name\n1,a\n").unwrap();
+        fs::write(dir.join(".hidden.csv"), "ID,Name\n1,a\n").unwrap();
+        fs::write(dir.join(".cache").join("cached.csv"), "ID,Name\n1,a\n").unwrap();
+
+        let result = scan_directory(&[&dir]).unwrap();
+        let names: Vec<&str> = result.families.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["items"]);
+        assert_eq!(result.total_files, 1);
+
+        let options = ScanOptions {
+            include_hidden: true,
+            ..Default::default()
+        };
+        let result = scan_directory_with_options(&[&dir], &options).unwrap();
+        let names: Vec<&str> = result.families.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec![".hidden", "cached", "items"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_scan_include_and_exclude_globs() {
+        let dir = scratch_dir("globs");
+        fs::create_dir_all(dir.join("override").join("spells")).unwrap();
+        fs::create_dir_all(dir.join("override").join("backup")).unwrap();
+        fs::write(dir.join("items.csv"), "ID,Name\n1,a\n").unwrap();
+        fs::write(dir.join("items.bak.csv"), "ID,Name\n1,a\n").unwrap();
+        fs::write(dir.join("override").join("spells").join("spells.csv"), "ID\n1\n").unwrap();
+        fs::write(dir.join("override").join("backup").join("old.csv"), "ID\n1\n").unwrap();
+
+        let options = ScanOptions {
+            exclude: vec!["**/*.bak.csv".to_string()],
+            ..Default::default()
+        };
+        let result = scan_directory_with_options(&[&dir], &options).unwrap();
+        assert_eq!(result.family_names(), vec!["items", "old", "spells"]);
+
+        // Excludes win over includes
+        let options = ScanOptions {
+            include: vec!["**/override/**/*.csv".to_string()],
+            exclude: vec!["**/backup/**".to_string()],
+            ..Default::default()
+        };
+        let result = scan_directory_with_options(&[&dir], &options).unwrap();
+        assert_eq!(result.family_names(), vec!["spells"]);
+
+        let options = ScanOptions {
+            include: vec!["[".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            scan_directory_with_options(&[&dir], &options),
+            Err(Error::InvalidGlob(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_scan_max_depth() {
+        let dir = scratch_dir("depth");
+        let nested = dir.join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(dir.join("top.csv"), "ID\n1\n").unwrap();
+        fs::write(dir.join("a").join("middle.csv"), "ID\n1\n").unwrap();
+        fs::write(nested.join("deep.csv"), "ID\n1\n").unwrap();
+
+        let scan = |max_depth| {
+            let options = ScanOptions {
+                max_depth,
+                ..Default::default()
+            };
+            let result = scan_directory_with_options(&[&dir], &options).unwrap();
+            result.families.into_iter().map(|f| f.name).collect::<Vec<_>>()
+        };
+        assert_eq!(scan(None), vec!["deep", "middle", "top"]);
+        assert_eq!(scan(Some(2)), vec!["middle", "top"]);
+        assert_eq!(scan(Some(1)), vec!["top"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_skips_symlink_loops() {
+        let dir = scratch_dir("symlink");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("items.csv"), "ID\n1\n").unwrap();
+        fs::write(dir.join("sub").join("spells.csv"), "ID\n1\n").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("sub").join("loop")).unwrap();
+
+        let result = scan_directory(&[&dir]).unwrap();
+        assert_eq!(result.family_names(), vec!["items", "spells"]);
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains("symlink loop"), "{}", result.warnings[0]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_scan_reports_progress() {
+        let dir = scratch_dir("progress");
+        fs::write(dir.join("items.csv"), "ID\n1\n").unwrap();
+        fs::write(dir.join("items_ep1.csv"), "ID\n2\n").unwrap();
+
+        let mut reports = Vec::new();
+        let result =
+            scan_directory_with_progress(&[&dir], &ScanOptions::default(), |p| reports.push(p))
+                .unwrap();
+        assert_eq!(result.total_files, 2);
+
+        let walk: Vec<usize> = reports
+            .iter()
+            .filter(|p| !p.walk_done)
+            .map(|p| p.discovered)
+            .collect();
+        assert_eq!(walk, vec![1, 2]);
+        assert_eq!(
+            reports.last(),
+            Some(&ScanProgress {
+                discovered: 2,
+                processed: 2,
+                walk_done: true,
+            })
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rescan_rebuilds_only_changed_families() {
+        let dir = scratch_dir("rescan");
+        fs::write(dir.join("items.csv"), "ID,Name\n1,a\n").unwrap();
+        fs::write(dir.join("items_ep1.csv"), "ID,Name\n2,b\n").unwrap();
+        fs::write(dir.join("spells.csv"), "ID,Name\n1,fire\n").unwrap();
+
+        let first = scan_directory(&[&dir]).unwrap();
+        let rescan = first.rescan(&[&dir]).unwrap();
+        assert!(rescan.changed.is_empty());
+
+        // Give the edited file a modification time that surely differs
+        let spells = dir.join("spells.csv");
+        fs::write(&spells, "ID,Name\n1,ice\n").unwrap();
+        let later = SystemTime::now() + std::time::Duration::from_secs(60);
+        fs::File::options().write(true).open(&spells).unwrap().set_modified(later).unwrap();
+
+        let rescan = first.rescan(&[&dir]).unwrap();
+        assert_eq!(rescan.changed, vec!["spells"]);
+        assert_eq!(rescan.result.find_family("items"), first.find_family("items"));
+        assert_eq!(rescan.result.find_family("spells").unwrap().members[0].modified, Some(later));
+
+        // Removed files drop out of their family, and empty families vanish
+        fs::remove_file(dir.join("items_ep1.csv")).unwrap();
+        fs::remove_file(&spells).unwrap();
+        let rescan = rescan.result.rescan(&[&dir]).unwrap();
+        assert_eq!(rescan.changed, vec!["items", "spells"]);
+        assert_eq!(rescan.result.family_names(), vec!["items"]);
+        assert_eq!(rescan.result.find_family("items").unwrap().members.len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_scan_zip_reads_families_from_memory() {
+        use std::io::{Cursor, Write};
+        use zip::write::{SimpleFileOptions, ZipWriter};
+
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let entries = [
+            ("data/abi_base.csv", "ID,Name\n1,fire\n2,ice\n"),
+            ("data/abi_base_kcc.csv", "ID,Name\n2,frost\n"),
+            ("data/readme.txt", "not a table"),
+            (".hidden/items.csv", "ID,Name\n1,sword\n"),
+            ("items.csv", "ID,Name\n1,axe\n"),
+        ];
+        for (name, contents) in entries {
+            writer.start_file(name, SimpleFileOptions::default()).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        let buffer = writer.finish().unwrap().into_inner();
+
+        let archive = Path::new("mod.zip");
+        let result =
+            scan_zip_reader(Cursor::new(&buffer), archive, &ScanOptions::default()).unwrap();
+        assert_eq!(result.roots, vec![archive.to_path_buf()]);
+        assert_eq!(result.family_names(), vec!["abi_base", "items"]);
+        assert_eq!(result.total_files, 3);
+
+        let family = result.find_family("abi_base").unwrap();
+        assert_eq!(family.members[0].path, archive.join("data/abi_base.csv"));
+        assert_eq!(family.members[1].suffix.as_deref(), Some("kcc"));
+        let table = merge_family(family).unwrap();
+        assert_eq!(table.row_count(), 2);
+        assert_eq!(table.find_row(2).unwrap().cells[1].value.as_str(), Some("frost"));
+
+        let options = ScanOptions {
+            include_hidden: true,
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let result = scan_zip_reader(Cursor::new(&buffer), archive, &options).unwrap();
+        assert_eq!(result.family_names(), vec!["items"]);
+        assert_eq!(result.families[0].members.len(), 1);
+
+        let err = scan_zip_reader(Cursor::new(b"not a zip"), archive, &options).unwrap_err();
+        assert!(matches!(err, Error::Archive { .. }));
+    }
+
+    #[test]
+    fn test_scan_zip_limits_entry_size() {
+        use std::io::{Cursor, Write};
+        use zip::write::{SimpleFileOptions, ZipWriter};
+        use zip::CompressionMethod;
+
+        let contents = "ID,Name\n1,fire\n2,ice\n";
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("spells.csv", stored).unwrap();
+        writer.write_all(contents.as_bytes()).unwrap();
+        let mut buffer = writer.finish().unwrap().into_inner();
+        let archive = Path::new("mod.zip");
+
+        // Claim a 2 GiB entry in both the local and the central header; only
+        // the bytes actually there are read
+        for (signature, offset) in [(b"PK\x03\x04", 22), (b"PK\x01\x02", 24)] {
+            let size = buffer.windows(4).position(|w| w == signature).unwrap() + offset;
+            buffer[size..size + 4].copy_from_slice(&0x7fff_ffffu32.to_le_bytes());
+        }
+        let options = ScanOptions::default();
+        let result = scan_zip_reader(Cursor::new(&buffer), archive, &options).unwrap();
+        let member = &result.families[0].members[0];
+        assert_eq!(member.contents.as_deref(), Some(contents.as_bytes()));
+
+        let options = ScanOptions {
+            max_archive_entry_size: 8,
+            ..Default::default()
+        };
+        let err = scan_zip_reader(Cursor::new(&buffer), archive, &options).unwrap_err();
+        assert!(matches!(err, Error::ArchiveEntryTooLarge { limit: 8, .. }), "{}", err);
+    }
 }
@@ -0,0 +1,184 @@
+//! Full-text search over merged cell values
+//!
+//! `search_values` answers "which table holds this value?". It merges the
+//! scanned families one at a time, in name order, and stops as soon as
+//! the hit limit is reached, so a search that finds its answer early
+//! doesn't parse the rest of the tree.
+
+use crate::error::{Error, Result};
+use crate::merger::{merge_family_with_options, MergeOptions};
+use crate::parser::ParseOptions;
+use crate::scanner::{Family, ScanResult};
+use crate::table::CellValue;
+use serde::{Deserialize, Serialize};
+
+/// Options controlling which cells `search_values` looks at
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValueSearchOptions {
+    /// Only search these families (all families if empty)
+    pub families: Vec<String>,
+    /// Only search columns with these names (all columns if empty)
+    pub columns: Vec<String>,
+    /// Match the pattern's case exactly instead of ignoring case
+    pub case_sensitive: bool,
+    /// Stop after this many hits
+    pub limit: Option<usize>,
+    /// How the families' files are parsed
+    pub parse: ParseOptions,
+}
+
+/// A cell whose merged value contains the search pattern
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValueHit {
+    /// Family the cell belongs to
+    pub family: String,
+    /// ID of the cell's row, if the row has one
+    pub row_id: Option<i64>,
+    /// Column name
+    pub column: String,
+    /// The matching value
+    pub value: CellValue,
+}
+
+/// Find merged cells whose text contains `pattern`
+///
+/// Family and column names in `options` match ignoring case. A family that
+/// isn't in the scan is an error; a listed column that a family lacks is
+/// simply not searched there. Empty cells never match.
+pub fn search_values(
+    scan: &ScanResult,
+    pattern: &str,
+    options: &ValueSearchOptions,
+) -> Result<Vec<ValueHit>> {
+    let families: Vec<&Family> = if options.families.is_empty() {
+        scan.families.iter().collect()
+    } else {
+        options
+            .families
+            .iter()
+            .map(|name| {
+                scan.find_family_ci(name)?
+                    .ok_or_else(|| Error::FamilyNotFound(name.clone()))
+            })
+            .collect::<Result<_>>()?
+    };
+
+    let needle = fold_case(pattern, options.case_sensitive);
+    let limit = options.limit.unwrap_or(usize::MAX);
+    let mut hits = Vec::new();
+    let merge = MergeOptions {
+        parse: options.parse.clone(),
+        ..Default::default()
+    };
+
+    for family in families {
+        if hits.len() >= limit {
+            break;
+        }
+        let (table, _) = merge_family_with_options(family, &merge)?;
+
+        let columns: Vec<usize> = table
+            .columns
+            .iter()
+            .filter(|c| {
+                options.columns.is_empty()
+                    || options.columns.iter().any(|name| name.eq_ignore_ascii_case(&c.name))
+            })
+            .map(|c| c.index)
+            .collect();
+
+        for row in &table.rows {
+            for &index in &columns {
+                let Some(cell) = row.cells.get(index) else {
+                    continue;
+                };
+                if cell.value.is_empty() {
+                    continue;
+                }
+                let text = fold_case(&cell.value.to_string_value(), options.case_sensitive);
+                if !text.contains(&needle) {
+                    continue;
+                }
+                hits.push(ValueHit {
+                    family: table.family_name.clone(),
+                    row_id: row.id,
+                    column: table.columns[index].name.clone(),
+                    value: cell.value.clone(),
+                });
+                if hits.len() >= limit {
+                    return Ok(hits);
+                }
+            }
+        }
+    }
+
+    Ok(hits)
+}
+
+fn fold_case(text: &str, case_sensitive: bool) -> String {
+    if case_sensitive {
+        text.to_string()
+    } else {
+        text.to_lowercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::scan_directory;
+    use crate::test_util::scratch_dir;
+    use std::fs;
+
+    #[test]
+    fn test_search_values_finds_cells_across_families() {
+        let dir = scratch_dir("search");
+        fs::write(dir.join("creatures.csv"), "ID,Name,Label\n1,wolf,x\n2,Mabari,mabari_hound\n")
+            .unwrap();
+        fs::write(dir.join("creatures_ep1.csv"), "ID,Name\n3,MABARI war hound\n").unwrap();
+        fs::write(dir.join("items.csv"), "ID,Name\n10,mabari collar\n11,sword\n").unwrap();
+        let scan = scan_directory(&[&dir]).unwrap();
+
+        let hits = search_values(&scan, "mabari", &ValueSearchOptions::default()).unwrap();
+        let found: Vec<_> = hits
+            .iter()
+            .map(|h| (h.family.as_str(), h.row_id, h.column.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("creatures", Some(2), "Name"),
+                ("creatures", Some(2), "Label"),
+                ("creatures", Some(3), "Name"),
+                ("items", Some(10), "Name"),
+            ]
+        );
+        assert_eq!(hits[3].value, CellValue::String("mabari collar".to_string()));
+
+        let options = ValueSearchOptions {
+            families: vec!["CREATURES".to_string()],
+            columns: vec!["name".to_string()],
+            case_sensitive: true,
+            ..Default::default()
+        };
+        let hits = search_values(&scan, "Mabari", &options).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].row_id, Some(2));
+
+        let options = ValueSearchOptions {
+            limit: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(search_values(&scan, "mabari", &options).unwrap().len(), 2);
+
+        let options = ValueSearchOptions {
+            families: vec!["missing".to_string()],
+            ..Default::default()
+        };
+        let err = search_values(&scan, "mabari", &options).unwrap_err();
+        assert!(matches!(err, Error::FamilyNotFound(_)));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+//! Export of merged tables to a SQLite database
+//!
+//! Each family becomes one table named after it. Columns are typed from the
+//! merged values (`INTEGER`, `REAL` or `TEXT`), and a `_source` column lists
+//! the files that provided each row, in merge order. The ID column is the
+//! primary key when every row has an ID.
+
+use crate::error::Result;
+use crate::merger::ResolvedTable;
+use crate::table::{CellValue, ColumnType};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use std::path::Path;
+
+/// Name of the column that records which files provided a row
+pub const SOURCE_COLUMN: &str = "_source";
+
+/// Write merged tables into the SQLite database at `path`
+///
+/// The database is created if needed. A table that already exists under a
+/// family's name is replaced; other tables are left alone.
+pub fn export_sqlite<P: AsRef<Path>>(tables: &[ResolvedTable], path: P) -> Result<()> {
+    let mut conn = Connection::open(path.as_ref())?;
+    let tx = conn.transaction()?;
+    for table in tables {
+        write_table(&tx, table)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Create and fill the table for one family
+fn write_table(conn: &Connection, table: &ResolvedTable) -> Result<()> {
+    let name = quote_ident(&table.family_name);
+    let keyed = !table.rows.is_empty() && table.rows.iter().all(|r| r.id.is_some());
+
+    let mut defs: Vec<String> = table
+        .columns
+        .iter()
+        .map(|col| {
+            let mut def = format!(
+                "{} {}",
+                quote_ident(&col.name),
+                sql_type(table.infer_column_type(col.index))
+            );
+            if keyed && col.index == table.id_column {
+                def.push_str(" PRIMARY KEY");
+            }
+            def
+        })
+        .collect();
+    defs.push(format!("{} TEXT", quote_ident(SOURCE_COLUMN)));
+
+    conn.execute(&format!("DROP TABLE IF EXISTS {}", name), [])?;
+    conn.execute(&format!("CREATE TABLE {} ({})", name, defs.join(", ")), [])?;
+
+    let placeholders = vec!["?"; table.columns.len() + 1].join(", ");
+    let mut insert = conn.prepare(&format!("INSERT INTO {} VALUES ({})", name, placeholders))?;
+
+    for row in &table.rows {
+        let mut values: Vec<Value> = table
+            .columns
+            .iter()
+            .map(|col| {
+                row.cells
+                    .get(col.index)
+                    .map_or(Value::Null, |c| sql_value(&c.value))
+            })
+            .collect();
+        let sources: Vec<String> = table
+            .sources
+            .iter()
+            .filter(|source| row.cells.iter().any(|c| &c.source == *source))
+            .map(|p| p.file_name().unwrap_or_default().to_string_lossy().into_owned())
+            .collect();
+        values.push(Value::Text(sources.join(", ")));
+
+        insert.execute(params_from_iter(values))?;
+    }
+
+    Ok(())
+}
+
+/// Get the SQLite column type for an inferred column type
+fn sql_type(ty: ColumnType) -> &'static str {
+    match ty {
+        ColumnType::Integer | ColumnType::Bool => "INTEGER",
+        ColumnType::Float => "REAL",
+        ColumnType::String | ColumnType::Empty => "TEXT",
+    }
+}
+
+/// Convert a cell value, storing empty cells as NULL
+fn sql_value(value: &CellValue) -> Value {
+    match value {
+        CellValue::Integer(i) => Value::Integer(*i),
+        CellValue::Float(f) => Value::Real(*f),
+        CellValue::Number { value: f, .. } => {
+            value.as_i64().map_or(Value::Real(*f), Value::Integer)
+        }
+        CellValue::String(s) | CellValue::Hex { text: s, .. } => Value::Text(s.clone()),
+        CellValue::Empty => Value::Null,
+    }
+}
+
+/// Quote a table or column name for use in SQL
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merger::merge_tables;
+    use crate::parser::parse_csv_str;
+    use crate::test_util::scratch_dir;
+
+    #[test]
+    fn test_export_sqlite_types_keys_and_sources() {
+        let base = parse_csv_str("ID,Name,Cost\n1,fire,1.5\n2,ice,\n", "spells.csv").unwrap();
+        let variant = parse_csv_str("ID,Name,Cost\n2,frost,3\n", "spells_ep1.csv").unwrap();
+        let spells = merge_tables("spells", vec![base, variant]).unwrap();
+        let unkeyed = parse_csv_str("Label,Value\nfoo,1\n", "labels.csv").unwrap();
+        let labels = merge_tables("labels", vec![unkeyed]).unwrap();
+
+        let dir = scratch_dir("sqlite");
+        let path = dir.join("spells.db");
+        export_sqlite(&[spells, labels], &path).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let rows: Vec<(i64, String, Option<f64>, String)> = conn
+            .prepare("SELECT ID, Name, Cost, _source FROM spells ORDER BY ID")
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                (1, "fire".to_string(), Some(1.5), "spells.csv".to_string()),
+                (2, "frost".to_string(), Some(3.0), "spells_ep1.csv".to_string()),
+            ]
+        );
+
+        let schema: String = conn
+            .query_row("SELECT sql FROM sqlite_master WHERE name = 'spells'", [], |r| r.get(0))
+            .unwrap();
+        assert!(schema.contains("\"ID\" INTEGER PRIMARY KEY"));
+        assert!(schema.contains("\"Cost\" REAL"));
+
+        let schema: String = conn
+            .query_row("SELECT sql FROM sqlite_master WHERE name = 'labels'", [], |r| r.get(0))
+            .unwrap();
+        assert!(!schema.contains("PRIMARY KEY"));
+
+        drop(conn);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
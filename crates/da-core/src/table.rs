@@ -1,5 +1,6 @@
 //! Core table types for representing 2DA data
 
+use crate::error::{Error, Result};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -12,6 +13,49 @@ pub struct Table {
     pub rows: Vec<Row>,
     /// Source file path
     pub source_path: PathBuf,
+    /// Index of the column the row IDs were read from
+    #[serde(default)]
+    pub id_column: usize,
+    /// Comment lines found above the header, without line terminators
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub leading_comments: Vec<String>,
+    /// Inferred type of each column, in column order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub column_types: Vec<ColumnType>,
+    /// Problems in kept rows that didn't stop the parse, such as repeated
+    /// IDs or extra cells
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<ParseWarning>,
+    /// Malformed rows left out under `RowErrorPolicy::Skip`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parse_warnings: Vec<ParseWarning>,
+}
+
+/// A problem with one row that didn't fail the parse
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseWarning {
+    /// What went wrong with the row
+    #[serde(default)]
+    pub kind: ParseWarningKind,
+    /// Line the row starts on, if known
+    pub line: Option<u64>,
+    /// The row's fields as read, joined by commas
+    pub raw: String,
+    /// Description of the problem, in English
+    pub message: String,
+}
+
+/// What a `ParseWarning` is about
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseWarningKind {
+    /// A malformed row was left out under `RowErrorPolicy::Skip`
+    #[default]
+    SkippedRow,
+    /// The row repeats an earlier row's ID
+    DuplicateId,
+    /// The row has more cells than the header and was truncated
+    ExtraCells,
 }
 
 impl Table {
@@ -21,6 +65,11 @@ impl Table {
             columns: Vec::new(),
             rows: Vec::new(),
             source_path,
+            id_column: 0,
+            leading_comments: Vec::new(),
+            column_types: Vec::new(),
+            warnings: Vec::new(),
+            parse_warnings: Vec::new(),
         }
     }
 
@@ -39,14 +88,42 @@ impl Table {
         self.columns.iter().find(|c| c.name == name)
     }
 
-    /// Find a row by ID (assumes first column is ID)
+    /// Find a column by name, ignoring case if there is no exact match
+    pub fn find_column_ci(&self, name: &str) -> Result<Option<&Column>> {
+        find_ignoring_case(&self.columns, name, |c| &c.name)
+    }
+
+    /// Find a row by ID
     pub fn find_row(&self, id: i64) -> Option<&Row> {
         self.rows.iter().find(|r| r.id == Some(id))
     }
+
+    /// Infer the type of every column from its values
+    ///
+    /// Uses the same rule as `ColumnType::infer`: one stray string makes the
+    /// whole column `String`, rather than being outvoted by the other rows.
+    pub fn infer_column_types(&self) -> Vec<ColumnType> {
+        self.columns
+            .iter()
+            .map(|col| ColumnType::infer(self.rows.iter().filter_map(|r| r.get(col.index))))
+            .collect()
+    }
+
+    /// Infer column types like `infer_column_types`, but report integer
+    /// columns holding only `0` and `1` as `Bool`
+    pub fn infer_column_types_with_bools(&self) -> Vec<ColumnType> {
+        self.columns
+            .iter()
+            .map(|col| {
+                let values = || self.rows.iter().filter_map(|r| r.get(col.index));
+                ColumnType::infer(values()).narrow_to_bool(values())
+            })
+            .collect()
+    }
 }
 
 /// A column definition
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Column {
     /// Column name (e.g., "ID" or "0xC4FDA9ED")
     pub name: String,
@@ -62,9 +139,9 @@ impl Column {
 }
 
 /// A row of data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Row {
-    /// Row ID (from first column, if it's numeric)
+    /// Row ID (from the ID column, if it's numeric)
     pub id: Option<i64>,
     /// Cell values for each column
     pub cells: Vec<CellValue>,
@@ -82,8 +159,82 @@ impl Row {
     }
 }
 
+/// The value type of a column, used when the schema is known
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    /// Integer values
+    Integer,
+    /// Floating-point values
+    Float,
+    /// String values
+    String,
+    /// No non-empty values to infer from
+    Empty,
+    /// Integer values that are all `0` or `1`, inferred only when
+    /// `ParseOptions::bool_columns` is set. The cells stay integers
+    Bool,
+}
+
+impl ColumnType {
+    /// Infer the narrowest type that holds every non-empty value
+    ///
+    /// Integers widen to `Float` when floats are present, and any string
+    /// or hex value makes the column `String`, so hex is written back as
+    /// text. A `Number` counts as an integer if its text is one. Returns
+    /// `Empty` if every value is empty.
+    pub fn infer<'a>(values: impl IntoIterator<Item = &'a CellValue>) -> ColumnType {
+        let mut ty = ColumnType::Empty;
+        for value in values {
+            match value {
+                CellValue::Empty => {}
+                CellValue::Integer(_) | CellValue::Number { .. } if value.as_i64().is_some() => {
+                    if ty == ColumnType::Empty {
+                        ty = ColumnType::Integer;
+                    }
+                }
+                CellValue::Integer(_) | CellValue::Float(_) | CellValue::Number { .. } => {
+                    ty = ColumnType::Float
+                }
+                CellValue::String(_) | CellValue::Hex { .. } => return ColumnType::String,
+            }
+        }
+        ty
+    }
+
+    /// Narrow an `Integer` type to `Bool` if every value is `0` or `1`
+    ///
+    /// Other types are returned unchanged.
+    pub fn narrow_to_bool<'a>(self, values: impl IntoIterator<Item = &'a CellValue>) -> Self {
+        let flags = |value: &CellValue| value.is_empty() || matches!(value.as_i64(), Some(0 | 1));
+        if self == ColumnType::Integer && values.into_iter().all(flags) {
+            ColumnType::Bool
+        } else {
+            self
+        }
+    }
+
+    /// Check whether this is a numeric type
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, ColumnType::Integer | ColumnType::Float | ColumnType::Bool)
+    }
+
+    /// Check whether a value can be stored in a column of this type
+    ///
+    /// Empty values fit every type and integers fit float columns.
+    /// `String` and `Empty` columns accept anything.
+    pub fn accepts(&self, value: &CellValue) -> bool {
+        match self {
+            ColumnType::String | ColumnType::Empty => true,
+            ColumnType::Integer => value.is_empty() || value.as_i64().is_some(),
+            ColumnType::Bool => value.is_empty() || matches!(value.as_i64(), Some(0 | 1)),
+            ColumnType::Float => value.is_empty() || value.as_f64().is_some(),
+        }
+    }
+}
+
 /// A cell value with type detection
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum CellValue {
     /// Integer value
     Integer(i64),
@@ -91,12 +242,35 @@ pub enum CellValue {
     Float(f64),
     /// String value
     String(String),
+    /// Hexadecimal value such as `0xC4FDA9ED`, recognized when
+    /// `ParseOptions::hex_values` is set
+    Hex {
+        /// The number
+        value: u64,
+        /// The value as written, keeping its prefix, case and width
+        text: String,
+    },
+    /// Number written some other way than this crate writes it, such as
+    /// `1.50`, `007` or `1e5`, kept as written so it is written back the
+    /// same way. It's an integer if its text parses as one.
+    Number {
+        /// The number
+        value: f64,
+        /// The value as written
+        text: String,
+    },
     /// Empty/null cell
     Empty,
 }
 
 impl CellValue {
     /// Parse a string into a CellValue, detecting the type
+    ///
+    /// Tokens that would parse to an infinite or NaN float, such as `inf`,
+    /// `NaN` or `1e400`, are kept as strings so they are written back as
+    /// they were read. So are whole numbers too big for an `i64`, which a
+    /// float would round. Numbers that `to_string_value` would spell
+    /// differently are kept as `Number` with their text.
     pub fn parse(s: &str) -> Self {
         let trimmed = s.trim();
 
@@ -106,62 +280,329 @@ impl CellValue {
 
         // Try parsing as integer first
         if let Ok(i) = trimmed.parse::<i64>() {
-            return CellValue::Integer(i);
+            return CellValue::Integer(i).spelled(trimmed);
+        }
+        let digits = trimmed.strip_prefix(['+', '-']).unwrap_or(trimmed);
+        if digits.bytes().all(|b| b.is_ascii_digit()) {
+            return CellValue::String(trimmed.to_string());
         }
 
         // Try parsing as float
-        if let Ok(f) = trimmed.parse::<f64>() {
-            return CellValue::Float(f);
+        if let Some(f) = parse_finite_float(trimmed) {
+            return CellValue::Float(f).spelled(trimmed);
         }
 
         // Otherwise, keep as string
         CellValue::String(trimmed.to_string())
     }
 
+    /// Parse a `0x`-prefixed hexadecimal string of up to 16 digits
+    pub fn parse_hex(s: &str) -> Option<Self> {
+        let trimmed = s.trim();
+        let digits = trimmed
+            .strip_prefix("0x")
+            .or_else(|| trimmed.strip_prefix("0X"))?;
+        if digits.is_empty() || digits.len() > 16 || !digits.bytes().all(|b| b.is_ascii_hexdigit())
+        {
+            return None;
+        }
+        Some(CellValue::Hex {
+            value: u64::from_str_radix(digits, 16).ok()?,
+            text: trimmed.to_string(),
+        })
+    }
+
+    /// Parse a string into a CellValue of a known column type
+    ///
+    /// Blank input is `Empty` for every type. Input that can't be coerced
+    /// to the requested type falls back to `String`, so no data is lost.
+    /// An `Empty` column type has nothing to go by and detects the type.
+    pub fn parse_typed(s: &str, ty: ColumnType) -> Self {
+        let trimmed = s.trim();
+
+        if trimmed.is_empty() {
+            return CellValue::Empty;
+        }
+
+        match ty {
+            ColumnType::Integer | ColumnType::Bool => trimmed
+                .parse::<i64>()
+                .map(|i| CellValue::Integer(i).spelled(trimmed))
+                .unwrap_or_else(|_| CellValue::String(trimmed.to_string())),
+            ColumnType::Float => parse_finite_float(trimmed)
+                .map(|f| CellValue::Float(f).spelled(trimmed))
+                .unwrap_or_else(|| CellValue::String(trimmed.to_string())),
+            ColumnType::String => CellValue::String(trimmed.to_string()),
+            ColumnType::Empty => CellValue::parse(trimmed),
+        }
+    }
+
+    /// Keep `text` with a parsed number that wouldn't be written back as it
+    fn spelled(self, text: &str) -> Self {
+        match self.as_f64() {
+            Some(value) if self.to_string_value() != text => CellValue::Number {
+                value,
+                text: text.to_string(),
+            },
+            _ => self,
+        }
+    }
+
+    /// The number a `Number` cell holds, as an `Integer` or `Float`
+    fn unspelled(&self) -> CellValue {
+        match self {
+            CellValue::Number { value, .. } => {
+                self.as_i64().map_or(CellValue::Float(*value), CellValue::Integer)
+            }
+            _ => self.clone(),
+        }
+    }
+
     /// Check if the cell is empty
     pub fn is_empty(&self) -> bool {
         matches!(self, CellValue::Empty)
     }
 
+    /// Get the value of an integer cell
+    ///
+    /// Floats aren't truncated, so `Float(2.0)` gives `None`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            CellValue::Integer(i) => Some(*i),
+            CellValue::Number { text, .. } => text.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Get the text of a string cell
+    ///
+    /// Numbers, hex values and empty cells give `None`; use
+    /// `to_string_value` for their text.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            CellValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Get the numeric value of an integer or float cell
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            CellValue::Integer(i) => Some(*i as f64),
+            CellValue::Float(f) | CellValue::Number { value: f, .. } => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Get the number held by a hex cell
+    pub fn as_hex(&self) -> Option<u64> {
+        match self {
+            CellValue::Hex { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+
     /// Convert to a display string
+    ///
+    /// Floats always keep a decimal point, so `1.0` doesn't come back as
+    /// the integer `1`, and `-0.0` keeps its sign. Numbers read with
+    /// another spelling, such as `1.50`, come back as they were read.
     pub fn to_string_value(&self) -> String {
         match self {
             CellValue::Integer(i) => i.to_string(),
-            CellValue::Float(f) => f.to_string(),
-            CellValue::String(s) => s.clone(),
+            CellValue::Float(f) => format_float(*f),
+            CellValue::String(s)
+            | CellValue::Hex { text: s, .. }
+            | CellValue::Number { text: s, .. } => s.clone(),
             CellValue::Empty => String::new(),
         }
     }
 }
 
+/// Numbers compare by value, so `1.50` equals `1.5` and `007` equals `7`
+impl PartialEq for CellValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (CellValue::Number { .. }, _) => self.unspelled() == *other,
+            (_, CellValue::Number { .. }) => *self == other.unspelled(),
+            (CellValue::Integer(a), CellValue::Integer(b)) => a == b,
+            (CellValue::Float(a), CellValue::Float(b)) => a == b,
+            (CellValue::String(a), CellValue::String(b)) => a == b,
+            (
+                CellValue::Hex { value: a, text: x },
+                CellValue::Hex { value: b, text: y },
+            ) => a == b && x == y,
+            (CellValue::Empty, CellValue::Empty) => true,
+            _ => false,
+        }
+    }
+}
+
 impl std::fmt::Display for CellValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             CellValue::Integer(i) => write!(f, "{}", i),
-            CellValue::Float(fl) => write!(f, "{}", fl),
-            CellValue::String(s) => write!(f, "{}", s),
+            CellValue::Float(fl) => write!(f, "{}", format_float(*fl)),
+            CellValue::String(s)
+            | CellValue::Hex { text: s, .. }
+            | CellValue::Number { text: s, .. } => write!(f, "{}", s),
             CellValue::Empty => write!(f, ""),
         }
     }
 }
 
+/// Parse a float, rejecting tokens that give infinity or NaN
+///
+/// Rust accepts `inf`, `infinity` and `nan` in any case, and overflows
+/// `1e400` to infinity; none of them would be written back as read.
+fn parse_finite_float(text: &str) -> Option<f64> {
+    text.parse::<f64>().ok().filter(|f| f.is_finite())
+}
+
+/// Format a float with at least one decimal place, so it reads back as a float
+fn format_float(value: f64) -> String {
+    let text = value.to_string();
+    if value.is_finite() && !text.contains('.') {
+        format!("{}.0", text)
+    } else {
+        text
+    }
+}
+
+/// Find the item named `name`, falling back to a case-insensitive match
+///
+/// An exact match always wins. Fails with `Error::AmbiguousName` when
+/// there is no exact match and several items match ignoring case.
+pub(crate) fn find_ignoring_case<'a, T>(
+    items: &'a [T],
+    name: &str,
+    key: impl Fn(&T) -> &str,
+) -> Result<Option<&'a T>> {
+    if let Some(item) = items.iter().find(|item| key(item) == name) {
+        return Ok(Some(item));
+    }
+
+    let lower = name.to_lowercase();
+    let matches: Vec<&T> = items
+        .iter()
+        .filter(|item| key(item).to_lowercase() == lower)
+        .collect();
+    match matches.as_slice() {
+        [] => Ok(None),
+        [item] => Ok(Some(item)),
+        _ => Err(Error::AmbiguousName {
+            name: name.to_string(),
+            candidates: matches.iter().map(|item| key(item).to_string()).collect(),
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_find_column_ci() {
+        let mut table = Table::new(PathBuf::from("test.csv"));
+        table.columns = vec![
+            Column::new("ID".to_string(), 0),
+            Column::new("Name".to_string(), 1),
+            Column::new("Icon".to_string(), 2),
+            Column::new("ICON".to_string(), 3),
+        ];
+
+        assert_eq!(table.find_column_ci("name").unwrap().map(|c| c.index), Some(1));
+        assert_eq!(table.find_column_ci("ICON").unwrap().map(|c| c.index), Some(3));
+        assert!(table.find_column_ci("cost").unwrap().is_none());
+
+        let err = table.find_column_ci("icon").unwrap_err();
+        assert_eq!(err.to_string(), "'icon' is ambiguous; it matches Icon, ICON");
+    }
+
     #[test]
     fn test_cell_value_parse_integer() {
         assert_eq!(CellValue::parse("42"), CellValue::Integer(42));
         assert_eq!(CellValue::parse("-123"), CellValue::Integer(-123));
         assert_eq!(CellValue::parse("0"), CellValue::Integer(0));
+
+        // Too big for an i64; a float would round it
+        let big = "-12345678901234567890";
+        assert_eq!(CellValue::parse(big), CellValue::String(big.to_string()));
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_cell_value_parse_float() {
         assert_eq!(CellValue::parse("3.14"), CellValue::Float(3.14));
         assert_eq!(CellValue::parse("-2.5"), CellValue::Float(-2.5));
     }
 
+    #[test]
+    fn test_float_text_round_trip() {
+        for (text, value, written) in [
+            ("1.0", CellValue::Float(1.0), "1.0"),
+            ("1", CellValue::Integer(1), "1"),
+            ("-0.0", CellValue::Float(-0.0), "-0.0"),
+            ("0.5", CellValue::Float(0.5), "0.5"),
+        ] {
+            let parsed = CellValue::parse(text);
+            assert_eq!(parsed, value, "{}", text);
+            assert_eq!(parsed.to_string_value(), written, "{}", text);
+            assert_eq!(parsed.to_string(), written, "{}", text);
+            assert_eq!(CellValue::parse(written), value, "{}", text);
+        }
+
+        // Other spellings are kept, and still compare by value
+        for (text, value) in [
+            ("1.50", CellValue::Float(1.5)),
+            ("007", CellValue::Integer(7)),
+            ("+3", CellValue::Integer(3)),
+            ("1e3", CellValue::Float(1000.0)),
+            ("-2.5E-3", CellValue::Float(-0.0025)),
+            (".5", CellValue::Float(0.5)),
+        ] {
+            let parsed = CellValue::parse(text);
+            assert!(matches!(parsed, CellValue::Number { .. }), "{}", text);
+            assert_eq!(parsed, value, "{}", text);
+            assert_eq!(parsed.to_string_value(), text);
+            assert_eq!(parsed.to_string(), text);
+        }
+        assert_eq!(CellValue::parse("007").as_i64(), Some(7));
+        assert_eq!(CellValue::parse("1.50").as_i64(), None);
+        assert_ne!(CellValue::parse("1.0"), CellValue::parse("01"));
+        assert_eq!(CellValue::Float(f64::INFINITY).to_string_value(), "inf");
+
+        // -0.0 keeps its sign rather than comparing equal to 0.0
+        let zero = CellValue::parse("-0.0").as_f64().unwrap();
+        assert!(zero == 0.0 && zero.is_sign_negative());
+
+        for text in ["inf", "-inf", "Infinity", "nan", "NaN", "1e400"] {
+            let string = CellValue::String(text.to_string());
+            assert_eq!(CellValue::parse(text), string);
+            assert_eq!(CellValue::parse_typed(text, ColumnType::Float), string);
+            assert_eq!(CellValue::parse(text).to_string_value(), text);
+        }
+    }
+
+    #[test]
+    fn test_typed_accessors() {
+        let hex = CellValue::parse_hex("0x1F").unwrap();
+        let cases = [
+            (CellValue::Integer(-3), Some(-3), Some(-3.0), None),
+            (CellValue::Float(2.5), None, Some(2.5), None),
+            (CellValue::Float(2.0), None, Some(2.0), None),
+            (CellValue::String("fire".to_string()), None, None, Some("fire")),
+            (CellValue::String("12".to_string()), None, None, Some("12")),
+            (hex, None, None, None),
+            (CellValue::Empty, None, None, None),
+        ];
+        for (value, int, float, text) in cases {
+            assert_eq!(value.as_i64(), int, "{:?}", value);
+            assert_eq!(value.as_f64(), float, "{:?}", value);
+            assert_eq!(value.as_str(), text, "{:?}", value);
+        }
+    }
+
     #[test]
     fn test_cell_value_parse_string() {
         assert_eq!(
@@ -186,4 +627,76 @@ mod tests {
         assert!(!CellValue::Integer(0).is_empty());
         assert!(!CellValue::String("".to_string()).is_empty());
     }
+
+    #[test]
+    fn test_cell_value_parse_typed_integer() {
+        assert_eq!(CellValue::parse_typed("42", ColumnType::Integer), CellValue::Integer(42));
+        assert_eq!(CellValue::parse_typed("", ColumnType::Integer), CellValue::Empty);
+        assert_eq!(
+            CellValue::parse_typed("2.5", ColumnType::Integer),
+            CellValue::String("2.5".to_string())
+        );
+    }
+
+    #[test]
+    fn test_cell_value_parse_typed_float() {
+        let one = CellValue::parse_typed("1", ColumnType::Float);
+        assert_eq!((one.as_f64(), one.to_string_value().as_str()), (Some(1.0), "1"));
+        assert_eq!(CellValue::parse_typed("-2.5", ColumnType::Float), CellValue::Float(-2.5));
+        assert_eq!(CellValue::parse_typed("  ", ColumnType::Float), CellValue::Empty);
+        assert_eq!(
+            CellValue::parse_typed("abc", ColumnType::Float),
+            CellValue::String("abc".to_string())
+        );
+    }
+
+    #[test]
+    fn test_cell_value_parse_typed_string() {
+        assert_eq!(
+            CellValue::parse_typed("1", ColumnType::String),
+            CellValue::String("1".to_string())
+        );
+        assert_eq!(CellValue::parse_typed("", ColumnType::String), CellValue::Empty);
+    }
+
+    #[test]
+    fn test_column_type_infer() {
+        let values = [CellValue::Empty, CellValue::Integer(1), CellValue::Float(0.5)];
+        assert_eq!(ColumnType::infer(&values), ColumnType::Float);
+
+        let values = [CellValue::Integer(1), CellValue::String("x".to_string())];
+        assert_eq!(ColumnType::infer(&values), ColumnType::String);
+
+        let values = [CellValue::parse("007"), CellValue::Integer(8)];
+        assert_eq!(ColumnType::infer(&values), ColumnType::Integer);
+        let values = [CellValue::parse("007"), CellValue::parse("1.50")];
+        assert_eq!(ColumnType::infer(&values), ColumnType::Float);
+
+        assert_eq!(ColumnType::infer(&[CellValue::Empty]), ColumnType::Empty);
+    }
+
+    #[test]
+    fn test_infer_column_types() {
+        let mut table = Table::new(PathBuf::from("test.csv"));
+        for (i, name) in ["ID", "Label", "Unused"].iter().enumerate() {
+            table.columns.push(Column::new(name.to_string(), i));
+        }
+        table.rows.push(Row::new(
+            Some(1),
+            vec![CellValue::Integer(1), CellValue::Integer(10), CellValue::Empty],
+        ));
+        table.rows.push(Row::new(
+            Some(2),
+            vec![CellValue::Integer(2), CellValue::String("n/a".to_string()), CellValue::Empty],
+        ));
+        table.rows.push(Row::new(
+            Some(3),
+            vec![CellValue::Integer(3), CellValue::Integer(30), CellValue::Empty],
+        ));
+
+        assert_eq!(
+            table.infer_column_types(),
+            vec![ColumnType::Integer, ColumnType::String, ColumnType::Empty]
+        );
+    }
 }
@@ -0,0 +1,18 @@
+//! Helpers shared by the unit tests
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Create an empty scratch directory for one test
+///
+/// The name carries the process ID and a counter, so tests running in
+/// parallel never share a directory, even when they pass the same name.
+pub(crate) fn scratch_dir(name: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    let dir = std::env::temp_dir().join(format!("da-core-{}-{}-{}", name, std::process::id(), n));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
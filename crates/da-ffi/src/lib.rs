@@ -4,14 +4,16 @@
 
 use std::cell::RefCell;
 use std::ffi::{CStr, CString};
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_void};
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::path::PathBuf;
 use std::ptr;
+use std::sync::Arc;
 
 use da_core::{
-    merge_family, scan_directory, CellValue, Family, HistoryEntry, HistoryFile, PatchFile,
-    ResolvedTable,
+    merge_family, merge_family_with_progress, scan_directory_with_progress, search_families,
+    validate_patch, CellValue, CompareOp, Family, HistoryEntry, HistoryFile, MergeOptions,
+    PatchFile, ResolvedCell, ResolvedTable, ScanOptions,
 };
 
 // Thread-local error storage
@@ -36,10 +38,22 @@ fn clear_error() {
 // ============================================================================
 
 /// Opaque handle to a scan result
+///
+/// The families are immutable once the scan finishes, so a handle may be
+/// read from any thread, including concurrently. Each handle holds a
+/// reference to the shared data; `ffi_scan_clone` makes another handle
+/// (e.g. to pass from a worker thread to the UI thread) and every handle
+/// is released separately with `ffi_scan_free`.
 pub struct FfiScanResult {
-    families: Vec<Family>,
+    families: Arc<Vec<Family>>,
 }
 
+// Handles are shared across threads by the C++ side
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<FfiScanResult>();
+};
+
 /// Opaque handle to a resolved table
 pub struct FfiResolvedTable {
     inner: ResolvedTable,
@@ -75,6 +89,14 @@ pub struct FfiFamilyInfo {
     pub member_count: usize,
 }
 
+#[repr(C)]
+pub struct FfiSearchHit {
+    pub name: *mut c_char,
+    pub member_count: usize,
+    pub match_offset: usize,
+    pub match_len: usize,
+}
+
 #[repr(C)]
 pub struct FfiMemberInfo {
     pub path: *mut c_char,
@@ -96,6 +118,19 @@ pub struct FfiResolvedCell {
     pub source_path: *mut c_char,
 }
 
+#[repr(C)]
+pub struct FfiCellSource {
+    pub path: *mut c_char,
+    pub is_winner: i32,
+}
+
+#[repr(C)]
+pub struct FfiCellExplanation {
+    pub winner_path: *mut c_char,
+    pub sources: *mut FfiCellSource,
+    pub source_count: usize,
+}
+
 #[repr(C)]
 pub struct FfiColumnInfo {
     pub name: *mut c_char,
@@ -128,6 +163,57 @@ fn from_c_str(ptr: *const c_char) -> Option<String> {
     }
 }
 
+/// Convert a resolved cell to its FFI representation
+fn to_ffi_cell(cell: &ResolvedCell) -> FfiResolvedCell {
+    let value = match &cell.value {
+        CellValue::Empty => FfiCellValue {
+            value_type: 0,
+            int_value: 0,
+            float_value: 0.0,
+            string_value: ptr::null_mut(),
+        },
+        CellValue::Integer(i) => FfiCellValue {
+            value_type: 1,
+            int_value: *i,
+            float_value: 0.0,
+            string_value: ptr::null_mut(),
+        },
+        CellValue::Float(f) => FfiCellValue {
+            value_type: 2,
+            int_value: 0,
+            float_value: *f,
+            string_value: ptr::null_mut(),
+        },
+        // Numbers kept with their spelling are passed by value
+        number @ CellValue::Number { value, .. } => match number.as_i64() {
+            Some(i) => FfiCellValue {
+                value_type: 1,
+                int_value: i,
+                float_value: 0.0,
+                string_value: ptr::null_mut(),
+            },
+            None => FfiCellValue {
+                value_type: 2,
+                int_value: 0,
+                float_value: *value,
+                string_value: ptr::null_mut(),
+            },
+        },
+        // Hex cells are passed as the text they were written as
+        CellValue::String(s) | CellValue::Hex { text: s, .. } => FfiCellValue {
+            value_type: 3,
+            int_value: 0,
+            float_value: 0.0,
+            string_value: to_c_string(s),
+        },
+    };
+
+    FfiResolvedCell {
+        value,
+        source_path: to_c_string(cell.source.to_string_lossy().as_ref()),
+    }
+}
+
 // ============================================================================
 // Error Handling
 // ============================================================================
@@ -153,9 +239,39 @@ pub extern "C" fn ffi_clear_error() {
 // Scanning and Family Operations
 // ============================================================================
 
+/// Scan progress callback: files discovered, files processed, whether the
+/// walk is done (1) or not (0), and the caller's user data
+pub type FfiScanProgressFn = extern "C" fn(usize, usize, i32, *mut c_void);
+
+/// Merge progress callback: path of the file just parsed, files parsed,
+/// total files, and the caller's user data. The path is only valid during
+/// the call.
+pub type FfiMergeProgressFn = extern "C" fn(*const c_char, usize, usize, *mut c_void);
+
 /// Scan a directory for CSV files and group into families
+///
+/// # Safety
+///
+/// `root_path` must be NULL or a NUL-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_scan_directory(root_path: *const c_char) -> *mut FfiScanResult {
+    ffi_scan_directory_with_progress(root_path, None, ptr::null_mut())
+}
+
+/// Scan a directory, calling `callback` (if not null) with progress
+///
+/// The callback runs on the calling thread, with no lock held.
+///
+/// # Safety
+///
+/// `root_path` must be NULL or a NUL-terminated string and `callback` NULL or
+/// safe to call with `user_data` from this thread until the call returns.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_scan_directory_with_progress(
+    root_path: *const c_char,
+    callback: Option<FfiScanProgressFn>,
+    user_data: *mut c_void,
+) -> *mut FfiScanResult {
     clear_error();
 
     let path = match from_c_str(root_path) {
@@ -166,9 +282,15 @@ pub unsafe extern "C" fn ffi_scan_directory(root_path: *const c_char) -> *mut Ff
         }
     };
 
-    match scan_directory(&[PathBuf::from(&path)]) {
+    let progress = |p: da_core::ScanProgress| {
+        if let Some(callback) = callback {
+            callback(p.discovered, p.processed, p.walk_done as i32, user_data);
+        }
+    };
+    match scan_directory_with_progress(&[PathBuf::from(&path)], &ScanOptions::default(), progress)
+    {
         Ok(result) => Box::into_raw(Box::new(FfiScanResult {
-            families: result.families,
+            families: Arc::new(result.families),
         })),
         Err(e) => {
             set_error(&e.to_string());
@@ -178,6 +300,11 @@ pub unsafe extern "C" fn ffi_scan_directory(root_path: *const c_char) -> *mut Ff
 }
 
 /// Get number of families in scan result
+///
+/// # Safety
+///
+/// `result` must be NULL or a scan result from ffi_scan_directory or
+/// ffi_scan_clone that hasn't been freed.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_scan_family_count(result: *const FfiScanResult) -> usize {
     if result.is_null() {
@@ -187,6 +314,11 @@ pub unsafe extern "C" fn ffi_scan_family_count(result: *const FfiScanResult) ->
 }
 
 /// Get family info by index
+///
+/// # Safety
+///
+/// `result` must be NULL or a scan result from ffi_scan_directory or
+/// ffi_scan_clone that hasn't been freed.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_scan_get_family(
     result: *const FfiScanResult,
@@ -196,7 +328,7 @@ pub unsafe extern "C" fn ffi_scan_get_family(
         return ptr::null_mut();
     }
 
-    match (*result).families.get(index) {
+    match (&*result).families.get(index) {
         Some(family) => {
             let info = Box::new(FfiFamilyInfo {
                 name: to_c_string(&family.name),
@@ -209,6 +341,12 @@ pub unsafe extern "C" fn ffi_scan_get_family(
 }
 
 /// Get members of a family by family name
+///
+/// # Safety
+///
+/// `result` must be NULL or a scan result from ffi_scan_directory or
+/// ffi_scan_clone that hasn't been freed, `family_name` NULL or a
+/// NUL-terminated string and `out_count` NULL or valid for writes.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_scan_get_members(
     result: *const FfiScanResult,
@@ -254,6 +392,12 @@ pub unsafe extern "C" fn ffi_scan_get_members(
 }
 
 /// Search families by name pattern (case-insensitive substring)
+///
+/// # Safety
+///
+/// `result` must be NULL or a scan result from ffi_scan_directory or
+/// ffi_scan_clone that hasn't been freed, `pattern` NULL or a NUL-terminated
+/// string and `out_count` NULL or valid for writes.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_search_families(
     result: *const FfiScanResult,
@@ -286,7 +430,79 @@ pub unsafe extern "C" fn ffi_search_families(
     }
 }
 
-/// Free scan result
+/// Search families by name pattern, reporting where each name matched
+///
+/// Offsets and lengths are in bytes of the UTF-8 family name.
+///
+/// # Safety
+///
+/// `result` must be NULL or a scan result from ffi_scan_directory or
+/// ffi_scan_clone that hasn't been freed, `pattern` NULL or a NUL-terminated
+/// string and `out_count` NULL or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_search(
+    result: *const FfiScanResult,
+    pattern: *const c_char,
+    out_count: *mut usize,
+) -> *mut FfiSearchHit {
+    if result.is_null() || pattern.is_null() || out_count.is_null() {
+        return ptr::null_mut();
+    }
+    *out_count = 0;
+
+    let pattern_str = match from_c_str(pattern) {
+        Some(p) => p,
+        None => return ptr::null_mut(),
+    };
+
+    let hits: Vec<FfiSearchHit> = search_families(&(*result).families, &pattern_str)
+        .into_iter()
+        .map(|hit| FfiSearchHit {
+            name: to_c_string(&hit.family.name),
+            member_count: hit.family.members.len(),
+            match_offset: hit.offset,
+            match_len: hit.len,
+        })
+        .collect();
+
+    *out_count = hits.len();
+
+    if hits.is_empty() {
+        ptr::null_mut()
+    } else {
+        let boxed = hits.into_boxed_slice();
+        Box::into_raw(boxed) as *mut FfiSearchHit
+    }
+}
+
+/// Create another handle to the same scan result
+///
+/// The new handle shares the families with `result` without copying them
+/// and stays valid after `result` is freed. Returns NULL if `result` is NULL.
+///
+/// # Safety
+///
+/// `result` must be NULL or a scan result from ffi_scan_directory or
+/// ffi_scan_clone that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_scan_clone(result: *const FfiScanResult) -> *mut FfiScanResult {
+    if result.is_null() {
+        return ptr::null_mut();
+    }
+    Box::into_raw(Box::new(FfiScanResult {
+        families: Arc::clone(&(*result).families),
+    }))
+}
+
+/// Free a scan result handle
+///
+/// Releases this handle's reference; the families are freed together with
+/// the last handle. Each handle must be freed exactly once.
+///
+/// # Safety
+///
+/// `result` must be NULL or a scan result from ffi_scan_directory or
+/// ffi_scan_clone, and must not be used after this call.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_scan_free(result: *mut FfiScanResult) {
     if !result.is_null() {
@@ -299,10 +515,36 @@ pub unsafe extern "C" fn ffi_scan_free(result: *mut FfiScanResult) {
 // ============================================================================
 
 /// Merge a family into a resolved table
+///
+/// # Safety
+///
+/// `scan_result` must be NULL or a scan result from ffi_scan_directory or
+/// ffi_scan_clone that hasn't been freed and `family_name` NULL or a
+/// NUL-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_merge_family(
     scan_result: *const FfiScanResult,
     family_name: *const c_char,
+) -> *mut FfiResolvedTable {
+    ffi_merge_family_with_progress(scan_result, family_name, None, ptr::null_mut())
+}
+
+/// Merge a family, calling `callback` (if not null) after each parsed file
+///
+/// The callback runs on the calling thread, with no lock held.
+///
+/// # Safety
+///
+/// `scan_result` must be NULL or a scan result from ffi_scan_directory or
+/// ffi_scan_clone that hasn't been freed, `family_name` NULL or a
+/// NUL-terminated string and `callback` NULL or safe to call with `user_data`
+/// from this thread until the call returns.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_merge_family_with_progress(
+    scan_result: *const FfiScanResult,
+    family_name: *const c_char,
+    callback: Option<FfiMergeProgressFn>,
+    user_data: *mut c_void,
 ) -> *mut FfiResolvedTable {
     clear_error();
 
@@ -327,8 +569,14 @@ pub unsafe extern "C" fn ffi_merge_family(
         }
     };
 
-    match merge_family(family) {
-        Ok(table) => Box::into_raw(Box::new(FfiResolvedTable { inner: table })),
+    let progress = |p: da_core::MergeProgress| {
+        if let Some(callback) = callback {
+            let file = CString::new(p.file.to_string_lossy().as_bytes()).unwrap_or_default();
+            callback(file.as_ptr(), p.parsed, p.total, user_data);
+        }
+    };
+    match merge_family_with_progress(family, &MergeOptions::default(), progress) {
+        Ok((table, _)) => Box::into_raw(Box::new(FfiResolvedTable { inner: table })),
         Err(e) => {
             set_error(&e.to_string());
             ptr::null_mut()
@@ -337,6 +585,11 @@ pub unsafe extern "C" fn ffi_merge_family(
 }
 
 /// Get column count
+///
+/// # Safety
+///
+/// `table` must be NULL or a table from ffi_merge_family that hasn't been
+/// freed.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_table_column_count(table: *const FfiResolvedTable) -> usize {
     if table.is_null() {
@@ -346,6 +599,11 @@ pub unsafe extern "C" fn ffi_table_column_count(table: *const FfiResolvedTable)
 }
 
 /// Get row count
+///
+/// # Safety
+///
+/// `table` must be NULL or a table from ffi_merge_family that hasn't been
+/// freed.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_table_row_count(table: *const FfiResolvedTable) -> usize {
     if table.is_null() {
@@ -355,6 +613,11 @@ pub unsafe extern "C" fn ffi_table_row_count(table: *const FfiResolvedTable) ->
 }
 
 /// Get column info by index
+///
+/// # Safety
+///
+/// `table` must be NULL or a table from ffi_merge_family that hasn't been
+/// freed.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_table_get_column(
     table: *const FfiResolvedTable,
@@ -364,7 +627,7 @@ pub unsafe extern "C" fn ffi_table_get_column(
         return ptr::null_mut();
     }
 
-    match (*table).inner.columns.get(index) {
+    match (&*table).inner.columns.get(index) {
         Some(col) => {
             let info = Box::new(FfiColumnInfo {
                 name: to_c_string(&col.name),
@@ -377,6 +640,11 @@ pub unsafe extern "C" fn ffi_table_get_column(
 }
 
 /// Get cell at row/column
+///
+/// # Safety
+///
+/// `table` must be NULL or a table from ffi_merge_family that hasn't been
+/// freed.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_table_get_cell(
     table: *const FfiResolvedTable,
@@ -387,7 +655,7 @@ pub unsafe extern "C" fn ffi_table_get_cell(
         return ptr::null_mut();
     }
 
-    let row = match (*table).inner.rows.get(row_index) {
+    let row = match (&*table).inner.rows.get(row_index) {
         Some(r) => r,
         None => return ptr::null_mut(),
     };
@@ -397,42 +665,111 @@ pub unsafe extern "C" fn ffi_table_get_cell(
         None => return ptr::null_mut(),
     };
 
-    let ffi_value = match &cell.value {
-        CellValue::Empty => FfiCellValue {
-            value_type: 0,
-            int_value: 0,
-            float_value: 0.0,
-            string_value: ptr::null_mut(),
-        },
-        CellValue::Integer(i) => FfiCellValue {
-            value_type: 1,
-            int_value: *i,
-            float_value: 0.0,
-            string_value: ptr::null_mut(),
-        },
-        CellValue::Float(f) => FfiCellValue {
-            value_type: 2,
-            int_value: 0,
-            float_value: *f,
-            string_value: ptr::null_mut(),
-        },
-        CellValue::String(s) => FfiCellValue {
-            value_type: 3,
-            int_value: 0,
-            float_value: 0.0,
-            string_value: to_c_string(s),
-        },
+    Box::into_raw(Box::new(to_ffi_cell(cell)))
+}
+
+/// Explain where a cell's value came from
+///
+/// Lists every file that contributed to the table, in merge order, and
+/// marks the one the cell's value was taken from. Free the result with
+/// `ffi_free_cell_explanation`.
+///
+/// # Safety
+///
+/// `table` must be NULL or a table from ffi_merge_family that hasn't been
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_table_explain_cell(
+    table: *const FfiResolvedTable,
+    row_index: usize,
+    col_index: usize,
+) -> *mut FfiCellExplanation {
+    if table.is_null() {
+        return ptr::null_mut();
+    }
+    let table = &(*table).inner;
+
+    let winner = match table.get_provenance(row_index, col_index) {
+        Some(path) => path,
+        None => return ptr::null_mut(),
     };
 
-    let ffi_cell = Box::new(FfiResolvedCell {
-        value: ffi_value,
-        source_path: to_c_string(cell.source.to_string_lossy().as_ref()),
-    });
+    let sources: Vec<FfiCellSource> = table
+        .sources
+        .iter()
+        .map(|source| FfiCellSource {
+            path: to_c_string(source.to_string_lossy().as_ref()),
+            is_winner: if source == winner { 1 } else { 0 },
+        })
+        .collect();
+    let source_count = sources.len();
 
-    Box::into_raw(ffi_cell)
+    Box::into_raw(Box::new(FfiCellExplanation {
+        winner_path: to_c_string(winner.to_string_lossy().as_ref()),
+        sources: if sources.is_empty() {
+            ptr::null_mut()
+        } else {
+            Box::into_raw(sources.into_boxed_slice()) as *mut FfiCellSource
+        },
+        source_count,
+    }))
+}
+
+/// Get a page of rows as a flat, row-major array of cells
+///
+/// Returns up to `count` rows starting at `start`, each with one cell per
+/// column, and sets `out_count` to the number of rows returned. Free the
+/// array with `ffi_free_cell_array(arr, out_count * column_count)`.
+///
+/// # Safety
+///
+/// `table` must be NULL or a table from ffi_merge_family that hasn't been
+/// freed and `out_count` NULL or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_table_get_rows(
+    table: *const FfiResolvedTable,
+    start: usize,
+    count: usize,
+    out_count: *mut usize,
+) -> *mut FfiResolvedCell {
+    if table.is_null() || out_count.is_null() {
+        return ptr::null_mut();
+    }
+
+    let table = &(*table).inner;
+    let rows = table.row_slice(start, count);
+    *out_count = rows.len();
+
+    let cells: Vec<FfiResolvedCell> = rows
+        .iter()
+        .flat_map(|row| (0..table.columns.len()).map(move |i| row.cells.get(i)))
+        .map(|cell| match cell {
+            Some(cell) => to_ffi_cell(cell),
+            None => FfiResolvedCell {
+                value: FfiCellValue {
+                    value_type: 0,
+                    int_value: 0,
+                    float_value: 0.0,
+                    string_value: ptr::null_mut(),
+                },
+                source_path: ptr::null_mut(),
+            },
+        })
+        .collect();
+
+    if cells.is_empty() {
+        ptr::null_mut()
+    } else {
+        Box::into_raw(cells.into_boxed_slice()) as *mut FfiResolvedCell
+    }
 }
 
 /// Get row ID for a given row index
+///
+/// # Safety
+///
+/// `table` must be NULL or a table from ffi_merge_family that hasn't been
+/// freed.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_table_get_row_id(
     table: *const FfiResolvedTable,
@@ -442,13 +779,19 @@ pub unsafe extern "C" fn ffi_table_get_row_id(
         return -1;
     }
 
-    match (*table).inner.rows.get(row_index) {
+    match (&*table).inner.rows.get(row_index) {
         Some(row) => row.id.unwrap_or(-1),
         None => -1,
     }
 }
 
 /// Filter rows by column value (case-insensitive substring)
+///
+/// # Safety
+///
+/// `table` must be NULL or a table from ffi_merge_family that hasn't been
+/// freed, `column_name` NULL or a NUL-terminated string, `value_pattern` NULL
+/// or a NUL-terminated string and `out_count` NULL or valid for writes.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_table_filter_rows(
     table: *const FfiResolvedTable,
@@ -483,11 +826,9 @@ pub unsafe extern "C" fn ffi_table_filter_rows(
         .iter()
         .enumerate()
         .filter(|(_, row)| {
-            row.cells.get(col_idx).map_or(false, |cell| {
-                cell.value
-                    .to_string_value()
-                    .to_lowercase()
-                    .contains(&pattern)
+            row.cells.get(col_idx).is_some_and(|cell| match cell.value.as_str() {
+                Some(text) => text.to_lowercase().contains(&pattern),
+                None => cell.value.to_string_value().to_lowercase().contains(&pattern),
             })
         })
         .map(|(idx, _)| idx)
@@ -503,7 +844,104 @@ pub unsafe extern "C" fn ffi_table_filter_rows(
     }
 }
 
+/// Filter rows by comparing a column's numbers to a value
+///
+/// `op` is one of "<", "<=", ">", ">=", "==" or "!="; empty and text cells
+/// never match. Returns the matching row indices, or NULL with `out_count`
+/// set to 0 if none match or the arguments are invalid (see ffi_last_error).
+///
+/// # Safety
+///
+/// `table` must be NULL or a table from ffi_merge_family that hasn't been
+/// freed, `column_name` NULL or a NUL-terminated string, `op` NULL or a
+/// NUL-terminated string and `out_count` NULL or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_table_filter_numeric(
+    table: *const FfiResolvedTable,
+    column_name: *const c_char,
+    op: *const c_char,
+    value: f64,
+    out_count: *mut usize,
+) -> *mut usize {
+    clear_error();
+
+    if table.is_null() || column_name.is_null() || op.is_null() || out_count.is_null() {
+        set_error("Null pointer");
+        return ptr::null_mut();
+    }
+    *out_count = 0;
+
+    let table = &(*table).inner;
+    let column = from_c_str(column_name).and_then(|name| table.find_column(&name).map(|c| c.index));
+    let col_idx = match column {
+        Some(idx) => idx,
+        None => {
+            set_error("Column not found");
+            return ptr::null_mut();
+        }
+    };
+
+    let op: CompareOp = match from_c_str(op).map(|o| o.parse()) {
+        Some(Ok(op)) => op,
+        Some(Err(e)) => {
+            set_error(&e.to_string());
+            return ptr::null_mut();
+        }
+        None => {
+            set_error("Invalid operator");
+            return ptr::null_mut();
+        }
+    };
+
+    let matches = table.filter_numeric(col_idx, op, value);
+    *out_count = matches.len();
+
+    if matches.is_empty() {
+        ptr::null_mut()
+    } else {
+        let boxed = matches.into_boxed_slice();
+        Box::into_raw(boxed) as *mut usize
+    }
+}
+
+/// Sort table rows by a column (stable, empty cells last)
+///
+/// Returns 1 on success, 0 if the table is null or the column is out of range.
+///
+/// # Safety
+///
+/// `table` must be NULL or a table from ffi_merge_family that hasn't been
+/// freed, and not in use elsewhere during the call.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_table_sort(
+    table: *mut FfiResolvedTable,
+    col_index: usize,
+    ascending: i32,
+    numeric: i32,
+) -> i32 {
+    clear_error();
+
+    if table.is_null() {
+        set_error("Null pointer");
+        return 0;
+    }
+
+    let table = &mut (*table).inner;
+    if col_index >= table.columns.len() {
+        set_error(&format!("Column index out of range: {}", col_index));
+        return 0;
+    }
+
+    table.sort_by_column(col_index, ascending != 0, numeric != 0);
+    1
+}
+
 /// Free resolved table
+///
+/// # Safety
+///
+/// `table` must be NULL or a table from ffi_merge_family, and must not be
+/// used after this call.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_table_free(table: *mut FfiResolvedTable) {
     if !table.is_null() {
@@ -516,6 +954,10 @@ pub unsafe extern "C" fn ffi_table_free(table: *mut FfiResolvedTable) {
 // ============================================================================
 
 /// Create a new patch (returns JSON string)
+///
+/// # Safety
+///
+/// `family_name` must be NULL or a NUL-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_create_patch(family_name: *const c_char) -> FfiStringResult {
     let name = match from_c_str(family_name) {
@@ -529,10 +971,7 @@ pub unsafe extern "C" fn ffi_create_patch(family_name: *const c_char) -> FfiStri
         }
     };
 
-    let patch = PatchFile {
-        family: name,
-        edits: vec![],
-    };
+    let patch = PatchFile::new(name);
 
     match serde_json::to_string_pretty(&patch) {
         Ok(json) => {
@@ -556,6 +995,13 @@ pub unsafe extern "C" fn ffi_create_patch(family_name: *const c_char) -> FfiStri
 }
 
 /// Apply a patch and export modified files
+///
+/// # Safety
+///
+/// `scan_result` must be NULL or a scan result from ffi_scan_directory or
+/// ffi_scan_clone that hasn't been freed, `patch_json` NULL or a
+/// NUL-terminated string, `output_dir` NULL or a NUL-terminated string and
+/// `history_path` NULL or a NUL-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_apply_patch(
     scan_result: *const FfiScanResult,
@@ -625,6 +1071,7 @@ pub unsafe extern "C" fn ffi_apply_patch(
                 // Save to history if path provided
                 if let Some(hist_path) = history_file_path {
                     let entry = da_core::create_history_entry(
+                        &table,
                         &patch,
                         result.files_written.clone(),
                         out_path,
@@ -655,7 +1102,45 @@ pub unsafe extern "C" fn ffi_apply_patch(
     }
 }
 
+/// Build a failed string result carrying `msg`
+fn error_result(msg: &str) -> FfiStringResult {
+    FfiStringResult {
+        data: to_c_string(msg),
+        len: msg.len(),
+        success: 0,
+    }
+}
+
+/// Parse a patch and merge the family it targets
+unsafe fn load_patch_table(
+    scan_result: *const FfiScanResult,
+    patch_json: *const c_char,
+) -> Result<(PatchFile, ResolvedTable), String> {
+    if scan_result.is_null() || patch_json.is_null() {
+        return Err("Null pointer".to_string());
+    }
+
+    let json = from_c_str(patch_json).ok_or("Invalid JSON string")?;
+    let patch: PatchFile =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid patch format: {}", e))?;
+
+    let family = (*scan_result)
+        .families
+        .iter()
+        .find(|f| f.name == patch.family)
+        .ok_or_else(|| format!("Family not found: {}", patch.family))?;
+
+    let table = merge_family(family).map_err(|e| format!("Failed to merge family: {}", e))?;
+    Ok((patch, table))
+}
+
 /// Validate a patch without applying
+///
+/// # Safety
+///
+/// `scan_result` must be NULL or a scan result from ffi_scan_directory or
+/// ffi_scan_clone that hasn't been freed and `patch_json` NULL or a
+/// NUL-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_validate_patch(
     scan_result: *const FfiScanResult,
@@ -663,114 +1148,75 @@ pub unsafe extern "C" fn ffi_validate_patch(
 ) -> FfiStringResult {
     // Wrap in catch_unwind to prevent panics from crashing the app
     let result = catch_unwind(AssertUnwindSafe(|| {
-        if scan_result.is_null() || patch_json.is_null() {
-            return FfiStringResult {
-                data: to_c_string("Null pointer"),
-                len: 12,
-                success: 0,
-            };
-        }
-
-        let json = match from_c_str(patch_json) {
-            Some(j) => j,
-            None => {
-                return FfiStringResult {
-                    data: to_c_string("Invalid JSON string"),
-                    len: 19,
-                    success: 0,
-                }
-            }
+        let (patch, table) = match load_patch_table(scan_result, patch_json) {
+            Ok(loaded) => loaded,
+            Err(msg) => return error_result(&msg),
         };
 
-        let patch: PatchFile = match serde_json::from_str(&json) {
-            Ok(p) => p,
-            Err(e) => {
-                let msg = format!("Invalid patch format: {}", e);
-                let len = msg.len();
-                return FfiStringResult {
-                    data: to_c_string(&msg),
-                    len,
-                    success: 0,
-                };
-            }
-        };
+        match validate_patch(&table, &patch, false).first_error() {
+            Some(msg) => error_result(msg),
+            None => FfiStringResult {
+                data: ptr::null_mut(),
+                len: 0,
+                success: 1,
+            },
+        }
+    }));
 
-        // Check family exists
-        let family = match (*scan_result)
-            .families
-            .iter()
-            .find(|f| f.name == patch.family)
-        {
-            Some(f) => f,
-            None => {
-                let msg = format!("Family not found: {}", patch.family);
-                let len = msg.len();
-                return FfiStringResult {
-                    data: to_c_string(&msg),
-                    len,
-                    success: 0,
-                };
-            }
-        };
+    match result {
+        Ok(r) => r,
+        Err(_) => error_result("Internal error: panic occurred in validate_patch"),
+    }
+}
 
-        // Merge to validate edits
-        let table = match merge_family(family) {
-            Ok(t) => t,
-            Err(e) => {
-                let msg = format!("Failed to merge family: {}", e);
-                let len = msg.len();
-                return FfiStringResult {
-                    data: to_c_string(&msg),
-                    len,
-                    success: 0,
-                };
-            }
+/// Validate a patch and return the per-edit `ValidationReport` as JSON
+///
+/// With `check_types` non-zero, edits whose value doesn't fit the column's
+/// inferred type carry a `type_mismatch`.
+///
+/// # Safety
+///
+/// `scan_result` must be NULL or a scan result from ffi_scan_directory or
+/// ffi_scan_clone that hasn't been freed and `patch_json` NULL or a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_validate_patch_report(
+    scan_result: *const FfiScanResult,
+    patch_json: *const c_char,
+    check_types: i32,
+) -> FfiStringResult {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let (patch, table) = match load_patch_table(scan_result, patch_json) {
+            Ok(loaded) => loaded,
+            Err(msg) => return error_result(&msg),
         };
 
-        // Validate each edit
-        for edit in &patch.edits {
-            // Check column exists
-            if !table.columns.iter().any(|c| c.name == edit.column) {
-                let msg = format!("Column not found: {}", edit.column);
-                let len = msg.len();
-                return FfiStringResult {
-                    data: to_c_string(&msg),
-                    len,
-                    success: 0,
-                };
-            }
-
-            // Check row exists
-            if !table.rows.iter().any(|r| r.id == Some(edit.row_id)) {
-                let msg = format!("Row not found: {}", edit.row_id);
-                let len = msg.len();
-                return FfiStringResult {
-                    data: to_c_string(&msg),
+        let report = validate_patch(&table, &patch, check_types != 0);
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => {
+                let len = json.len();
+                FfiStringResult {
+                    data: to_c_string(&json),
                     len,
-                    success: 0,
-                };
+                    success: 1,
+                }
             }
-        }
-
-        // Valid
-        FfiStringResult {
-            data: ptr::null_mut(),
-            len: 0,
-            success: 1,
+            Err(e) => error_result(&e.to_string()),
         }
     }));
 
     match result {
         Ok(r) => r,
-        Err(_) => FfiStringResult {
-            data: to_c_string("Internal error: panic occurred in validate_patch"),
-            len: 47,
-            success: 0,
-        },
+        Err(_) => error_result("Internal error: panic occurred in validate_patch_report"),
     }
 }
 
 /// Get number of files exported from patch result
+///
+/// # Safety
+///
+/// `result` must be NULL or a result from ffi_apply_patch that hasn't been
+/// freed.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_patch_export_count(result: *const FfiPatchResult) -> usize {
     if result.is_null() {
@@ -780,6 +1226,11 @@ pub unsafe extern "C" fn ffi_patch_export_count(result: *const FfiPatchResult) -
 }
 
 /// Get exported file path by index
+///
+/// # Safety
+///
+/// `result` must be NULL or a result from ffi_apply_patch that hasn't been
+/// freed.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_patch_get_export_path(
     result: *const FfiPatchResult,
@@ -789,14 +1240,18 @@ pub unsafe extern "C" fn ffi_patch_get_export_path(
         return ptr::null_mut();
     }
 
-    (*result)
-        .exported_files
+    (&*result).exported_files
         .get(index)
         .map(|p| to_c_string(p.to_string_lossy().as_ref()))
         .unwrap_or(ptr::null_mut())
 }
 
 /// Free patch result
+///
+/// # Safety
+///
+/// `result` must be NULL or a result from ffi_apply_patch, and must not be
+/// used after this call.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_patch_free(result: *mut FfiPatchResult) {
     if !result.is_null() {
@@ -809,6 +1264,10 @@ pub unsafe extern "C" fn ffi_patch_free(result: *mut FfiPatchResult) {
 // ============================================================================
 
 /// Load history file
+///
+/// # Safety
+///
+/// `path` must be NULL or a NUL-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_history_load(path: *const c_char) -> *mut FfiHistoryFile {
     let path_str = match from_c_str(path) {
@@ -816,7 +1275,7 @@ pub unsafe extern "C" fn ffi_history_load(path: *const c_char) -> *mut FfiHistor
         None => return ptr::null_mut(),
     };
 
-    match HistoryFile::load(&PathBuf::from(path_str)) {
+    match HistoryFile::load(PathBuf::from(path_str)) {
         Ok(history) => {
             // Flatten entries for indexed access, sorted by timestamp (most recent first)
             let mut entries: Vec<HistoryEntry> = history
@@ -825,7 +1284,7 @@ pub unsafe extern "C" fn ffi_history_load(path: *const c_char) -> *mut FfiHistor
                 .flatten()
                 .cloned()
                 .collect();
-            entries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+            entries.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
 
             Box::into_raw(Box::new(FfiHistoryFile { inner: history, entries }))
         }
@@ -840,6 +1299,11 @@ pub unsafe extern "C" fn ffi_history_load(path: *const c_char) -> *mut FfiHistor
 }
 
 /// Get history entry count
+///
+/// # Safety
+///
+/// `history` must be NULL or a history from ffi_history_load that hasn't been
+/// freed.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_history_count(history: *const FfiHistoryFile) -> usize {
     if history.is_null() {
@@ -849,6 +1313,11 @@ pub unsafe extern "C" fn ffi_history_count(history: *const FfiHistoryFile) -> us
 }
 
 /// Get history entry by index (already sorted most recent first)
+///
+/// # Safety
+///
+/// `history` must be NULL or a history from ffi_history_load that hasn't been
+/// freed.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_history_get_entry(
     history: *const FfiHistoryFile,
@@ -858,7 +1327,7 @@ pub unsafe extern "C" fn ffi_history_get_entry(
         return ptr::null_mut();
     }
 
-    let entry = match (*history).entries.get(index) {
+    let entry = match (&*history).entries.get(index) {
         Some(e) => e,
         None => return ptr::null_mut(),
     };
@@ -874,6 +1343,11 @@ pub unsafe extern "C" fn ffi_history_get_entry(
 }
 
 /// Free history file
+///
+/// # Safety
+///
+/// `history` must be NULL or a history from ffi_history_load, and must not be
+/// used after this call.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_history_free(history: *mut FfiHistoryFile) {
     if !history.is_null() {
@@ -885,6 +1359,10 @@ pub unsafe extern "C" fn ffi_history_free(history: *mut FfiHistoryFile) {
 // Memory Management
 // ============================================================================
 
+/// # Safety
+///
+/// `s` must be NULL or a string returned by this library, and must not be
+/// used after this call.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_free_string(s: *mut c_char) {
     if !s.is_null() {
@@ -892,6 +1370,11 @@ pub unsafe extern "C" fn ffi_free_string(s: *mut c_char) {
     }
 }
 
+/// # Safety
+///
+/// `arr` must be NULL or an array of `count` strings from
+/// ffi_search_families, and neither it nor its strings may be used after this
+/// call.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_free_string_array(arr: *mut *mut c_char, count: usize) {
     if !arr.is_null() {
@@ -901,10 +1384,14 @@ pub unsafe extern "C" fn ffi_free_string_array(arr: *mut *mut c_char, count: usi
                 drop(CString::from_raw(*s));
             }
         }
-        drop(Box::from_raw(std::slice::from_raw_parts_mut(arr, count) as *mut [*mut c_char]));
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(arr, count)));
     }
 }
 
+/// # Safety
+///
+/// `info` must be NULL or a value from ffi_scan_get_family, and must not be
+/// used after this call.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_free_family_info(info: *mut FfiFamilyInfo) {
     if !info.is_null() {
@@ -915,6 +1402,10 @@ pub unsafe extern "C" fn ffi_free_family_info(info: *mut FfiFamilyInfo) {
     }
 }
 
+/// # Safety
+///
+/// `info` must be NULL or a single FfiMemberInfo allocated by this library,
+/// and must not be used after this call.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_free_member_info(info: *mut FfiMemberInfo) {
     if !info.is_null() {
@@ -928,10 +1419,14 @@ pub unsafe extern "C" fn ffi_free_member_info(info: *mut FfiMemberInfo) {
     }
 }
 
+/// # Safety
+///
+/// `arr` must be NULL or an array of `count` members from
+/// ffi_scan_get_members, and must not be used after this call.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_free_member_info_array(arr: *mut FfiMemberInfo, count: usize) {
     if !arr.is_null() {
-        let slice = Box::from_raw(std::slice::from_raw_parts_mut(arr, count));
+        let slice = Box::from_raw(ptr::slice_from_raw_parts_mut(arr, count));
         for info in slice.iter() {
             if !info.path.is_null() {
                 drop(CString::from_raw(info.path));
@@ -943,6 +1438,26 @@ pub unsafe extern "C" fn ffi_free_member_info_array(arr: *mut FfiMemberInfo, cou
     }
 }
 
+/// # Safety
+///
+/// `arr` must be NULL or an array of `count` hits from ffi_search, and must
+/// not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_free_search_hits(arr: *mut FfiSearchHit, count: usize) {
+    if !arr.is_null() {
+        let slice = Box::from_raw(ptr::slice_from_raw_parts_mut(arr, count));
+        for hit in slice.iter() {
+            if !hit.name.is_null() {
+                drop(CString::from_raw(hit.name));
+            }
+        }
+    }
+}
+
+/// # Safety
+///
+/// `info` must be NULL or a value from ffi_table_get_column, and must not be
+/// used after this call.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_free_column_info(info: *mut FfiColumnInfo) {
     if !info.is_null() {
@@ -953,6 +1468,10 @@ pub unsafe extern "C" fn ffi_free_column_info(info: *mut FfiColumnInfo) {
     }
 }
 
+/// # Safety
+///
+/// `cell` must be NULL or a cell from ffi_table_get_cell, and must not be
+/// used after this call.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_free_cell(cell: *mut FfiResolvedCell) {
     if !cell.is_null() {
@@ -966,16 +1485,81 @@ pub unsafe extern "C" fn ffi_free_cell(cell: *mut FfiResolvedCell) {
     }
 }
 
+/// # Safety
+///
+/// `explanation` must be NULL or a value from ffi_table_explain_cell, and
+/// must not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_free_cell_explanation(explanation: *mut FfiCellExplanation) {
+    if !explanation.is_null() {
+        let explanation = Box::from_raw(explanation);
+        if !explanation.winner_path.is_null() {
+            drop(CString::from_raw(explanation.winner_path));
+        }
+        if !explanation.sources.is_null() {
+            let sources = Box::from_raw(ptr::slice_from_raw_parts_mut(
+                explanation.sources,
+                explanation.source_count,
+            ));
+            for source in sources.iter() {
+                if !source.path.is_null() {
+                    drop(CString::from_raw(source.path));
+                }
+            }
+        }
+    }
+}
+
+/// # Safety
+///
+/// `arr` must be NULL or an array from ffi_table_get_rows, with `count` the
+/// number of cells it holds (rows times columns), and must not be used after
+/// this call.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_free_cell_array(arr: *mut FfiResolvedCell, count: usize) {
+    if !arr.is_null() {
+        let slice = Box::from_raw(ptr::slice_from_raw_parts_mut(arr, count));
+        for cell in slice.iter() {
+            if !cell.value.string_value.is_null() {
+                drop(CString::from_raw(cell.value.string_value));
+            }
+            if !cell.source_path.is_null() {
+                drop(CString::from_raw(cell.source_path));
+            }
+        }
+    }
+}
+
+/// Kept for older callers; without the length it can't free anything, so
+/// the array leaks. Use ffi_free_index_array_n instead.
+///
+/// # Safety
+///
+/// `arr` is never read, so any pointer is accepted.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_free_index_array(arr: *mut usize) {
+    let _ = arr;
+}
+
+/// Free an index array from ffi_table_filter_rows or
+/// ffi_table_filter_numeric, given the `out_count` it came with
+///
+/// # Safety
+///
+/// `arr` must be NULL or an array from ffi_table_filter_rows or
+/// ffi_table_filter_numeric, with `count` the `out_count` it came with, and
+/// must not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_free_index_array_n(arr: *mut usize, count: usize) {
     if !arr.is_null() {
-        // We need to know the length, but we don't have it here
-        // This is a limitation - caller must track length
-        // For now, just leak it (not ideal, but safe)
-        // TODO: Return a struct with length
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(arr, count)));
     }
 }
 
+/// # Safety
+///
+/// `entry` must be NULL or an entry from ffi_history_get_entry, and must not
+/// be used after this call.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_free_history_entry(entry: *mut FfiHistoryEntry) {
     if !entry.is_null() {
@@ -992,6 +1576,11 @@ pub unsafe extern "C" fn ffi_free_history_entry(entry: *mut FfiHistoryEntry) {
     }
 }
 
+/// # Safety
+///
+/// `arr` must be NULL or an array of `count` entry pointers, each NULL or
+/// from ffi_history_get_entry, allocated by this library; none of them may be
+/// used after this call.
 #[no_mangle]
 pub unsafe extern "C" fn ffi_free_history_entry_array(arr: *mut *mut FfiHistoryEntry, count: usize) {
     if !arr.is_null() {
@@ -1001,8 +1590,6 @@ pub unsafe extern "C" fn ffi_free_history_entry_array(arr: *mut *mut FfiHistoryE
                 ffi_free_history_entry(*entry_ptr);
             }
         }
-        drop(Box::from_raw(
-            std::slice::from_raw_parts_mut(arr, count) as *mut [*mut FfiHistoryEntry]
-        ));
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(arr, count)));
     }
 }
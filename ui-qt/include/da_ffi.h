@@ -48,6 +48,27 @@ typedef struct {
     char* source_path;
 } FfiResolvedCell;
 
+// A file that contributed to a table, for explaining a cell
+typedef struct {
+    char* path;
+    int is_winner;  // 1 if the cell's value came from this file
+} FfiCellSource;
+
+// Where a cell's value came from
+typedef struct {
+    char* winner_path;
+    FfiCellSource* sources;  // every contributing file, in merge order
+    size_t source_count;
+} FfiCellExplanation;
+
+// A family matched by ffi_search
+typedef struct {
+    char* name;
+    size_t member_count;
+    size_t match_offset;  // byte offset of the match in the UTF-8 name
+    size_t match_len;     // length of the match in bytes
+} FfiSearchHit;
+
 // Column info
 typedef struct {
     char* name;
@@ -75,8 +96,22 @@ typedef struct {
 
 // Scan a directory for CSV files and group into families
 // Returns NULL on error (check ffi_last_error)
+// A scan result is read-only once created and may be read from any
+// thread, e.g. scanned on a worker thread and browsed from the UI thread
 FfiScanResult* ffi_scan_directory(const char* root_path);
 
+// Scan progress callback: files discovered, files processed, walk_done
+// (1 once every file has been discovered) and the caller's user_data
+typedef void (*FfiScanProgressFn)(size_t discovered, size_t processed,
+                                  int walk_done, void* user_data);
+
+// Like ffi_scan_directory, calling callback (may be NULL) as files are
+// found and classified. The callback runs on the calling thread with no
+// lock held.
+FfiScanResult* ffi_scan_directory_with_progress(const char* root_path,
+                                                FfiScanProgressFn callback,
+                                                void* user_data);
+
 // Get number of families in scan result
 size_t ffi_scan_family_count(const FfiScanResult* result);
 
@@ -96,7 +131,18 @@ char** ffi_search_families(const FfiScanResult* result,
                            const char* pattern,
                            size_t* out_count);
 
-// Free scan result
+// Search families by name pattern (case-insensitive substring), reporting
+// where each name matched and how many members the family has
+// Returns array of hits, sets out_count (free with ffi_free_search_hits)
+FfiSearchHit* ffi_search(const FfiScanResult* result,
+                         const char* pattern,
+                         size_t* out_count);
+
+// Create another handle to the same scan result without copying it
+// The new handle stays valid after the original is freed
+FfiScanResult* ffi_scan_clone(const FfiScanResult* result);
+
+// Free a scan result handle (the data is freed with the last handle)
 void ffi_scan_free(FfiScanResult* result);
 
 // ============================================================================
@@ -108,6 +154,18 @@ void ffi_scan_free(FfiScanResult* result);
 FfiResolvedTable* ffi_merge_family(const FfiScanResult* scan_result,
                                     const char* family_name);
 
+// Merge progress callback: path of the file just parsed (valid only during
+// the call), files parsed, total files and the caller's user_data
+typedef void (*FfiMergeProgressFn)(const char* file, size_t parsed,
+                                   size_t total, void* user_data);
+
+// Like ffi_merge_family, calling callback (may be NULL) after each parsed
+// file. The callback runs on the calling thread with no lock held.
+FfiResolvedTable* ffi_merge_family_with_progress(const FfiScanResult* scan_result,
+                                                 const char* family_name,
+                                                 FfiMergeProgressFn callback,
+                                                 void* user_data);
+
 // Get column count
 size_t ffi_table_column_count(const FfiResolvedTable* table);
 
@@ -123,16 +181,47 @@ FfiResolvedCell* ffi_table_get_cell(const FfiResolvedTable* table,
                                      size_t row_index,
                                      size_t col_index);
 
+// Explain a cell: the file its value came from and every file that
+// contributed to the table, in merge order
+// Returns NULL if the cell doesn't exist
+// Free with ffi_free_cell_explanation
+FfiCellExplanation* ffi_table_explain_cell(const FfiResolvedTable* table,
+                                           size_t row_index,
+                                           size_t col_index);
+
+// Get a page of rows as a flat, row-major cell array
+// Returns up to count rows starting at start, with one cell per column;
+// sets out_count to the number of rows returned
+// Free with ffi_free_cell_array(arr, out_count * column_count)
+FfiResolvedCell* ffi_table_get_rows(const FfiResolvedTable* table,
+                                    size_t start, size_t count, size_t* out_count);
+
 // Get row ID for a given row index
 int64_t ffi_table_get_row_id(const FfiResolvedTable* table, size_t row_index);
 
 // Filter rows by column value (case-insensitive substring)
-// Returns array of matching row indices, sets out_count
+// Returns array of matching row indices (free with ffi_free_index_array_n),
+// sets out_count
 size_t* ffi_table_filter_rows(const FfiResolvedTable* table,
                                const char* column_name,
                                const char* value_pattern,
                                size_t* out_count);
 
+// Filter rows by comparing a column's numbers to value
+// op is one of "<", "<=", ">", ">=", "==", "!="; empty and text cells never match
+// Returns array of matching row indices (free with ffi_free_index_array_n),
+// sets out_count; NULL if none match or on error (check ffi_last_error)
+size_t* ffi_table_filter_numeric(const FfiResolvedTable* table,
+                                 const char* column_name,
+                                 const char* op,
+                                 double value,
+                                 size_t* out_count);
+
+// Sort rows by a column in place (stable, empty cells always last)
+// numeric != 0 compares numbers by value; otherwise values compare as text
+// Returns 1 on success, 0 on error
+int ffi_table_sort(FfiResolvedTable* table, size_t col_index, int ascending, int numeric);
+
 // Free resolved table
 void ffi_table_free(FfiResolvedTable* table);
 
@@ -157,6 +246,14 @@ FfiPatchResult* ffi_apply_patch(const FfiScanResult* scan_result,
 FfiStringResult ffi_validate_patch(const FfiScanResult* scan_result,
                                     const char* patch_json);
 
+// Validate a patch and return a JSON report with one result per edit and
+// row operation; check_types != 0 also flags values that don't fit the
+// column's inferred type (type_mismatch)
+// Free data with ffi_free_string
+FfiStringResult ffi_validate_patch_report(const FfiScanResult* scan_result,
+                                           const char* patch_json,
+                                           int check_types);
+
 // Get number of files exported from patch result
 size_t ffi_patch_export_count(const FfiPatchResult* result);
 
@@ -196,9 +293,15 @@ void ffi_free_string_array(char** arr, size_t count);
 void ffi_free_family_info(FfiFamilyInfo* info);
 void ffi_free_member_info(FfiMemberInfo* info);
 void ffi_free_member_info_array(FfiMemberInfo* arr, size_t count);
+void ffi_free_search_hits(FfiSearchHit* arr, size_t count);
 void ffi_free_column_info(FfiColumnInfo* info);
 void ffi_free_cell(FfiResolvedCell* cell);
+void ffi_free_cell_explanation(FfiCellExplanation* explanation);
+void ffi_free_cell_array(FfiResolvedCell* arr, size_t count);
+// Leaks the array, as it has no length; use ffi_free_index_array_n
 void ffi_free_index_array(size_t* arr);
+// Free an index array given the out_count it was returned with
+void ffi_free_index_array_n(size_t* arr, size_t count);
 void ffi_free_history_entry(FfiHistoryEntry* entry);
 void ffi_free_history_entry_array(FfiHistoryEntry** arr, size_t count);
 
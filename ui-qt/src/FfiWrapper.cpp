@@ -16,14 +16,19 @@ FfiWrapper::FfiWrapper()
     , m_ffi_scan_get_family(nullptr)
     , m_ffi_scan_get_members(nullptr)
     , m_ffi_search_families(nullptr)
+    , m_ffi_search(nullptr)
+    , m_ffi_scan_clone(nullptr)
     , m_ffi_scan_free(nullptr)
     , m_ffi_merge_family(nullptr)
     , m_ffi_table_column_count(nullptr)
     , m_ffi_table_row_count(nullptr)
     , m_ffi_table_get_column(nullptr)
     , m_ffi_table_get_cell(nullptr)
+    , m_ffi_table_explain_cell(nullptr)
+    , m_ffi_table_get_rows(nullptr)
     , m_ffi_table_get_row_id(nullptr)
     , m_ffi_table_filter_rows(nullptr)
+    , m_ffi_table_sort(nullptr)
     , m_ffi_table_free(nullptr)
     , m_ffi_create_patch(nullptr)
     , m_ffi_apply_patch(nullptr)
@@ -40,9 +45,12 @@ FfiWrapper::FfiWrapper()
     , m_ffi_free_family_info(nullptr)
     , m_ffi_free_member_info(nullptr)
     , m_ffi_free_member_info_array(nullptr)
+    , m_ffi_free_search_hits(nullptr)
     , m_ffi_free_column_info(nullptr)
     , m_ffi_free_cell(nullptr)
-    , m_ffi_free_index_array(nullptr)
+    , m_ffi_free_cell_explanation(nullptr)
+    , m_ffi_free_cell_array(nullptr)
+    , m_ffi_free_index_array_n(nullptr)
     , m_ffi_free_history_entry(nullptr)
     , m_ffi_last_error(nullptr)
     , m_ffi_clear_error(nullptr)
@@ -93,6 +101,8 @@ bool FfiWrapper::initialize()
     success &= loadFunction("ffi_scan_get_family", (void**)&m_ffi_scan_get_family);
     success &= loadFunction("ffi_scan_get_members", (void**)&m_ffi_scan_get_members);
     success &= loadFunction("ffi_search_families", (void**)&m_ffi_search_families);
+    success &= loadFunction("ffi_search", (void**)&m_ffi_search);
+    success &= loadFunction("ffi_scan_clone", (void**)&m_ffi_scan_clone);
     success &= loadFunction("ffi_scan_free", (void**)&m_ffi_scan_free);
 
     success &= loadFunction("ffi_merge_family", (void**)&m_ffi_merge_family);
@@ -100,8 +110,11 @@ bool FfiWrapper::initialize()
     success &= loadFunction("ffi_table_row_count", (void**)&m_ffi_table_row_count);
     success &= loadFunction("ffi_table_get_column", (void**)&m_ffi_table_get_column);
     success &= loadFunction("ffi_table_get_cell", (void**)&m_ffi_table_get_cell);
+    success &= loadFunction("ffi_table_explain_cell", (void**)&m_ffi_table_explain_cell);
+    success &= loadFunction("ffi_table_get_rows", (void**)&m_ffi_table_get_rows);
     success &= loadFunction("ffi_table_get_row_id", (void**)&m_ffi_table_get_row_id);
     success &= loadFunction("ffi_table_filter_rows", (void**)&m_ffi_table_filter_rows);
+    success &= loadFunction("ffi_table_sort", (void**)&m_ffi_table_sort);
     success &= loadFunction("ffi_table_free", (void**)&m_ffi_table_free);
 
     success &= loadFunction("ffi_create_patch", (void**)&m_ffi_create_patch);
@@ -121,9 +134,12 @@ bool FfiWrapper::initialize()
     success &= loadFunction("ffi_free_family_info", (void**)&m_ffi_free_family_info);
     success &= loadFunction("ffi_free_member_info", (void**)&m_ffi_free_member_info);
     success &= loadFunction("ffi_free_member_info_array", (void**)&m_ffi_free_member_info_array);
+    success &= loadFunction("ffi_free_search_hits", (void**)&m_ffi_free_search_hits);
     success &= loadFunction("ffi_free_column_info", (void**)&m_ffi_free_column_info);
     success &= loadFunction("ffi_free_cell", (void**)&m_ffi_free_cell);
-    success &= loadFunction("ffi_free_index_array", (void**)&m_ffi_free_index_array);
+    success &= loadFunction("ffi_free_cell_explanation", (void**)&m_ffi_free_cell_explanation);
+    success &= loadFunction("ffi_free_cell_array", (void**)&m_ffi_free_cell_array);
+    success &= loadFunction("ffi_free_index_array_n", (void**)&m_ffi_free_index_array_n);
     success &= loadFunction("ffi_free_history_entry", (void**)&m_ffi_free_history_entry);
 
     success &= loadFunction("ffi_last_error", (void**)&m_ffi_last_error);
@@ -202,6 +218,21 @@ QStringList FfiWrapper::searchFamilies(const FfiScanResult* result, const QStrin
     return results;
 }
 
+FfiSearchHit* FfiWrapper::search(const FfiScanResult* result,
+                                 const QString& pattern,
+                                 size_t* outCount)
+{
+    if (!m_ffi_search || !result) return nullptr;
+    QByteArray patternBytes = pattern.toUtf8();
+    return m_ffi_search(result, patternBytes.constData(), outCount);
+}
+
+FfiScanResult* FfiWrapper::scanClone(const FfiScanResult* result)
+{
+    if (!m_ffi_scan_clone || !result) return nullptr;
+    return m_ffi_scan_clone(result);
+}
+
 void FfiWrapper::scanFree(FfiScanResult* result)
 {
     if (m_ffi_scan_free && result) {
@@ -243,6 +274,21 @@ FfiResolvedCell* FfiWrapper::tableGetCell(const FfiResolvedTable* table,
     return m_ffi_table_get_cell(table, rowIndex, colIndex);
 }
 
+FfiCellExplanation* FfiWrapper::tableExplainCell(const FfiResolvedTable* table,
+                                                 size_t rowIndex, size_t colIndex)
+{
+    if (!m_ffi_table_explain_cell || !table) return nullptr;
+    return m_ffi_table_explain_cell(table, rowIndex, colIndex);
+}
+
+FfiResolvedCell* FfiWrapper::tableGetRows(const FfiResolvedTable* table,
+                                           size_t start, size_t count, size_t* outCount)
+{
+    if (outCount) *outCount = 0;
+    if (!m_ffi_table_get_rows || !table) return nullptr;
+    return m_ffi_table_get_rows(table, start, count, outCount);
+}
+
 int64_t FfiWrapper::tableGetRowId(const FfiResolvedTable* table, size_t rowIndex)
 {
     if (!m_ffi_table_get_row_id || !table) return -1;
@@ -266,12 +312,18 @@ QList<size_t> FfiWrapper::tableFilterRows(const FfiResolvedTable* table,
         for (size_t i = 0; i < count; ++i) {
             results.append(indices[i]);
         }
-        m_ffi_free_index_array(indices);
+        m_ffi_free_index_array_n(indices, count);
     }
 
     return results;
 }
 
+bool FfiWrapper::tableSort(FfiResolvedTable* table, size_t colIndex, bool ascending, bool numeric)
+{
+    if (!m_ffi_table_sort || !table) return false;
+    return m_ffi_table_sort(table, colIndex, ascending ? 1 : 0, numeric ? 1 : 0) != 0;
+}
+
 void FfiWrapper::tableFree(FfiResolvedTable* table)
 {
     if (m_ffi_table_free && table) {
@@ -402,6 +454,13 @@ void FfiWrapper::freeMemberInfoArray(FfiMemberInfo* arr, size_t count)
     }
 }
 
+void FfiWrapper::freeSearchHits(FfiSearchHit* arr, size_t count)
+{
+    if (m_ffi_free_search_hits && arr) {
+        m_ffi_free_search_hits(arr, count);
+    }
+}
+
 void FfiWrapper::freeColumnInfo(FfiColumnInfo* info)
 {
     if (m_ffi_free_column_info && info) {
@@ -416,6 +475,20 @@ void FfiWrapper::freeCell(FfiResolvedCell* cell)
     }
 }
 
+void FfiWrapper::freeCellExplanation(FfiCellExplanation* explanation)
+{
+    if (m_ffi_free_cell_explanation && explanation) {
+        m_ffi_free_cell_explanation(explanation);
+    }
+}
+
+void FfiWrapper::freeCellArray(FfiResolvedCell* arr, size_t count)
+{
+    if (m_ffi_free_cell_array && arr) {
+        m_ffi_free_cell_array(arr, count);
+    }
+}
+
 void FfiWrapper::freeHistoryEntry(FfiHistoryEntry* entry)
 {
     if (m_ffi_free_history_entry && entry) {
@@ -24,6 +24,10 @@ public:
                                    const QString& familyName,
                                    size_t* outCount);
     QStringList searchFamilies(const FfiScanResult* result, const QString& pattern);
+    FfiSearchHit* search(const FfiScanResult* result,
+                         const QString& pattern,
+                         size_t* outCount);
+    FfiScanResult* scanClone(const FfiScanResult* result);
     void scanFree(FfiScanResult* result);
 
     // Tables
@@ -34,10 +38,15 @@ public:
     FfiColumnInfo* tableGetColumn(const FfiResolvedTable* table, size_t index);
     FfiResolvedCell* tableGetCell(const FfiResolvedTable* table,
                                    size_t rowIndex, size_t colIndex);
+    FfiCellExplanation* tableExplainCell(const FfiResolvedTable* table,
+                                         size_t rowIndex, size_t colIndex);
+    FfiResolvedCell* tableGetRows(const FfiResolvedTable* table,
+                                   size_t start, size_t count, size_t* outCount);
     int64_t tableGetRowId(const FfiResolvedTable* table, size_t rowIndex);
     QList<size_t> tableFilterRows(const FfiResolvedTable* table,
                                    const QString& columnName,
                                    const QString& valuePattern);
+    bool tableSort(FfiResolvedTable* table, size_t colIndex, bool ascending, bool numeric);
     void tableFree(FfiResolvedTable* table);
 
     // Patches
@@ -62,8 +71,11 @@ public:
     void freeFamilyInfo(FfiFamilyInfo* info);
     void freeMemberInfo(FfiMemberInfo* info);
     void freeMemberInfoArray(FfiMemberInfo* arr, size_t count);
+    void freeSearchHits(FfiSearchHit* arr, size_t count);
     void freeColumnInfo(FfiColumnInfo* info);
     void freeCell(FfiResolvedCell* cell);
+    void freeCellExplanation(FfiCellExplanation* explanation);
+    void freeCellArray(FfiResolvedCell* arr, size_t count);
     void freeHistoryEntry(FfiHistoryEntry* entry);
 
 private:
@@ -83,6 +95,8 @@ private:
     decltype(&ffi_scan_get_family) m_ffi_scan_get_family;
     decltype(&ffi_scan_get_members) m_ffi_scan_get_members;
     decltype(&ffi_search_families) m_ffi_search_families;
+    decltype(&ffi_search) m_ffi_search;
+    decltype(&ffi_scan_clone) m_ffi_scan_clone;
     decltype(&ffi_scan_free) m_ffi_scan_free;
 
     decltype(&ffi_merge_family) m_ffi_merge_family;
@@ -90,8 +104,11 @@ private:
     decltype(&ffi_table_row_count) m_ffi_table_row_count;
     decltype(&ffi_table_get_column) m_ffi_table_get_column;
     decltype(&ffi_table_get_cell) m_ffi_table_get_cell;
+    decltype(&ffi_table_explain_cell) m_ffi_table_explain_cell;
+    decltype(&ffi_table_get_rows) m_ffi_table_get_rows;
     decltype(&ffi_table_get_row_id) m_ffi_table_get_row_id;
     decltype(&ffi_table_filter_rows) m_ffi_table_filter_rows;
+    decltype(&ffi_table_sort) m_ffi_table_sort;
     decltype(&ffi_table_free) m_ffi_table_free;
 
     decltype(&ffi_create_patch) m_ffi_create_patch;
@@ -111,9 +128,12 @@ private:
     decltype(&ffi_free_family_info) m_ffi_free_family_info;
     decltype(&ffi_free_member_info) m_ffi_free_member_info;
     decltype(&ffi_free_member_info_array) m_ffi_free_member_info_array;
+    decltype(&ffi_free_search_hits) m_ffi_free_search_hits;
     decltype(&ffi_free_column_info) m_ffi_free_column_info;
     decltype(&ffi_free_cell) m_ffi_free_cell;
-    decltype(&ffi_free_index_array) m_ffi_free_index_array;
+    decltype(&ffi_free_cell_explanation) m_ffi_free_cell_explanation;
+    decltype(&ffi_free_cell_array) m_ffi_free_cell_array;
+    decltype(&ffi_free_index_array_n) m_ffi_free_index_array_n;
     decltype(&ffi_free_history_entry) m_ffi_free_history_entry;
 
     decltype(&ffi_last_error) m_ffi_last_error;
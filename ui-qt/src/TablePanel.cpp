@@ -203,7 +203,8 @@ void TablePanel::populateTable()
 
     // Determine which rows to show
     QList<size_t> rowsToShow;
-    if (m_filteredIndices.isEmpty() && m_filterText.isEmpty()) {
+    bool unfiltered = m_filteredIndices.isEmpty() && m_filterText.isEmpty();
+    if (unfiltered) {
         // No filter, show all rows for current page
         for (size_t i = 0; i < rowCount; ++i) {
             rowsToShow.append(i);
@@ -218,6 +219,13 @@ void TablePanel::populateTable()
     int startRow = m_currentPage * m_rowsPerPage;
     int endRow = qMin(startRow + m_rowsPerPage, m_totalRows);
 
+    // Without a filter the page is a contiguous range, so fetch it in one call
+    FfiResolvedCell* page = nullptr;
+    size_t pageRows = 0;
+    if (unfiltered && endRow > startRow) {
+        page = ffi.tableGetRows(m_resolvedTable, startRow, endRow - startRow, &pageRows);
+    }
+
     // Populate rows
     for (int i = startRow; i < endRow; ++i) {
         size_t rowIndex = rowsToShow[i];
@@ -227,7 +235,10 @@ void TablePanel::populateTable()
         QString rowSource;
 
         for (size_t c = 0; c < colCount; ++c) {
-            FfiResolvedCell* cell = ffi.tableGetCell(m_resolvedTable, rowIndex, c);
+            size_t pageRow = static_cast<size_t>(i - startRow);
+            FfiResolvedCell* cell = page && pageRow < pageRows
+                ? &page[pageRow * colCount + c]
+                : ffi.tableGetCell(m_resolvedTable, rowIndex, c);
 
             QString value;
             QString source;
@@ -258,7 +269,9 @@ void TablePanel::populateTable()
                     }
                 }
 
-                ffi.freeCell(cell);
+                if (!page || pageRow >= pageRows) {
+                    ffi.freeCell(cell);
+                }
             }
 
             QStandardItem* item = new QStandardItem(value);
@@ -276,6 +289,8 @@ void TablePanel::populateTable()
         m_model->appendRow(items);
     }
 
+    ffi.freeCellArray(page, pageRows * colCount);
+
     // Resize columns to content
     m_tableView->resizeColumnsToContents();
 